@@ -1,5 +1,7 @@
 use std::env;
 extern crate cc;
+#[cfg(feature = "wayland")]
+extern crate wayland_scanner;
 
 //cargo build --target=wasm32-unknown-unknown --verbose --no-default-features --features web
 
@@ -27,9 +29,13 @@ fn main() {
             target_os = "redox",
             target_arch = "wasm32", // this is ignored. Why?
         )))
-        && cfg!(not(any(feature = "wayland", feature = "x11")))
+        && cfg!(not(any(
+            feature = "wayland",
+            feature = "x11",
+            feature = "fbdev"
+        )))
     {
-        panic!("At least one of the x11 or wayland features must be enabled");
+        panic!("At least one of the x11, wayland or fbdev features must be enabled");
     }
 
     let env = env::var("TARGET").unwrap();
@@ -47,6 +53,19 @@ fn main() {
         cc::Build::new()
             .file("src/native/posix/scalar.cpp")
             .opt_level(3) // always build with opts for scaler so it's fast in debug also
-            .compile("libscalar.a")
+            .compile("libscalar.a");
+
+        // Protocols that are too new for the wayland-protocols crate
+        #[cfg(feature = "wayland")]
+        {
+            let out_dir = env::var("OUT_DIR").unwrap();
+            let protocol = "src/os/posix/protocols/fractional-scale-v1.xml";
+            println!("cargo:rerun-if-changed={}", protocol);
+            wayland_scanner::generate_code(
+                protocol,
+                format!("{}/fractional_scale_v1_client_api.rs", out_dir),
+                wayland_scanner::Side::Client,
+            );
+        }
     }
 }
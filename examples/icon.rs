@@ -18,7 +18,9 @@ fn main() {
     .expect("Unable to open Window");
 
     #[cfg(target_os = "windows")]
-    window.set_icon(Icon::from_str("resources/icon256.ico").unwrap());
+    window
+        .try_set_icon(Icon::from_str("resources/icon256.ico").unwrap())
+        .expect("Unable to set the icon");
 
     #[cfg(target_os = "linux")]
     {
@@ -170,7 +172,10 @@ fn main() {
             1476329472, 1342111744, 1207894016, 1056899072, 939458560, 788463616, 654245888,
             536281096, 385810432, 251592704, 134152192,
         ];
-        window.set_icon(Icon::try_from(&test[..]).unwrap());
+        // Wayland takes the icon from the application's .desktop file instead
+        if let Err(e) = window.try_set_icon(Icon::try_from(&test[..]).unwrap()) {
+            println!("{}", e);
+        }
     }
 
     while window.is_open() && !window.is_key_down(Key::Escape) {
@@ -1,4 +1,4 @@
-use minifb::{Key, ScaleMode, Window, WindowOptions};
+use minifb::{rgb, Key, ScaleMode, Window, WindowOptions};
 
 fn main() {
     use std::fs::File;
@@ -14,10 +14,7 @@ fn main() {
     reader.next_frame(&mut buf).unwrap();
     // convert buffer to u32
 
-    let u32_buffer: Vec<u32> = buf
-        .chunks(3)
-        .map(|v| ((v[0] as u32) << 16) | ((v[1] as u32) << 8) | v[2] as u32)
-        .collect();
+    let u32_buffer: Vec<u32> = buf.chunks(3).map(|v| rgb(v[0], v[1], v[2])).collect();
 
     let mut window = Window::new(
         "Noise Test - Press ESC to exit",
@@ -0,0 +1,56 @@
+use minifb::{Key, MouseButton, MouseMode, Window, WindowOptions};
+
+const WIDTH: usize = 640;
+const HEIGHT: usize = 360;
+const MAX_RADIUS: f32 = 12.0;
+
+fn main() {
+    let mut buffer: Vec<u32> = vec![0x00ffffff; WIDTH * HEIGHT];
+
+    let mut window = Window::new(
+        "Pen - Draw with a pen or the mouse, press C to clear, ESC to exit",
+        WIDTH,
+        HEIGHT,
+        WindowOptions::default(),
+    )
+    .expect("Unable to create window");
+
+    window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        if window.is_key_down(Key::C) {
+            buffer.iter_mut().for_each(|pixel| *pixel = 0x00ffffff);
+        }
+
+        // The brush gets bigger the harder the pen is pressed, the mouse draws at half pressure
+        let stroke = match window.get_pen() {
+            Some(pen) if pen.pressure > 0.0 => Some((pen.x, pen.y, pen.pressure)),
+            _ if window.get_mouse_down(MouseButton::Left) => window
+                .get_mouse_pos(MouseMode::Discard)
+                .map(|(x, y)| (x, y, 0.5)),
+            _ => None,
+        };
+
+        if let Some((x, y, pressure)) = stroke {
+            draw_circle(&mut buffer, x, y, 1.0 + pressure * MAX_RADIUS);
+        }
+
+        window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
+    }
+}
+
+fn draw_circle(buffer: &mut [u32], cx: f32, cy: f32, radius: f32) {
+    let x0 = (cx - radius).max(0.0) as usize;
+    let y0 = (cy - radius).max(0.0) as usize;
+    let x1 = ((cx + radius) as usize).min(WIDTH - 1);
+    let y1 = ((cy + radius) as usize).min(HEIGHT - 1);
+
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            let (dx, dy) = (x as f32 - cx, y as f32 - cy);
+            if dx * dx + dy * dy <= radius * radius {
+                buffer[y * WIDTH + x] = 0;
+            }
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use minifb::{Key, Window, WindowOptions};
+use std::sync::mpsc;
+use std::thread;
+
+const WIDTH: usize = 640;
+const HEIGHT: usize = 360;
+
+fn main() {
+    // The window stays on the main thread, which is required on macOS
+    let mut window = Window::new(
+        "Render Thread Test - Press ESC to exit",
+        WIDTH,
+        HEIGHT,
+        WindowOptions::default(),
+    )
+    .expect("Unable to create window");
+
+    window.limit_update_rate(Some(std::time::Duration::from_micros(16600)));
+
+    // Finished frames are sent to the main thread and empty buffers are sent back for reuse,
+    // so at most two buffers are in flight
+    let (frame_sender, frame_receiver) = mpsc::sync_channel::<Vec<u32>>(1);
+    let (buffer_sender, buffer_receiver) = mpsc::channel::<Vec<u32>>();
+
+    buffer_sender.send(vec![0; WIDTH * HEIGHT]).unwrap();
+    buffer_sender.send(vec![0; WIDTH * HEIGHT]).unwrap();
+
+    thread::spawn(move || {
+        let mut frame = 0u32;
+
+        // Stops when the main thread has gone away
+        while let Ok(mut buffer) = buffer_receiver.recv() {
+            for (i, pixel) in buffer.iter_mut().enumerate() {
+                let x = (i % WIDTH) as u32;
+                let y = (i / WIDTH) as u32;
+                *pixel = ((x + frame) & 0xff) << 16 | ((y + frame) & 0xff) << 8;
+            }
+            frame = frame.wrapping_add(1);
+
+            if frame_sender.send(buffer).is_err() {
+                break;
+            }
+        }
+    });
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        match frame_receiver.try_recv() {
+            Ok(buffer) => {
+                window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
+                buffer_sender.send(buffer).ok();
+            }
+            // Keep processing input while the next frame is being rendered
+            Err(_) => window.update(),
+        }
+    }
+}
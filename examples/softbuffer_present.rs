@@ -0,0 +1,55 @@
+//! Opens a window with minifb and presents to it with softbuffer instead of
+//! `Window::update_with_buffer`. Run with `cargo run --example softbuffer_present --features rwh_06`.
+
+use minifb::{Key, Window, WindowHandles, WindowOptions};
+use std::num::NonZeroU32;
+
+const WIDTH: usize = 640;
+const HEIGHT: usize = 360;
+
+fn main() {
+    let mut window = Window::new(
+        "softbuffer - Press ESC to exit",
+        WIDTH,
+        HEIGHT,
+        WindowOptions {
+            resize: true,
+            ..WindowOptions::default()
+        },
+    )
+    .expect("Unable to create window");
+
+    window.set_target_fps(60);
+
+    // The surface is declared after the window so it's dropped first
+    let handles = unsafe { WindowHandles::new(&window) }.expect("Window has no handles");
+    let context = softbuffer::Context::new(handles).expect("Unable to create context");
+    let mut surface =
+        softbuffer::Surface::new(&context, handles).expect("Unable to create surface");
+
+    let mut frame = 0u32;
+
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        let (width, height) = window.get_size();
+
+        if let (Some(w), Some(h)) = (
+            NonZeroU32::new(width as u32),
+            NonZeroU32::new(height as u32),
+        ) {
+            surface.resize(w, h).expect("Unable to resize surface");
+
+            let mut buffer = surface.buffer_mut().expect("Unable to get buffer");
+            for (i, pixel) in buffer.iter_mut().enumerate() {
+                let x = (i % width) as u32;
+                let y = (i / width) as u32;
+                *pixel = minifb::rgb((x + frame) as u8, (y + frame) as u8, 0x80);
+            }
+            buffer.present().expect("Unable to present buffer");
+        }
+
+        frame = frame.wrapping_add(1);
+
+        // Only processes input, the frame has already been presented by softbuffer
+        window.update();
+    }
+}
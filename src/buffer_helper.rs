@@ -1,15 +1,18 @@
 use crate::error::Error;
-use crate::Result;
+use crate::{BorderFill, PixelFormat, Rect, Result, ScaleMode};
+use std::borrow::Cow;
+use std::mem;
 
-pub fn check_buffer_size(
+pub fn check_buffer_size<T>(
     buffer_width: usize,
     buffer_height: usize,
     buffer_stride: usize,
-    buffer: &[u32],
+    buffer: &[T],
 ) -> Result<()> {
+    let pixel_size = mem::size_of::<T>();
     let width = usize::max(buffer_width, buffer_stride);
-    let buffer_size = buffer.len() * 4; // len is the number of entries so * 4 as we want bytes
-    let required_buffer_size = width * buffer_height * 4; // * 4 for 32-bit buffer
+    let buffer_size = mem::size_of_val(buffer);
+    let required_buffer_size = width * buffer_height * pixel_size;
 
     if buffer_size < required_buffer_size {
         let err = format!(
@@ -21,3 +24,220 @@ pub fn check_buffer_size(
         Ok(())
     }
 }
+
+/// Returns the ratio between window pixels and buffer pixels when a buffer of the given size is
+/// drawn into the window with `scale_mode`.
+pub fn buffer_scale(
+    scale_mode: ScaleMode,
+    window_width: usize,
+    window_height: usize,
+    buffer_width: usize,
+    buffer_height: usize,
+) -> (f32, f32) {
+    if buffer_width == 0 || buffer_height == 0 {
+        return (1.0, 1.0);
+    }
+
+    let x = window_width as f32 / buffer_width as f32;
+    let y = window_height as f32 / buffer_height as f32;
+
+    match scale_mode {
+        ScaleMode::Stretch => (x, y),
+        // The buffer is scaled by the same amount in both directions until it touches the edges
+        ScaleMode::AspectRatioStretch => {
+            let scale = x.min(y);
+            (scale, scale)
+        }
+        ScaleMode::Center | ScaleMode::UpperLeft => (1.0, 1.0),
+    }
+}
+
+/// Returns the `(x, y, width, height)` of the area a buffer of the given size covers when it's
+/// drawn into the window with `scale_mode`, using the same math as the scalers. The area can
+/// extend past the window when the buffer is larger than it.
+pub fn image_rect(
+    scale_mode: ScaleMode,
+    window_width: usize,
+    window_height: usize,
+    buffer_width: usize,
+    buffer_height: usize,
+) -> (isize, isize, isize, isize) {
+    let (ww, wh) = (window_width as isize, window_height as isize);
+    let (bw, bh) = (buffer_width as isize, buffer_height as isize);
+
+    match scale_mode {
+        ScaleMode::Stretch => (0, 0, ww, wh),
+        ScaleMode::AspectRatioStretch => {
+            if bw == 0 || bh == 0 || wh == 0 {
+                return (0, 0, ww, wh);
+            }
+
+            let buffer_aspect = bw as f32 / bh as f32;
+            let win_aspect = ww as f32 / wh as f32;
+
+            if buffer_aspect > win_aspect {
+                let new_height = (ww as f32 / buffer_aspect) as isize;
+                (0, (wh - new_height) / 2, ww, new_height)
+            } else {
+                let new_width = (wh as f32 * buffer_aspect) as isize;
+                ((ww - new_width) / 2, 0, new_width, wh)
+            }
+        }
+        ScaleMode::Center => ((ww - bw) / 2, (wh - bh) / 2, bw, bh),
+        ScaleMode::UpperLeft => (0, 0, bw, bh),
+    }
+}
+
+/// Returns the `(x, y, width, height)` of the window area that shows `rect` of a buffer of the
+/// given size, or `None` if none of it is visible. The area is rounded outwards and grown by a
+/// pixel on each side, as the scalers filter across the edges of the rectangle.
+pub fn damage_rect(
+    scale_mode: ScaleMode,
+    window_width: usize,
+    window_height: usize,
+    buffer_width: usize,
+    buffer_height: usize,
+    rect: Rect,
+) -> Option<(i32, i32, i32, i32)> {
+    if buffer_width == 0 || buffer_height == 0 {
+        return None;
+    }
+
+    let (x, y, width, height) = image_rect(
+        scale_mode,
+        window_width,
+        window_height,
+        buffer_width,
+        buffer_height,
+    );
+    let scale_x = width as f32 / buffer_width as f32;
+    let scale_y = height as f32 / buffer_height as f32;
+
+    let left = (x as f32 + rect.x as f32 * scale_x).floor() as isize - 1;
+    let top = (y as f32 + rect.y as f32 * scale_y).floor() as isize - 1;
+    let right = (x as f32 + (rect.x + rect.width) as f32 * scale_x).ceil() as isize + 1;
+    let bottom = (y as f32 + (rect.y + rect.height) as f32 * scale_y).ceil() as isize + 1;
+
+    let (left, top) = (left.max(0), top.max(0));
+    let right = right.min(window_width as isize);
+    let bottom = bottom.min(window_height as isize);
+
+    if right <= left || bottom <= top {
+        return None;
+    }
+
+    Some((
+        left as i32,
+        top as i32,
+        (right - left) as i32,
+        (bottom - top) as i32,
+    ))
+}
+
+/// Fills the pixels of `target` that are outside of `image` (see `image_rect`) with `fill`.
+pub fn fill_border(
+    target: &mut [u32],
+    width: usize,
+    height: usize,
+    image: (isize, isize, isize, isize),
+    fill: &BorderFill,
+) {
+    let (image_x, image_y, image_width, image_height) = image;
+    let left = image_x.clamp(0, width as isize) as usize;
+    let right = (image_x + image_width).clamp(left as isize, width as isize) as usize;
+
+    for (y, row) in target.chunks_exact_mut(width).take(height).enumerate() {
+        let inside = (y as isize) >= image_y && (y as isize) < image_y + image_height;
+        let (left, right) = if inside {
+            (left, right)
+        } else {
+            (width, width)
+        };
+
+        for x in (0..left).chain(right..width) {
+            row[x] = match *fill {
+                BorderFill::Solid(color) => color,
+                BorderFill::Gradient(top, bottom) => {
+                    let t = y as u32 * 256 / (height.max(2) - 1) as u32;
+                    let channel = |shift: u32| {
+                        let a = (top >> shift) & 0xff;
+                        let b = (bottom >> shift) & 0xff;
+                        ((a * (256 - t) + b * t) >> 8) << shift
+                    };
+                    channel(24) | channel(16) | channel(8) | channel(0)
+                }
+                BorderFill::Tile(ref pattern, tile_width, tile_height) => {
+                    pattern[(y % tile_height) * tile_width + x % tile_width]
+                }
+            };
+        }
+    }
+}
+
+/// Converts a buffer of `A2RGB10` pixels to the regular 8-bit `ARGB` format, which is what every
+/// backend presents. The two bits that are dropped from each channel are turned into an ordered
+/// dither instead of being truncated, which avoids banding in smooth gradients.
+pub fn dither_rgb10(buffer: &[u32], width: usize, height: usize) -> Vec<u32> {
+    // 2x2 Bayer matrix, one threshold for each value of the dropped bits
+    const THRESHOLDS: [[u32; 2]; 2] = [[0, 2], [3, 1]];
+
+    let mut output = Vec::with_capacity(width * height);
+    if width == 0 {
+        return output;
+    }
+
+    for (y, row) in buffer.chunks_exact(width).take(height).enumerate() {
+        for (x, &pixel) in row.iter().enumerate() {
+            let threshold = THRESHOLDS[y & 1][x & 1];
+            let channel = |shift: u32| (((pixel >> shift) & 0x3ff) + threshold).min(0x3ff) >> 2;
+
+            let a = (pixel >> 30) * 0x55;
+            output.push((a << 24) | (channel(20) << 16) | (channel(10) << 8) | channel(0));
+        }
+    }
+
+    output
+}
+
+/// Converts a buffer of linear `[r, g, b, a]` floats to 8-bit `ARGB`. The color channels are
+/// clamped to 0.0..=1.0 and sRGB encoded, alpha is clamped and stored as it is.
+pub fn encode_f32(buffer: &[[f32; 4]], width: usize, height: usize) -> Vec<u32> {
+    fn to_u8(value: f32) -> u32 {
+        // NaN stays NaN through clamp and is cast to 0
+        (value.clamp(0.0, 1.0) * 255.0 + 0.5) as u32
+    }
+
+    fn srgb(linear: f32) -> u32 {
+        let linear = linear.clamp(0.0, 1.0);
+        let encoded = if linear <= 0.003_130_8 {
+            linear * 12.92
+        } else {
+            1.055 * linear.powf(1.0 / 2.4) - 0.055
+        };
+        to_u8(encoded)
+    }
+
+    buffer[..width * height]
+        .iter()
+        .map(|&[r, g, b, a]| (to_u8(a) << 24) | (srgb(r) << 16) | (srgb(g) << 8) | srgb(b))
+        .collect()
+}
+
+/// Multiplies the color channels of an `0xAARRGGBB` pixel by its alpha.
+pub fn premultiply(pixel: u32) -> u32 {
+    let a = pixel >> 24;
+    let channel = |shift: u32| (((pixel >> shift) & 0xff) * a / 255) << shift;
+    (a << 24) | channel(16) | channel(8) | channel(0)
+}
+
+/// Converts a buffer in `format` to the `0xAARRGGBB` layout the backends work with, buffers that
+/// are already in that layout are returned as they are.
+pub fn to_argb(buffer: &[u32], format: PixelFormat) -> Cow<'_, [u32]> {
+    let convert: fn(u32) -> u32 = match format {
+        PixelFormat::Argb8888 => return Cow::Borrowed(buffer),
+        PixelFormat::Xrgb8888 => |pixel| pixel | 0xff00_0000,
+        PixelFormat::Bgra8888 => u32::swap_bytes,
+    };
+
+    Cow::Owned(buffer.iter().map(|&pixel| convert(pixel)).collect())
+}
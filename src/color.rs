@@ -0,0 +1,111 @@
+//! Helpers for building and taking apart the pixel values used by the window buffer.
+//!
+//! Each pixel in the buffer given to `Window::update_with_buffer` is a `u32` laid out as
+//! `0xAARRGGBB`. The alpha channel is ignored unless the window was created with
+//! `WindowOptions::transparency` enabled.
+
+/// Packs the given alpha, red, green and blue channels into a buffer pixel.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(minifb::argb(0x80, 0x11, 0x22, 0x33), 0x8011_2233);
+/// ```
+#[inline]
+pub const fn argb(a: u8, r: u8, g: u8, b: u8) -> u32 {
+    ((a as u32) << 24) | ((r as u32) << 16) | ((g as u32) << 8) | (b as u32)
+}
+
+/// Packs the given red, green and blue channels into a fully opaque buffer pixel.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(minifb::rgb(0x11, 0x22, 0x33), 0xff11_2233);
+/// ```
+#[inline]
+pub const fn rgb(r: u8, g: u8, b: u8) -> u32 {
+    argb(0xff, r, g, b)
+}
+
+/// Splits a buffer pixel into its `(alpha, red, green, blue)` channels.
+///
+/// # Examples
+///
+/// ```
+/// assert_eq!(minifb::unpack(0x8011_2233), (0x80, 0x11, 0x22, 0x33));
+/// ```
+#[inline]
+pub const fn unpack(color: u32) -> (u8, u8, u8, u8) {
+    (
+        (color >> 24) as u8,
+        (color >> 16) as u8,
+        (color >> 8) as u8,
+        color as u8,
+    )
+}
+
+/// Converts a buffer pixel into `[r, g, b, a]` bytes, the order used by most image crates.
+///
+/// # Examples
+///
+/// ```
+/// use minifb::color;
+///
+/// assert_eq!(color::to_rgba8(0x8011_2233), [0x11, 0x22, 0x33, 0x80]);
+/// ```
+#[inline]
+pub const fn to_rgba8(color: u32) -> [u8; 4] {
+    let (a, r, g, b) = unpack(color);
+    [r, g, b, a]
+}
+
+/// Converts `[r, g, b, a]` bytes into a buffer pixel.
+///
+/// # Examples
+///
+/// ```
+/// use minifb::color;
+///
+/// assert_eq!(color::from_rgba8([0x11, 0x22, 0x33, 0x80]), 0x8011_2233);
+/// ```
+#[inline]
+pub const fn from_rgba8(rgba: [u8; 4]) -> u32 {
+    argb(rgba[3], rgba[0], rgba[1], rgba[2])
+}
+
+/// Converts a slice of tightly packed RGBA8 bytes into buffer pixels.
+///
+/// Any trailing bytes that don't make up a whole pixel are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use minifb::color;
+///
+/// let bytes = [0x11, 0x22, 0x33, 0xff, 0x44, 0x55, 0x66, 0x00];
+/// assert_eq!(color::from_rgba8_slice(&bytes), vec![0xff11_2233, 0x0044_5566]);
+/// ```
+pub fn from_rgba8_slice(bytes: &[u8]) -> Vec<u32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| from_rgba8([c[0], c[1], c[2], c[3]]))
+        .collect()
+}
+
+/// Converts buffer pixels into a vector of tightly packed RGBA8 bytes.
+///
+/// # Examples
+///
+/// ```
+/// use minifb::color;
+///
+/// let pixels = [0xff11_2233, 0x0044_5566];
+/// assert_eq!(
+///     color::to_rgba8_vec(&pixels),
+///     vec![0x11, 0x22, 0x33, 0xff, 0x44, 0x55, 0x66, 0x00]
+/// );
+/// ```
+pub fn to_rgba8_vec(pixels: &[u32]) -> Vec<u8> {
+    pixels.iter().flat_map(|&p| to_rgba8(p)).collect()
+}
@@ -3,6 +3,29 @@ use std::fmt;
 
 /// Errors that can be returned from various operations
 ///
+/// Most setters of `Window` are best-effort: when the platform can't do what's asked they do
+/// nothing, as the window still works the same. This is the case for hints to the window manager
+/// such as `topmost`, `set_window_level` or `set_skip_taskbar`. Operations whose result the
+/// application relies on return `Error::Unsupported` instead, so it can fall back to something
+/// else. These are:
+///
+/// - `Window::try_set_icon`
+/// - `Window::new_child`
+/// - `Window::new` with `WindowOptions::gl`
+/// - `Window::set_gamma_ramp`
+///
+/// # Examples
+///
+/// ```
+/// use minifb::Error;
+///
+/// let error = Error::Unsupported {
+///     operation: "set_icon",
+///     platform: "Wayland",
+/// };
+/// assert_eq!(error.to_string(), "set_icon is not supported on Wayland");
+/// ```
+///
 pub enum Error {
     /// Returned if menu Menu function isn't supported
     MenusNotSupported,
@@ -12,6 +35,23 @@ pub enum Error {
     WindowCreate(String),
     /// Unable to Update
     UpdateFailed(String),
+    /// The buffer was too small, only its first `rows` of `height` rows were presented. Only
+    /// returned when `Window::set_partial_update` is enabled
+    PartialUpdate {
+        /// Number of rows that were presented
+        rows: usize,
+        /// Height the buffer should have had
+        height: usize,
+    },
+    /// The gamma ramp of the monitor couldn't be set
+    GammaRamp(String),
+    /// The operation isn't supported on the platform the window runs on
+    Unsupported {
+        /// The operation that was attempted, such as `"set_icon"`
+        operation: &'static str,
+        /// The platform or backend, such as `"Wayland"`
+        platform: &'static str,
+    },
 }
 
 impl fmt::Display for Error {
@@ -21,6 +61,14 @@ impl fmt::Display for Error {
             Error::MenuExists(_) => write!(formatter, "Menu already exists"),
             Error::WindowCreate(_) => write!(formatter, "Failed to create window"),
             Error::UpdateFailed(_) => write!(formatter, "Failed to Update"),
+            Error::GammaRamp(_) => write!(formatter, "Failed to set the gamma ramp"),
+            Error::PartialUpdate { rows, height } => {
+                write!(formatter, "Only {} of {} rows were updated", rows, height)
+            }
+            Error::Unsupported {
+                operation,
+                platform,
+            } => write!(formatter, "{} is not supported on {}", operation, platform),
         }
     }
 }
@@ -32,6 +80,9 @@ impl fmt::Debug for Error {
             Error::MenuExists(ref e) => write!(fmt, "{}, {:?}", self, e),
             Error::WindowCreate(ref e) => write!(fmt, "{}, {:?}", self, e),
             Error::UpdateFailed(ref e) => write!(fmt, "{}, {:?}", self, e),
+            Error::GammaRamp(ref e) => write!(fmt, "{}, {:?}", self, e),
+            Error::PartialUpdate { .. } => write!(fmt, "{}", self),
+            Error::Unsupported { .. } => write!(fmt, "{}", self),
         }
     }
 }
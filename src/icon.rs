@@ -10,7 +10,8 @@ use std::{ffi::OsStr, os::windows::prelude::OsStrExt, str::FromStr};
 ///
 /// **Windows**: Icon can be created from a relative path string
 ///
-/// **Linux / X11:** Icon can be created from an ARGB buffer
+/// **Linux / X11:** Icon can be created from an ARGB buffer, or from an `image::RgbaImage`
+/// through `IconImage` when the `image` feature is enabled
 ///
 ///
 #[derive(Clone, Copy, Debug)]
@@ -49,3 +50,62 @@ impl TryFrom<&[u64]> for Icon {
         Ok(Icon::Buffer(value.as_ptr(), value.len() as u32))
     }
 }
+
+///
+/// An icon converted from an `image::RgbaImage`. It owns the pixels in the `_NET_WM_ICON` layout
+/// used by X11: the width and height followed by one ARGB pixel per `u64`. `as_icon` borrows
+/// them as an `Icon::Buffer`, so the `IconImage` has to be kept around while the icon is used.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use minifb::*;
+/// # use std::convert::TryFrom;
+/// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+/// let image = image::RgbaImage::new(32, 32);
+/// let icon = IconImage::try_from(image).unwrap();
+/// window.set_icon(icon.as_icon());
+/// ```
+#[cfg(all(target_os = "linux", feature = "image"))]
+#[derive(Clone, Debug)]
+pub struct IconImage {
+    buffer: Vec<u64>,
+}
+
+#[cfg(all(target_os = "linux", feature = "image"))]
+impl IconImage {
+    /// Returns an `Icon::Buffer` pointing at the converted pixels
+    #[inline]
+    pub fn as_icon(&self) -> Icon {
+        Icon::Buffer(self.buffer.as_ptr(), self.buffer.len() as u32)
+    }
+}
+
+#[cfg(all(target_os = "linux", feature = "image"))]
+impl TryFrom<image::RgbaImage> for IconImage {
+    type Error = &'static str;
+
+    fn try_from(value: image::RgbaImage) -> Result<Self, Self::Error> {
+        let (width, height) = value.dimensions();
+        if width == 0 || height == 0 {
+            return Err("Icon image cannot be empty!");
+        }
+
+        let len = (width as usize)
+            .checked_mul(height as usize)
+            .and_then(|pixels| pixels.checked_add(2))
+            .filter(|&len| u32::try_from(len).is_ok())
+            .ok_or("Icon image is too large!")?;
+
+        let mut buffer = Vec::with_capacity(len);
+        buffer.push(width as u64);
+        buffer.push(height as u64);
+        buffer.extend(
+            value
+                .pixels()
+                .map(|p| u64::from(crate::color::from_rgba8(p.0))),
+        );
+
+        Ok(IconImage { buffer })
+    }
+}
@@ -125,3 +125,346 @@ pub enum Key {
 
     Count = 107,
 }
+
+impl Key {
+    /// Returns true if the key types a character, see `to_char`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minifb::Key;
+    ///
+    /// assert!(Key::A.is_printable());
+    /// assert!(Key::Space.is_printable());
+    /// assert!(!Key::Left.is_printable());
+    /// ```
+    pub fn is_printable(&self) -> bool {
+        self.to_char(false).is_some()
+    }
+
+    /// Returns the character the key types on a US keyboard layout, or `None` for keys that
+    /// don't type a character such as the arrow and function keys. Enter, Tab and Backspace
+    /// count as control keys.
+    ///
+    /// This doesn't take the active keyboard layout into account, use
+    /// `Window::get_text_input` or `InputCallback::add_char` for text entry in other layouts.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minifb::Key;
+    ///
+    /// assert_eq!(Key::A.to_char(false), Some('a'));
+    /// assert_eq!(Key::A.to_char(true), Some('A'));
+    /// assert_eq!(Key::Key1.to_char(true), Some('!'));
+    /// assert_eq!(Key::F1.to_char(false), None);
+    /// ```
+    pub fn to_char(&self, shift: bool) -> Option<char> {
+        let (lower, upper) = match *self {
+            Key::Key0 => ('0', ')'),
+            Key::Key1 => ('1', '!'),
+            Key::Key2 => ('2', '@'),
+            Key::Key3 => ('3', '#'),
+            Key::Key4 => ('4', '$'),
+            Key::Key5 => ('5', '%'),
+            Key::Key6 => ('6', '^'),
+            Key::Key7 => ('7', '&'),
+            Key::Key8 => ('8', '*'),
+            Key::Key9 => ('9', '('),
+
+            Key::A
+            | Key::B
+            | Key::C
+            | Key::D
+            | Key::E
+            | Key::F
+            | Key::G
+            | Key::H
+            | Key::I
+            | Key::J
+            | Key::K
+            | Key::L
+            | Key::M
+            | Key::N
+            | Key::O
+            | Key::P
+            | Key::Q
+            | Key::R
+            | Key::S
+            | Key::T
+            | Key::U
+            | Key::V
+            | Key::W
+            | Key::X
+            | Key::Y
+            | Key::Z => {
+                let c = (b'a' + (*self as u8 - Key::A as u8)) as char;
+                (c, c.to_ascii_uppercase())
+            }
+
+            Key::Apostrophe => ('\'', '"'),
+            Key::Backquote => ('`', '~'),
+            Key::Backslash => ('\\', '|'),
+            Key::Comma => (',', '<'),
+            Key::Equal => ('=', '+'),
+            Key::LeftBracket => ('[', '{'),
+            Key::Minus => ('-', '_'),
+            Key::Period => ('.', '>'),
+            Key::RightBracket => (']', '}'),
+            Key::Semicolon => (';', ':'),
+            Key::Slash => ('/', '?'),
+            Key::Space => (' ', ' '),
+
+            Key::NumPad0 => ('0', '0'),
+            Key::NumPad1 => ('1', '1'),
+            Key::NumPad2 => ('2', '2'),
+            Key::NumPad3 => ('3', '3'),
+            Key::NumPad4 => ('4', '4'),
+            Key::NumPad5 => ('5', '5'),
+            Key::NumPad6 => ('6', '6'),
+            Key::NumPad7 => ('7', '7'),
+            Key::NumPad8 => ('8', '8'),
+            Key::NumPad9 => ('9', '9'),
+            Key::NumPadDot => ('.', '.'),
+            Key::NumPadSlash => ('/', '/'),
+            Key::NumPadAsterisk => ('*', '*'),
+            Key::NumPadMinus => ('-', '-'),
+            Key::NumPadPlus => ('+', '+'),
+
+            _ => return None,
+        };
+
+        Some(if shift { upper } else { lower })
+    }
+}
+
+// Number of `u64`s a `KeySet` needs for one bit per key
+const KEY_SET_WORDS: usize = (Key::Count as usize).div_ceil(64);
+
+/// A set of keys stored as a bitmask, which makes it cheap to copy, compare and send over the
+/// network. `Window::get_key_set` returns the keys that are currently down as a `KeySet`.
+///
+/// # Examples
+///
+/// ```
+/// use minifb::{Key, KeySet};
+///
+/// let mut keys = KeySet::new();
+/// keys.insert(Key::W);
+/// keys.insert(Key::Space);
+///
+/// assert!(keys.contains(Key::W));
+/// assert_eq!(KeySet::from_bits(keys.to_bits()), keys);
+/// ```
+#[derive(Debug, Default, Eq, PartialEq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeySet {
+    bits: [u64; KEY_SET_WORDS],
+}
+
+impl KeySet {
+    /// Creates an empty set
+    #[inline]
+    pub const fn new() -> KeySet {
+        KeySet {
+            bits: [0; KEY_SET_WORDS],
+        }
+    }
+
+    /// Creates a set from the bits returned by `to_bits`. Bits that don't belong to a key are
+    /// ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minifb::{Key, KeySet};
+    ///
+    /// let mut bits = KeySet::new().to_bits();
+    /// bits.iter_mut().for_each(|word| *word = u64::MAX);
+    ///
+    /// let all = KeySet::from_bits(bits);
+    /// assert_eq!(all.len(), Key::Count as usize);
+    /// ```
+    #[inline]
+    pub const fn from_bits(mut bits: [u64; KEY_SET_WORDS]) -> KeySet {
+        // Only the keys before `Key::Count` exist, which leaves the last word partly unused
+        let used = Key::Count as usize % 64;
+        if used != 0 {
+            bits[KEY_SET_WORDS - 1] &= (1 << used) - 1;
+        }
+        KeySet { bits }
+    }
+
+    /// Returns the set as a bitmask where bit `n` is set if the key with the value `n` is in it
+    #[inline]
+    pub const fn to_bits(&self) -> [u64; KEY_SET_WORDS] {
+        self.bits
+    }
+
+    /// Adds a key to the set
+    #[inline]
+    pub fn insert(&mut self, key: Key) {
+        let index = key as usize;
+        self.bits[index / 64] |= 1 << (index % 64);
+    }
+
+    /// Removes a key from the set
+    #[inline]
+    pub fn remove(&mut self, key: Key) {
+        let index = key as usize;
+        self.bits[index / 64] &= !(1 << (index % 64));
+    }
+
+    /// Returns true if the key is in the set
+    #[inline]
+    pub const fn contains(&self, key: Key) -> bool {
+        let index = key as usize;
+        self.bits[index / 64] & (1 << (index % 64)) != 0
+    }
+
+    /// Returns the number of keys in the set
+    #[inline]
+    pub const fn len(&self) -> usize {
+        let mut len = 0;
+        let mut i = 0;
+        while i < KEY_SET_WORDS {
+            len += self.bits[i].count_ones() as usize;
+            i += 1;
+        }
+        len
+    }
+
+    /// Returns true if the set doesn't contain any keys
+    #[inline]
+    pub const fn is_empty(&self) -> bool {
+        let mut i = 0;
+        while i < KEY_SET_WORDS {
+            if self.bits[i] != 0 {
+                return false;
+            }
+            i += 1;
+        }
+        true
+    }
+
+    /// Returns the keys that are in this set but not in `other`. With the keys of two updates
+    /// this gives the keys that were pressed or released in between.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use minifb::{Key, KeySet};
+    ///
+    /// let before: KeySet = [Key::A, Key::B].iter().copied().collect();
+    /// let after: KeySet = [Key::B, Key::C].iter().copied().collect();
+    ///
+    /// assert_eq!(after.difference(&before).iter().collect::<Vec<_>>(), vec![Key::C]);
+    /// assert_eq!(before.difference(&after).iter().collect::<Vec<_>>(), vec![Key::A]);
+    /// ```
+    #[inline]
+    pub const fn difference(&self, other: &KeySet) -> KeySet {
+        let mut bits = self.bits;
+        let mut i = 0;
+        while i < KEY_SET_WORDS {
+            bits[i] &= !other.bits[i];
+            i += 1;
+        }
+        KeySet { bits }
+    }
+
+    /// Returns an iterator over the keys in the set, in the order of their values
+    pub fn iter(&self) -> impl Iterator<Item = Key> {
+        let bits = self.bits;
+
+        (0..Key::Count as usize)
+            .filter(move |&index| bits[index / 64] & (1 << (index % 64)) != 0)
+            // The indices are below `Key::Count` so they're all valid keys
+            .map(|index| unsafe { std::mem::transmute::<u8, Key>(index as u8) })
+    }
+}
+
+impl std::iter::FromIterator<Key> for KeySet {
+    fn from_iter<I: IntoIterator<Item = Key>>(iter: I) -> KeySet {
+        let mut set = KeySet::new();
+        for key in iter {
+            set.insert(key);
+        }
+        set
+    }
+}
+
+/// A key that triggers an action of a `KeyMap`, either a `Key` or the raw scancode of a physical
+/// key as passed to `InputCallback::set_key_state_scancode`.
+#[derive(Debug, Eq, PartialEq, Hash, Clone, Copy)]
+pub enum KeyBinding {
+    Key(Key),
+    Scancode(u32),
+}
+
+impl From<Key> for KeyBinding {
+    #[inline]
+    fn from(key: Key) -> KeyBinding {
+        KeyBinding::Key(key)
+    }
+}
+
+/// Maps the names of actions, such as `"jump"`, to the keys that trigger them. An action can have
+/// any number of keys and is down while one of them is. Give it to the window with
+/// `Window::set_key_map` and check the actions with `Window::is_action_down`.
+///
+/// # Examples
+///
+/// ```
+/// use minifb::{Key, KeyBinding, KeyMap};
+///
+/// let mut map = KeyMap::new();
+/// map.bind("jump", Key::Space);
+/// map.bind("jump", Key::W);
+/// map.bind("crouch", KeyBinding::Scancode(0x1d));
+///
+/// assert_eq!(map.bindings("jump"), &[KeyBinding::Key(Key::Space), KeyBinding::Key(Key::W)]);
+///
+/// // Rebind the action
+/// map.unbind("jump");
+/// map.bind("jump", Key::Up);
+/// assert_eq!(map.bindings("jump"), &[KeyBinding::Key(Key::Up)]);
+/// ```
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct KeyMap {
+    actions: std::collections::HashMap<String, Vec<KeyBinding>>,
+}
+
+impl KeyMap {
+    /// Creates a map without any actions
+    #[inline]
+    pub fn new() -> KeyMap {
+        KeyMap::default()
+    }
+
+    /// Adds a key to the keys that trigger the action
+    pub fn bind<B: Into<KeyBinding>>(&mut self, action: &str, binding: B) {
+        let binding = binding.into();
+        let bindings = self.actions.entry(action.to_owned()).or_default();
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Removes all keys of the action
+    #[inline]
+    pub fn unbind(&mut self, action: &str) {
+        self.actions.remove(action);
+    }
+
+    /// Returns the keys that trigger the action, empty if it has none
+    pub fn bindings(&self, action: &str) -> &[KeyBinding] {
+        self.actions.get(action).map_or(&[], |b| b.as_slice())
+    }
+
+    /// Returns an iterator over the actions and their keys, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &[KeyBinding])> {
+        self.actions
+            .iter()
+            .map(|(action, bindings)| (action.as_str(), bindings.as_slice()))
+    }
+}
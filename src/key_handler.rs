@@ -1,7 +1,7 @@
 #[cfg(feature = "web")]
 extern crate instant;
 
-use crate::{InputCallback, Key, KeyRepeat};
+use crate::{InputCallback, Key, KeyBinding, KeyMap, KeyRepeat, Modifiers};
 #[cfg(feature = "web")]
 use instant::{Duration, Instant};
 use std::mem;
@@ -15,10 +15,40 @@ pub struct KeyHandler {
     keys: [bool; 512],
     keys_prev: [bool; 512],
     keys_down_duration: [f32; 512],
+    // Keys that are currently down, kept up to date so it can be borrowed without allocating
+    keys_down: Vec<Key>,
+    // Keys that went down while processing events, repeats don't count as they don't change the
+    // state. These are moved to `keys_just_pressed` once all events have been processed.
+    keys_pressed_pending: Vec<Key>,
+    keys_just_pressed: Vec<Key>,
+    // Text entered while processing events, moved to `text_input` the same way as the keys above
+    text_pending: String,
+    text_input: String,
     key_repeat_delay: f32,
     key_repeat_rate: f32,
+    // Delay and rate of keys that don't follow the ones above
+    key_repeat_overrides: [Option<(f32, f32)>; 512],
+    // Only used to tell the callback when the layout changes
+    keyboard_layout: Option<String>,
+    // Only used to tell the callback when a display is connected or disconnected
+    display_count: Option<usize>,
+    // Timestamp of the latest input event and of the latest change of each key
+    event_time: Option<Duration>,
+    key_times: [Option<Duration>; 512],
+    // Last raw millisecond timestamp and how often it wrapped around, see `set_event_time_ms`
+    event_time_raw: Option<u32>,
+    event_time_wraps: u64,
+    // Set while the keys that are already down are reported, see `begin_held_keys`
+    reporting_held: bool,
+    // Scancodes of the physical keys that are down, only needed for the key map
+    scancodes_down: Vec<u32>,
+    key_map: KeyMap,
 }
 
+// Down duration of keys that were already held when they were reported, these never count as
+// pressed or repeating
+const HELD_DURATION: f32 = f32::INFINITY;
+
 impl KeyHandler {
     pub fn new() -> KeyHandler {
         KeyHandler {
@@ -26,28 +56,106 @@ impl KeyHandler {
             keys: [false; 512],
             keys_prev: [false; 512],
             keys_down_duration: [-1.0; 512],
+            keys_down: Vec::with_capacity(16),
+            keys_pressed_pending: Vec::with_capacity(16),
+            keys_just_pressed: Vec::with_capacity(16),
+            text_pending: String::new(),
+            text_input: String::new(),
             prev_time: Instant::now(),
             delta_time: Duration::from_secs(0),
             key_repeat_delay: 0.250,
             key_repeat_rate: 0.050,
+            key_repeat_overrides: [None; 512],
+            keyboard_layout: None,
+            display_count: None,
+            event_time: None,
+            key_times: [None; 512],
+            event_time_raw: None,
+            event_time_wraps: 0,
+            reporting_held: false,
+            scancodes_down: Vec::with_capacity(16),
+            key_map: KeyMap::new(),
         }
     }
 
     #[inline]
     pub fn set_key_state(&mut self, key: Key, state: bool) {
+        if self.reporting_held {
+            if state {
+                self.set_key_held(key);
+            }
+            return;
+        }
+
+        if self.keys[key as usize] != state {
+            if state {
+                self.keys_down.push(key);
+                if !self.keys_pressed_pending.contains(&key) {
+                    self.keys_pressed_pending.push(key);
+                }
+            } else {
+                self.keys_down.retain(|&k| k != key);
+            }
+        }
+
         self.keys[key as usize] = state;
+        self.key_times[key as usize] = self.event_time;
         if let Some(cb) = &mut self.key_callback {
             cb.set_key_state(key, state);
         }
     }
 
+    /// Passes the raw scancode of a key event on to the input callback.
+    #[inline]
+    pub fn set_scancode_state(&mut self, scancode: u32, state: bool) {
+        if !state {
+            self.scancodes_down.retain(|&s| s != scancode);
+        } else if !self.scancodes_down.contains(&scancode) {
+            self.scancodes_down.push(scancode);
+        }
+
+        if self.reporting_held {
+            return;
+        }
+
+        if let Some(cb) = &mut self.key_callback {
+            cb.set_key_state_scancode(scancode, state);
+        }
+    }
+
+    /// Starts reporting the keys that are already down, for example when the window opens. Until
+    /// `end_held_keys` is called, keys passed to `set_key_state` count as down but not as
+    /// pressed, and they don't repeat until they have been released. Neither the keys nor any
+    /// text they produce are passed on to the input callback.
+    #[inline]
+    pub fn begin_held_keys(&mut self) {
+        self.reporting_held = true;
+    }
+
+    #[inline]
+    pub fn end_held_keys(&mut self) {
+        self.reporting_held = false;
+    }
+
+    fn set_key_held(&mut self, key: Key) {
+        let index = key as usize;
+        if self.keys[index] {
+            return;
+        }
+
+        self.keys[index] = true;
+        self.keys_prev[index] = true;
+        self.keys_down_duration[index] = HELD_DURATION;
+        self.keys_down.push(key);
+    }
+
     pub fn get_keys(&self) -> Vec<Key> {
         let mut keys: Vec<Key> = Vec::new();
 
         for (index, i) in self.keys.iter().enumerate() {
             if *i {
                 unsafe {
-                    keys.push(mem::transmute(index as u8));
+                    keys.push(mem::transmute::<u8, Key>(index as u8));
                 }
             }
         }
@@ -55,6 +163,47 @@ impl KeyHandler {
         keys
     }
 
+    #[inline]
+    pub fn keys_down(&self) -> &[Key] {
+        &self.keys_down
+    }
+
+    #[inline]
+    pub fn get_keys_just_pressed(&self) -> Vec<Key> {
+        self.keys_just_pressed.clone()
+    }
+
+    /// Publishes the keys pressed and the text entered since the previous call, has to be called
+    /// after the events of an update have been processed.
+    pub fn update_just_pressed(&mut self) {
+        mem::swap(&mut self.keys_just_pressed, &mut self.keys_pressed_pending);
+        self.keys_pressed_pending.clear();
+        mem::swap(&mut self.text_input, &mut self.text_pending);
+        self.text_pending.clear();
+    }
+
+    /// Records a character entered by the user and passes it on to the input callback. Control
+    /// characters such as backspace are only passed on, they aren't part of the text input.
+    pub fn add_char(&mut self, code_point: u32) {
+        if self.reporting_held {
+            return;
+        }
+
+        match char::from_u32(code_point) {
+            Some(c) if !c.is_control() => self.text_pending.push(c),
+            _ => (),
+        }
+
+        if let Some(cb) = &mut self.key_callback {
+            cb.add_char(code_point);
+        }
+    }
+
+    #[inline]
+    pub fn get_text_input(&self) -> String {
+        self.text_input.clone()
+    }
+
     pub fn update(&mut self) {
         let current_time = Instant::now();
         self.delta_time = self.prev_time.elapsed();
@@ -75,6 +224,84 @@ impl KeyHandler {
         }
     }
 
+    /// Sets the timestamp of the input event that is being processed. The timestamps never go
+    /// back, an event that is older than the one before it gets the same timestamp.
+    #[inline]
+    pub fn set_event_time(&mut self, time: Duration) {
+        self.event_time = Some(match self.event_time {
+            Some(prev) if prev > time => prev,
+            _ => time,
+        });
+    }
+
+    /// Sets the timestamp of the input event that is being processed from a 32-bit millisecond
+    /// clock, as used by X11, Wayland and Windows. These wrap around after about 49 days, which
+    /// is undone here so the timestamps keep increasing.
+    pub fn set_event_time_ms(&mut self, time: u32) {
+        let mut wraps = Some(self.event_time_wraps);
+
+        match self.event_time_raw {
+            // Events can arrive slightly out of order, only a large jump back is a wrap around
+            Some(prev) if time < prev && prev - time > u32::MAX / 2 => {
+                self.event_time_wraps += 1;
+                wraps = Some(self.event_time_wraps);
+                self.event_time_raw = Some(time);
+            }
+            // A large jump forward is a late event from before the last wrap around, which
+            // mustn't move the clock to the next one
+            Some(prev) if time > prev && time - prev > u32::MAX / 2 => {
+                wraps = self.event_time_wraps.checked_sub(1);
+            }
+            _ => self.event_time_raw = Some(time),
+        }
+
+        // An event from before the first one is clamped to it by `set_event_time`
+        let ms = wraps.map_or(0, |wraps| (wraps << 32) | u64::from(time));
+        self.set_event_time(Duration::from_millis(ms));
+    }
+
+    #[inline]
+    pub fn get_event_time(&self) -> Option<Duration> {
+        self.event_time
+    }
+
+    #[inline]
+    pub fn get_key_time(&self, key: Key) -> Option<Duration> {
+        self.key_times[key as usize]
+    }
+
+    /// Records the active keyboard layout, the callback is told when it differs from a layout
+    /// that was recorded before.
+    pub fn set_keyboard_layout(&mut self, layout: Option<String>) {
+        if layout == self.keyboard_layout {
+            return;
+        }
+
+        let changed = self.keyboard_layout.is_some();
+        self.keyboard_layout = layout;
+
+        if let (true, Some(cb), Some(name)) =
+            (changed, &mut self.key_callback, &self.keyboard_layout)
+        {
+            cb.layout_changed(name);
+        }
+    }
+
+    /// Records the number of displays, the callback is told when it differs from a count that
+    /// was recorded before.
+    pub fn set_display_count(&mut self, count: usize) {
+        if Some(count) == self.display_count {
+            return;
+        }
+
+        let changed = self.display_count.is_some();
+        self.display_count = Some(count);
+
+        if let (true, Some(cb)) = (changed, &mut self.key_callback) {
+            cb.displays_changed();
+        }
+    }
+
     pub fn set_input_callback(&mut self, callback: Box<dyn InputCallback>) {
         self.key_callback = Some(callback);
     }
@@ -85,8 +312,8 @@ impl KeyHandler {
         for (index, i) in self.keys.iter().enumerate() {
             if *i {
                 unsafe {
-                    if Self::key_pressed(self, index as usize, repeat) {
-                        keys.push(mem::transmute(index as u8));
+                    if Self::key_pressed(self, index, repeat) {
+                        keys.push(mem::transmute::<u8, Key>(index as u8));
                     }
                 }
             }
@@ -101,7 +328,7 @@ impl KeyHandler {
         for (idx, is_down) in self.keys.iter().enumerate() {
             if !(*is_down) && self.is_key_index_released(idx) {
                 unsafe {
-                    keys.push(mem::transmute(idx as u8));
+                    keys.push(mem::transmute::<u8, Key>(idx as u8));
                 }
             }
         }
@@ -114,6 +341,30 @@ impl KeyHandler {
         self.keys[key as usize]
     }
 
+    pub fn modifiers(&self) -> Modifiers {
+        Modifiers {
+            shift: self.is_key_down(Key::LeftShift) || self.is_key_down(Key::RightShift),
+            ctrl: self.is_key_down(Key::LeftCtrl) || self.is_key_down(Key::RightCtrl),
+            alt: self.is_key_down(Key::LeftAlt) || self.is_key_down(Key::RightAlt),
+            logo: self.is_key_down(Key::LeftSuper) || self.is_key_down(Key::RightSuper),
+        }
+    }
+
+    #[inline]
+    pub fn set_key_map(&mut self, key_map: KeyMap) {
+        self.key_map = key_map;
+    }
+
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.key_map
+            .bindings(action)
+            .iter()
+            .any(|&binding| match binding {
+                KeyBinding::Key(key) => self.is_key_down(key),
+                KeyBinding::Scancode(scancode) => self.scancodes_down.contains(&scancode),
+            })
+    }
+
     #[inline]
     pub fn set_key_repeat_delay(&mut self, delay: f32) {
         self.key_repeat_delay = delay;
@@ -124,6 +375,13 @@ impl KeyHandler {
         self.key_repeat_rate = rate;
     }
 
+    /// Gives the keys their own repeat delay and rate instead of the ones shared by all keys.
+    pub fn set_key_repeat_for(&mut self, keys: &[Key], delay: f32, rate: f32) {
+        for &key in keys {
+            self.key_repeat_overrides[key as usize] = Some((delay, rate));
+        }
+    }
+
     pub fn key_pressed(&self, index: usize, repeat: KeyRepeat) -> bool {
         let t = self.keys_down_duration[index];
 
@@ -131,10 +389,15 @@ impl KeyHandler {
             return true;
         }
 
-        if repeat == KeyRepeat::Yes && t > self.key_repeat_delay {
+        if t == HELD_DURATION {
+            return false;
+        }
+
+        let (delay, rate) = self.key_repeat_overrides[index]
+            .unwrap_or((self.key_repeat_delay, self.key_repeat_rate));
+
+        if repeat == KeyRepeat::Yes && t > delay {
             let delta_time = self.delta_time.as_secs_f32();
-            let delay = self.key_repeat_delay;
-            let rate = self.key_repeat_rate;
             if (((t - delay) % rate) > rate * 0.5)
                 != (((t - delay - delta_time) % rate) > rate * 0.5)
             {
@@ -150,6 +413,13 @@ impl KeyHandler {
         Self::key_pressed(self, key as usize, repeat)
     }
 
+    /// True when the key repeats during this update, the initial press doesn't count.
+    #[inline]
+    pub fn is_key_repeat(&self, key: Key) -> bool {
+        let index = key as usize;
+        self.keys_down_duration[index] > 0.0 && self.key_pressed(index, KeyRepeat::Yes)
+    }
+
     #[inline]
     pub fn is_key_released(&self, key: Key) -> bool {
         let idx = key as usize;
@@ -161,3 +431,115 @@ impl KeyHandler {
         self.keys_prev[idx] && !self.keys[idx]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Keys held while the window opens, as the backends report them
+    fn handler_with_held(key: Key) -> KeyHandler {
+        let mut handler = KeyHandler::new();
+        handler.begin_held_keys();
+        handler.set_key_state(key, true);
+        handler.end_held_keys();
+        handler.update_just_pressed();
+        handler.update();
+        handler
+    }
+
+    #[test]
+    fn held_keys_are_down_but_not_pressed() {
+        let handler = handler_with_held(Key::Enter);
+
+        assert!(handler.is_key_down(Key::Enter));
+        assert_eq!(handler.get_keys(), vec![Key::Enter]);
+        assert!(handler.get_keys_pressed(KeyRepeat::No).is_empty());
+        assert!(handler.get_keys_pressed(KeyRepeat::Yes).is_empty());
+        assert!(handler.get_keys_just_pressed().is_empty());
+    }
+
+    #[test]
+    fn held_keys_never_repeat() {
+        let mut handler = handler_with_held(Key::Enter);
+        handler.set_key_repeat_delay(0.0);
+        handler.set_key_repeat_rate(0.001);
+
+        for _ in 0..10 {
+            std::thread::sleep(Duration::from_millis(1));
+            handler.update();
+            assert!(!handler.is_key_pressed(Key::Enter, KeyRepeat::Yes));
+            assert!(!handler.is_key_repeat(Key::Enter));
+        }
+    }
+
+    #[test]
+    fn held_keys_are_pressed_after_release() {
+        let mut handler = handler_with_held(Key::Enter);
+
+        handler.set_key_state(Key::Enter, false);
+        assert!(!handler.is_key_down(Key::Enter));
+        assert_eq!(handler.get_keys_released(), vec![Key::Enter]);
+        handler.update_just_pressed();
+        handler.update();
+
+        handler.set_key_state(Key::Enter, true);
+        handler.update_just_pressed();
+        handler.update();
+        assert_eq!(handler.get_keys_pressed(KeyRepeat::No), vec![Key::Enter]);
+        assert_eq!(handler.get_keys_just_pressed(), vec![Key::Enter]);
+    }
+
+    #[test]
+    fn keys_pressed_in_the_window_are_pressed() {
+        let mut handler = KeyHandler::new();
+        handler.set_key_state(Key::Space, true);
+        handler.update_just_pressed();
+        handler.update();
+
+        assert_eq!(handler.get_keys_pressed(KeyRepeat::No), vec![Key::Space]);
+        assert_eq!(handler.get_keys_just_pressed(), vec![Key::Space]);
+    }
+
+    #[test]
+    fn event_times_never_decrease() {
+        let mut handler = KeyHandler::new();
+
+        handler.set_event_time_ms(1000);
+        handler.set_event_time_ms(990);
+        assert_eq!(handler.get_event_time(), Some(Duration::from_millis(1000)));
+
+        // The 32-bit clock wrapping around keeps counting up
+        handler.set_event_time_ms(u32::MAX / 2);
+        handler.set_event_time_ms(u32::MAX - 10);
+        handler.set_event_time_ms(5);
+        assert_eq!(
+            handler.get_event_time(),
+            Some(Duration::from_millis((1 << 32) + 5))
+        );
+
+        // A late event from before the wrap around belongs to the previous one, so it neither
+        // jumps ahead nor counts as another wrap around
+        handler.set_event_time_ms(u32::MAX - 1);
+        assert_eq!(
+            handler.get_event_time(),
+            Some(Duration::from_millis((1 << 32) + 5))
+        );
+        handler.set_event_time_ms(10);
+        assert_eq!(
+            handler.get_event_time(),
+            Some(Duration::from_millis((1 << 32) + 10))
+        );
+    }
+
+    #[test]
+    fn late_event_before_the_first_one_is_clamped() {
+        let mut handler = KeyHandler::new();
+
+        handler.set_event_time_ms(5);
+        handler.set_event_time_ms(u32::MAX - 1);
+        assert_eq!(handler.get_event_time(), Some(Duration::from_millis(5)));
+
+        handler.set_event_time_ms(20);
+        assert_eq!(handler.get_event_time(), Some(Duration::from_millis(20)));
+    }
+}
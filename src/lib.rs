@@ -12,6 +12,7 @@ extern crate dlib;
 
 use std::fmt;
 use std::os::raw;
+use std::path::Path;
 
 /// Scale will scale the frame buffer and the window that is being sent in when calling the update
 /// function. This is useful if you for example want to display a 320 x 256 window on a screen with
@@ -56,6 +57,19 @@ pub enum MouseButton {
     Right,
 }
 
+/// The modifier keys that are held down, either side of the keyboard counts
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct Modifiers {
+    /// Left or right shift
+    pub shift: bool,
+    /// Left or right control
+    pub ctrl: bool,
+    /// Left or right alt, option on MacOS
+    pub alt: bool,
+    /// Left or right super, the Windows key or command on MacOS
+    pub logo: bool,
+}
+
 /// The different modes that can be used to decide how mouse coordinates should be handled
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum MouseMode {
@@ -67,6 +81,21 @@ pub enum MouseMode {
     Discard,
 }
 
+/// Stacking level of a window, windows on a higher level are shown above windows on a lower one
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum WindowLevel {
+    /// Below all normal windows, like a desktop widget
+    Bottom,
+    /// Regular application windows
+    Normal,
+    /// Above normal windows, like tool palettes (same as `topmost(true)`)
+    Floating,
+    /// Above floating windows, used for modal panels and dialogs
+    ModalPanel,
+    /// Above nearly everything else, used for screen savers and overlays
+    ScreenSaver,
+}
+
 /// Different style of cursors that can be used
 #[derive(PartialEq, Clone, Copy, Debug)]
 pub enum CursorStyle {
@@ -86,6 +115,146 @@ pub enum CursorStyle {
     ResizeUpDown,
     /// Resize in all directions
     ResizeAll,
+    /// Busy cursor shown while the application can't take input, uses `Arrow` on macOS
+    Wait,
+    /// Arrow with a busy indicator, shown while work happens in the background. Uses `Arrow`
+    /// on macOS
+    Progress,
+    /// Arrow with a question mark, uses `Arrow` on macOS
+    Help,
+    /// Indicates that the hovered item can't be used or dropped onto
+    NotAllowed,
+    /// Magnifying glass with a plus sign, uses `Crosshair` on Windows, macOS and cursor themes
+    /// without it
+    ZoomIn,
+    /// Magnifying glass with a minus sign, uses `Crosshair` on Windows, macOS and cursor themes
+    /// without it
+    ZoomOut,
+}
+
+impl CursorStyle {
+    /// The closest cursor that every platform has, used when a platform or cursor theme lacks
+    /// this one.
+    #[allow(dead_code)]
+    fn fallback(self) -> CursorStyle {
+        match self {
+            CursorStyle::ZoomIn | CursorStyle::ZoomOut => CursorStyle::Crosshair,
+            CursorStyle::Wait
+            | CursorStyle::Progress
+            | CursorStyle::Help
+            | CursorStyle::NotAllowed => CursorStyle::Arrow,
+            style => style,
+        }
+    }
+}
+
+/// A rectangle of pixels within a buffer
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct Rect {
+    /// Left edge of the rectangle
+    pub x: usize,
+    /// Top edge of the rectangle
+    pub y: usize,
+    /// Width of the rectangle
+    pub width: usize,
+    /// Height of the rectangle
+    pub height: usize,
+}
+
+/// A monitor connected to the computer
+#[derive(PartialEq, Clone, Debug)]
+pub struct Monitor {
+    /// Name of the monitor as given by the system, for example the name of the output port
+    pub name: String,
+    /// Left edge of the monitor on the desktop
+    pub x: isize,
+    /// Top edge of the monitor on the desktop
+    pub y: isize,
+    /// Width of the monitor in pixels
+    pub width: usize,
+    /// Height of the monitor in pixels
+    pub height: usize,
+    /// Refresh rate in Hz, if known
+    pub refresh_rate: Option<f32>,
+}
+
+/// The gamma ramp of a monitor, as returned by `Window::get_gamma_ramp`. Each channel maps the
+/// 256 input levels to output levels from 0 to 65535.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct GammaRamp {
+    /// Output levels of the red channel
+    pub red: [u16; 256],
+    /// Output levels of the green channel
+    pub green: [u16; 256],
+    /// Output levels of the blue channel
+    pub blue: [u16; 256],
+}
+
+/// The geometry of a window as returned by `save_state`, to be restored with `restore_state`
+/// the next time the application runs. Can be serialized with the `serde` feature.
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WindowState {
+    /// Position of the window, as returned by `get_position`
+    pub position: (isize, isize),
+    /// Size of the window, as returned by `get_size`
+    pub size: (usize, usize),
+    /// If the window was maximized
+    pub maximized: bool,
+    /// Name of the monitor the window was on, if known
+    pub monitor: Option<String>,
+}
+
+/// Why a window was closed, as reported by `close_reason`
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum CloseReason {
+    /// The user closed the window, for example with the close button
+    UserRequested,
+    /// The connection to the display server was lost
+    DisplayError,
+    /// The application closed the window with `close`
+    ProgrammaticClose,
+}
+
+/// The stage a touch reported by `get_touch_points` is in
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub enum TouchPhase {
+    /// The finger touched the window since the last update
+    Begin,
+    /// The finger is still touching the window
+    Move,
+    /// The finger was lifted since the last update, the touch isn't reported after this
+    End,
+}
+
+/// A finger touching the window
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct TouchPoint {
+    /// Identifies the touch for as long as the finger touches the window
+    pub id: u64,
+    /// Horizontal position, in the same coordinates as `get_mouse_pos`
+    pub x: f32,
+    /// Vertical position, in the same coordinates as `get_mouse_pos`
+    pub y: f32,
+    /// Whether the touch just started, is ongoing or just ended
+    pub phase: TouchPhase,
+}
+
+/// State of a pen or stylus as returned by `get_pen`
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub struct PenState {
+    /// Horizontal position, in the same coordinates as `get_mouse_pos`
+    pub x: f32,
+    /// Vertical position, in the same coordinates as `get_mouse_pos`
+    pub y: f32,
+    /// Pressure from 0.0 when the pen doesn't touch the surface up to 1.0
+    pub pressure: f32,
+    /// Tilt to the left (negative) or right (positive) in degrees, from -90.0 to 90.0
+    pub tilt_x: f32,
+    /// Tilt away from (negative) or towards (positive) the user in degrees, from -90.0 to 90.0
+    pub tilt_y: f32,
+    /// If the pen is close enough to the surface to be tracked
+    pub in_range: bool,
 }
 
 /// This trait can be implemented and set with ```set_input_callback``` to receive a callback
@@ -99,22 +268,78 @@ pub trait InputCallback {
     /// key in the `state` argument, as well as the translated key in the `key` argument.
     /// This includes control characters such as `Key::LeftShift`.
     fn set_key_state(&mut self, _key: Key, _state: bool) {}
+
+    /// Called whenever a key is pressed or released with the raw scancode of the physical key,
+    /// before `set_key_state`. It's also called for keys that have no `Key`. Scancodes don't
+    /// depend on the keyboard layout, which makes them useful for rebinding controls, but they
+    /// are platform specific:
+    ///
+    /// **Windows:** the scancode with `0x100` set for extended keys.
+    ///
+    /// **Linux:** the XKB keycode, the evdev code plus 8, on both X11 and Wayland.
+    ///
+    /// **MacOS:** the virtual key code of the key event.
+    ///
+    /// **RedoxOS:** the Orbital scancode.
+    ///
+    /// **Web:** not supported.
+    fn set_key_state_scancode(&mut self, _scancode: u32, _state: bool) {}
+
+    /// Called when the user switches to another keyboard layout while the window is open. The
+    /// name is the same as the one returned by `Window::get_keyboard_layout`.
+    fn layout_changed(&mut self, _name: &str) {}
+
+    /// Called when a display is connected or disconnected while the window is open. Use
+    /// `Window::display_count` and `Window::current_monitor` to find out what changed.
+    fn displays_changed(&mut self) {}
 }
 
 mod error;
 pub use self::error::Error;
 pub type Result<T> = std::result::Result<T, Error>;
 pub use icon::Icon;
+#[cfg(all(target_os = "linux", feature = "image"))]
+pub use icon::IconImage;
 pub use raw_window_handle::HasRawWindowHandle;
 
 mod key;
-pub use key::Key;
+pub use key::{Key, KeyBinding, KeyMap, KeySet};
+pub mod color;
+pub use color::{argb, rgb, unpack};
+#[cfg(feature = "text")]
+pub mod text;
+#[cfg(feature = "text")]
+pub use text::draw_text;
+// The framebuffer backend only needs part of the shared helpers
+#[cfg_attr(
+    all(feature = "fbdev", not(any(feature = "x11", feature = "wayland"))),
+    allow(dead_code)
+)]
 mod buffer_helper;
 mod icon;
+#[cfg_attr(
+    all(feature = "fbdev", not(any(feature = "x11", feature = "wayland"))),
+    allow(dead_code)
+)]
 mod key_handler;
+#[cfg_attr(
+    all(feature = "fbdev", not(any(feature = "x11", feature = "wayland"))),
+    allow(dead_code)
+)]
 mod mouse_handler;
 mod os;
+#[cfg_attr(
+    all(feature = "fbdev", not(any(feature = "x11", feature = "wayland"))),
+    allow(dead_code)
+)]
+mod present;
 mod rate;
+mod staging;
+#[cfg_attr(
+    all(feature = "fbdev", not(any(feature = "x11", feature = "wayland"))),
+    allow(dead_code)
+)]
+mod touch_handler;
 mod window_flags;
 
 #[cfg(target_os = "macos")]
@@ -137,7 +362,7 @@ use self::os::windows as imp;
 /// Window is used to open up a window. It's possible to optionally display a 32-bit buffer when
 /// the widow is set as non-resizable.
 ///
-pub struct Window(imp::Window);
+pub struct Window(imp::Window, staging::StagingBuffer);
 
 impl fmt::Debug for Window {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -145,12 +370,130 @@ impl fmt::Debug for Window {
     }
 }
 
+/// The framebuffer backend has no native window, its handle is an empty
+/// `RawWindowHandle::Wayland` with null pointers.
 unsafe impl raw_window_handle::HasRawWindowHandle for Window {
     fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
         self.0.raw_window_handle()
     }
 }
 
+#[cfg(feature = "rwh_06")]
+impl raw_window_handle_06::HasWindowHandle for Window {
+    fn window_handle(
+        &self,
+    ) -> std::result::Result<
+        raw_window_handle_06::WindowHandle<'_>,
+        raw_window_handle_06::HandleError,
+    > {
+        let raw = self
+            .0
+            .raw_window_handle_06()
+            .ok_or(raw_window_handle_06::HandleError::Unavailable)?;
+
+        // The handle stays valid for as long as the window is borrowed
+        Ok(unsafe { raw_window_handle_06::WindowHandle::borrow_raw(raw) })
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+impl raw_window_handle_06::HasDisplayHandle for Window {
+    fn display_handle(
+        &self,
+    ) -> std::result::Result<
+        raw_window_handle_06::DisplayHandle<'_>,
+        raw_window_handle_06::HandleError,
+    > {
+        let raw = self
+            .0
+            .raw_display_handle_06()
+            .ok_or(raw_window_handle_06::HandleError::Unavailable)?;
+
+        Ok(unsafe { raw_window_handle_06::DisplayHandle::borrow_raw(raw) })
+    }
+}
+
+///
+/// The window and display handles of a `Window`, detached from its lifetime. Presenting
+/// libraries like `softbuffer` keep the handles they are given for as long as their surface
+/// exists, which would keep the window borrowed and make it impossible to call `update` on it.
+/// Handing them `WindowHandles` instead leaves the window free to be updated.
+///
+/// Requires the `rwh_06` feature.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use minifb::*;
+/// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+/// // Safe as the window is dropped after the surface
+/// let handles = unsafe { WindowHandles::new(&window) }.unwrap();
+/// let context = softbuffer::Context::new(handles).unwrap();
+/// let mut surface = softbuffer::Surface::new(&context, handles).unwrap();
+///
+/// while window.is_open() {
+///     // Draw and present with `surface`, then process the input
+///     window.update();
+/// }
+/// ```
+///
+#[cfg(feature = "rwh_06")]
+#[derive(Clone, Copy, Debug)]
+pub struct WindowHandles {
+    window: raw_window_handle_06::RawWindowHandle,
+    display: raw_window_handle_06::RawDisplayHandle,
+}
+
+#[cfg(feature = "rwh_06")]
+impl WindowHandles {
+    ///
+    /// Gets the handles of `window`, or `None` if the window has been closed.
+    ///
+    /// # Safety
+    ///
+    /// The window has to outlive the returned handles and everything that's created from them.
+    ///
+    pub unsafe fn new(window: &Window) -> Option<WindowHandles> {
+        Some(WindowHandles {
+            window: window.0.raw_window_handle_06()?,
+            display: window.0.raw_display_handle_06()?,
+        })
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+impl raw_window_handle_06::HasWindowHandle for WindowHandles {
+    fn window_handle(
+        &self,
+    ) -> std::result::Result<
+        raw_window_handle_06::WindowHandle<'_>,
+        raw_window_handle_06::HandleError,
+    > {
+        // `WindowHandles::new` requires the window to outlive the handles
+        Ok(unsafe { raw_window_handle_06::WindowHandle::borrow_raw(self.window) })
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+impl raw_window_handle_06::HasDisplayHandle for WindowHandles {
+    fn display_handle(
+        &self,
+    ) -> std::result::Result<
+        raw_window_handle_06::DisplayHandle<'_>,
+        raw_window_handle_06::HandleError,
+    > {
+        Ok(unsafe { raw_window_handle_06::DisplayHandle::borrow_raw(self.display) })
+    }
+}
+
+/// Clamps `value` between `low` and `high`.
+///
+/// This was never meant to be part of the API, use [`Ord::clamp`] or [`f32::clamp`] instead.
+#[deprecated(
+    since = "0.25.0",
+    note = "`clamp` will be removed in 1.0.0, use `Ord::clamp` or `f32::clamp` instead"
+)]
+#[doc(hidden)]
 pub fn clamp<T: PartialOrd>(low: T, value: T, high: T) -> T {
     if value < low {
         low
@@ -179,6 +522,71 @@ pub enum ScaleMode {
     UpperLeft,
 }
 
+///
+/// How the parts of the window that aren't covered by the buffer are filled, see
+/// `Window::set_border_fill`. Colors are packed as `0xAARRGGBB`, the same as the buffer.
+///
+#[derive(Clone, Debug, PartialEq)]
+pub enum BorderFill {
+    /// A single color, the same as `Window::set_background_color_u32`
+    Solid(u32),
+    /// A vertical gradient going from the first color at the top of the window to the second
+    /// color at the bottom
+    Gradient(u32, u32),
+    /// A pattern of the given width and height that is repeated from the upper left corner of
+    /// the window
+    Tile(Vec<u32>, usize, usize),
+}
+
+///
+/// The color space the pixels in the buffer are in. On displays with a wider gamut than sRGB,
+/// colors that aren't tagged with a color space look oversaturated.
+///
+/// Only macOS color matches the window contents, the other platforms always send the pixels to
+/// the display as they are.
+///
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorSpace {
+    /// The pixels are in sRGB, which is what most images and colors are specified in
+    Srgb,
+    /// The pixels are linear sRGB values, without the sRGB transfer function applied
+    Linear,
+    /// The pixels are sent to the display without any color matching (default)
+    DisplayNative,
+}
+
+///
+/// The windowing system used on Linux and the BSDs, see `WindowOptions::backend`. Only the
+/// backends whose features are enabled can be used.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PosixBackend {
+    /// Wayland when a compositor is running, then X11 (default). The framebuffer is never chosen
+    /// automatically.
+    Auto,
+    /// X11, needs the `x11` feature. This also runs under XWayland.
+    X11,
+    /// Wayland, needs the `wayland` feature
+    Wayland,
+    /// The Linux framebuffer device, needs the `fbdev` feature
+    Fbdev,
+}
+
+///
+/// How the channels of each pixel are packed into the `u32` values of the buffer, see
+/// `WindowOptions::pixel_format`. The pixels are converted when the display expects a
+/// different layout.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// `0xAARRGGBB`, the alpha channel is used for transparency (default)
+    Argb8888,
+    /// `0x00RRGGBB`, the upper byte is ignored and the pixels are always opaque
+    Xrgb8888,
+    /// `0xBBGGRRAA`
+    Bgra8888,
+}
+
 ///
 /// WindowOptions is creation settings for the window. By default the settings are defined for
 /// displayng a 32-bit buffer (no scaling of window is possible)
@@ -206,12 +614,72 @@ pub struct WindowOptions {
     /// Should be mutually exclusive to resize, automatically assumes borderless.
     /// Not supported on OSX.
     pub none: bool,
+    /// The color space of the buffer, see `ColorSpace` (default: DisplayNative)
+    pub color_space: ColorSpace,
+    /// If the window should be shown when it's created (default: true). A hidden window can be
+    /// positioned and given its first frame before it's shown with `Window::set_visible`.
+    /// Not supported on Wayland, Redox and Web, where the window is always shown.
+    pub visible: bool,
+    /// Identifies the application to the desktop, which uses it to group its windows in the
+    /// taskbar and to find its icon (default: None). On Linux this should match the name of the
+    /// application's `.desktop` file, without the extension.
+    ///
+    /// - Windows: Sets the AppUserModelID of the whole process
+    /// - X11: Sets both names of `WM_CLASS`, unless `wm_class` is set
+    /// - Wayland: Sets the xdg-shell app id
+    /// - MacOS, Redox and Web: Not supported, on macOS the bundle identifier is used instead
+    pub app_id: Option<&'static str>,
+    /// The instance and class names of `WM_CLASS`, for window manager rules that match on them
+    /// separately (default: None). Takes precedence over `app_id`, which is used for both names
+    /// otherwise. Only used on X11.
+    pub wm_class: Option<(&'static str, &'static str)>,
+    /// The layout of the pixels in the buffers given to the window, see `PixelFormat`
+    /// (default: Argb8888). The pixels are converted when the display uses another layout,
+    /// `Window::get_native_pixel_format` returns the one it uses.
+    pub pixel_format: PixelFormat,
+    /// How many frames can be queued for presentation, 2 for double buffering or 3 for triple
+    /// buffering (default: 3). Double buffering keeps the latency between drawing a frame and
+    /// seeing it low, triple buffering lets the application start on the next frame while the
+    /// display is still busy with the previous ones. Other values are clamped to 2..=3.
+    ///
+    /// - Wayland: Limits the number of shm buffers, `update_with_buffer` waits for the compositor
+    ///   to release one when they are all in use
+    /// - MacOS: Limits the number of frames in flight on the GPU
+    /// - Windows, X11, Redox and Web: No effect, the buffer is copied to the window when it's
+    ///   presented so there is nothing to queue
+    pub buffer_count: u8,
+    /// If the window should be created for OpenGL rendering (default: false). The window gets a
+    /// double buffered visual or pixel format and a GL context that is made current on the
+    /// calling thread. Load the GL functions with `Window::gl_get_proc_address` and present
+    /// frames with `Window::gl_swap_buffers`, the `update_with_buffer` functions shouldn't be
+    /// used with such a window.
+    ///
+    /// - X11: Uses GLX
+    /// - Wayland: Not supported, the window is created with X11 (XWayland) instead when it's
+    ///   available and creating it fails otherwise
+    /// - Windows: Uses WGL
+    /// - MacOS: Uses an `NSOpenGLContext`
+    /// - Redox and Web: Not supported, creating the window fails with `Error::Unsupported`
+    pub gl: bool,
+    /// The windowing system to use on Linux and the BSDs (default: None, which is the same as
+    /// `PosixBackend::Auto`). By default Wayland is tried first and X11 when that fails, set this
+    /// to force one of them, for example X11 for its MIT-SHM path. The framebuffer is only used
+    /// when `PosixBackend::Fbdev` is set, as it takes over the input devices and the console.
+    /// Creating the window fails with `Error::WindowCreate` when the requested backend isn't
+    /// enabled or can't be reached, there's no fallback to the others. Ignored on the other
+    /// platforms.
+    pub backend: Option<PosixBackend>,
 }
 
 impl Window {
     ///
     /// Opens up a new window
     ///
+    /// A `Window` can't be sent to other threads. On macOS it also has to be created on the main
+    /// thread since AppKit only runs there, this function panics when called from another thread.
+    /// To render on a separate thread, send the finished buffers to the main thread (for example
+    /// over a channel) and present them there, see the `render_thread` example.
+    ///
     /// # Examples
     ///
     /// Open up a window with default settings
@@ -244,7 +712,61 @@ impl Window {
                 "Window transparency requires the borderless property".to_owned(),
             ));
         }
-        imp::Window::new(name, width, height, opts).map(Window)
+        imp::Window::new(name, width, height, opts)
+            .map(|w| Window(w, staging::StagingBuffer::new()))
+    }
+
+    ///
+    /// Opens up a new window inside an existing native window, for example to show the editor of
+    /// an audio plugin in the window its host provides. The window has no title bar or border
+    /// and is placed in the top left corner of the parent, the other options work as with `new`.
+    ///
+    /// **Windows:** `parent` is an `HWND`, the window is created with `WS_CHILD`.
+    ///
+    /// **Linux:**
+    /// - X11: `parent` is an XID, the window is created as its subwindow. This is also used when
+    ///   Wayland is available, since the parent has to be an X11 window.
+    /// - Wayland: Not supported, returns `Error::Unsupported`.
+    ///
+    /// **MacOS:** `parent` is an `NSView`. The window is attached as a child window of the view's
+    /// `NSWindow`, placed over the view, so it moves along with the parent.
+    ///
+    /// **RedoxOS / Web:** Not supported, returns `Error::Unsupported`.
+    ///
+    /// # Safety
+    ///
+    /// `parent` has to be a valid handle of the kind listed above, and the parent has to outlive
+    /// the returned window.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let parent: *mut std::os::raw::c_void = std::ptr::null_mut();
+    /// // `parent` is the handle given by the plugin host
+    /// let mut window = unsafe {
+    ///     Window::new_child(parent, "Editor", 400, 300, WindowOptions::default()).unwrap()
+    /// };
+    /// ```
+    pub unsafe fn new_child(
+        parent: *mut raw::c_void,
+        name: &str,
+        width: usize,
+        height: usize,
+        opts: WindowOptions,
+    ) -> Result<Window> {
+        if parent.is_null() {
+            return Err(Error::WindowCreate("The parent window is null".to_owned()));
+        }
+
+        let opts = WindowOptions {
+            borderless: true,
+            title: false,
+            ..opts
+        };
+
+        imp::Window::new_child(parent, name, width, height, opts)
+            .map(|w| Window(w, staging::StagingBuffer::new()))
     }
 
     ///
@@ -257,6 +779,9 @@ impl Window {
     /// let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
     ///
     /// window.set_title("My New Title!");
+    ///
+    /// // Titles are UTF-8 so non-ASCII characters are shown as-is
+    /// window.set_title("Grüße, ウィンドウ 🦀");
     /// ```
     ///
     pub fn set_title(&mut self, title: &str) {
@@ -264,19 +789,83 @@ impl Window {
     }
 
     ///
-    /// Sets the icon of the window after creation.
+    /// Returns the title of the window, as given to `new` or `set_title`.
+    ///
+    /// **Windows:** reads the title back from the window.
+    ///
+    /// **Web:** returns the title of the document, which the page may have changed as well.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// let mut window = Window::new("file.txt", 640, 400, WindowOptions::default()).unwrap();
+    ///
+    /// // Mark the document as modified
+    /// let title = window.get_title();
+    /// window.set_title(&format!("{} *", title));
+    /// ```
+    ///
+    #[inline]
+    pub fn get_title(&self) -> String {
+        self.0.get_title()
+    }
+
+    ///
+    /// Marks the document shown in the window as having unsaved changes, or as saved again.
+    ///
+    /// **MacOS:** Shows a dot in the close button of the window.
+    ///
+    /// **Windows / Linux / RedoxOS / Web:** Does nothing, these have no document state. Add a
+    /// marker to the title with `set_title` instead.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_document_edited(true);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_document_edited(&mut self, edited: bool) {
+        self.0.set_document_edited(edited)
+    }
+
+    ///
+    /// Sets the file whose contents the window shows.
+    ///
+    /// **MacOS:** Shows the icon of the file next to the title. The icon can be dragged like the
+    /// file itself, and Cmd-clicking the title shows the folders the file is in.
+    ///
+    /// **Windows / Linux / RedoxOS / Web:** Does nothing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_represented_file(std::path::Path::new("/tmp/drawing.png"));
+    /// ```
+    ///
+    #[inline]
+    pub fn set_represented_file(&mut self, path: &Path) {
+        self.0.set_represented_file(path)
+    }
+
+    ///
+    /// Sets the icon of the window after creation. Platforms that can't set window icons, or
+    /// can't use this kind of icon, ignore it, use `try_set_icon` to find out.
     ///
     /// The file path has to be relative to the current working directory.
     ///
     /// **Windows:** Has to be a `.ico` file. To also set the icon of the `.exe` file, see the `rc.exe` tool
     ///
     /// **Linux:**
-    /// - X11: Needs a `u64` buffer with ARGB data
-    /// - Wayland: *not implemented* (use a `.desktop` file)
+    /// - X11: Needs a `u64` buffer with ARGB data, or an `IconImage` with the `image` feature
+    /// - Wayland: *not supported* (use a `.desktop` file)
     ///
-    /// **MacOS:**
-    ///
-    /// **RedoxOS:** *not implemented*
+    /// **MacOS / RedoxOS / Web:** *not supported*
     ///
     /// # Examples
     ///
@@ -290,9 +879,58 @@ impl Window {
     /// ```
     ///
     pub fn set_icon(&mut self, icon: Icon) {
+        let _ = self.0.set_icon(icon);
+    }
+
+    ///
+    /// Same as `set_icon`, but returns `Error::Unsupported` when the platform can't set window
+    /// icons, or can't use this kind of icon.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # use std::convert::TryFrom;
+    /// let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    ///
+    /// // 1x1 red icon, width and height come first
+    /// let icon = Icon::try_from(&[1, 1, 0xffff_0000][..]).unwrap();
+    /// if let Err(Error::Unsupported { platform, .. }) = window.try_set_icon(icon) {
+    ///     println!("{} can't set window icons", platform);
+    /// }
+    /// ```
+    ///
+    pub fn try_set_icon(&mut self, icon: Icon) -> Result<()> {
         self.0.set_icon(icon)
     }
 
+    ///
+    /// Returns the name of the backend the window was created with, for diagnostics and bug
+    /// reports or for behavior that depends on it. On Linux this is the backend minifb ended up
+    /// with, which depends on the environment unless `WindowOptions::backend` is set.
+    ///
+    /// **Windows:** `"win32"`
+    ///
+    /// **Linux:** `"x11"`, `"wayland"` or `"fbdev"`
+    ///
+    /// **MacOS:** `"cocoa"`
+    ///
+    /// **RedoxOS:** `"orbital"`
+    ///
+    /// **Web:** `"wasm"`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// println!("minifb is using {}", window.backend_name());
+    /// ```
+    #[inline]
+    pub fn backend_name(&self) -> &'static str {
+        self.0.backend_name()
+    }
+
     ///
     /// Returns the native handle for a window which is an opaque pointer/handle which
     /// dependens on the current operating system:
@@ -313,8 +951,14 @@ impl Window {
     /// The upper 8-bits are ignored, the next 8-bits are for the red channel, the next 8-bits
     /// afterwards for the green channel, and the lower 8-bits for the blue channel.
     ///
-    /// Notice that the buffer needs to be at least the size of the created window. Also only one of
-    /// `update_with_buffer` or `update` should be called for updating a single window.
+    /// Notice that the buffer needs to be at least the size of the created window, see
+    /// `set_partial_update` for showing smaller buffers. Also only one of `update_with_buffer` or
+    /// `update` should be called for updating a single window.
+    ///
+    /// While the window is minimized or occluded (see `is_occluded`) the buffer isn't presented,
+    /// only its size is checked and input is processed like `update`. The window keeps the last
+    /// frame it presented, and the first call after it becomes visible again presents the
+    /// buffer passed to it, so the contents are up to date as soon as the window can be seen.
     ///
     /// # Examples
     ///
@@ -345,70 +989,1209 @@ impl Window {
         height: usize,
     ) -> Result<()> {
         self.0.update_rate();
-        self.0
-            .update_with_buffer_stride(buffer, width, height, width)
+        self.present_buffer(buffer, width, height)
     }
 
     ///
-    /// Updates the window (this is required to call in order to get keyboard/mouse input, etc)
-    ///
-    /// Notice that when using this function then `update_with_buffer` should not be called for the same window.
-    /// Only one of the functions should be used.
+    /// Same as `update_with_buffer`, but returns right away instead of waiting for the rate set
+    /// with `set_target_fps` or `limit_update_rate`. This is meant for applications that pace
+    /// their frames themselves, the limit set on the window is left as it is.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
-    /// let mut buffer: Vec<u32> = vec![0; 640 * 400];
+    /// # use std::time::{Duration, Instant};
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let buffer = vec![0u32; 640 * 400];
+    /// let frame_time = Duration::from_micros(16_667);
+    /// let mut next_frame = Instant::now();
     ///
-    /// let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// while window.is_open() {
+    ///     window.update_with_buffer_no_wait(&buffer, 640, 400).unwrap();
     ///
-    /// window.update();
+    ///     next_frame += frame_time;
+    ///     while Instant::now() < next_frame {
+    ///         std::hint::spin_loop();
+    ///     }
+    /// }
     /// ```
     #[inline]
-    pub fn update(&mut self) {
-        self.0.update_rate();
-        self.0.update()
+    pub fn update_with_buffer_no_wait(
+        &mut self,
+        buffer: &[u32],
+        width: usize,
+        height: usize,
+    ) -> Result<()> {
+        self.present_buffer(buffer, width, height)
+    }
+
+    // Everything `update_with_buffer` does besides waiting for the update rate
+    fn present_buffer(&mut self, buffer: &[u32], width: usize, height: usize) -> Result<()> {
+        if self.1.is_partial(buffer, width, height) {
+            return self.present_partial(buffer, width, height);
+        }
+
+        buffer_helper::check_buffer_size(width, height, width, buffer)?;
+
+        let window_size = self.0.get_size();
+        if self.1.is_presented(buffer, width, height, window_size) {
+            self.0.update();
+            return Ok(());
+        }
+
+        if self.0.is_occluded() {
+            // Kept for `flush` to present once the window is shown
+            self.1.set_frame(buffer, width, height);
+            self.0.update();
+            return Ok(());
+        }
+
+        self.0
+            .update_with_buffer_stride(buffer, width, height, width)?;
+        self.1
+            .set_presented_frame(buffer, width, height, window_size);
+        Ok(())
+    }
+
+    // Presents the rows a buffer that is too small has, see `set_partial_update`
+    fn present_partial(&mut self, buffer: &[u32], width: usize, height: usize) -> Result<()> {
+        let rows = self.1.set_partial_frame(buffer, width, height);
+        let window_size = self.0.get_size();
+
+        if self.0.is_occluded() {
+            self.0.update();
+        } else if let Some((frame, width, height)) = self.1.frame() {
+            self.0
+                .update_with_buffer_stride(frame, width, height, width)?;
+            self.1.mark_presented(window_size);
+        }
+
+        Err(Error::PartialUpdate { rows, height })
     }
 
     ///
-    /// Checks if the window is still open. A window can be closed by the user (by for example
-    /// pressing the close button on the window) It's up to the user to make sure that this is
-    /// being checked and take action depending on the state.
+    /// Same as `update_with_buffer` but blocks until the next vertical blank after the frame was
+    /// uploaded, so a game loop can draw, call this and repeat without a separate wait.
+    ///
+    /// Vsync takes precedence over `limit_update_rate` and `set_target_fps`, they aren't waited
+    /// for as well when the platform vsync is used. Where it isn't available the rate limiter
+    /// paces the updates instead, as with `update_with_buffer`.
+    ///
+    /// **Windows:** Waits with `DwmFlush`, falls back to the rate limiter if desktop composition is off.
     ///
+    /// **Linux:**
+    /// - X11: Not supported, uses the rate limiter.
+    /// - Wayland: Waits for the frame callback of the surface. Compositors don't send these while
+    ///   the window is hidden, after 100 ms the rate limiter is used instead.
+    ///
+    /// **MacOS:** Not supported, uses the rate limiter. The window is redrawn in sync with the
+    /// display regardless.
+    ///
+    /// **RedoxOS / Web:** Not supported, same as `update_with_buffer`.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let buffer = vec![0u32; 640 * 400];
+    ///
     /// while window.is_open() {
-    ///     // Update window
+    ///     window.update_with_buffer_vsync(&buffer, 640, 400).unwrap();
     /// }
     /// ```
-    #[inline]
-    pub fn is_open(&self) -> bool {
-        self.0.is_open()
+    pub fn update_with_buffer_vsync(
+        &mut self,
+        buffer: &[u32],
+        width: usize,
+        height: usize,
+    ) -> Result<()> {
+        buffer_helper::check_buffer_size(width, height, width, buffer)?;
+
+        let window_size = self.0.get_size();
+        if self.1.is_presented(buffer, width, height, window_size) {
+            self.0.update_rate();
+            self.0.update();
+            return Ok(());
+        }
+
+        if self.0.is_occluded() {
+            // Kept for `flush` to present once the window is shown
+            self.1.set_frame(buffer, width, height);
+            self.0.update_rate();
+            self.0.update();
+            return Ok(());
+        }
+
+        self.0
+            .update_with_buffer_vsync(buffer, width, height, width)?;
+        self.1
+            .set_presented_frame(buffer, width, height, window_size);
+        Ok(())
     }
 
     ///
-    /// Sets the position of the window. This is useful if you have
-    /// more than one window and want to align them up on the screen
+    /// Same as `update_with_buffer` but calls `on_presented` once the frame was handed over for
+    /// display, so pipelined renderers know when the buffer went out.
+    ///
+    /// Only Wayland tells when the frame is actually shown. On the other platforms the callback
+    /// means the frame was submitted, the compositor or display may show it a bit later, so it
+    /// isn't a presentation fence to time frames against.
+    ///
+    /// The callback is called from a later call to `update`, `update_with_buffer` or one of
+    /// their variants, or from this one when the platform presents synchronously. When the
+    /// buffer is the same as the frame on screen nothing is presented and it's called right
+    /// away. While the window is hidden or minimized it waits for the next frame that is shown.
+    /// If the update fails or the window is closed the callback is dropped without being called.
+    ///
+    /// **Windows:** Called once the frame was painted to the window, DWM shows it with a later
+    /// composition
+    ///
+    /// **Linux:**
+    /// - X11: Called once the X server has handled the drawing requests, after an `XSync`. A
+    ///   compositing manager may show the frame later.
+    /// - Wayland: Called when the frame callback of the commit arrives. Compositors don't send
+    ///   these while the window is hidden.
+    /// - Framebuffer: Called right away, writes to the framebuffer show up immediately
+    ///
+    /// **MacOS:** Called from the next update, after the frame was given to the view. The view
+    /// draws it with the display's next refresh.
+    ///
+    /// **RedoxOS / Web:** Called once the frame was handed to Orbital or the canvas
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// // Moves the window to pixel position 20, 20 on the screen
-    /// window.set_position(20, 20);
-    /// ```
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
     ///
-    #[inline]
-    pub fn set_position(&mut self, x: isize, y: isize) {
-        self.0.set_position(x, y)
-    }
-
+    /// let buffer = vec![0u32; 640 * 400];
+    /// let frames_shown = Rc::new(Cell::new(0));
+    ///
+    /// while window.is_open() {
+    ///     let frames_shown = frames_shown.clone();
+    ///     window
+    ///         .update_with_buffer_callback(&buffer, 640, 400, move || {
+    ///             frames_shown.set(frames_shown.get() + 1);
+    ///         })
+    ///         .unwrap();
+    /// }
+    /// ```
+    pub fn update_with_buffer_callback<F>(
+        &mut self,
+        buffer: &[u32],
+        width: usize,
+        height: usize,
+        on_presented: F,
+    ) -> Result<()>
+    where
+        F: FnOnce() + 'static,
+    {
+        // Nothing is presented when the buffer is the same as the frame on screen
+        let window_size = self.0.get_size();
+        let unchanged = buffer_helper::check_buffer_size(width, height, width, buffer).is_ok()
+            && self.1.is_presented(buffer, width, height, window_size);
+
+        if unchanged {
+            self.update_with_buffer(buffer, width, height)?;
+            on_presented();
+            return Ok(());
+        }
+
+        self.0.set_present_callback(Box::new(on_presented));
+        let result = self.update_with_buffer(buffer, width, height);
+
+        // The callback would otherwise be called for a later, unrelated frame
+        if result.is_err() || !self.0.is_open() {
+            self.0.cancel_present_callbacks();
+        }
+
+        result
+    }
+
+    ///
+    /// Same as `update_with_buffer` but takes pixels with 10 bits per color channel, encoded as
+    /// `A2RGB10`: the upper 2 bits are for alpha, followed by 10 bits each for the red, green
+    /// and blue channels.
+    ///
+    /// The window is still presented with 8 bits per channel: the buffer is dithered down to
+    /// `ARGB8888` and passed to `update_with_buffer`. No deep color surface or visual is
+    /// negotiated, so this doesn't show more than 8 bits on any display. The dithering keeps
+    /// smooth gradients free of the banding that truncating the dropped bits would cause.
+    ///
+    /// **Windows / MacOS / Linux / RedoxOS / Web:** Dithered to 8 bits per channel. This includes
+    /// X11 servers with a 30-bit visual and Wayland compositors that support `ARGB2101010`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // A horizontal gray gradient using the full 10-bit range
+    /// let buffer: Vec<u32> = (0..640 * 400)
+    ///     .map(|i| {
+    ///         let v = ((i % 640) * 1023 / 639) as u32;
+    ///         (3 << 30) | (v << 20) | (v << 10) | v
+    ///     })
+    ///     .collect();
+    ///
+    /// window.update_with_buffer_rgb10(&buffer, 640, 400).unwrap();
+    /// ```
+    pub fn update_with_buffer_rgb10(
+        &mut self,
+        buffer: &[u32],
+        width: usize,
+        height: usize,
+    ) -> Result<()> {
+        buffer_helper::check_buffer_size(width, height, width, buffer)?;
+        let buffer = buffer_helper::dither_rgb10(buffer, width, height);
+        self.update_with_buffer(&buffer, width, height)
+    }
+
+    ///
+    /// Same as `update_with_buffer` but takes linear `[r, g, b, a]` pixels with an `f32` per
+    /// channel, as kept by most rendering pipelines. The color channels are clamped to
+    /// `0.0..=1.0`, so tone mapping of HDR values has to be done before, and sRGB encoded on the
+    /// way to the 8-bit surface. Alpha is clamped but not encoded. NaN values count as 0.
+    ///
+    /// The result is in sRGB, which suits windows using the default `ColorSpace` or
+    /// `ColorSpace::Srgb` and the default `PixelFormat::Argb8888`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // A horizontal gray gradient that is linear in light intensity
+    /// let buffer: Vec<[f32; 4]> = (0..640 * 400)
+    ///     .map(|i| {
+    ///         let v = (i % 640) as f32 / 639.0;
+    ///         [v, v, v, 1.0]
+    ///     })
+    ///     .collect();
+    ///
+    /// window.update_with_buffer_f32(&buffer, 640, 400).unwrap();
+    /// ```
+    pub fn update_with_buffer_f32(
+        &mut self,
+        buffer: &[[f32; 4]],
+        width: usize,
+        height: usize,
+    ) -> Result<()> {
+        buffer_helper::check_buffer_size(width, height, width, buffer)?;
+        let buffer = buffer_helper::encode_f32(buffer, width, height);
+        self.update_with_buffer(&buffer, width, height)
+    }
+
+    ///
+    /// Updates the window (this is required to call in order to get keyboard/mouse input, etc)
+    ///
+    /// Notice that when using this function then `update_with_buffer` should not be called for the same window.
+    /// Only one of the functions should be used.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// let mut buffer: Vec<u32> = vec![0; 640 * 400];
+    ///
+    /// let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    ///
+    /// window.update();
+    /// ```
+    #[inline]
+    pub fn update(&mut self) {
+        self.0.update_rate();
+        self.0.update()
+    }
+
+    ///
+    /// Same as `update`, but returns right away instead of waiting for the rate set with
+    /// `set_target_fps` or `limit_update_rate`, see `update_with_buffer_no_wait`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// while window.is_open() {
+    ///     window.update_no_wait();
+    ///     // Wait for the next frame in the application's own way
+    /// }
+    /// ```
+    #[inline]
+    pub fn update_no_wait(&mut self) {
+        self.0.update()
+    }
+
+    ///
+    /// Processes the pending input events without presenting a frame or waiting for the rate
+    /// limiter. This can be called several times in between updates to sample input more often
+    /// than the frame rate, which lowers the input latency of rhythm games and the like.
+    ///
+    /// Functions reporting the current state, such as `is_key_down`, `get_mouse_pos` and
+    /// `get_mouse_down`, see the polled events right away. Functions reporting what changed
+    /// during an update, such as `is_key_pressed`, `get_keys_just_pressed` and `get_scroll_wheel`,
+    /// still change at the next update, which includes the events that were polled.
+    ///
+    /// **RedoxOS:** Not supported.
+    ///
+    /// **Web:** Does nothing, input events are delivered by the browser as they happen.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let frame = std::time::Duration::from_millis(16);
+    ///
+    /// while window.is_open() {
+    ///     let start = std::time::Instant::now();
+    ///     while start.elapsed() < frame {
+    ///         window.poll_input();
+    ///         if window.is_key_down(Key::Space) {
+    ///             println!("hit");
+    ///         }
+    ///         std::thread::sleep(std::time::Duration::from_millis(1));
+    ///     }
+    ///     window.update();
+    /// }
+    /// ```
+    #[inline]
+    pub fn poll_input(&mut self) {
+        self.0.poll_input()
+    }
+
+    ///
+    /// Copies a region of `buffer` into the frame that is shown by the next call to `present`.
+    /// `buffer` holds the whole `width` x `height` frame and only the pixels within `region` are
+    /// copied, so renderers that redraw a few areas per frame can upload each of them and then
+    /// present once.
+    ///
+    /// When `width` or `height` differ from the previous upload the whole buffer is copied.
+    /// Returns an error if the buffer is too small or the region doesn't fit within it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let mut buffer = vec![0u32; 640 * 400];
+    ///
+    /// while window.is_open() {
+    ///     // Draw a cursor and a status bar, then present both at once
+    ///     buffer[10 * 640 + 10] = 0xffffff;
+    ///     window.upload_region(&buffer, 640, 400, Rect { x: 10, y: 10, width: 1, height: 1 }).unwrap();
+    ///     window.upload_region(&buffer, 640, 400, Rect { x: 0, y: 380, width: 640, height: 20 }).unwrap();
+    ///     window.present().unwrap();
+    /// }
+    /// ```
+    #[inline]
+    pub fn upload_region(
+        &mut self,
+        buffer: &[u32],
+        width: usize,
+        height: usize,
+        region: Rect,
+    ) -> Result<()> {
+        self.1.upload_region(buffer, width, height, region)
+    }
+
+    ///
+    /// Shows the frame built up by `upload_region` and processes input, like
+    /// `update_with_buffer`. If nothing has been uploaded yet this only processes input, like
+    /// `update`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.present().unwrap();
+    /// ```
+    #[inline]
+    pub fn present(&mut self) -> Result<()> {
+        self.0.update_rate();
+        self.flush()
+    }
+
+    ///
+    /// Shows the last buffer passed to `update_with_buffer` or built up by `upload_region` again,
+    /// using the current background color and scale mode, and processes input like `update`.
+    /// This shows changes that don't touch the pixels without passing the whole buffer again.
+    /// If no buffer has been given yet this only processes input.
+    ///
+    /// minifb keeps its own copy of the last buffer for this, so the original doesn't have to
+    /// be kept around. Copying every frame costs time though, so buffers passed to
+    /// `update_with_buffer` are only kept while `set_retain_frame`, `set_skip_identical_frames`
+    /// or `set_partial_update` is on, or when they weren't presented because the window was
+    /// hidden. Frames built up with `upload_region` are always kept.
+    ///
+    /// Unlike `update_with_buffer` this doesn't wait for the update rate limit.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_retain_frame(true);
+    /// window.update_with_buffer(&vec![0; 640 * 400], 640, 400).unwrap();
+    ///
+    /// window.set_background_color(255, 255, 255);
+    /// window.flush().unwrap();
+    /// ```
+    pub fn flush(&mut self) -> Result<()> {
+        let window_size = self.0.get_size();
+        match self.1.frame() {
+            Some((buffer, width, height)) => {
+                self.0
+                    .update_with_buffer_stride(buffer, width, height, width)?;
+                self.1.mark_presented(window_size);
+                Ok(())
+            }
+            None => {
+                self.0.update();
+                Ok(())
+            }
+        }
+    }
+
+    ///
+    /// Makes minifb keep a copy of the last buffer passed to `update_with_buffer` and its
+    /// variants, so `flush` can present it again. Off by default, as it adds a copy of the
+    /// buffer to every frame.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_retain_frame(true);
+    /// window.update_with_buffer(&vec![0; 640 * 400], 640, 400).unwrap();
+    ///
+    /// // Shows the same buffer again with the new background color around it
+    /// window.set_background_color(40, 40, 40);
+    /// window.flush().unwrap();
+    /// ```
+    ///
+    #[inline]
+    pub fn set_retain_frame(&mut self, enabled: bool) {
+        self.1.set_retain_frame(enabled)
+    }
+
+    ///
+    /// Makes `update_with_buffer` and `update_with_buffer_vsync` skip presenting a buffer that is
+    /// identical to the one presented last, they only process input like `update` then. For
+    /// mostly static interfaces this takes the copy, scaling and compositor work out of most
+    /// frames. Off by default.
+    ///
+    /// The check compares the whole buffer with minifb's copy of the last one, which costs about
+    /// as much as copying it, so it doesn't pay off for buffers that change every frame. The
+    /// buffer is presented again when the window size changes. Changes that don't touch the
+    /// pixels, such as `set_background_color`, need a call to `flush` to show up.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let buffer = vec![0u32; 640 * 400];
+    /// window.set_skip_identical_frames(true);
+    ///
+    /// while window.is_open() {
+    ///     // Only the first frame is presented, the rest just process input
+    ///     window.update_with_buffer(&buffer, 640, 400).unwrap();
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn set_skip_identical_frames(&mut self, enabled: bool) {
+        self.1.set_skip_identical(enabled)
+    }
+
+    ///
+    /// Lets `update_with_buffer` present a buffer that is too small for the given size instead of
+    /// failing. The whole rows the buffer has are shown, the rows after them keep what the
+    /// previous frame showed, or are black if its size was different. The call still returns
+    /// `Error::PartialUpdate` with the number of rows that were shown. Off by default, so a
+    /// buffer that is too small is an `Error::UpdateFailed` and nothing is presented.
+    ///
+    /// This is meant for streaming or progressive renderers that want to show the part of the
+    /// frame they have so far.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_partial_update(true);
+    ///
+    /// // Only the first 100 rows have been decoded so far
+    /// let buffer = vec![0u32; 640 * 100];
+    ///
+    /// match window.update_with_buffer(&buffer, 640, 400) {
+    ///     Err(Error::PartialUpdate { rows, .. }) => println!("{} rows shown", rows),
+    ///     other => other.unwrap(),
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn set_partial_update(&mut self, allow: bool) {
+        self.1.set_partial_update(allow)
+    }
+
+    ///
+    /// Declares which parts of the buffer changed since the last frame, in buffer pixels. Only
+    /// the next present, through `update_with_buffer`, `present` or `flush`, uses these, after
+    /// that the whole window counts as changed again. Calls before the same present add up, so
+    /// frames that weren't presented, for example while the window was occluded, aren't lost.
+    /// An empty slice means nothing changed.
+    ///
+    /// The rectangles are mapped to the window with the current scale mode and grown by a pixel
+    /// for the filtering of the scalers. The whole window is still redrawn when the buffer or
+    /// window size changed since the last frame.
+    ///
+    /// **Linux:**
+    /// - X11: only the damaged parts are sent to the X server.
+    /// - Wayland: the rectangles are passed to the compositor as buffer damage, so it doesn't
+    ///   have to recomposite the whole surface.
+    ///
+    /// **Windows / MacOS / RedoxOS / Web:** no effect, the whole window is always redrawn.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let mut buffer = vec![0u32; 640 * 400];
+    ///
+    /// while window.is_open() {
+    ///     // Only the status bar at the bottom changes
+    ///     buffer[390 * 640..].iter_mut().for_each(|p| *p = p.wrapping_add(1));
+    ///     window.set_damage(&[Rect { x: 0, y: 390, width: 640, height: 10 }]);
+    ///     window.update_with_buffer(&buffer, 640, 400).unwrap();
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn set_damage(&mut self, rects: &[Rect]) {
+        self.0.set_damage(rects)
+    }
+
+    ///
+    /// Returns the address of a GL function, or null when the window wasn't created with
+    /// `WindowOptions::gl` or the function isn't available. This is meant to be passed to a GL
+    /// loader such as the `gl` or `glow` crates.
+    ///
+    /// The context is made current on the thread that created the window, the functions should
+    /// only be called from there.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// let mut window = Window::new(
+    ///     "GL",
+    ///     640,
+    ///     400,
+    ///     WindowOptions {
+    ///         gl: true,
+    ///         ..WindowOptions::default()
+    ///     },
+    /// )
+    /// .unwrap();
+    ///
+    /// let clear = window.gl_get_proc_address("glClear");
+    /// assert!(!clear.is_null());
+    /// ```
+    ///
+    #[inline]
+    pub fn gl_get_proc_address(&self, name: &str) -> *const raw::c_void {
+        self.0.gl_get_proc_address(name)
+    }
+
+    ///
+    /// Presents the frame rendered with GL. Does nothing when the window wasn't created with
+    /// `WindowOptions::gl`.
+    ///
+    /// This doesn't process any input, call `update` once per frame as well.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions { gl: true, ..WindowOptions::default() }).unwrap();
+    /// while window.is_open() {
+    ///     // Render with GL
+    ///     window.gl_swap_buffers();
+    ///     window.update();
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn gl_swap_buffers(&mut self) {
+        self.0.gl_swap_buffers()
+    }
+
+    ///
+    /// Checks if the window is still open. A window can be closed by the user (by for example
+    /// pressing the close button on the window) It's up to the user to make sure that this is
+    /// being checked and take action depending on the state.
+    ///
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// while window.is_open() {
+    ///     // Update window
+    /// }
+    /// ```
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.0.is_open()
+    }
+
+    ///
+    /// Returns true if the window is currently not visible to the user, either because it's
+    /// minimized or because other windows fully cover it. This can be used to skip rendering
+    /// while nothing would be shown anyway.
+    ///
+    /// Platform specific notes:
+    ///
+    /// * **macOS**: uses the occlusion state reported by the window server.
+    /// * **X11**: tracks visibility events, so a window is only reported as occluded if the X
+    ///   server knows it's fully obscured or unmapped. Compositing window managers usually never
+    ///   report a window as obscured.
+    /// * **Windows**: there is no direct API for this so a minimized window, or one whose
+    ///   visible region is empty, is reported as occluded. With desktop composition enabled a
+    ///   covered window is usually still considered visible.
+    /// * **Wayland** and **Redox**: always returns false.
+    /// * **Web**: returns true when the page is hidden.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// # let buffer = vec![0u32; 640 * 400];
+    /// while window.is_open() {
+    ///     if window.is_occluded() {
+    ///         window.update();
+    ///     } else {
+    ///         window.update_with_buffer(&buffer, 640, 400).unwrap();
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn is_occluded(&self) -> bool {
+        self.0.is_occluded()
+    }
+
+    ///
+    /// Closes the window. The native window is removed from the screen right away and `is_open`
+    /// returns false from then on. Calling `update` or `update_with_buffer` on a closed window
+    /// does nothing. The native window itself is only released when the `Window` is dropped, so
+    /// the other functions can still be called, they act on the hidden window.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// while window.is_open() {
+    ///     if window.is_key_down(Key::Q) {
+    ///         window.close();
+    ///     }
+    ///     window.update();
+    /// }
+    /// ```
+    #[inline]
+    pub fn close(&mut self) {
+        self.0.close()
+    }
+
+    ///
+    /// Returns why the window was closed, or `None` while it's still open. This makes it
+    /// possible to tell a normal shutdown apart from a lost connection to the display server
+    /// once `is_open` returns false.
+    ///
+    /// `CloseReason::DisplayError` is only reported on Wayland. On X11 Xlib ends the process
+    /// when the connection to the X server is lost.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// while window.is_open() {
+    ///     window.update();
+    /// }
+    ///
+    /// if window.close_reason() == Some(CloseReason::DisplayError) {
+    ///     eprintln!("Lost the connection to the display");
+    ///     std::process::exit(1);
+    /// }
+    /// ```
+    #[inline]
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        self.0.close_reason()
+    }
+
+    ///
+    /// Controls what happens when the user asks to close the window (for example by pressing the
+    /// close button). By default (`true`) the window is closed right away and `is_open` starts
+    /// returning false. When set to `false` the window stays open and `close_requested` returns
+    /// true instead, which leaves it up to the application to decide what to do.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_close_requested_handling(false);
+    ///
+    /// let unsaved_changes = false;
+    ///
+    /// while window.is_open() {
+    ///     if window.close_requested() && !unsaved_changes {
+    ///         break;
+    ///     }
+    ///     window.update();
+    /// }
+    /// ```
+    #[inline]
+    pub fn set_close_requested_handling(&mut self, auto: bool) {
+        self.0.set_close_requested_handling(auto)
+    }
+
+    ///
+    /// Controls whether the system's own keyboard shortcuts for the window are handled. By
+    /// default (`false`) these keys are only reported to the application, so games can use
+    /// Alt and F10 freely.
+    ///
+    /// Platform specific notes:
+    ///
+    /// * **Windows**: when enabled, Alt and F10 open the window menu, Alt+Space opens the system
+    ///   menu and Alt+F4 closes the window. The keys are still reported either way.
+    /// * **macOS**: the standard menu shortcuts such as Cmd+Q and Cmd+H always work.
+    /// * **X11**, **Wayland** and **Redox**: minifb doesn't handle any keys itself. Shortcuts of
+    ///   the window manager or compositor always take precedence and can't be turned off here.
+    /// * **Web**: browser shortcuts always apply.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // Let Alt+F4 close the window on Windows
+    /// window.set_default_accelerators(true);
+    /// ```
+    #[inline]
+    pub fn set_default_accelerators(&mut self, enabled: bool) {
+        self.0.set_default_accelerators(enabled)
+    }
+
+    ///
+    /// Returns true if the user asked to close the window during the last call to `update` or
+    /// `update_with_buffer`. This is only reported when automatic closing has been turned off
+    /// with `set_close_requested_handling(false)`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// if window.close_requested() {
+    ///     println!("Are you sure you want to quit?");
+    /// }
+    /// ```
+    #[inline]
+    pub fn close_requested(&self) -> bool {
+        self.0.close_requested()
+    }
+
+    ///
+    /// Returns the ratio between window pixels and buffer pixels in x and y, according to the
+    /// `ScaleMode` and the size of the last buffer given to `update_with_buffer`. For example
+    /// `(2.0, 2.0)` means each buffer pixel covers 2 x 2 pixels of the window. With
+    /// `ScaleMode::Center` and `ScaleMode::UpperLeft` the buffer isn't scaled and `(1.0, 1.0)` is
+    /// returned.
+    ///
+    /// Before the first buffer is shown, the buffer is assumed to have the size the window was
+    /// created with.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let (scale_x, scale_y) = window.get_buffer_scale();
+    /// println!("Each buffer pixel is {} x {} window pixels", scale_x, scale_y);
+    /// ```
+    #[inline]
+    pub fn get_buffer_scale(&self) -> (f32, f32) {
+        self.0.get_buffer_scale()
+    }
+
+    ///
+    /// Returns the scale the desktop applies to the content of the window, such as `1.5` on a
+    /// display set to 150%. Rendering at the window size times this scale gives crisp output, for
+    /// example for text.
+    ///
+    /// This is unrelated to `WindowOptions::scale` and `get_buffer_scale`, which only describe
+    /// how minifb scales the buffer to the size of the window. Unlike a DPI value rounded to a
+    /// whole scale factor, this is the real, possibly fractional, scale of the surface.
+    ///
+    /// **Windows:** The DPI of the window divided by 96. Processes that aren't DPI aware are
+    /// scaled by the system and always get `1.0`
+    ///
+    /// **Linux:**
+    /// - X11: Based on the `Xft.dpi` resource, which desktops set to scale X11 applications.
+    ///   Changes made after the window was opened aren't picked up
+    /// - Wayland: The preferred scale sent through the `wp_fractional_scale_v1` protocol,
+    ///   `1.0` on compositors that don't support it
+    ///
+    /// **MacOS:** The backing scale factor of the window, `2.0` on Retina displays
+    ///
+    /// **RedoxOS:** Always `1.0`
+    ///
+    /// **Web:** The device pixel ratio of the page
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let scale = window.get_content_scale();
+    /// let font_size = (16.0 * scale).round();
+    /// ```
+    #[inline]
+    pub fn get_content_scale(&self) -> f32 {
+        self.0.get_content_scale()
+    }
+
+    ///
+    /// Returns the pixel layout the display uses for the window, or `None` if it isn't one of
+    /// the `PixelFormat` layouts. Buffers in another layout than this are converted on every
+    /// update, see `WindowOptions::pixel_format`.
+    ///
+    /// **Linux:**
+    /// - X11: The layout of the visual the window was created with. Visuals in another order
+    ///   than `0x00RRGGBB` are only used when no other TrueColor visual is available.
+    /// - Wayland: Argb8888 for transparent windows, Xrgb8888 otherwise
+    ///
+    /// **Windows:** Always Xrgb8888
+    ///
+    /// **MacOS / RedoxOS:** Always Argb8888
+    ///
+    /// **Web:** Always `None`, the canvas takes RGBA bytes
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// println!("The display uses {:?}", window.get_native_pixel_format());
+    /// ```
+    #[inline]
+    pub fn get_native_pixel_format(&self) -> Option<PixelFormat> {
+        self.0.get_native_pixel_format()
+    }
+    ///
+    /// Returns true if the window was created with `WindowOptions::transparency` and the
+    /// transparent pixels are actually blended with what's behind the window. On X11 this
+    /// requires a running compositing manager and on Windows that desktop composition is
+    /// enabled. Always false on macOS and the web.
+    ///
+    /// Apps can use this to fall back to an opaque background when transparency isn't available.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// let mut window = Window::new(
+    ///     "Test",
+    ///     640,
+    ///     400,
+    ///     WindowOptions {
+    ///         borderless: true,
+    ///         transparency: true,
+    ///         ..WindowOptions::default()
+    ///     },
+    /// )
+    /// .unwrap();
+    ///
+    /// if !window.is_transparency_active() {
+    ///     window.set_background_color(0, 0, 0);
+    /// }
+    /// ```
+    #[inline]
+    pub fn is_transparency_active(&self) -> bool {
+        self.0.is_transparency_active()
+    }
+
+    ///
+    /// Returns the monitor the window is on. When the window spans several monitors, the one
+    /// that contains the center of the window is returned.
+    ///
+    /// Returns `None` on Wayland, Redox and the web, or when the monitor can't be determined
+    /// (for example when the RandR extension is missing on X11).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// if let Some(monitor) = window.current_monitor() {
+    ///     println!("{} runs at {:?} Hz", monitor.name, monitor.refresh_rate);
+    /// }
+    /// ```
+    #[inline]
+    pub fn current_monitor(&self) -> Option<Monitor> {
+        self.0.current_monitor()
+    }
+
+    ///
+    /// Returns the number of connected displays. Use `InputCallback::displays_changed` to be
+    /// told when a display is connected or disconnected, which apps that open a window on each
+    /// monitor need to react to.
+    ///
+    /// **Windows:** Uses `GetSystemMetrics(SM_CMONITORS)`, changes are reported on
+    /// `WM_DISPLAYCHANGE`
+    ///
+    /// **Linux:**
+    /// - X11: The number of active RandR CRTCs, changes are reported on RandR screen-change
+    ///   events. Always 0 when the RandR extension is missing.
+    /// - Wayland: The number of `wl_output` globals
+    /// - Framebuffer: Always 1
+    ///
+    /// **MacOS:** The number of `NSScreen`s, changes are reported by
+    /// `CGDisplayRegisterReconfigurationCallback`
+    ///
+    /// **RedoxOS / Web:** Always 0, displays aren't listed
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// println!("{} displays connected", window.display_count());
+    /// ```
+    #[inline]
+    pub fn display_count(&self) -> usize {
+        self.0.display_count()
+    }
+
+    ///
+    /// Sets the gamma ramp of the monitor the window is on, for example for a calibration tool.
+    /// Each channel maps the 256 input levels to output levels from 0 to 65535.
+    ///
+    /// This changes the whole monitor, not just the window. The ramp the monitor had before the
+    /// first change is restored when the window is dropped.
+    ///
+    /// **Windows:** Uses `SetDeviceGammaRamp`, which may refuse ramps that are too far from the
+    /// identity
+    ///
+    /// **Linux:**
+    /// - X11: Uses the gamma of the RandR CRTC, the ramp is resampled to the size of the CRTC
+    /// - Wayland / Framebuffer: Returns `Error::Unsupported`
+    ///
+    /// **MacOS:** Uses `CGSetDisplayTransferByTable`
+    ///
+    /// **RedoxOS / Web:** Returns `Error::Unsupported`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // Darken the monitor with a gamma of 2.0
+    /// let mut ramp = [0u16; 256];
+    /// for (i, level) in ramp.iter_mut().enumerate() {
+    ///     *level = ((i as f32 / 255.0).powf(2.0) * 65535.0) as u16;
+    /// }
+    ///
+    /// window.set_gamma_ramp(&ramp, &ramp, &ramp).unwrap();
+    /// ```
+    #[inline]
+    pub fn set_gamma_ramp(
+        &mut self,
+        red: &[u16; 256],
+        green: &[u16; 256],
+        blue: &[u16; 256],
+    ) -> Result<()> {
+        self.0.set_gamma_ramp(&GammaRamp {
+            red: *red,
+            green: *green,
+            blue: *blue,
+        })
+    }
+
+    ///
+    /// Returns the current gamma ramp of the monitor the window is on, see `set_gamma_ramp`.
+    /// Returns `None` when it can't be read, and always on the platforms where
+    /// `set_gamma_ramp` isn't supported.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// if let Some(ramp) = window.get_gamma_ramp() {
+    ///     println!("white is at {} {} {}", ramp.red[255], ramp.green[255], ramp.blue[255]);
+    /// }
+    /// ```
+    #[inline]
+    pub fn get_gamma_ramp(&self) -> Option<GammaRamp> {
+        self.0.get_gamma_ramp()
+    }
+
+    ///
+    /// Returns true if the window is maximized. Apps that persist their window state can store
+    /// this, `save_state` already includes it.
+    ///
+    /// **Windows:** Uses `IsZoomed`
+    ///
+    /// **Linux:**
+    /// - X11: True when `_NET_WM_STATE` has both `_NET_WM_STATE_MAXIMIZED_HORZ` and
+    ///   `_NET_WM_STATE_MAXIMIZED_VERT`
+    /// - Wayland: Uses the maximized state of the last configure event
+    ///
+    /// **MacOS:** True when the window frame equals the visible frame of its screen (zoomed)
+    ///
+    /// **RedoxOS / Web:** Always false
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// if window.is_maximized() {
+    ///     println!("the window fills the screen");
+    /// }
+    /// ```
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        self.0.is_maximized()
+    }
+
+    ///
+    /// Returns the position, size and maximized state of the window along with the monitor it
+    /// is on, so it can be put back in the same place with `restore_state` on the next run.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let state = window.save_state();
+    /// println!("window at {:?} with size {:?}", state.position, state.size);
+    /// ```
+    pub fn save_state(&self) -> WindowState {
+        WindowState {
+            position: self.get_position(),
+            size: self.get_size(),
+            maximized: self.0.is_maximized(),
+            monitor: self.current_monitor().map(|monitor| monitor.name),
+        }
+    }
+
+    ///
+    /// Moves and resizes the window to a state returned by `save_state`. When the monitor the
+    /// window was on is gone, or the position is no longer on a monitor, the window is moved
+    /// onto the closest visible area instead. Monitors can't be listed on Wayland, Redox and
+    /// the web, so the position is used as is there.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let state = WindowState {
+    ///     position: (100, 100),
+    ///     size: (800, 600),
+    ///     maximized: false,
+    ///     monitor: None,
+    /// };
+    /// window.restore_state(&state);
+    /// ```
+    pub fn restore_state(&mut self, state: &WindowState) {
+        let (width, height) = state.size;
+        let (mut x, mut y) = state.position;
+
+        let monitors = self.0.get_monitors();
+        let contains = |m: &&Monitor| {
+            x >= m.x && y >= m.y && x < m.x + m.width as isize && y < m.y + m.height as isize
+        };
+
+        // Prefer the monitor the window was on, then the one the position is on
+        let monitor = state
+            .monitor
+            .as_ref()
+            .and_then(|name| monitors.iter().find(|m| &m.name == name))
+            .or_else(|| monitors.iter().find(contains))
+            .or_else(|| monitors.first());
+
+        if let Some(monitor) = monitor {
+            if !contains(&monitor) {
+                let right = monitor.x + monitor.width as isize - width as isize;
+                let bottom = monitor.y + monitor.height as isize - height as isize;
+                x = x.min(right).max(monitor.x);
+                y = y.min(bottom).max(monitor.y);
+            }
+        }
+
+        self.set_size(width, height);
+        self.set_position(x, y);
+        self.0.set_maximized(state.maximized);
+    }
+
+    ///
+    /// Returns true if the window moved to a different monitor during the last call to `update`
+    /// or `update_with_buffer`. This can be used to reload resources that depend on the monitor,
+    /// such as the frame rate to render at.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// while window.is_open() {
+    ///     window.update();
+    ///
+    ///     if window.moved_to_monitor() {
+    ///         println!("Now on {:?}", window.current_monitor());
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn moved_to_monitor(&self) -> bool {
+        self.0.moved_to_monitor()
+    }
+
+    ///
+    /// Sets the position of the window. This is useful if you have
+    /// more than one window and want to align them up on the screen
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // Moves the window to pixel position 20, 20 on the screen
+    /// window.set_position(20, 20);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_position(&mut self, x: isize, y: isize) {
+        self.0.set_position(x, y)
+    }
+
     ///
     /// Gets the position of the window. This is useful if you want
     /// to store the position of the window across sessions
@@ -418,307 +2201,1495 @@ impl Window {
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// // Retrieves the current window position
-    /// let (x,y) = window.get_position();
+    /// // Retrieves the current window position
+    /// let (x,y) = window.get_position();
+    /// ```
+    ///
+    #[inline]
+    pub fn get_position(&self) -> (isize, isize) {
+        self.0.get_position()
+    }
+
+    ///
+    /// Returns the position of the top-left corner of the window's frame, including the title
+    /// bar and borders, on the screen.
+    ///
+    /// **Windows:** the same as `get_position`, both include the invisible resize borders.
+    ///
+    /// **Linux:**
+    /// - X11: uses `_NET_FRAME_EXTENTS`, the same as the client area when the window manager
+    ///   doesn't report it.
+    /// - Wayland: always (0, 0) as clients can't know where their windows are.
+    ///
+    /// **MacOS:** the same as `get_position`.
+    ///
+    /// **RedoxOS / Web:** the position of the client area.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let (x, y) = window.get_outer_position();
+    /// ```
+    ///
+    #[inline]
+    pub fn get_outer_position(&self) -> (isize, isize) {
+        self.0.get_outer_position()
+    }
+
+    ///
+    /// Returns the size of the window's frame in screen pixels, including the title bar and
+    /// borders. `get_size` only returns the client area.
+    ///
+    /// **Windows:** the size of `GetWindowRect`, which includes the invisible resize borders.
+    ///
+    /// **Linux:**
+    /// - X11: adds `_NET_FRAME_EXTENTS` to the client area.
+    /// - Wayland: the size of the client area, the compositor doesn't report its decorations.
+    ///
+    /// **MacOS:** the size of the window's frame.
+    ///
+    /// **RedoxOS / Web:** the size of the client area.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let (width, height) = window.get_outer_size();
+    /// ```
+    ///
+    #[inline]
+    pub fn get_outer_size(&self) -> (usize, usize) {
+        self.0.get_outer_size()
+    }
+
+    ///
+    /// Makes the window the topmost window and makes it stay always on top. This is useful if you
+    /// want the window to float above all over windows
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // Makes the window always on top
+    /// window.topmost(true);
+    /// ```
+    ///
+    #[inline]
+    pub fn topmost(&self, topmost: bool) {
+        self.0.topmost(topmost)
+    }
+
+    ///
+    /// Keeps the window below all other windows, which is useful for desktop widgets and
+    /// wallpaper-like windows. This is a hint and some window managers ignore it.
+    ///
+    /// **Windows:** the window is moved to the bottom of the z-order, it comes back up when
+    /// the user activates it.
+    ///
+    /// **Linux:**
+    /// - X11: sets `_NET_WM_STATE_BELOW` and marks the window as a desktop window.
+    /// - Wayland: not supported, the compositor decides the stacking order.
+    ///
+    /// **MacOS:** the window level is set just below the normal level.
+    ///
+    /// **RedoxOS / Web:** not supported.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // Keeps the window behind all other windows
+    /// window.set_always_on_bottom(true);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_always_on_bottom(&mut self, enabled: bool) {
+        self.0.set_always_on_bottom(enabled)
+    }
+
+    ///
+    /// Sets the stacking level of the window. This is a more fine-grained version of `topmost`
+    /// and `set_always_on_bottom`, the levels are hints and some window managers ignore them.
+    ///
+    /// **Windows:** only has topmost and normal windows, `Floating`, `ModalPanel` and
+    /// `ScreenSaver` all make the window topmost.
+    ///
+    /// **Linux:**
+    /// - X11: uses `_NET_WM_STATE_ABOVE` and `_NET_WM_STATE_BELOW`. `ModalPanel` also marks the
+    ///   window as a dialog, `ScreenSaver` is the same as `Floating`.
+    /// - Wayland: not supported, the compositor decides the stacking order.
+    ///
+    /// **MacOS:** maps directly to the `NSWindow` levels.
+    ///
+    /// **RedoxOS / Web:** not supported.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // Keeps the window above other windows, like a tool palette
+    /// window.set_window_level(WindowLevel::Floating);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_window_level(&mut self, level: WindowLevel) {
+        self.0.set_window_level(level)
+    }
+
+    ///
+    /// Plays the system beep, for example to signal an error.
+    ///
+    /// **Windows:** plays the default sound with `MessageBeep`.
+    ///
+    /// **Linux:**
+    /// - X11: rings the bell with `XBell`, at the volume set by the user.
+    /// - Wayland: not supported.
+    ///
+    /// **MacOS:** plays the alert sound with `NSBeep`.
+    ///
+    /// **RedoxOS / Web:** not supported.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.beep();
+    /// ```
+    ///
+    #[inline]
+    pub fn beep(&self) {
+        self.0.beep()
+    }
+
+    ///
+    /// Keeps the window out of the taskbar and the window switcher, which is useful for tool
+    /// palettes and overlays.
+    ///
+    /// **Windows:** gives the window the `WS_EX_TOOLWINDOW` style, which also gives it a smaller
+    /// title bar. The window is briefly hidden so the taskbar picks the change up.
+    ///
+    /// **Linux:**
+    /// - X11: sets `_NET_WM_STATE_SKIP_TASKBAR` and `_NET_WM_STATE_SKIP_PAGER`.
+    /// - Wayland: not supported.
+    ///
+    /// **MacOS:** the Dock shows applications rather than windows, so this only keeps the window
+    /// out of window cycling, Mission Control and the Window menu.
+    ///
+    /// **RedoxOS / Web:** not supported.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_skip_taskbar(true);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_skip_taskbar(&mut self, skip: bool) {
+        self.0.set_skip_taskbar(skip)
+    }
+
+    ///
+    /// Chooses which of the minimize, maximize and close buttons the title bar shows. All of them
+    /// are shown by default, except for maximize on windows that can't be resized. Hiding the
+    /// close button doesn't keep the application from closing the window itself.
+    ///
+    /// **Windows:** toggles `WS_MINIMIZEBOX` and `WS_MAXIMIZEBOX`. A button that is hidden while
+    /// the other one is shown is grayed out instead. The close button can't be removed, it's
+    /// grayed out through the system menu, which also disables Alt+F4.
+    ///
+    /// **Linux:**
+    /// - X11: sets the functions and decorations of `_MOTIF_WM_HINTS`. How these are honored
+    ///   depends on the window manager, some ignore them or only hide the buttons.
+    /// - Wayland: not supported, the decorations are up to the compositor.
+    ///
+    /// **MacOS:** hides the standard window buttons, Cmd+W and the Window menu still work.
+    ///
+    /// **RedoxOS / Web:** not supported.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // A tool window that can be minimized and closed but not maximized
+    /// window.set_caption_buttons(true, false, true);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_caption_buttons(&mut self, minimize: bool, maximize: bool, close: bool) {
+        self.0.set_caption_buttons(minimize, maximize, close)
+    }
+
+    ///
+    /// Turns the drop shadow around the window on or off. Borderless windows can look flat
+    /// without one, while an overlay with a custom shape usually shouldn't have one.
+    ///
+    /// **Windows:** uses the non-client rendering of DWM. A borderless window gets its shadow by
+    /// extending the frame a pixel into the window. Turning the shadow of a window with a title
+    /// bar off also gives it a plain frame.
+    ///
+    /// **Linux:**
+    /// - X11: sets `_COMPTON_SHADOW`, which is honored by picom and compton. Other compositors
+    ///   ignore it.
+    /// - Wayland: not supported, shadows are up to the compositor.
+    ///
+    /// **MacOS:** sets `hasShadow` of the window.
+    ///
+    /// **RedoxOS / Web:** not supported.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// let mut window = Window::new(
+    ///     "Overlay",
+    ///     640,
+    ///     400,
+    ///     WindowOptions {
+    ///         borderless: true,
+    ///         ..WindowOptions::default()
+    ///     },
+    /// )
+    /// .unwrap();
+    ///
+    /// window.set_shadow(false);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_shadow(&mut self, enabled: bool) {
+        self.0.set_shadow(enabled)
+    }
+
+    ///
+    /// Shows or hides the window. Together with `WindowOptions::visible` this makes it possible
+    /// to position the window and draw its first frame before it appears on screen.
+    ///
+    /// Buffers given to `update_with_buffer` while the window is hidden aren't presented, the
+    /// last one is kept instead. Call `flush` after showing the window to present it right away.
+    ///
+    /// **Linux:**
+    /// - X11: Supported
+    /// - Wayland: *not supported*
+    ///
+    /// **RedoxOS / Web:** *not supported*
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// let mut window = Window::new(
+    ///     "Test",
+    ///     640,
+    ///     400,
+    ///     WindowOptions {
+    ///         visible: false,
+    ///         ..WindowOptions::default()
+    ///     },
+    /// )
+    /// .unwrap();
+    ///
+    /// window.set_position(100, 100);
+    /// window.update_with_buffer(&vec![0; 640 * 400], 640, 400).unwrap();
+    ///
+    /// window.set_visible(true);
+    /// window.flush().unwrap();
+    /// ```
+    ///
+    #[inline]
+    pub fn set_visible(&mut self, visible: bool) {
+        self.0.set_visible(visible)
+    }
+
+    ///
+    /// Sets the background color that is used with update_with_buffer.
+    /// In some cases there will be a blank area around the buffer depending on the ScaleMode that has been set.
+    /// This color will be used in the in that area.
+    /// The function takes 3 parameters in (red, green, blue) and each value is in the range of 0-255 where 255 is the brightest value
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // Set background color to bright red
+    /// window.set_background_color(255, 0, 0);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_background_color(&mut self, red: usize, green: usize, blue: usize) {
+        let r = red.clamp(0, 255);
+        let g = green.clamp(0, 255);
+        let b = blue.clamp(0, 255);
+        self.0
+            .set_background_color(((r << 16) | (g << 8) | b) as u32);
+    }
+
+    ///
+    /// Same as `set_background_color` but takes a color packed as `0xAARRGGBB`, the same format
+    /// as the pixels in the buffer. The alpha channel is only used if the window was created with
+    /// `WindowOptions::transparency` on a platform that supports it, otherwise it's ignored.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // Set background color to bright red
+    /// window.set_background_color_u32(0xff_ff_00_00);
+    /// // Or using the color helpers
+    /// window.set_background_color_u32(rgb(255, 0, 0));
+    /// ```
+    ///
+    #[inline]
+    pub fn set_background_color_u32(&mut self, color: u32) {
+        self.0.set_background_color(color);
+    }
+
+    ///
+    /// Sets how the borders left by `ScaleMode::Center`, `ScaleMode::AspectRatioStretch` and
+    /// `ScaleMode::UpperLeft` are filled. `BorderFill::Solid` is the same as
+    /// `set_background_color_u32`, a later call to `set_background_color` replaces any fill.
+    ///
+    /// A `BorderFill::Tile` with a zero size, or with a buffer that's smaller than its size, is
+    /// ignored.
+    ///
+    /// **MacOS / RedoxOS / Web:** Only `BorderFill::Solid` is supported, other fills are ignored
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_border_fill(BorderFill::Gradient(rgb(32, 32, 64), rgb(0, 0, 0)));
+    ///
+    /// // A 2x2 checkerboard
+    /// let pattern = vec![0xff40_4040, 0xff20_2020, 0xff20_2020, 0xff40_4040];
+    /// window.set_border_fill(BorderFill::Tile(pattern, 2, 2));
+    /// ```
+    ///
+    pub fn set_border_fill(&mut self, fill: BorderFill) {
+        match fill {
+            BorderFill::Solid(color) => self.0.set_background_color(color),
+            BorderFill::Tile(ref pattern, width, height)
+                if width == 0 || height == 0 || pattern.len() < width * height => {}
+            fill => self.0.set_border_fill(fill),
+        }
+    }
+
+    ///
+    /// Changes whether or not the cursor image should be shown or if the cursor image
+    /// should be invisible inside the window
+    /// When creating a new window the cursor is visible
+    #[inline]
+    pub fn set_cursor_visibility(&mut self, visibility: bool) {
+        self.0.set_cursor_visibility(visibility);
+    }
+
+    ///
+    /// Returns the visibility last set with `set_cursor_visibility` (or `set_gameplay_cursor_mode`),
+    /// true for a new window. A cursor hidden by `set_cursor_auto_hide` still counts as visible,
+    /// as it comes back once the mouse moves.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// if window.is_key_pressed(Key::H, KeyRepeat::No) {
+    ///     window.set_cursor_visibility(!window.is_cursor_visible());
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn is_cursor_visible(&self) -> bool {
+        self.0.is_cursor_visible()
+    }
+
+    ///
+    /// Hides the cursor inside the window once the mouse hasn't moved for `timeout` and shows it
+    /// again as soon as it moves, which is useful for media players and fullscreen applications.
+    /// Pass None to turn this off again. A cursor hidden with `set_cursor_visibility` stays
+    /// hidden.
+    ///
+    /// The mouse is checked when the window is updated, so the cursor is hidden on the first
+    /// update after the timeout has passed. Not supported on the web.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_cursor_auto_hide(Some(std::time::Duration::from_secs(2)));
+    /// ```
+    #[inline]
+    pub fn set_cursor_auto_hide(&mut self, timeout: Option<std::time::Duration>) {
+        self.0.set_cursor_auto_hide(timeout);
+    }
+
+    ///
+    /// Keeps the cursor inside the window while it's free to move within it. The confinement is
+    /// lifted while the window doesn't have focus and applied again once it regains it.
+    ///
+    /// **Windows:** The cursor is clipped to the client area
+    ///
+    /// **Linux:**
+    /// - X11: The pointer is grabbed while the window has focus
+    /// - Wayland: Requires a compositor with the pointer constraints protocol
+    ///
+    /// **MacOS:** The cursor is moved back inside the window when it leaves it during an update
+    ///
+    /// **RedoxOS / Web:** *not supported*
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_cursor_confined(true);
+    /// ```
+    #[inline]
+    pub fn set_cursor_confined(&mut self, confined: bool) {
+        self.0.set_cursor_confined(confined);
+    }
+
+    ///
+    /// Turns on the "mouselook" cursor handling most games need: the cursor is hidden and kept
+    /// inside the window, and `get_mouse_delta` reports how far the mouse moved during each
+    /// update. The cursor is moved back to the center of the window so the movement isn't stopped
+    /// at the edges. Like `set_cursor_confined` the cursor is released while the window doesn't
+    /// have focus. Turning it off restores the cursor visibility and confinement the window had
+    /// before it was turned on.
+    ///
+    /// **Windows:** The cursor is clipped to the client area and moved back to the center
+    ///
+    /// **Linux:**
+    /// - X11: The pointer is grabbed and moved back to the center
+    /// - Wayland: The pointer is locked in place and the movement comes from the relative pointer
+    ///   protocol. Without it the pointer is only confined, so the movement stops at the edges
+    ///
+    /// **MacOS:** The cursor is moved back to the center
+    ///
+    /// **RedoxOS:** The cursor is only hidden, no movement is reported
+    ///
+    /// **Web:** *not supported*
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_gameplay_cursor_mode(true);
+    ///
+    /// let (mut yaw, mut pitch) = (0.0, 0.0);
+    ///
+    /// while window.is_open() {
+    ///     window.update();
+    ///
+    ///     let (dx, dy) = window.get_mouse_delta();
+    ///     yaw += dx * 0.1;
+    ///     pitch += dy * 0.1;
+    /// }
+    /// ```
+    #[inline]
+    pub fn set_gameplay_cursor_mode(&mut self, enabled: bool) {
+        self.0.set_gameplay_cursor_mode(enabled);
+    }
+
+    ///
+    /// Returns how far the mouse moved in screen pixels during the last update while the gameplay
+    /// cursor mode is on, see `set_gameplay_cursor_mode`. Returns (0.0, 0.0) otherwise.
+    ///
+    #[inline]
+    pub fn get_mouse_delta(&self) -> (f32, f32) {
+        self.0.get_mouse_delta()
+    }
+
+    ///
+    /// Sends the keys the system would normally intercept, such as Alt+Tab or the Windows key, to
+    /// the window instead. This is meant for kiosk style applications. The grab is lifted while
+    /// the window doesn't have focus and applied again once it regains it, so the user can't be
+    /// locked out by another window.
+    ///
+    /// **Windows:** A low-level keyboard hook catches the Windows keys, Alt+Tab, Alt+Esc and
+    /// Ctrl+Esc. Ctrl+Alt+Del is always handled by the system
+    ///
+    /// **Linux:**
+    /// - X11: The keyboard is grabbed while the window has focus, which can fail if another
+    ///   client already holds a grab
+    /// - Wayland: Requires a compositor with the keyboard shortcuts inhibit protocol, which may
+    ///   ask the user for permission
+    ///
+    /// **MacOS:** Only turns off process switching, hiding the application and the Dock.
+    /// Capturing every key would require an event tap, which needs the accessibility permission
+    /// and isn't done by minifb
+    ///
+    /// **RedoxOS / Web:** *not supported*
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_keyboard_grab(true);
+    /// ```
+    #[inline]
+    pub fn set_keyboard_grab(&mut self, grab: bool) {
+        self.0.set_keyboard_grab(grab);
+    }
+
+    ///
+    /// Limits the update rate of polling for new events in order to reduce CPU usage.
+    /// The problem of having a tight loop that does something like this
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// loop {
+    ///    window.update();
+    /// }
+    /// ```
+    /// Is that lots of CPU time will be spent calling system functions to check for new events in a tight loop making the CPU time go up.
+    /// Using `limit_update_rate` minifb will check how much time has passed since the last time and if it's less than the selected time it will sleep for the remainder of it.
+    /// This means that if more time has spent than the set time (external code taking longer) minifb will not do any waiting at all so there is no loss in CPU performance with this feature.
+    /// By default it's set to 4 milliseconds. Setting this value to None and no waiting will be done
+    ///
+    /// `update_with_buffer_vsync` ignores this limit on platforms where it can wait for vsync.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // Make sure that at least 4 ms has passed since the last event poll
+    /// window.limit_update_rate(Some(std::time::Duration::from_millis(4)));
+    /// ```
+    ///
+    #[inline]
+    pub fn limit_update_rate(&mut self, time: Option<std::time::Duration>) {
+        self.0.set_rate(time)
+    }
+
+    ///
+    /// Paces the updates to the given number of frames per second, replacing the limit set by
+    /// `limit_update_rate`. Unlike `limit_update_rate` the end of each wait is spent spinning
+    /// instead of sleeping, which costs a bit of CPU time but keeps the frame times even.
+    /// Passing 0 turns off the limit.
+    ///
+    /// Use `get_frame_time` to see how close the updates got to the target. This has no effect
+    /// on the web where the browser paces the frames.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_target_fps(60);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_target_fps(&mut self, fps: u32) {
+        self.0.set_target_fps(fps)
+    }
+
+    ///
+    /// Returns the time between the last two updates of the window, including the time spent
+    /// waiting for the update rate limit. Returns zero before the first update and on the web.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_target_fps(60);
+    /// window.update();
+    /// println!("last frame took {:?}", window.get_frame_time());
+    /// ```
+    ///
+    #[inline]
+    pub fn get_frame_time(&self) -> std::time::Duration {
+        self.0.get_frame_time()
+    }
+
+    ///
+    /// Returns the current size of the window
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let size = window.get_size();
+    /// println!("width {} height {}", size.0, size.1);
+    /// ```
+    ///
+    #[inline]
+    pub fn get_size(&self) -> (usize, usize) {
+        self.0.get_size()
+    }
+
+    ///
+    /// Returns the size of the window in screen pixels, before the `Scale` the window was
+    /// created with is taken out. This is the area `get_unscaled_mouse_pos` is measured in.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let (width, height) = window.get_unscaled_size();
+    /// if let Some((x, y)) = window.get_unscaled_mouse_pos(MouseMode::Discard) {
+    ///     println!("mouse at {:.0}% x {:.0}%", x * 100.0 / width as f32, y * 100.0 / height as f32);
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn get_unscaled_size(&self) -> (usize, usize) {
+        self.0.get_unscaled_size()
+    }
+
+    ///
+    /// Resizes the client area of the window to the given size, in the same units as `get_size`.
+    /// A window created without `resize` keeps its fixed size at the new value. The next
+    /// `update_with_buffer` scales the buffer to the new size according to the window's
+    /// `ScaleMode`.
+    ///
+    /// Has no effect on the web, where the canvas size follows the buffer.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_size(800, 600);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_size(&mut self, width: usize, height: usize) {
+        self.0.set_size(width, height)
+    }
+
+    ///
+    /// Keeps the content area of the window at the given aspect ratio while the user resizes
+    /// it, so a buffer with that shape fills the window without the borders added by
+    /// `ScaleMode::AspectRatioStretch`. Passing 0 for either value removes the constraint.
+    ///
+    /// Only resizing by the user is constrained, `set_size` still uses the size it's given. This
+    /// has no effect on windows that can't be resized.
+    ///
+    /// **Windows:** The size is adjusted while the window frame is dragged
+    ///
+    /// **Linux:**
+    /// - X11: The aspect ratio is passed to the window manager, which may ignore it
+    /// - Wayland: *not supported*
+    ///
+    /// **MacOS:** Supported
+    ///
+    /// **RedoxOS / Web:** *not supported*
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 360, WindowOptions { resize: true, ..WindowOptions::default() }).unwrap();
+    /// window.set_aspect_ratio(16, 9);
+    /// ```
+    #[inline]
+    pub fn set_aspect_ratio(&mut self, numerator: u32, denominator: u32) {
+        let ratio = if numerator == 0 || denominator == 0 {
+            None
+        } else {
+            Some((numerator, denominator))
+        };
+
+        self.0.set_aspect_ratio(ratio)
+    }
+
+    ///
+    /// Get the fingers touching the window as of the last update. Touches that started since
+    /// the previous update are reported with `TouchPhase::Begin` and touches that ended are
+    /// reported once with `TouchPhase::End`. A tap that starts and ends between two updates is
+    /// reported with `TouchPhase::Begin` first and with `TouchPhase::End` in the next update.
+    ///
+    /// Touch screens are supported on Windows, on Wayland and on X11 when the XInput 2.2
+    /// extension is present. On macOS the touches on the trackpad are reported, mapped onto the window.
+    /// Returns an empty list on other platforms.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// for touch in window.get_touch_points() {
+    ///     if touch.phase == TouchPhase::Begin {
+    ///         println!("touch {} at {} {}", touch.id, touch.x, touch.y);
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn get_touch_points(&self) -> Vec<TouchPoint> {
+        self.0.get_touch_points()
+    }
+
+    ///
+    /// Get the state of a pen or stylus, or None if no pen has been used with the window yet.
+    /// The state is kept after the pen is lifted or leaves the window, with the pressure going
+    /// back to 0.0 and `in_range` telling if the pen is still being tracked.
+    ///
+    /// Supported on Windows 8 and later, on X11 through the XInput2 extension and on macOS.
+    /// X11 doesn't report when the pen goes out of range, so `in_range` is always true there.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// if let Some(pen) = window.get_pen() {
+    ///     if pen.pressure > 0.0 {
+    ///         println!("drawing at {} {} with pressure {}", pen.x, pen.y, pen.pressure);
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn get_pen(&self) -> Option<PenState> {
+        self.0.get_pen()
+    }
+
+    ///
+    /// Get the current position of the mouse relative to the current window
+    /// The coordinate system is as 0, 0 as the upper left corner
+    ///
+    /// The position keeps the sub-pixel precision reported by the platform, which is available
+    /// on Wayland, macOS and on X11 when the XInput2 extension is present. Windows only reports
+    /// whole pixels.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.get_mouse_pos(MouseMode::Clamp).map(|mouse| {
+    ///     println!("x {} y {}", mouse.0, mouse.1);
+    /// });
+    /// ```
+    ///
+    #[inline]
+    pub fn get_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
+        self.0.get_mouse_pos(mode)
+    }
+
+    ///
+    /// Returns every position the mouse moved to since the last update, oldest first, with the
+    /// time of each movement. The positions are in the same coordinates as `get_mouse_pos`
+    /// (without any clamping) so the exact path of the mouse can be followed, for example to
+    /// draw smooth strokes when the mouse moves faster than the window updates.
+    ///
+    /// The times use the same clock as `get_input_timestamp` where the platform timestamps mouse
+    /// events, otherwise they're the time since the window was created.
+    ///
+    /// **Windows:** Every `WM_MOUSEMOVE` message is reported.
+    ///
+    /// **Linux:**
+    /// - X11: Every motion event is reported.
+    /// - Wayland: Every motion event is reported.
+    ///
+    /// **MacOS / Web:** Only the position at the time of the update is reported.
+    ///
+    /// **RedoxOS:** Every mouse event is reported, without a timestamp.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// for (x, y, time) in window.get_mouse_history() {
+    ///     println!("x {} y {} at {:?}", x, y, time);
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn get_mouse_history(&self) -> Vec<(f32, f32, std::time::Duration)> {
+        self.0.get_mouse_history()
+    }
+
+    ///
+    /// Get the current position of the mouse relative to the current window
+    /// The coordinate system is as 0, 0 as the upper left corner and ignores
+    /// any scaling set to the window.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.get_unscaled_mouse_pos(MouseMode::Clamp).map(|mouse| {
+    ///     println!("x {} y {}", mouse.0, mouse.1);
+    /// });
+    /// ```
+    ///
+    #[inline]
+    pub fn get_unscaled_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
+        self.0.get_unscaled_mouse_pos(mode)
+    }
+
+    ///
+    /// Get the current position of the mouse on the desktop, with 0, 0 as the upper left corner
+    /// of the primary monitor. Unlike `get_mouse_pos` this also reports the pointer when it's
+    /// outside of the window.
+    ///
+    /// **Windows:** uses `GetCursorPos`.
+    ///
+    /// **Linux:**
+    /// - X11: the position relative to the root window, as of the last update. Returns `None`
+    ///   while the pointer is on another X screen.
+    /// - Wayland: always returns `None`, clients can't see global coordinates.
+    ///
+    /// **MacOS:** uses `NSEvent.mouseLocation`, flipped so y grows downwards.
+    ///
+    /// **RedoxOS:** only known while the pointer is over the window.
+    ///
+    /// **Web:** always returns `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// if let Some((x, y)) = window.get_mouse_pos_on_screen() {
+    ///     println!("pointer at {} {} on the desktop", x, y);
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn get_mouse_pos_on_screen(&self) -> Option<(f32, f32)> {
+        self.0.get_mouse_pos_on_screen()
+    }
+
+    ///
+    /// Check if a mouse button is down or not
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let left_down = window.get_mouse_down(MouseButton::Left);
+    /// println!("is left down? {}", left_down)
+    /// ```
+    ///
+    #[inline]
+    pub fn get_mouse_down(&self, button: MouseButton) -> bool {
+        self.0.get_mouse_down(button)
+    }
+
+    ///
+    /// Returns the modifier keys that were held when the mouse button went down, or `None` if
+    /// the button isn't down. Unlike checking `is_key_down` after the click this doesn't race
+    /// with the modifier being released, which makes it a good fit for shift- or ctrl-click.
+    ///
+    /// **MacOS:** The modifiers are captured at the end of the update in which the button went down.
+    ///
+    /// **RedoxOS / Web:** The modifiers aren't captured, the ones held right now are returned.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// if let Some(modifiers) = window.get_mouse_down_with_modifiers(MouseButton::Left) {
+    ///     if modifiers.shift {
+    ///         println!("extending the selection");
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn get_mouse_down_with_modifiers(&self, button: MouseButton) -> Option<Modifiers> {
+        self.0.get_mouse_down_with_modifiers(button)
+    }
+
+    ///
+    /// Get the current movement of the scroll wheel.
+    /// Scroll wheel can mean different thing depending on the device attach.
+    /// For example on Mac with trackpad "scroll wheel" means two finger
+    /// swiping up/down (y axis) and to the sides (x-axis)
+    /// When using a mouse this assumes the scroll wheel which often is only y direction.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.get_scroll_wheel().map(|scroll| {
+    ///     println!("scrolling - x {} y {}", scroll.0, scroll.1);
+    /// });
+    /// ```
+    ///
+    ///
+    #[inline]
+    pub fn get_scroll_wheel(&self) -> Option<(f32, f32)> {
+        self.0.get_scroll_wheel()
+    }
+
+    ///
+    /// Get the scroll wheel movement summed up since the window was created or since the last
+    /// call to `reset_scroll_accumulator`. Every scroll event is added, including several events
+    /// that arrive during a single update, so this is useful for precise zooming without
+    /// summing up the values of `get_scroll_wheel`.
+    ///
+    /// **RedoxOS / Web:** *not supported*, always returns `(0.0, 0.0)`
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let (_, scroll_y) = window.get_scroll_accumulated();
+    /// let zoom = 1.1f32.powf(scroll_y);
+    ///
+    /// if window.is_key_pressed(Key::Key0, KeyRepeat::No) {
+    ///     window.reset_scroll_accumulator();
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn get_scroll_accumulated(&self) -> (f32, f32) {
+        self.0.get_scroll_accumulated()
+    }
+
+    ///
+    /// Reset the value returned by `get_scroll_accumulated` back to `(0.0, 0.0)`.
+    ///
+    #[inline]
+    pub fn reset_scroll_accumulator(&mut self) {
+        self.0.reset_scroll_accumulator()
+    }
+
+    ///
+    /// Set a different cursor style. This can be used if you have resizing
+    /// elements or something like that
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_cursor_style(CursorStyle::ResizeLeftRight);
     /// ```
     ///
-    #[inline]
-    pub fn get_position(&self) -> (isize, isize) {
-        self.0.get_position()
+    pub fn set_cursor_style(&mut self, cursor: CursorStyle) {
+        self.0.set_cursor_style(cursor)
     }
 
     ///
-    /// Makes the window the topmost window and makes it stay always on top. This is useful if you
-    /// want the window to float above all over windows
+    /// Returns the cursor style last set with `set_cursor_style`, `CursorStyle::Arrow` for a new
+    /// window. The style is returned even on platforms that can't show it.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// // Makes the window always on top
-    /// window.topmost(true);
+    /// // Show a hand over a button and put the previous cursor back afterwards
+    /// let previous = window.get_cursor_style();
+    /// window.set_cursor_style(CursorStyle::ClosedHand);
+    /// // ...
+    /// window.set_cursor_style(previous);
     /// ```
     ///
     #[inline]
-    pub fn topmost(&self, topmost: bool) {
-        self.0.topmost(topmost)
+    pub fn get_cursor_style(&self) -> CursorStyle {
+        self.0.get_cursor_style()
     }
 
     ///
-    /// Sets the background color that is used with update_with_buffer.
-    /// In some cases there will be a blank area around the buffer depending on the ScaleMode that has been set.
-    /// This color will be used in the in that area.
-    /// The function takes 3 parameters in (red, green, blue) and each value is in the range of 0-255 where 255 is the brightest value
+    /// Returns the hotspot of the current cursor: the point of the cursor image, relative to its
+    /// top-left corner, that is at the mouse position. For a cursor set with
+    /// `set_animated_cursor` this is the hotspot that was given, clamped to the first frame. For
+    /// the styles it's the one of the system cursor, which depends on the platform and the
+    /// cursor theme.
+    ///
+    /// **Linux:**
+    /// - X11 / Wayland: Read from the cursor theme.
+    /// - Framebuffer: Always `(0, 0)`, no cursor is drawn.
+    ///
+    /// **RedoxOS / Web:** Always `(0, 0)`, the hotspots of the cursors aren't known.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
-    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// // Set background color to bright red
-    /// window.set_background_color(255, 0, 0);
+    /// # let window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // Line a tooltip up with the point of the cursor instead of its corner
+    /// let (hot_x, hot_y) = window.get_cursor_hotspot();
+    /// println!("The cursor points {} pixels right and {} down of its corner", hot_x, hot_y);
     /// ```
     ///
     #[inline]
-    pub fn set_background_color(&mut self, red: usize, green: usize, blue: usize) {
-        let r = clamp(0, red, 255);
-        let g = clamp(0, green, 255);
-        let b = clamp(0, blue, 255);
+    pub fn get_cursor_hotspot(&self) -> (i32, i32) {
+        self.0.get_cursor_hotspot()
+    }
+
+    ///
+    /// Shows a cursor that cycles through the given frames, such as a custom busy indicator.
+    /// Each frame is a buffer of `0xAARRGGBB` pixels with its width and height, `hot_x` and
+    /// `hot_y` are the point of the frames that is at the mouse position. A single frame, or a
+    /// `frame_duration` of zero, shows a still image. `set_cursor_style` switches back to the
+    /// regular cursors, `get_cursor_style` keeps returning the last style meanwhile.
+    ///
+    /// Returns an error if no frames are given, a frame is empty or a buffer is too small for its
+    /// size.
+    ///
+    /// **Windows / MacOS / Linux:** The frames are swapped during `update`, except on X11 where
+    /// the server animates the cursor.
+    ///
+    /// **RedoxOS / Web:** Not supported, the cursor is left as it is.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # use std::time::Duration;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // A spinner of four 16x16 frames, each lighting up another quarter
+    /// let frames: Vec<Vec<u32>> = (0..4)
+    ///     .map(|frame| {
+    ///         (0..16 * 16)
+    ///             .map(|i| {
+    ///                 let quarter = (i % 16 / 8) + (i / 16 / 8) * 2;
+    ///                 if quarter == frame { 0xffff_ffff } else { 0xff40_4040 }
+    ///             })
+    ///             .collect()
+    ///     })
+    ///     .collect();
+    /// let frames: Vec<(&[u32], u32, u32)> = frames.iter().map(|f| (&f[..], 16, 16)).collect();
+    ///
+    /// window
+    ///     .set_animated_cursor(&frames, Duration::from_millis(100), 8, 8)
+    ///     .unwrap();
+    /// ```
+    ///
+    pub fn set_animated_cursor(
+        &mut self,
+        frames: &[(&[u32], u32, u32)],
+        frame_duration: std::time::Duration,
+        hot_x: u32,
+        hot_y: u32,
+    ) -> Result<()> {
+        if frames.is_empty() {
+            return Err(Error::UpdateFailed(
+                "An animated cursor needs at least one frame".to_owned(),
+            ));
+        }
+
+        for &(buffer, width, height) in frames {
+            if width == 0 || height == 0 {
+                return Err(Error::UpdateFailed(
+                    "Cursor frames can't be empty".to_owned(),
+                ));
+            }
+            buffer_helper::check_buffer_size(
+                width as usize,
+                height as usize,
+                width as usize,
+                buffer,
+            )?;
+        }
+
         self.0
-            .set_background_color(((r << 16) | (g << 8) | b) as u32);
+            .set_animated_cursor(frames, frame_duration, hot_x, hot_y);
+        Ok(())
     }
 
     ///
-    /// Changes whether or not the cursor image should be shown or if the cursor image
-    /// should be invisible inside the window
-    /// When creating a new window the default is 'false'
+    /// Get the current keys that are down.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.get_keys().iter().for_each(|key|
+    ///         match key {
+    ///             Key::W => println!("holding w"),
+    ///             Key::T => println!("holding t"),
+    ///             _ => (),
+    ///         }
+    ///     );
+    /// ```
     #[inline]
-    pub fn set_cursor_visibility(&mut self, visibility: bool) {
-        self.0.set_cursor_visibility(visibility);
+    pub fn get_keys(&self) -> Vec<Key> {
+        self.0.get_keys()
     }
 
     ///
-    /// Limits the update rate of polling for new events in order to reduce CPU usage.
-    /// The problem of having a tight loop that does something like this
+    /// Get the current keys that are down, in the order they were pressed. Unlike `get_keys` this
+    /// borrows an internal list so no allocation is done when calling it.
+    ///
+    /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// loop {
-    ///    window.update();
+    /// for key in window.keys_down() {
+    ///     match key {
+    ///         Key::W => println!("holding w"),
+    ///         Key::T => println!("holding t"),
+    ///         _ => (),
+    ///     }
     /// }
     /// ```
-    /// Is that lots of CPU time will be spent calling system functions to check for new events in a tight loop making the CPU time go up.
-    /// Using `limit_update_rate` minifb will check how much time has passed since the last time and if it's less than the selected time it will sleep for the remainder of it.
-    /// This means that if more time has spent than the set time (external code taking longer) minifb will not do any waiting at all so there is no loss in CPU performance with this feature.
-    /// By default it's set to 4 milliseconds. Setting this value to None and no waiting will be done
+    #[inline]
+    pub fn keys_down(&self) -> &[Key] {
+        self.0.keys_down()
+    }
+
+    ///
+    /// Get the current keys that are down as a `KeySet`. The set is a small bitmask, so it can
+    /// be checked with `contains`, compared with the set of a previous update or sent over the
+    /// network without allocating.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// // Make sure that at least 4 ms has passed since the last event poll
-    /// window.limit_update_rate(Some(std::time::Duration::from_millis(4)));
-    /// ```
+    /// let mut previous = KeySet::new();
+    ///
+    /// while window.is_open() {
+    ///     window.update();
     ///
+    ///     let keys = window.get_key_set();
+    ///     if keys != previous {
+    ///         println!("sending input {:?}", keys.to_bits());
+    ///     }
+    ///     previous = keys;
+    /// }
+    /// ```
     #[inline]
-    pub fn limit_update_rate(&mut self, time: Option<std::time::Duration>) {
-        self.0.set_rate(time)
+    pub fn get_key_set(&self) -> KeySet {
+        self.0.keys_down().iter().copied().collect()
     }
 
     ///
-    /// Returns the current size of the window
+    /// Get the current pressed keys. Repeat can be used to control if keys should
+    /// be repeated if down or not.
+    ///
+    /// Keys that are already held when the window opens, such as Enter after launching the
+    /// application from a terminal, are down but were never pressed in the window. They are
+    /// reported by `get_keys` and `is_key_down`, but not by this function, not even as repeats,
+    /// until they have been released and pressed again. On Wayland this also applies whenever
+    /// the window gets focus. Redox and the web can't tell which keys are held, so these only
+    /// show up once they're pressed again.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// let size = window.get_size();
-    /// println!("width {} height {}", size.0, size.1);
+    /// window.get_keys_pressed(KeyRepeat::No).iter().for_each(|key|
+    ///         match key {
+    ///             Key::W => println!("pressed w"),
+    ///             Key::T => println!("pressed t"),
+    ///             _ => (),
+    ///         }
+    ///     );
     /// ```
+    #[inline]
+    pub fn get_keys_pressed(&self, repeat: KeyRepeat) -> Vec<Key> {
+        self.0.get_keys_pressed(repeat)
+    }
+
+    ///
+    /// Get the keys that were physically pressed since the last update, in the order they were
+    /// pressed. Unlike `get_keys_pressed` this never includes repeats, no matter if the key
+    /// repeat is simulated or comes from the OS, and a key that is pressed and released again
+    /// between two updates is still reported.
+    ///
+    /// # Examples
     ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// for key in window.get_keys_just_pressed() {
+    ///     println!("{:?} went down", key);
+    /// }
+    /// ```
     #[inline]
-    pub fn get_size(&self) -> (usize, usize) {
-        self.0.get_size()
+    pub fn get_keys_just_pressed(&self) -> Vec<Key> {
+        self.0.get_keys_just_pressed()
     }
 
     ///
-    /// Get the current position of the mouse relative to the current window
-    /// The coordinate system is as 0, 0 as the upper left corner
+    /// Get the text entered since the previous update, such as typed characters or the text
+    /// committed by an input method. This comes from the same source as
+    /// [`InputCallback::add_char`] so it follows the keyboard layout, but control characters
+    /// like backspace and enter are left out, use the key functions for those. Works without
+    /// setting an input callback.
+    ///
+    /// **RedoxOS / Web:** Not supported, always returns an empty string.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// window.get_mouse_pos(MouseMode::Clamp).map(|mouse| {
-    ///     println!("x {} y {}", mouse.0, mouse.1);
-    /// });
+    /// let mut line = String::new();
+    /// line.push_str(&window.get_text_input());
+    /// if window.is_key_pressed(Key::Backspace, KeyRepeat::Yes) {
+    ///     line.pop();
+    /// }
     /// ```
+    #[inline]
+    pub fn get_text_input(&self) -> String {
+        self.0.get_text_input()
+    }
+
+    ///
+    /// Get the current released keys.
+    ///
+    /// # Examples
     ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.get_keys_released().iter().for_each(|key|
+    ///         match key {
+    ///             Key::W => println!("released w"),
+    ///             Key::T => println!("released t"),
+    ///             _ => (),
+    ///         }
+    ///     );
+    /// ```
     #[inline]
-    pub fn get_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
-        self.0.get_mouse_pos(mode)
+    pub fn get_keys_released(&self) -> Vec<Key> {
+        self.0.get_keys_released()
     }
 
     ///
-    /// Get the current position of the mouse relative to the current window
-    /// The coordinate system is as 0, 0 as the upper left corner and ignores
-    /// any scaling set to the window.
+    /// Check if a single key is down.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// window.get_unscaled_mouse_pos(MouseMode::Clamp).map(|mouse| {
-    ///     println!("x {} y {}", mouse.0, mouse.1);
-    /// });
+    /// if window.is_key_down(Key::A) {
+    ///     println!("Key A is down");
+    /// }
     /// ```
     ///
     #[inline]
-    pub fn get_unscaled_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
-        self.0.get_unscaled_mouse_pos(mode)
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.0.is_key_down(key)
     }
 
     ///
-    /// Check if a mouse button is down or not
+    /// Replaces the key map used by `is_action_down`, see `KeyMap`. Rebinding controls is done by
+    /// changing the map and setting it again.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// let left_down = window.get_mouse_down(MouseButton::Left);
-    /// println!("is left down? {}", left_down)
+    /// let mut map = KeyMap::new();
+    /// map.bind("left", Key::A);
+    /// map.bind("left", Key::Left);
+    /// map.bind("fire", Key::Space);
+    /// window.set_key_map(map);
     /// ```
     ///
     #[inline]
-    pub fn get_mouse_down(&self, button: MouseButton) -> bool {
-        self.0.get_mouse_down(button)
+    pub fn set_key_map(&mut self, key_map: KeyMap) {
+        self.0.set_key_map(key_map)
     }
 
     ///
-    /// Get the current movement of the scroll wheel.
-    /// Scroll wheel can mean different thing depending on the device attach.
-    /// For example on Mac with trackpad "scroll wheel" means two finger
-    /// swiping up/down (y axis) and to the sides (x-axis)
-    /// When using a mouse this assumes the scroll wheel which often is only y direction.
+    /// Returns true if one of the keys bound to the action in the key map is down. Actions that
+    /// aren't in the map are never down.
+    ///
+    /// Keys bound by scancode depend on the platform, see `InputCallback::set_key_state_scancode`.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// window.get_scroll_wheel().map(|scroll| {
-    ///     println!("scrolling - x {} y {}", scroll.0, scroll.1);
-    /// });
+    /// # let mut x = 0;
+    /// if window.is_action_down("left") {
+    ///     x -= 1;
+    /// }
     /// ```
     ///
-    ///
     #[inline]
-    pub fn get_scroll_wheel(&self) -> Option<(f32, f32)> {
-        self.0.get_scroll_wheel()
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.0.is_action_down(action)
     }
 
     ///
-    /// Set a different cursor style. This can be used if you have resizing
-    /// elements or something like that
+    /// Returns the native timestamp of the latest key or mouse event the window has processed,
+    /// or `None` if there hasn't been one yet. This can be used to order input or to predict
+    /// where something is between two updates.
+    ///
+    /// The timestamp is the time since an epoch that depends on the platform:
+    ///
+    /// * **Windows**: the start of the system, from `GetMessageTime`.
+    /// * **macOS**: the start of the system, from the `timestamp` of the `NSEvent`. Only key
+    ///   events are timestamped.
+    /// * **X11**: the start of the X server, from the server time of the event.
+    /// * **Wayland**: an unspecified point chosen by the compositor.
+    /// * **Redox** and **Web**: not supported, always returns `None`.
+    ///
+    /// Only compare timestamps of the same window with each other. They never decrease: an event
+    /// that arrives out of order gets the timestamp of the event before it, and the 32-bit
+    /// millisecond clocks used by Windows, X11 and Wayland wrapping around after about 49 days is
+    /// accounted for.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// window.set_cursor_style(CursorStyle::ResizeLeftRight);
+    /// if let Some(time) = window.get_input_timestamp() {
+    ///     println!("last input at {:?}", time);
+    /// }
     /// ```
-    ///
-    pub fn set_cursor_style(&mut self, cursor: CursorStyle) {
-        self.0.set_cursor_style(cursor)
+    #[inline]
+    pub fn get_input_timestamp(&self) -> Option<std::time::Duration> {
+        self.0.get_input_timestamp()
     }
 
     ///
-    /// Get the current keys that are down.
+    /// Returns the native timestamp of the event that last pressed or released `key`, or `None`
+    /// if that hasn't happened yet. The timestamps use the same clock as `get_input_timestamp`,
+    /// so comparing them gives the order in which keys changed within a single update.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// window.get_keys().iter().for_each(|key|
-    ///         match key {
-    ///             Key::W => println!("holding w"),
-    ///             Key::T => println!("holding t"),
-    ///             _ => (),
-    ///         }
-    ///     );
+    /// let left = window.get_key_timestamp(Key::Left);
+    /// let right = window.get_key_timestamp(Key::Right);
+    ///
+    /// // Whichever direction was pressed last wins
+    /// if window.is_key_down(Key::Left) && window.is_key_down(Key::Right) && left > right {
+    ///     println!("moving left");
+    /// }
     /// ```
     #[inline]
-    pub fn get_keys(&self) -> Vec<Key> {
-        self.0.get_keys()
+    pub fn get_key_timestamp(&self, key: Key) -> Option<std::time::Duration> {
+        self.0.get_key_timestamp(key)
     }
 
     ///
-    /// Get the current pressed keys. Repeat can be used to control if keys should
-    /// be repeated if down or not.
+    /// Check if at least one of the given keys is down.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// window.get_keys_pressed(KeyRepeat::No).iter().for_each(|key|
-    ///         match key {
-    ///             Key::W => println!("pressed w"),
-    ///             Key::T => println!("pressed t"),
-    ///             _ => (),
-    ///         }
-    ///     );
+    /// if window.are_any_keys_down(&[Key::Left, Key::A]) {
+    ///     println!("Moving left");
+    /// }
     /// ```
+    ///
     #[inline]
-    pub fn get_keys_pressed(&self, repeat: KeyRepeat) -> Vec<Key> {
-        self.0.get_keys_pressed(repeat)
+    pub fn are_any_keys_down(&self, keys: &[Key]) -> bool {
+        keys.iter().any(|&key| self.0.is_key_down(key))
     }
 
     ///
-    /// Get the current released keys.
+    /// Check if all of the given keys are down. Returns true if `keys` is empty.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// window.get_keys_released().iter().for_each(|key|
-    ///         match key {
-    ///             Key::W => println!("released w"),
-    ///             Key::T => println!("released t"),
-    ///             _ => (),
-    ///         }
-    ///     );
+    /// if window.are_all_keys_down(&[Key::LeftCtrl, Key::S]) {
+    ///     println!("Saving");
+    /// }
     /// ```
+    ///
     #[inline]
-    pub fn get_keys_released(&self) -> Vec<Key> {
-        self.0.get_keys_released()
+    pub fn are_all_keys_down(&self, keys: &[Key]) -> bool {
+        keys.iter().all(|&key| self.0.is_key_down(key))
+    }
+
+    ///
+    /// Returns the keys from `keys` that are down, in the order they are given.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let arrows = [Key::Up, Key::Down, Key::Left, Key::Right];
+    ///
+    /// for key in window.get_keys_down_in(&arrows) {
+    ///     println!("{:?} is down", key);
+    /// }
+    /// ```
+    ///
+    pub fn get_keys_down_in(&self, keys: &[Key]) -> Vec<Key> {
+        keys.iter()
+            .copied()
+            .filter(|&key| self.0.is_key_down(key))
+            .collect()
     }
 
     ///
-    /// Check if a single key is down.
+    /// Check if a single key is pressed. KeyRepeat will control if the key should be repeated or
+    /// not while being pressed.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// if window.is_key_down(Key::A) {
+    /// if window.is_key_pressed(Key::A, KeyRepeat::No) {
     ///     println!("Key A is down");
     /// }
     /// ```
     ///
     #[inline]
-    pub fn is_key_down(&self, key: Key) -> bool {
-        self.0.is_key_down(key)
+    pub fn is_key_pressed(&self, key: Key, repeat: KeyRepeat) -> bool {
+        self.0.is_key_pressed(key, repeat)
     }
 
     ///
-    /// Check if a single key is pressed. KeyRepeat will control if the key should be repeated or
-    /// not while being pressed.
+    /// Check if a single key repeats during this update because it's being held down. Unlike
+    /// `is_key_pressed(key, KeyRepeat::Yes)` this is false for the update where the key was first
+    /// pressed, which lets text fields treat the initial press and held repeats differently.
+    ///
+    /// The repeats follow the delay and rate given to `set_key_repeat_delay` and
+    /// `set_key_repeat_rate`, or the system settings after a call to `use_os_key_repeat`.
     ///
     /// # Examples
     ///
     /// ```no_run
     /// # use minifb::*;
     /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
-    /// if window.is_key_pressed(Key::A, KeyRepeat::No) {
-    ///     println!("Key A is down");
+    /// if window.is_key_pressed(Key::Backspace, KeyRepeat::No) {
+    ///     println!("Delete a character");
+    /// } else if window.is_key_repeat(Key::Backspace) {
+    ///     println!("Delete a word");
     /// }
     /// ```
     ///
     #[inline]
-    pub fn is_key_pressed(&self, key: Key, repeat: KeyRepeat) -> bool {
-        self.0.is_key_pressed(key, repeat)
+    pub fn is_key_repeat(&self, key: Key) -> bool {
+        self.0.is_key_repeat(key)
     }
 
     ///
@@ -763,6 +3734,68 @@ impl Window {
         self.0.set_key_repeat_rate(rate)
     }
 
+    ///
+    /// Gives the listed keys their own repeat delay and rate, in seconds like
+    /// `set_key_repeat_delay` and `set_key_repeat_rate`. All other keys keep the shared timing,
+    /// and `use_os_key_repeat` leaves these keys alone. Calling this again for a key replaces its
+    /// timing.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// // Arrows start repeating sooner and repeat faster than the other keys
+    /// window.set_key_repeat_for(&[Key::Left, Key::Right, Key::Up, Key::Down], 0.15, 0.02);
+    /// ```
+    ///
+    #[inline]
+    pub fn set_key_repeat_for(&mut self, keys: &[Key], delay: f32, rate: f32) {
+        self.0.set_key_repeat_for(keys, delay, rate)
+    }
+
+    ///
+    /// Replaces the key repeat delay and rate with the ones configured in the keyboard settings
+    /// of the operating system, so held keys repeat the same way as in other applications.
+    /// Platforms that don't expose these settings keep the current values.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.use_os_key_repeat();
+    /// ```
+    ///
+    #[inline]
+    pub fn use_os_key_repeat(&mut self) {
+        self.0.use_os_key_repeat()
+    }
+
+    ///
+    /// Returns the name of the active keyboard layout, or `None` if it can't be determined.
+    /// Use `InputCallback::layout_changed` to be told when the user switches layouts.
+    ///
+    /// The name comes from the platform and isn't translated: X11 returns the xkb layout
+    /// such as "us", Wayland the description of the xkb layout such as "English (US)", Windows
+    /// the layout identifier such as "00000409" and macOS the input source such as
+    /// "com.apple.keylayout.US". Redox and the web always return `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// if let Some(layout) = window.get_keyboard_layout() {
+    ///     println!("typing with the {} layout", layout);
+    /// }
+    /// ```
+    ///
+    #[inline]
+    pub fn get_keyboard_layout(&self) -> Option<String> {
+        self.0.get_keyboard_layout()
+    }
+
     ///
     /// Returns if this windows is the current active one
     ///
@@ -771,6 +3804,48 @@ impl Window {
         self.0.is_active()
     }
 
+    ///
+    /// Returns the contents of the X11 PRIMARY selection, the text that was last selected in any
+    /// application and that is usually pasted with the middle mouse button. This is separate from
+    /// the regular clipboard.
+    ///
+    /// This blocks for a short while when the selection is owned by another application.
+    /// Returns None if nothing is selected, the contents aren't text or the platform doesn't
+    /// have a primary selection (everything except X11 currently).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// if window.get_mouse_down(MouseButton::Middle) {
+    ///     if let Some(text) = window.get_primary_selection() {
+    ///         println!("paste: {}", text);
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn get_primary_selection(&self) -> Option<String> {
+        self.0.get_primary_selection()
+    }
+
+    ///
+    /// Makes this window the owner of the X11 PRIMARY selection with `text` as contents. Other
+    /// applications get the text when they paste from the primary selection, as long as the
+    /// window keeps being updated. Does nothing on platforms without a primary selection.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// window.set_primary_selection("selected text");
+    /// ```
+    #[inline]
+    pub fn set_primary_selection(&mut self, text: &str) {
+        self.0.set_primary_selection(text)
+    }
+
     ///
     /// Set input callback to recive callback on char input
     ///
@@ -799,6 +3874,36 @@ impl Window {
         self.0.add_menu(&menu.0)
     }
 
+    ///
+    /// Puts the items of `menu` into the application menu on macOS, the first menu in the menu
+    /// bar that is named after the application. The items are placed below "About", where
+    /// items such as "Preferences..." are expected, while the standard Hide and Quit (Cmd+Q)
+    /// items are kept. Selecting one of the items is reported by `is_menu_pressed` like any
+    /// other menu item.
+    ///
+    /// The items are copied, so call this again after changing `menu`. This does nothing on
+    /// other platforms as they have no application menu.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// const MENU_PREFERENCES: usize = 1;
+    ///
+    /// let mut app_menu = Menu::new("App").unwrap();
+    /// app_menu
+    ///     .add_item("Preferences...", MENU_PREFERENCES)
+    ///     .shortcut(Key::Comma, MENU_KEY_CTRL)
+    ///     .build();
+    ///
+    /// window.set_app_menu(&app_menu);
+    /// ```
+    #[inline]
+    pub fn set_app_menu(&mut self, menu: &Menu) {
+        self.0.set_app_menu(&menu.0)
+    }
+
     ///
     /// Remove a menu that has been added with [#add_menu]
     ///
@@ -807,6 +3912,45 @@ impl Window {
         self.0.remove_menu(handle)
     }
 
+    ///
+    /// Returns the height in pixels of the menu bar attached to the window. Returns 0 if no menu
+    /// has been added, or if the platform shows menus outside of the window (like the global
+    /// menu bar at the top of the screen on macOS).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let menu_height = window.get_menu_height();
+    /// ```
+    #[inline]
+    pub fn get_menu_height(&self) -> usize {
+        self.0.get_menu_height()
+    }
+
+    ///
+    /// Replaces the contents of a menu that has been added with [#add_menu] with the given menu.
+    /// The menu keeps its position in the menu bar and `handle` stays valid. This is useful for
+    /// menus that change at runtime, like a list of recently opened files.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let mut recent = Menu::new("Recent").unwrap();
+    /// let handle = window.add_menu(&recent);
+    ///
+    /// recent.clear();
+    /// recent.add_item("file.txt", 1).build();
+    /// window.update_menu(handle, &recent);
+    /// ```
+    #[inline]
+    pub fn update_menu(&mut self, handle: MenuHandle, menu: &Menu) {
+        self.0.update_menu(handle, &menu.0)
+    }
+
     ///
     /// Get POSIX menus. Will only return menus on POSIX-like OSes like Linux or BSD
     /// otherwise ```None```
@@ -828,6 +3972,95 @@ impl Window {
         self.0.get_posix_menus()
     }
 
+    ///
+    /// Shows `menu` as a popup (context) menu at the given position, relative to the top left
+    /// corner of the window in the same coordinates as `get_mouse_pos`. Selected items are
+    /// reported through `is_menu_pressed` just like items in the menu bar.
+    ///
+    /// On Windows and macOS the native popup menu is used and this call blocks until the menu
+    /// is dismissed. There are no native menus on POSIX-like OSes so the menu is stored instead
+    /// and can be fetched with `take_posix_context_menu` for the application to draw itself.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// let mut menu = Menu::new("Edit").unwrap();
+    /// menu.add_item("Copy", 1).build();
+    /// menu.add_item("Paste", 2).build();
+    ///
+    /// if window.get_mouse_down(MouseButton::Right) {
+    ///     if let Some((x, y)) = window.get_mouse_pos(MouseMode::Discard) {
+    ///         window.show_context_menu(&menu, x as i32, y as i32);
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn show_context_menu(&mut self, menu: &Menu, x: i32, y: i32) {
+        self.0.show_context_menu(&menu.0, x, y)
+    }
+
+    ///
+    /// Takes the menu most recently passed to `show_context_menu` together with the position it
+    /// should be shown at. Will only return a menu on POSIX-like OSes like Linux or BSD
+    /// otherwise ```None```
+    ///
+    #[cfg(any(target_os = "macos", target_os = "windows", target_arch = "wasm32"))]
+    pub fn take_posix_context_menu(&mut self) -> Option<(UnixMenu, i32, i32)> {
+        None
+    }
+
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd",
+        target_os = "redox"
+    ))]
+    pub fn take_posix_context_menu(&mut self) -> Option<(UnixMenu, i32, i32)> {
+        self.0.take_posix_context_menu()
+    }
+
+    ///
+    /// Sends all pending requests to the display server and, on X11, waits until they have been
+    /// processed. Only available on POSIX-like OSes like Linux or BSD, it's meant for
+    /// applications that make their own Xlib or Wayland calls on the same connection and need
+    /// them ordered with the ones made by minifb.
+    ///
+    /// minifb itself flushes after presenting a buffer in `update_with_buffer`, when checking for
+    /// new events in `update` and `poll_input`, and after most calls that move or resize the
+    /// window such as `set_position`. Other requests, like a change of cursor, go out with the
+    /// next of these. It never waits for the server to catch up on its own.
+    ///
+    /// **Linux:**
+    /// - X11: calls `XSync` without discarding queued events.
+    /// - Wayland: flushes the display connection, there is nothing to wait for.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// # let buffer = vec![0u32; 640 * 400];
+    /// window.update_with_buffer(&buffer, 640, 400).unwrap();
+    /// #[cfg(target_os = "linux")]
+    /// window.sync();
+    /// ```
+    ///
+    #[cfg(any(
+        target_os = "linux",
+        target_os = "freebsd",
+        target_os = "dragonfly",
+        target_os = "netbsd",
+        target_os = "openbsd"
+    ))]
+    #[inline]
+    pub fn sync(&mut self) {
+        self.0.sync()
+    }
+
     #[deprecated(
         since = "0.17.0",
         note = "`get_unix_menus` will be removed in 1.0.0, use `get_posix_menus` instead"
@@ -843,6 +4076,81 @@ impl Window {
     pub fn is_menu_pressed(&mut self) -> Option<usize> {
         self.0.is_menu_pressed()
     }
+
+    ///
+    /// Tells how the menu item that was last returned by `is_menu_pressed` was activated. Returns
+    /// None if the last call to `is_menu_pressed` didn't return a menu item or if the platform
+    /// doesn't report this (only Windows and macOS do currently).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut window = Window::new("Test", 640, 400, WindowOptions::default()).unwrap();
+    /// if let Some(menu_id) = window.is_menu_pressed() {
+    ///     if window.menu_activation_source() == Some(MenuSource::Shortcut) {
+    ///         println!("menu item {} activated from the keyboard", menu_id);
+    ///     }
+    /// }
+    /// ```
+    #[inline]
+    pub fn menu_activation_source(&self) -> Option<MenuSource> {
+        self.0.menu_activation_source()
+    }
+}
+
+///
+/// Updates several windows at once. Windows with a frame built up by `upload_region` that
+/// hasn't been shown yet present it, like `Window::present`, the others only process input, like
+/// `Window::update`. Frames that are already on screen aren't presented again.
+///
+/// Calling `update` on each window waits for the rate limit of every window in turn, so the
+/// waits add up and the windows get slower the more there are. This only waits for the rate set
+/// on the first window, the limits of the other windows are ignored.
+///
+/// There is no shared event queue to pump once and route from: on X11 and Wayland every window
+/// has its own connection to the display server with its own queue, and on Windows and macOS
+/// the system queue already hands each event to the window it belongs to. After the first window
+/// has emptied that queue, processing the input of the others is cheap.
+///
+/// All windows are updated even if one of them fails, the first error is returned.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use minifb::*;
+/// let mut main = Window::new("Main", 640, 400, WindowOptions::default()).unwrap();
+/// let mut tools = Window::new("Tools", 200, 400, WindowOptions::default()).unwrap();
+/// main.set_target_fps(60);
+///
+/// let main_buffer = vec![0u32; 640 * 400];
+/// let tools_buffer = vec![0u32; 200 * 400];
+///
+/// while main.is_open() {
+///     main.upload_region(&main_buffer, 640, 400, Rect { x: 0, y: 0, width: 640, height: 400 }).unwrap();
+///     tools.upload_region(&tools_buffer, 200, 400, Rect { x: 0, y: 0, width: 200, height: 400 }).unwrap();
+///     update_all(&mut [&mut main, &mut tools]).unwrap();
+/// }
+/// ```
+pub fn update_all(windows: &mut [&mut Window]) -> Result<()> {
+    if let Some(window) = windows.first_mut() {
+        window.0.update_rate();
+    }
+
+    let mut result = Ok(());
+
+    for window in windows.iter_mut() {
+        if window.1.has_unpresented_frame() {
+            let flushed = window.flush();
+            if result.is_ok() {
+                result = flushed;
+            }
+        } else {
+            window.0.update();
+        }
+    }
+
+    result
 }
 
 /// Command key on Mac OS
@@ -897,6 +4205,8 @@ pub struct UnixMenuItem {
     pub key: Key,
     /// Modifier for the key (Shift, Ctrl, etc)
     pub modifier: usize,
+    /// Icon to draw next to the label
+    pub icon: Option<MenuIcon>,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -907,6 +4217,28 @@ pub struct MenuItemHandle(pub u64);
 #[doc(hidden)]
 pub struct MenuHandle(pub u64);
 
+/// Describes how a menu item was activated
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MenuSource {
+    /// The menu item was clicked in the menu
+    Click,
+    /// The menu item was activated with its keyboard shortcut
+    Shortcut,
+}
+
+///
+/// Small image shown next to the label of a menu item
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MenuIcon {
+    /// Width of the icon in pixels
+    pub width: usize,
+    /// Height of the icon in pixels
+    pub height: usize,
+    /// Pixels of the icon in `0xAARRGGBB` format, row by row
+    pub data: Vec<u32>,
+}
+
 ///
 /// Menu holds info for menus
 ///
@@ -960,7 +4292,7 @@ impl Menu {
     /// menu.add_item("test", 1).shortcut(Key::A, 0).build()
     /// # ;
     /// ```
-    pub fn add_item(&mut self, name: &str, id: usize) -> MenuItem {
+    pub fn add_item(&mut self, name: &str, id: usize) -> MenuItem<'_> {
         MenuItem {
             id,
             label: name.to_owned(),
@@ -974,6 +4306,102 @@ impl Menu {
     pub fn remove_item(&mut self, item: &MenuItemHandle) {
         self.0.remove_item(item)
     }
+
+    #[inline]
+    /// Removes all items from the menu. If the menu has already been added to a window call
+    /// `Window::update_menu` afterwards so the changes are picked up.
+    pub fn clear(&mut self) {
+        self.0.clear()
+    }
+}
+
+///
+/// Icon shown in the system tray (the notification area on Windows, the status bar on MacOS)
+///
+/// Like the window menus the tray icon is polled: call `update` regularly, for example next to
+/// `Window::update`, and check `is_menu_pressed` and `is_clicked` afterwards.
+///
+/// **Windows:** The icon is loaded from an `Icon::Path`. The menu pops up on right click.
+///
+/// **MacOS:** The icon is drawn from an `Icon::Buffer`, for an `Icon::Path` the tooltip is shown
+/// in the status bar instead. The menu pops up on click, so `is_clicked` only reports clicks while
+/// no menu is set.
+///
+/// **Linux:**
+/// - X11: Uses the XEmbed system tray protocol and draws the first image of an `Icon::Buffer`.
+///   Fails when no tray is running. Menus can't be shown, `set_menu` returns
+///   `Error::Unsupported` and `is_menu_pressed` always returns `None`.
+/// - Wayland: Fails with `Error::Unsupported` unless the X11 backend is enabled as well.
+///
+/// **RedoxOS / Web:** Not supported, `new` always fails with `Error::Unsupported`.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use minifb::*;
+/// # let icon: Icon = unimplemented!();
+/// let mut menu = Menu::new("Tray").unwrap();
+/// menu.add_item("Quit", 1).build();
+///
+/// let mut tray = TrayIcon::new(icon, "My tool").unwrap();
+/// // Without a menu a click on the icon quits instead
+/// let has_menu = tray.set_menu(&menu).is_ok();
+///
+/// loop {
+///     tray.update();
+///     if tray.is_menu_pressed() == Some(1) || (!has_menu && tray.is_clicked()) {
+///         break;
+///     }
+///     std::thread::sleep(std::time::Duration::from_millis(16));
+/// }
+/// ```
+pub struct TrayIcon(imp::TrayIcon);
+
+impl fmt::Debug for TrayIcon {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("TrayIcon")
+            .field(&format_args!(".."))
+            .finish()
+    }
+}
+
+impl TrayIcon {
+    /// Adds an icon with the given tooltip to the system tray. The icon is removed again when
+    /// the `TrayIcon` is dropped.
+    pub fn new(icon: Icon, tooltip: &str) -> Result<TrayIcon> {
+        imp::TrayIcon::new(icon, tooltip).map(TrayIcon)
+    }
+
+    #[inline]
+    /// Changes the text shown when hovering the icon
+    pub fn set_tooltip(&mut self, tooltip: &str) {
+        self.0.set_tooltip(tooltip)
+    }
+
+    #[inline]
+    /// Sets the menu that pops up for the icon. The items are copied, so call this again after
+    /// changing the menu. Returns `Error::Unsupported` on X11, where tray icons have no menus.
+    pub fn set_menu(&mut self, menu: &Menu) -> Result<()> {
+        self.0.set_menu(&menu.0)
+    }
+
+    #[inline]
+    /// Processes the pending events of the icon
+    pub fn update(&mut self) {
+        self.0.update()
+    }
+
+    #[inline]
+    /// Check if a menu item of the tray menu has been pressed
+    pub fn is_menu_pressed(&mut self) -> Option<usize> {
+        self.0.is_menu_pressed()
+    }
+
+    #[inline]
+    /// Check if the icon has been clicked since the last call
+    pub fn is_clicked(&mut self) -> bool {
+        self.0.is_clicked()
+    }
 }
 
 ///
@@ -986,6 +4414,7 @@ pub struct MenuItem<'a> {
     pub enabled: bool,
     pub key: Key,
     pub modifier: usize,
+    pub icon: Option<MenuIcon>,
     #[doc(hidden)]
     pub menu: Option<&'a mut Menu>,
 }
@@ -998,6 +4427,7 @@ impl<'a> Default for MenuItem<'a> {
             enabled: true,
             key: Key::Unknown,
             modifier: 0,
+            icon: None,
             menu: None,
         }
     }
@@ -1011,6 +4441,7 @@ impl<'a> Clone for MenuItem<'a> {
             enabled: self.enabled,
             key: self.key,
             modifier: self.modifier,
+            icon: self.icon.clone(),
             menu: None,
         }
     }
@@ -1018,7 +4449,7 @@ impl<'a> Clone for MenuItem<'a> {
 
 impl<'a> MenuItem<'a> {
     /// Creates a new menu item
-    pub fn new(name: &str, id: usize) -> MenuItem {
+    pub fn new(name: &str, id: usize) -> MenuItem<'_> {
         MenuItem {
             id,
             label: name.to_owned(),
@@ -1076,6 +4507,39 @@ impl<'a> MenuItem<'a> {
         MenuItem { enabled, ..self }
     }
     #[inline]
+    /// Sets an icon that is shown next to the label. The buffer holds `width * height` pixels in
+    /// `0xAARRGGBB` format, the icon is left out if the buffer is smaller than that. Icons should
+    /// be small, 16x16 fits the menus of most platforms.
+    ///
+    /// **Windows:** the alpha channel is used, this requires Windows Vista or later.
+    ///
+    /// **Linux / RedoxOS / Web:** there are no native menus, the icon is passed on in
+    /// `UnixMenuItem::icon` so it can be drawn together with the rest of the menu.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use minifb::*;
+    /// # let mut menu = Menu::new("test").unwrap();
+    /// let icon = vec![0xff00_80ff; 16 * 16];
+    /// menu.add_item("test", 1).icon(&icon, 16, 16).build()
+    /// # ;
+    /// ```
+    pub fn icon(self, data: &[u32], width: usize, height: usize) -> Self {
+        let len = width * height;
+        let icon = if len > 0 && data.len() >= len {
+            Some(MenuIcon {
+                width,
+                height,
+                data: data[..len].to_vec(),
+            })
+        } else {
+            None
+        };
+
+        MenuItem { icon, ..self }
+    }
+    #[inline]
     /// Must be called to finalize building of a menu item when started with ```menu.add_item()```
     ///
     /// # Examples
@@ -1109,6 +4573,14 @@ impl Default for WindowOptions {
             scale_mode: ScaleMode::Stretch,
             topmost: false,
             none: false,
+            color_space: ColorSpace::DisplayNative,
+            visible: true,
+            app_id: None,
+            wm_class: None,
+            pixel_format: PixelFormat::Argb8888,
+            buffer_count: 3,
+            gl: false,
+            backend: None,
         }
     }
 }
@@ -1,4 +1,11 @@
-use crate::MouseMode;
+#[cfg(feature = "web")]
+extern crate instant;
+
+use crate::{Modifiers, MouseButton, MouseMode};
+#[cfg(feature = "web")]
+use instant::{Duration, Instant};
+#[cfg(not(feature = "web"))]
+use std::time::{Duration, Instant};
 
 fn clamp(v: f32, lb: f32, ub: f32) -> f32 {
     f32::min(f32::max(v, lb), ub)
@@ -12,7 +19,7 @@ pub fn get_pos(
     width: f32,
     height: f32,
 ) -> Option<(f32, f32)> {
-    let s = 1.0 / scale as f32;
+    let s = 1.0 / scale;
     let x = mx * s;
     let y = my * s;
     let window_width = width * s;
@@ -33,3 +40,295 @@ pub fn get_pos(
         }
     }
 }
+
+/// Tracks mouse movement to hide the cursor after a period of inactivity.
+pub struct CursorAutoHide {
+    timeout: Option<Duration>,
+    last_pos: (f32, f32),
+    last_move: Instant,
+    // Visibility asked for with `set_cursor_visibility`, auto-hide never shows a hidden cursor
+    visible: bool,
+    hidden: bool,
+}
+
+impl CursorAutoHide {
+    pub fn new() -> CursorAutoHide {
+        CursorAutoHide {
+            timeout: None,
+            last_pos: (0.0, 0.0),
+            last_move: Instant::now(),
+            visible: true,
+            hidden: false,
+        }
+    }
+
+    /// Returns the visibility the cursor has to be changed to, if any.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) -> Option<bool> {
+        self.timeout = timeout;
+        self.last_move = Instant::now();
+
+        if timeout.is_none() && self.hidden {
+            self.hidden = false;
+            Some(true)
+        } else {
+            None
+        }
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+        self.hidden = false;
+        self.last_move = Instant::now();
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Called once per update with the current mouse position. Returns the visibility the cursor
+    /// has to be changed to, if any.
+    pub fn update(&mut self, x: f32, y: f32) -> Option<bool> {
+        let timeout = self.timeout?;
+
+        if (x, y) != self.last_pos {
+            self.last_pos = (x, y);
+            self.last_move = Instant::now();
+
+            if self.hidden {
+                self.hidden = false;
+                return Some(true);
+            }
+        } else if self.visible && !self.hidden && self.last_move.elapsed() >= timeout {
+            self.hidden = true;
+            return Some(false);
+        }
+
+        None
+    }
+}
+
+/// Frames of an animated cursor in the format of the platform, and which of them is shown. The
+/// animation loops, starting when the cursor is created.
+#[allow(dead_code)] // X11 animates cursors itself
+pub struct AnimatedCursor<T> {
+    frames: Vec<T>,
+    frame_duration: Duration,
+    start: Instant,
+    current: usize,
+}
+
+#[allow(dead_code)]
+impl<T> AnimatedCursor<T> {
+    pub fn new(frames: Vec<T>, frame_duration: Duration) -> AnimatedCursor<T> {
+        AnimatedCursor {
+            frames,
+            frame_duration,
+            start: Instant::now(),
+            current: 0,
+        }
+    }
+
+    #[inline]
+    pub fn current(&self) -> &T {
+        &self.frames[self.current]
+    }
+
+    #[inline]
+    pub fn frames(&self) -> &[T] {
+        &self.frames
+    }
+
+    /// Called once per update. Returns the frame the cursor has to be changed to, if any.
+    pub fn update(&mut self) -> Option<&T> {
+        if self.frames.len() < 2 || self.frame_duration.as_nanos() == 0 {
+            return None;
+        }
+
+        let elapsed = self.start.elapsed().as_nanos() / self.frame_duration.as_nanos();
+        let frame = (elapsed % self.frames.len() as u128) as usize;
+
+        if frame == self.current {
+            return None;
+        }
+
+        self.current = frame;
+        Some(&self.frames[frame])
+    }
+}
+
+/// Sums the scroll wheel movement of every event until it's reset, so nothing is lost when
+/// several events arrive during one update.
+#[derive(Default)]
+pub struct ScrollAccumulator {
+    x: f32,
+    y: f32,
+}
+
+impl ScrollAccumulator {
+    #[inline]
+    pub fn add(&mut self, x: f32, y: f32) {
+        self.x += x;
+        self.y += y;
+    }
+
+    #[inline]
+    pub fn get(&self) -> (f32, f32) {
+        (self.x, self.y)
+    }
+
+    #[inline]
+    pub fn reset(&mut self) {
+        self.x = 0.0;
+        self.y = 0.0;
+    }
+}
+
+/// Keeps every position the mouse moved to since it was last reset, together with the time of
+/// the event.
+pub struct MouseHistory {
+    events: Vec<(f32, f32, Duration)>,
+    // Timestamps of platforms whose events don't have any are relative to this
+    start: Instant,
+}
+
+impl MouseHistory {
+    pub fn new() -> MouseHistory {
+        MouseHistory {
+            events: Vec::new(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Adds a position in window pixels, without a `time` the time since the history was
+    /// created is used.
+    pub fn push(&mut self, x: f32, y: f32, time: Option<Duration>) {
+        // Some platforms report motion without any movement
+        if let Some(&(last_x, last_y, _)) = self.events.last() {
+            if last_x == x && last_y == y {
+                return;
+            }
+        }
+
+        let time = time.unwrap_or_else(|| self.start.elapsed());
+        self.events.push((x, y, time));
+    }
+
+    pub fn get(&self, scale: f32) -> Vec<(f32, f32, Duration)> {
+        let s = 1.0 / scale;
+        self.events
+            .iter()
+            .map(|&(x, y, time)| (x * s, y * s, time))
+            .collect()
+    }
+
+    #[inline]
+    pub fn reset(&mut self) {
+        self.events.clear();
+    }
+}
+
+/// Remembers the modifier keys that were held when each mouse button went down, so they can be
+/// looked up later without racing with the keys being released.
+#[derive(Default)]
+pub struct ButtonModifiers {
+    modifiers: [Option<Modifiers>; 3],
+}
+
+impl ButtonModifiers {
+    /// Has to be called while processing the button event, `modifiers` is only kept when the
+    /// button goes down.
+    pub fn set_button_state(&mut self, button: MouseButton, is_down: bool, modifiers: Modifiers) {
+        let slot = &mut self.modifiers[button as usize];
+
+        if !is_down {
+            *slot = None;
+        } else if slot.is_none() {
+            *slot = Some(modifiers);
+        }
+    }
+
+    #[inline]
+    pub fn get(&self, button: MouseButton) -> Option<Modifiers> {
+        self.modifiers[button as usize]
+    }
+}
+
+/// Measures the relative mouse movement for the gameplay cursor mode. The cursor is moved back to
+/// the center of the window whenever it has moved away from it, so the edges of the window or the
+/// screen never stop the movement.
+#[derive(Default)]
+pub struct MouseDelta {
+    enabled: bool,
+    // False until the cursor has been moved to the center, the first jump there isn't movement
+    centered: bool,
+    delta: (f32, f32),
+    // Cursor visibility and confinement from before the mode was turned on
+    saved_cursor: (bool, bool),
+}
+
+impl MouseDelta {
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        self.centered = false;
+        self.delta = (0.0, 0.0);
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Remembers the cursor visibility and confinement the window had before the mode was turned
+    /// on, so they can be restored when it's turned off again.
+    #[inline]
+    pub fn save_cursor(&mut self, visible: bool, confined: bool) {
+        self.saved_cursor = (visible, confined);
+    }
+
+    #[inline]
+    pub fn saved_cursor(&self) -> (bool, bool) {
+        self.saved_cursor
+    }
+
+    /// Adds movement that has already been measured by the platform.
+    #[inline]
+    pub fn add(&mut self, x: f32, y: f32) {
+        self.delta.0 += x;
+        self.delta.1 += y;
+    }
+
+    /// Called with the current mouse position whenever input is processed. Returns true if the
+    /// cursor has to be moved back to `center`. Nothing is measured while the window doesn't have
+    /// focus, as the cursor is free to leave it then.
+    #[cfg_attr(
+        all(
+            feature = "wayland",
+            not(any(feature = "x11", windows, target_os = "macos"))
+        ),
+        allow(dead_code)
+    )]
+    pub fn update(&mut self, active: bool, pos: (f32, f32), center: (f32, f32)) -> bool {
+        if !self.is_enabled() || !active {
+            self.centered = false;
+            return false;
+        }
+
+        if self.centered {
+            self.add(pos.0 - center.0, pos.1 - center.1);
+        }
+
+        self.centered = true;
+        pos != center
+    }
+
+    #[inline]
+    pub fn get(&self) -> (f32, f32) {
+        self.delta
+    }
+
+    #[inline]
+    pub fn reset(&mut self) {
+        self.delta = (0.0, 0.0);
+    }
+}
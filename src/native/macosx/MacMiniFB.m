@@ -3,6 +3,7 @@
 #include <Cocoa/Cocoa.h>
 #include <Carbon/Carbon.h>
 #include <MetalKit/MetalKit.h>
+#include <dlfcn.h>
 #include <unistd.h>
 
 extern id<MTLCommandQueue> g_command_queue;
@@ -60,6 +61,9 @@ static bool s_init = false;
 const uint32_t WINDOW_BORDERLESS = 1 << 1;
 const uint32_t WINDOW_RESIZE = 1 << 2;
 const uint32_t WINDOW_TITLE = 1 << 3;
+const uint32_t WINDOW_COLOR_SPACE_SRGB = 1 << 4;
+const uint32_t WINDOW_COLOR_SPACE_LINEAR = 1 << 5;
+const uint32_t WINDOW_HIDDEN = 1 << 6;
 
 static void create_standard_menu();
 
@@ -73,6 +77,12 @@ enum CursorStyle {
     CursorStyle_ResizeLeftRight,
     CursorStyle_ResizeUpDown,
     CursorStyle_SizeAll,
+    CursorStyle_Wait,
+    CursorStyle_Progress,
+    CursorStyle_Help,
+    CursorStyle_NotAllowed,
+    CursorStyle_ZoomIn,
+    CursorStyle_ZoomOut,
     CursorStyle_Count,
 };
 
@@ -89,6 +99,13 @@ void cursor_init() {
     s_cursors[CursorStyle_ResizeLeftRight] = [[NSCursor resizeLeftRightCursor] retain];
     s_cursors[CursorStyle_ResizeUpDown] = [[NSCursor resizeUpDownCursor] retain];
     s_cursors[CursorStyle_SizeAll] = [[NSCursor closedHandCursor] retain];
+    // There are no public busy, help or zoom cursors so the closest ones are used
+    s_cursors[CursorStyle_Wait] = [[NSCursor arrowCursor] retain];
+    s_cursors[CursorStyle_Progress] = [[NSCursor arrowCursor] retain];
+    s_cursors[CursorStyle_Help] = [[NSCursor arrowCursor] retain];
+    s_cursors[CursorStyle_NotAllowed] = [[NSCursor operationNotAllowedCursor] retain];
+    s_cursors[CursorStyle_ZoomIn] = [[NSCursor crosshairCursor] retain];
+    s_cursors[CursorStyle_ZoomOut] = [[NSCursor crosshairCursor] retain];
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -148,7 +165,7 @@ static bool create_shaders() {
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-void* mfb_open(const char* name, int width, int height, uint32_t flags, int scale, void** view_handle)
+void* mfb_open(const char* name, int width, int height, uint32_t flags, int scale, int buffer_count, void** view_handle)
 {
 	bool prev_init = s_init;
 
@@ -241,8 +258,9 @@ void* mfb_open(const char* name, int width, int height, uint32_t flags, int scal
 		viewController->m_delayed_delete_textures[i].frame_count = -1;
 	}
 
-	// Used for syncing the CPU and GPU
-	viewController->m_semaphore = dispatch_semaphore_create(MaxBuffersInFlight);
+	// Used for syncing the CPU and GPU, buffer_count is at most MaxBuffersInFlight
+	viewController->m_buffer_count = buffer_count;
+	viewController->m_semaphore = dispatch_semaphore_create(buffer_count);
     viewController->m_draw_parameters = window->draw_parameters;
     //viewController->m_width = width;
     //viewController->m_height = height;
@@ -251,6 +269,20 @@ void* mfb_open(const char* name, int width, int height, uint32_t flags, int scal
     view.device = g_metal_device;
     view.delegate = viewController;
     view.autoresizingMask = NSViewWidthSizable | NSViewHeightSizable;
+
+	// Without a color space the pixels are sent to the display without any color matching
+	CGColorSpaceRef color_space = NULL;
+
+	if (flags & WINDOW_COLOR_SPACE_SRGB)
+		color_space = CGColorSpaceCreateWithName(kCGColorSpaceSRGB);
+	else if (flags & WINDOW_COLOR_SPACE_LINEAR)
+		color_space = CGColorSpaceCreateWithName(kCGColorSpaceLinearSRGB);
+
+	if (color_space) {
+		view.colorspace = color_space;
+		CGColorSpaceRelease(color_space);
+	}
+
     [window.contentView addSubview:view];
 
     OSXWindowFrameView* temp_view = window->frame_view;
@@ -262,7 +294,10 @@ void* mfb_open(const char* name, int width, int height, uint32_t flags, int scal
 	window->key_callback = 0;
 	window->shared_data = 0;
 	window->active_menu_id = -1;
+	window->active_menu_from_shortcut = false;
 	window->prev_cursor = 0;
+	window->auto_close = true;
+	window->close_requested = false;
 
 	window->menu_data = malloc(sizeof(MenuData));
 	memset(window->menu_data, 0, sizeof(MenuData));
@@ -271,7 +306,8 @@ void* mfb_open(const char* name, int width, int height, uint32_t flags, int scal
 
 	[window setTitle:[NSString stringWithUTF8String:name]];
 	[window setReleasedWhenClosed:NO];
-	[window performSelectorOnMainThread:@selector(makeKeyAndOrderFront:) withObject:nil waitUntilDone:YES];
+	if (!(flags & WINDOW_HIDDEN))
+		[window performSelectorOnMainThread:@selector(makeKeyAndOrderFront:) withObject:nil waitUntilDone:YES];
 	[window setAcceptsMouseMovedEvents:YES];
 
 	[window center];
@@ -305,6 +341,166 @@ void mfb_topmost(void* window, bool topmost)
 	}
 
 }
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_set_visible(void* window, bool visible)
+{
+	OSXWindow* win = (OSXWindow*)window;
+
+	if (visible)
+		[win makeKeyAndOrderFront:nil];
+	else
+		[win orderOut:nil];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_beep()
+{
+	NSBeep();
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+bool mfb_is_key_down(uint16_t key)
+{
+	return CGEventSourceKeyState(kCGEventSourceStateCombinedSessionState, key);
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// GL renders to a plain subview covering the Metal view, the subview doesn't handle any events so
+// they still reach the Metal view
+
+void* mfb_gl_create(void* window)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	NSView* content = [win contentView];
+
+	NSOpenGLPixelFormatAttribute attributes[] =
+	{
+		NSOpenGLPFADoubleBuffer,
+		NSOpenGLPFAAccelerated,
+		NSOpenGLPFAColorSize, 24,
+		NSOpenGLPFAAlphaSize, 8,
+		NSOpenGLPFADepthSize, 24,
+		0
+	};
+
+	NSOpenGLPixelFormat* format = [[NSOpenGLPixelFormat alloc] initWithAttributes:attributes];
+	if (!format)
+		return NULL;
+
+	NSOpenGLContext* context = [[NSOpenGLContext alloc] initWithFormat:format shareContext:nil];
+	[format release];
+
+	if (!context)
+		return NULL;
+
+	NSView* view = [[NSView alloc] initWithFrame:[content bounds]];
+	[view setAutoresizingMask:NSViewWidthSizable | NSViewHeightSizable];
+	[view setWantsBestResolutionOpenGLSurface:YES];
+	[content addSubview:view];
+	[view release];
+
+	[context setView:view];
+	[context makeCurrentContext];
+
+	return context;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_gl_swap(void* context)
+{
+	NSOpenGLContext* ctx = (NSOpenGLContext*)context;
+	// Picks up size changes of the view, it does nothing otherwise
+	[ctx update];
+	[ctx flushBuffer];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_gl_destroy(void* context)
+{
+	NSOpenGLContext* ctx = (NSOpenGLContext*)context;
+
+	if ([NSOpenGLContext currentContext] == ctx)
+		[NSOpenGLContext clearCurrentContext];
+
+	[ctx clearDrawable];
+	[ctx release];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+const void* mfb_gl_get_proc_address(const char* name)
+{
+	static void* framework = NULL;
+
+	if (!framework)
+		framework = dlopen("/System/Library/Frameworks/OpenGL.framework/OpenGL", RTLD_LAZY);
+
+	return framework ? dlsym(framework, name) : NULL;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_set_caption_buttons(void* window, bool minimize, bool maximize, bool close)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	[[win standardWindowButton:NSWindowMiniaturizeButton] setHidden:!minimize];
+	[[win standardWindowButton:NSWindowZoomButton] setHidden:!maximize];
+	[[win standardWindowButton:NSWindowCloseButton] setHidden:!close];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_set_shadow(void* window, bool enabled)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	[win setHasShadow:enabled];
+	// The shadow follows the shape of the contents, which may have changed while it was off
+	[win invalidateShadow];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// The Dock shows applications rather than windows, so this only takes the window out of window cycling
+// (Cmd+`), Mission Control's window list and the Window menu
+
+void mfb_set_skip_taskbar(void* window, bool skip)
+{
+	OSXWindow* win = (OSXWindow*)window;
+
+	NSWindowCollectionBehavior behavior = [win collectionBehavior];
+	if (skip)
+		behavior |= NSWindowCollectionBehaviorIgnoresCycle | NSWindowCollectionBehaviorTransient;
+	else
+		behavior &= ~(NSWindowCollectionBehaviorIgnoresCycle | NSWindowCollectionBehaviorTransient);
+
+	[win setCollectionBehavior:behavior];
+	[win setExcludedFromWindowsMenu:skip];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Same order as WindowLevel in lib.rs
+void mfb_set_window_level(void* window, uint32_t level)
+{
+	OSXWindow* win = (OSXWindow*)window;
+
+	switch (level)
+	{
+		case 0: win.level = NSNormalWindowLevel - 1; break;
+		case 2: win.level = NSFloatingWindowLevel; break;
+		case 3: win.level = NSModalPanelWindowLevel; break;
+		case 4: win.level = NSScreenSaverWindowLevel; break;
+		default: win.level = NSNormalWindowLevel; break;
+	}
+}
+
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
 static NSString* findAppName(void)
@@ -344,22 +540,28 @@ static NSString* findAppName(void)
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-static void create_standard_menu(void)
+// Fills the application menu with the standard items, the items of extra_menu are placed below
+// the About item (where Preferences usually goes)
+static void fill_app_menu(NSMenu* appMenu, NSMenu* extraMenu)
 {
     NSString* appName = findAppName();
 
-    NSMenu* bar = [[NSMenu alloc] init];
-    [NSApp setMainMenu:bar];
-
-    NSMenuItem* appMenuItem =
-        [bar addItemWithTitle:@"" action:NULL keyEquivalent:@""];
-    NSMenu* appMenu = [[NSMenu alloc] init];
-    [appMenuItem setSubmenu:appMenu];
-
     [appMenu addItemWithTitle:[NSString stringWithFormat:@"About %@", appName]
                       action:@selector(orderFrontStandardAboutPanel:)
                keyEquivalent:@""];
     [appMenu addItem:[NSMenuItem separatorItem]];
+
+    if (extraMenu && [extraMenu numberOfItems] > 0) {
+        // A menu item can only be in one menu so the items are copied, they keep their tag and
+        // action so selecting them is reported like any other menu item
+        for (NSMenuItem* item in [extraMenu itemArray]) {
+            NSMenuItem* copy = [item copy];
+            [appMenu addItem:copy];
+            [copy release];
+        }
+        [appMenu addItem:[NSMenuItem separatorItem]];
+    }
+
     NSMenu* servicesMenu = [[NSMenu alloc] init];
     [NSApp setServicesMenu:servicesMenu];
     [[appMenu addItemWithTitle:@"Services"
@@ -381,6 +583,21 @@ static void create_standard_menu(void)
     [appMenu addItemWithTitle:[NSString stringWithFormat:@"Quit %@", appName]
                        action:@selector(terminate:)
                 keyEquivalent:@"q"];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+static void create_standard_menu(void)
+{
+    NSMenu* bar = [[NSMenu alloc] init];
+    [NSApp setMainMenu:bar];
+
+    NSMenuItem* appMenuItem =
+        [bar addItemWithTitle:@"" action:NULL keyEquivalent:@""];
+    NSMenu* appMenu = [[NSMenu alloc] init];
+    [appMenuItem setSubmenu:appMenu];
+
+    fill_app_menu(appMenu, nil);
 
 	/*
     NSMenuItem* windowMenuItem =
@@ -447,6 +664,21 @@ void mfb_close(void* win)
 	[pool drain];
 }
 
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// Windows aren't released when closed as the Rust side keeps using them until it's dropped
+
+void mfb_destroy(void* win)
+{
+	NSWindow* window = (NSWindow*)win;
+
+	NSAutoreleasePool* pool = [[NSAutoreleasePool alloc] init];
+
+	if (window)
+		[window release];
+
+	[pool drain];
+}
+
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
 static int update_events()
@@ -477,28 +709,47 @@ static int update_events()
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-static int generic_update(OSXWindow* win)
+static void update_mouse_pos(OSXWindow* win)
 {
 	if (win->shared_data) {
-		win->shared_data->scroll_x = 0.0f;
-		win->shared_data->scroll_y = 0.0f;
-	}
-
-	int state = update_events();
-
-    if (win->shared_data) {
 		NSPoint p = [win mouseLocationOutsideOfEventStream];
 		NSRect originalFrame = [win frame];
 		NSRect contentRect = [NSWindow contentRectForFrameRect: originalFrame styleMask: NSWindowStyleMaskTitled];
 		win->shared_data->mouse_x = p.x;
 		win->shared_data->mouse_y = contentRect.size.height - p.y;
 	}
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+static int generic_update(OSXWindow* win)
+{
+	if (win->shared_data) {
+		win->shared_data->scroll_x = 0.0f;
+		win->shared_data->scroll_y = 0.0f;
+	}
+
+	win->close_requested = false;
+
+	int state = update_events();
+	update_mouse_pos(win);
 
 	return state;
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+// Processes pending events without clearing the state of the previous ones
+
+void mfb_poll_events(void* window)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	update_events();
+	update_mouse_pos(win);
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
 int mfb_update(void* window, void* buffer)
 {
 	(void)buffer;
@@ -559,6 +810,102 @@ void mfb_set_position(void* window, int x, int y)
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+// There is no way to clip the cursor on macOS so it's warped back inside the window instead
+
+void mfb_confine_cursor(void* window)
+{
+	OSXWindow* win = (OSXWindow*)window;
+
+	if (![win isKeyWindow]) {
+		return;
+	}
+
+	const NSRect contentRect = [win contentRectForFrameRect:[win frame]];
+	NSPoint p = [NSEvent mouseLocation];
+
+	if (NSPointInRect(p, contentRect)) {
+		return;
+	}
+
+	p.x = MAX(NSMinX(contentRect), MIN(p.x, NSMaxX(contentRect) - 1));
+	p.y = MAX(NSMinY(contentRect) + 1, MIN(p.y, NSMaxY(contentRect)));
+
+	CGWarpMouseCursorPosition(CGPointMake(p.x, transformY(p.y)));
+	// Warping suppresses mouse movement for a short while unless the cursor is re-associated
+	CGAssociateMouseAndMouseCursorPosition(true);
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// The position is relative to the top left of the content view, the same as the mouse position
+
+void mfb_set_cursor_position(void* window, float x, float y)
+{
+	OSXWindow* win = (OSXWindow*)window;
+
+	const NSRect contentRect = [win contentRectForFrameRect:[win frame]];
+	const NSPoint p = NSMakePoint(NSMinX(contentRect) + x, NSMaxY(contentRect) - y);
+
+	CGWarpMouseCursorPosition(CGPointMake(p.x, transformY(p.y)));
+	// Warping suppresses mouse movement for a short while unless the cursor is re-associated
+	CGAssociateMouseAndMouseCursorPosition(true);
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// Intercepting every key would need an event tap and the accessibility permission, so this only turns off the
+// shortcuts the presentation options cover. These are only applied while the application is active.
+
+void mfb_set_keyboard_grab(bool grab)
+{
+	NSApplicationPresentationOptions options = NSApplicationPresentationDefault;
+
+	if (grab) {
+		options = NSApplicationPresentationHideDock |
+			NSApplicationPresentationDisableProcessSwitching |
+			NSApplicationPresentationDisableHideApplication;
+	}
+
+	[NSApp setPresentationOptions:options];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_set_aspect_ratio(void* window, uint32_t numerator, uint32_t denominator)
+{
+	OSXWindow* win = (OSXWindow*)window;
+
+	// The aspect ratio and resize increments exclude each other, so setting the increments clears the ratio
+	if (numerator == 0 || denominator == 0)
+		[win setContentResizeIncrements:NSMakeSize(1.0, 1.0)];
+	else
+		[win setContentAspectRatio:NSMakeSize(numerator, denominator)];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_set_size(void* window, int width, int height)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	NSSize size = NSMakeSize(width, height);
+
+	// setContentSize: ignores the content min/max size so clamp to them here
+	const NSSize min_size = [win contentMinSize];
+	const NSSize max_size = [win contentMaxSize];
+	size.width = MAX(min_size.width, MIN(size.width, max_size.width));
+	size.height = MAX(min_size.height, MIN(size.height, max_size.height));
+
+	[win setContentSize:size];
+
+	// The frame view picks up the new size on resize, update it right away as well so the
+	// next update uses it
+	if (win->shared_data) {
+		win->shared_data->width = (int)size.width;
+		win->shared_data->height = (int)size.height;
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
 void mfb_get_position(const void* window, int *px, int *py)
 {
 	OSXWindow* win = (OSXWindow*)window;
@@ -577,6 +924,49 @@ void mfb_get_position(const void* window, int *px, int *py)
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+// Child windows of the view's window follow it when it moves, the window is placed over the top
+// left corner of the view
+
+void mfb_attach_to_parent(void* window, void* parent_view)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	NSView* view = (NSView*)parent_view;
+	NSWindow* parent = [view window];
+
+	if (!parent)
+		return;
+
+	const NSRect bounds = [view convertRect:[view bounds] toView:nil];
+	const NSRect frame = [parent convertRectToScreen:bounds];
+	const NSRect own = [win frame];
+
+	[win setFrameOrigin:NSMakePoint(frame.origin.x, frame.origin.y + frame.size.height - own.size.height)];
+	[parent addChildWindow:win ordered:NSWindowAbove];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_get_mouse_screen_pos(float* x, float* y)
+{
+	// Cocoa puts the origin in the bottom left corner of the main screen
+	const NSPoint location = [NSEvent mouseLocation];
+	const NSRect main = [[[NSScreen screens] objectAtIndex:0] frame];
+	*x = location.x;
+	*y = main.size.height - location.y;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_get_outer_size(const void* window, int* width, int* height)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	const NSRect frame = [win frame];
+	*width = frame.size.width;
+	*height = frame.size.height;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
 int mfb_should_close(void* window)
 {
 	OSXWindow* win = (OSXWindow*)window;
@@ -585,6 +975,22 @@ int mfb_should_close(void* window)
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+void mfb_set_close_requested_handling(void* window, bool auto_close)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	win->auto_close = auto_close;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+int mfb_close_requested(void* window)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	return win->close_requested;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
 uint32_t mfb_get_screen_size()
 {
 	NSRect e = [[NSScreen mainScreen] frame];
@@ -595,6 +1001,15 @@ uint32_t mfb_get_screen_size()
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+void mfb_get_key_repeat(float* delay, float* rate)
+{
+	// These follow the InitialKeyRepeat and KeyRepeat settings from the keyboard preferences
+	*delay = (float)[NSEvent keyRepeatDelay];
+	*rate = (float)[NSEvent keyRepeatInterval];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
 void mfb_set_key_callback(void* window, void* rust_data,
 						  void (*key_callback)(void* user_data, int key, int state),
 						  void (*char_callback)(void* user_data, uint32_t key))
@@ -634,6 +1049,78 @@ void mfb_set_cursor_style(void* window, int cursor)
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+void* mfb_create_cursor(const uint32_t* data, uint32_t width, uint32_t height, uint32_t hot_x, uint32_t hot_y)
+{
+	NSBitmapImageRep* rep = [[NSBitmapImageRep alloc]
+		initWithBitmapDataPlanes:NULL
+		pixelsWide:width
+		pixelsHigh:height
+		bitsPerSample:8
+		samplesPerPixel:4
+		hasAlpha:YES
+		isPlanar:NO
+		colorSpaceName:NSDeviceRGBColorSpace
+		bitmapFormat:NSBitmapFormatAlphaNonpremultiplied
+		bytesPerRow:width * 4
+		bitsPerPixel:32];
+
+	unsigned char* pixels = [rep bitmapData];
+
+	for (uint32_t i = 0; i < width * height; ++i) {
+		uint32_t color = data[i];
+		pixels[i * 4 + 0] = (color >> 16) & 0xff;
+		pixels[i * 4 + 1] = (color >> 8) & 0xff;
+		pixels[i * 4 + 2] = color & 0xff;
+		pixels[i * 4 + 3] = (color >> 24) & 0xff;
+	}
+
+	NSImage* image = [[NSImage alloc] initWithSize:NSMakeSize(width, height)];
+	[image addRepresentation:rep];
+	[rep release];
+
+	NSCursor* cursor = [[NSCursor alloc] initWithImage:image hotSpot:NSMakePoint(hot_x, hot_y)];
+	[image release];
+
+	return cursor;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_set_cursor(void* window, void* cursor)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	[(NSCursor*)cursor set];
+	// Makes the next mfb_set_cursor_style set its cursor even if the style didn't change
+	win->prev_cursor = -1;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_destroy_cursor(void* cursor)
+{
+	[(NSCursor*)cursor release];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Uses the cursor of the style when no cursor is given
+void mfb_get_cursor_hotspot(void* cursor, int style, int32_t* x, int32_t* y)
+{
+	NSCursor* ns_cursor = (NSCursor*)cursor;
+
+	if (!ns_cursor) {
+		if (style < 0 || style >= CursorStyle_Count)
+			return;
+		ns_cursor = s_cursors[style];
+	}
+
+	NSPoint hotspot = [ns_cursor hotSpot];
+	*x = (int32_t)hotspot.x;
+	*y = (int32_t)hotspot.y;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
 uint32_t mfb_is_active(void* window)
 {
 	OSXWindow* win = (OSXWindow*)window;
@@ -642,6 +1129,147 @@ uint32_t mfb_is_active(void* window)
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+uint32_t mfb_is_main_thread()
+{
+	return [NSThread isMainThread] ? 1 : 0;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+uint32_t mfb_get_screen_id(void* window)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	NSScreen* screen = [win screen];
+
+	if (!screen)
+		return 0;
+
+	return [[[screen deviceDescription] objectForKey:@"NSScreenNumber"] unsignedIntValue];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+static uint32_t get_screen_info(NSScreen* screen, char* name, int name_len, int* x, int* y, int* width, int* height, float* refresh_rate)
+{
+	if (!screen)
+		return 0;
+
+	const NSRect frame = [screen frame];
+	*x = (int)frame.origin.x;
+	*y = (int)transformY(frame.origin.y + frame.size.height);
+	*width = (int)frame.size.width;
+	*height = (int)frame.size.height;
+
+	CGDirectDisplayID display_id = [[[screen deviceDescription] objectForKey:@"NSScreenNumber"] unsignedIntValue];
+	CGDisplayModeRef mode = CGDisplayCopyDisplayMode(display_id);
+	*refresh_rate = mode ? (float)CGDisplayModeGetRefreshRate(mode) : 0.0f;
+	CGDisplayModeRelease(mode);
+
+	name[0] = 0;
+	if (@available(macOS 10.15, *)) {
+		if (![[screen localizedName] getCString:name maxLength:name_len encoding:NSUTF8StringEncoding])
+			name[0] = 0;
+	}
+
+	return 1;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+uint32_t mfb_get_monitor(void* window, char* name, int name_len, int* x, int* y, int* width, int* height, float* refresh_rate)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	return get_screen_info([win screen], name, name_len, x, y, width, height, refresh_rate);
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+uint32_t mfb_get_screen_count()
+{
+	return (uint32_t)[[NSScreen screens] count];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+uint32_t mfb_get_screen_monitor(uint32_t index, char* name, int name_len, int* x, int* y, int* width, int* height, float* refresh_rate)
+{
+	NSArray<NSScreen*>* screens = [NSScreen screens];
+
+	if (index >= [screens count])
+		return 0;
+
+	return get_screen_info([screens objectAtIndex:index], name, name_len, x, y, width, height, refresh_rate);
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// A zoomed window fills the visible frame of its screen, which leaves out the menu bar and Dock
+
+uint32_t mfb_is_zoomed(void* window)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	NSScreen* screen = [win screen];
+
+	if (!screen)
+		return 0;
+
+	return NSEqualRects([win frame], [screen visibleFrame]) ? 1 : 0;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_set_zoomed(void* window, bool zoomed)
+{
+	OSXWindow* win = (OSXWindow*)window;
+
+	if ((mfb_is_zoomed(window) != 0) != zoomed)
+		[win zoom:nil];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+// Seconds since system startup of the event that is being handled, or a negative value outside of event handling
+
+double mfb_current_event_time()
+{
+	NSEvent* event = [NSApp currentEvent];
+	return event ? [event timestamp] : -1.0;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+uint32_t mfb_get_keyboard_layout(char* name, int name_len)
+{
+	TISInputSourceRef source = TISCopyCurrentKeyboardLayoutInputSource();
+
+	if (!source)
+		return 0;
+
+	CFStringRef source_id = (CFStringRef)TISGetInputSourceProperty(source, kTISPropertyInputSourceID);
+	Boolean found = source_id && CFStringGetCString(source_id, name, name_len, kCFStringEncodingUTF8);
+
+	CFRelease(source);
+
+	return found ? 1 : 0;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+uint32_t mfb_is_occluded(void* window)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	return ([win occlusionState] & NSWindowOcclusionStateVisible) == 0;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+float mfb_get_content_scale(void* window)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	return (float)[win backingScaleFactor];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
 void mfb_remove_menu(void* window, const char* name)
 {
 	OSXWindow* win = (OSXWindow*)window;
@@ -673,10 +1301,12 @@ void mfb_remove_menu(void* window, const char* name)
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
-int mfb_active_menu(void* window) {
+int mfb_active_menu(void* window, bool* from_shortcut) {
 	OSXWindow* win = (OSXWindow*)window;
 	int active_menu_id = win->active_menu_id;
+	*from_shortcut = win->active_menu_from_shortcut;
 	win->active_menu_id = -1;
+	win->active_menu_from_shortcut = false;
 	return active_menu_id;
 }
 
@@ -833,6 +1463,44 @@ uint64_t mfb_add_menu_item(
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+void mfb_set_menu_item_icon(uint64_t menu_item, const uint32_t* data, uint32_t width, uint32_t height)
+{
+	NSMenuItem* item = (NSMenuItem*)menu_item;
+
+	NSBitmapImageRep* rep = [[NSBitmapImageRep alloc]
+		initWithBitmapDataPlanes:NULL
+		pixelsWide:width
+		pixelsHigh:height
+		bitsPerSample:8
+		samplesPerPixel:4
+		hasAlpha:YES
+		isPlanar:NO
+		colorSpaceName:NSDeviceRGBColorSpace
+		bitmapFormat:NSBitmapFormatAlphaNonpremultiplied
+		bytesPerRow:width * 4
+		bitsPerPixel:32];
+
+	// The buffer is 0xAARRGGBB while the image wants the bytes in RGBA order
+	unsigned char* pixels = [rep bitmapData];
+	for (uint32_t i = 0; i < width * height; ++i)
+	{
+		uint32_t color = data[i];
+		pixels[i * 4 + 0] = (color >> 16) & 0xff;
+		pixels[i * 4 + 1] = (color >> 8) & 0xff;
+		pixels[i * 4 + 2] = color & 0xff;
+		pixels[i * 4 + 3] = (color >> 24) & 0xff;
+	}
+
+	NSImage* image = [[NSImage alloc] initWithSize:NSMakeSize(width, height)];
+	[image addRepresentation:rep];
+	[rep release];
+
+	[item setImage:image];
+	[image release];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
 void mfb_add_sub_menu(void* parent_menu, const char* menu_name, void* attach_menu) {
 	NSMenu* parent = (NSMenu*)parent_menu;
 	NSMenu* attach = (NSMenu*)attach_menu;
@@ -898,4 +1566,241 @@ void mfb_remove_menu_at(void* window, int index)
 	[main_menu removeItemAtIndex:index];
 }
 
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_replace_menu_at(void* window, int index, void* m)
+{
+	(void)window;
+	NSMenu* menu = (NSMenu*)m;
+ 	NSMenu* main_menu = [NSApp mainMenu];
+	NSMenuItem* menu_item = [main_menu itemAtIndex:index];
+	[NSApp setWindowsMenu:menu];
+	[menu_item setSubmenu:menu];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_clear_menu(void* m)
+{
+	NSMenu* menu = (NSMenu*)m;
+	[menu removeAllItems];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_set_app_menu(void* m)
+{
+	NSMenu* menu = (NSMenu*)m;
+	NSMenu* app_menu = [[[NSApp mainMenu] itemAtIndex:0] submenu];
+
+	[app_menu removeAllItems];
+	fill_app_menu(app_menu, menu);
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_show_context_menu(void* window, void* m, int x, int y)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	NSMenu* menu = (NSMenu*)m;
+	NSView* view = [win contentView];
+	NSRect frame = [view frame];
+
+	// Coordinates are given from the top left corner of the window
+	NSPoint location = NSMakePoint(x, [view isFlipped] ? y : frame.size.height - y);
+
+	// This runs the menu modally, selected items end up in onMenuPress like the menu bar
+	[menu popUpMenuPositioningItem:nil atLocation:location inView:view];
+}
+
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Receives the clicks on a status item and on the items of its menu
+@interface MinifbTray : NSObject
+{
+	@public NSStatusItem* status_item;
+	@public int active_menu_id;
+	@public bool clicked;
+}
+@end
+
+@implementation MinifbTray
+
+- (void)onMenuPress:(id)sender
+{
+	self->active_menu_id = (int)((NSMenuItem*)sender).tag;
+}
+
+- (void)onClick:(id)sender
+{
+	(void)sender;
+	self->clicked = true;
+}
+
+@end
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+// Builds an image from the width, height and ARGB pixels of an icon buffer
+static NSImage* create_tray_image(const uint64_t* data, uint32_t len)
+{
+	if (!data || len < 2)
+		return nil;
+
+	uint64_t width = data[0];
+	uint64_t height = data[1];
+
+	if (width == 0 || height == 0 || width * height + 2 > len)
+		return nil;
+
+	NSBitmapImageRep* rep = [[NSBitmapImageRep alloc]
+		initWithBitmapDataPlanes:NULL
+		pixelsWide:(NSInteger)width
+		pixelsHigh:(NSInteger)height
+		bitsPerSample:8
+		samplesPerPixel:4
+		hasAlpha:YES
+		isPlanar:NO
+		colorSpaceName:NSDeviceRGBColorSpace
+		bitmapFormat:NSBitmapFormatAlphaNonpremultiplied
+		bytesPerRow:(NSInteger)width * 4
+		bitsPerPixel:32];
+
+	unsigned char* pixels = [rep bitmapData];
+
+	for (uint64_t i = 0; i < width * height; ++i) {
+		uint32_t color = (uint32_t)data[i + 2];
+		pixels[i * 4 + 0] = (color >> 16) & 0xff;
+		pixels[i * 4 + 1] = (color >> 8) & 0xff;
+		pixels[i * 4 + 2] = color & 0xff;
+		pixels[i * 4 + 3] = (color >> 24) & 0xff;
+	}
+
+	// The status bar is 22 points high, icons are usually drawn at 18
+	NSImage* image = [[NSImage alloc] initWithSize:NSMakeSize(18.0 * width / height, 18.0)];
+	[image addRepresentation:rep];
+	[rep release];
+
+	return image;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+// Points the items of the menu and its sub menus at the tray instead of the key window
+static void retarget_tray_menu(NSMenu* menu, id target)
+{
+	for (NSMenuItem* item in [menu itemArray]) {
+		if ([item hasSubmenu])
+			retarget_tray_menu([item submenu], target);
+		else if ([item action] == @selector(onMenuPress:))
+			[item setTarget:target];
+	}
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_tray_set_tooltip(void* t, const char* tooltip)
+{
+	MinifbTray* tray = (MinifbTray*)t;
+	NSStatusBarButton* button = [tray->status_item button];
+	NSString* text = [NSString stringWithUTF8String:tooltip];
+
+	// Without an image the tooltip is the only thing that can be shown in the status bar
+	if (![button image])
+		[button setTitle:text];
+
+	[button setToolTip:text];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void* mfb_tray_create(const uint64_t* icon, uint32_t icon_len, const char* tooltip)
+{
+	[NSApplication sharedApplication];
+
+	MinifbTray* tray = [[MinifbTray alloc] init];
+	tray->active_menu_id = -1;
+	tray->clicked = false;
+	tray->status_item = [[[NSStatusBar systemStatusBar] statusItemWithLength:NSVariableStatusItemLength] retain];
+
+	NSStatusBarButton* button = [tray->status_item button];
+	[button setTarget:tray];
+	[button setAction:@selector(onClick:)];
+
+	NSImage* image = create_tray_image(icon, icon_len);
+	if (image) {
+		[button setImage:image];
+		[image release];
+	}
+
+	mfb_tray_set_tooltip(tray, tooltip);
+
+	return tray;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_tray_set_menu(void* t, void* m)
+{
+	MinifbTray* tray = (MinifbTray*)t;
+
+	// A copy so the same menu can still be used by a window
+	NSMenu* menu = [(NSMenu*)m copy];
+	retarget_tray_menu(menu, tray);
+	[tray->status_item setMenu:menu];
+	[menu release];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+int mfb_tray_active_menu(void* t)
+{
+	MinifbTray* tray = (MinifbTray*)t;
+	int active_menu_id = tray->active_menu_id;
+	tray->active_menu_id = -1;
+	return active_menu_id;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+bool mfb_tray_clicked(void* t)
+{
+	MinifbTray* tray = (MinifbTray*)t;
+	bool clicked = tray->clicked;
+	tray->clicked = false;
+	return clicked;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_tray_update()
+{
+	update_events();
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_tray_destroy(void* t)
+{
+	MinifbTray* tray = (MinifbTray*)t;
+	[[NSStatusBar systemStatusBar] removeStatusItem:tray->status_item];
+	[tray->status_item release];
+	[tray release];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_set_document_edited(void* window, bool edited)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	[win setDocumentEdited:edited];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+void mfb_set_represented_file(void* window, const char* path)
+{
+	OSXWindow* win = (OSXWindow*)window;
+	[win setRepresentedFilename:[NSString stringWithUTF8String:path]];
+}
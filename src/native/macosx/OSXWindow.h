@@ -49,8 +49,11 @@ void build_submenu(NSMenu* menu, MenuDesc* desc);
 	@public void* rust_data;
 	@public SharedData* shared_data;
 	@public bool should_close;
+	@public bool auto_close;
+	@public bool close_requested;
 	@public bool is_active;
 	@public int active_menu_id;
+	@public bool active_menu_from_shortcut;
 	@public int prev_cursor;
 	@public MenuData* menu_data;
 	@public void* frame_view;
@@ -9,6 +9,7 @@
 {
 	[[NSNotificationCenter defaultCenter]
 		removeObserver:self];
+	free(menu_data);
 	[super dealloc];
 }
 
@@ -142,6 +143,13 @@
 - (BOOL)windowShouldClose:(id)sender
 {
 	(void)sender;
+
+	if (!auto_close) {
+		// Let the application decide if the window should be closed
+		close_requested = true;
+		return FALSE;
+	}
+
 	should_close = true;
 	return TRUE;
 }
@@ -234,6 +242,8 @@
 {
 	int menu_id = (int)((NSButton*)sender).tag;
 	self->active_menu_id = menu_id;
+	// Key equivalents are dispatched while handling the key down event
+	self->active_menu_from_shortcut = [[NSApp currentEvent] type] == NSEventTypeKeyDown;
 }
 
 @end
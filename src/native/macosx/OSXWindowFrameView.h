@@ -2,7 +2,7 @@
 #import <MetalKit/MetalKit.h>
 #include "shared_data.h"
 
-// Number of textures in flight (tripple buffered)
+// Maximum number of textures in flight (tripple buffered)
 const int MaxBuffersInFlight = 3;
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -35,6 +35,8 @@ typedef struct DelayedTextureDelete {
 	@public DrawState m_draw_state[MaxBuffersInFlight];
 	@public DelayedTextureDelete m_delayed_delete_textures[MaxBuffersInFlight];
 	@public int m_current_buffer;
+	// Number of textures in flight, set by WindowOptions::buffer_count
+	@public int m_buffer_count;
     @public DrawParameters* m_draw_parameters;
 	@public float m_width;
 	@public float m_height;
@@ -177,12 +177,12 @@ uint32_t upper_power_of_two(uint32_t v) {
     	return;
     }
 
-    // Wait to ensure only MaxBuffersInFlight number of frames are getting proccessed
+    // Wait to ensure only m_buffer_count number of frames are getting proccessed
     //   by any stage in the Metal pipeline (App, Metal, Drivers, GPU, etc)
     dispatch_semaphore_wait(m_semaphore, DISPATCH_TIME_FOREVER);
 
-    // Iterate through our Metal buffers, and cycle back to the first when we've written to MaxBuffersInFlight
-    m_current_buffer = (m_current_buffer + 1) % MaxBuffersInFlight;
+    // Iterate through our Metal buffers, and cycle back to the first when we've written to m_buffer_count
+    m_current_buffer = (m_current_buffer + 1) % m_buffer_count;
 
 	for (int i = 0; i < MaxBuffersInFlight; ++i) {
 		DelayedTextureDelete* del_texture = &m_delayed_delete_textures[i];
@@ -350,20 +350,73 @@ uint32_t upper_power_of_two(uint32_t v) {
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
+- (void)updatePen:(NSEvent*)event
+{
+    OSXWindow* window = (OSXWindow*)[self window];
+    SharedData* data = window->shared_data;
+    if (!data || [event subtype] != NSEventSubtypeTabletPoint) {
+        return;
+    }
+
+    NSPoint p = [self convertPoint:[event locationInWindow] fromView:nil];
+    NSPoint tilt = [event tilt];
+
+    data->pen_x = p.x;
+    data->pen_y = [self bounds].size.height - p.y;
+    data->pen_pressure = [event pressure];
+    // Tilt is reported from -1 to 1 on both axes
+    data->pen_tilt_x = tilt.x * 90.0f;
+    data->pen_tilt_y = tilt.y * 90.0f;
+    data->pen_state = 2;
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+- (void)tabletPoint:(NSEvent*)event
+{
+    [self updatePen:event];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+- (void)tabletProximity:(NSEvent*)event
+{
+    OSXWindow* window = (OSXWindow*)[self window];
+    if (window->shared_data) {
+        window->shared_data->pen_state = [event isEnteringProximity] ? 2 : 1;
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+- (void)mouseMoved:(NSEvent*)event
+{
+    [self updatePen:event];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+- (void)mouseDragged:(NSEvent*)event
+{
+    [self updatePen:event];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
 - (void)mouseDown:(NSEvent*)event
 {
-    (void)event;
     OSXWindow* window = (OSXWindow*)[self window];
     window->shared_data->mouse_state[0] = 1;
+    [self updatePen:event];
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
 
 - (void)mouseUp:(NSEvent*)event
 {
-    (void)event;
     OSXWindow* window = (OSXWindow*)[self window];
     window->shared_data->mouse_state[0] = 0;
+    [self updatePen:event];
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -389,8 +442,55 @@ uint32_t upper_power_of_two(uint32_t v) {
 - (void)scrollWheel:(NSEvent *)event
 {
     OSXWindow* window = (OSXWindow*)[self window];
-    window->shared_data->scroll_x = [event deltaX];
-    window->shared_data->scroll_y = [event deltaY];
+    window->shared_data->scroll_x += [event deltaX];
+    window->shared_data->scroll_y += [event deltaY];
+}
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
+- (void)addTouches:(NSEvent*)event phase:(NSTouchPhase)phase state:(int)state
+{
+    OSXWindow* window = (OSXWindow*)[self window];
+    SharedData* data = window->shared_data;
+    if (!data) {
+        return;
+    }
+
+    NSSize size = [self bounds].size;
+
+    for (NSTouch* touch in [event touchesMatchingPhase:phase inView:self]) {
+        if (data->touch_event_count >= MAX_TOUCH_EVENTS) {
+            return;
+        }
+
+        // Trackpad touches only have a normalized position so map it onto the view
+        NSPoint p = [touch normalizedPosition];
+        TouchEvent* touch_event = &data->touch_events[data->touch_event_count++];
+        touch_event->id = (unsigned long long)[[touch identity] hash];
+        touch_event->x = p.x * size.width;
+        touch_event->y = (1.0 - p.y) * size.height;
+        touch_event->phase = state;
+    }
+}
+
+- (void)touchesBeganWithEvent:(NSEvent*)event
+{
+    [self addTouches:event phase:NSTouchPhaseBegan state:0];
+}
+
+- (void)touchesMovedWithEvent:(NSEvent*)event
+{
+    [self addTouches:event phase:NSTouchPhaseMoved state:1];
+}
+
+- (void)touchesEndedWithEvent:(NSEvent*)event
+{
+    [self addTouches:event phase:NSTouchPhaseEnded state:2];
+}
+
+- (void)touchesCancelledWithEvent:(NSEvent*)event
+{
+    [self addTouches:event phase:NSTouchPhaseCancelled state:2];
 }
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -421,6 +521,10 @@ uint32_t upper_power_of_two(uint32_t v) {
 
 - (void)viewDidMoveToWindow
 {
+    [self setAllowedTouchTypes:NSTouchTypeMaskIndirect];
+    // Needed to track a pen hovering over the window
+    [[self window] setAcceptsMouseMovedEvents:YES];
+
     [[NSNotificationCenter defaultCenter] addObserver:self
     selector:@selector(windowResized:) name:NSWindowDidResizeNotification
     object:[self window]];
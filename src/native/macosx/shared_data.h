@@ -1,5 +1,17 @@
 #pragma once
 
+// Touch events that arrive between two updates, anything past this is dropped
+#define MAX_TOUCH_EVENTS 32
+
+typedef struct TouchEvent {
+    unsigned long long id;
+    float x;
+    float y;
+    int phase; // 0 = began, 1 = moved, 2 = ended
+} TouchEvent;
+
+///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
+
 typedef struct SharedData {
     unsigned int bg_color;
     unsigned int scale_mode;
@@ -10,6 +22,14 @@ typedef struct SharedData {
     float scroll_x;
     float scroll_y;
     unsigned char mouse_state[8];
+    TouchEvent touch_events[MAX_TOUCH_EVENTS];
+    unsigned int touch_event_count;
+    float pen_x;
+    float pen_y;
+    float pen_pressure;
+    float pen_tilt_x;
+    float pen_tilt_y;
+    unsigned int pen_state; // 0 = no pen seen yet, 1 = out of range, 2 = in range
 } SharedData;
 
 ///////////////////////////////////////////////////////////////////////////////////////////////////////////////////////
@@ -3,22 +3,39 @@
 use crate::error::Error;
 use crate::key_handler::KeyHandler;
 use crate::rate::UpdateRate;
+use crate::touch_handler::TouchHandler;
+use crate::GammaRamp;
+use crate::Modifiers;
+use crate::PixelFormat;
+use crate::Rect;
 use crate::Result;
-use crate::{Key, KeyRepeat, MouseButton, MouseMode, Scale, WindowOptions};
+use crate::WindowLevel;
+use crate::{
+    Key, KeyMap, KeyRepeat, MouseButton, MouseMode, PenState, Scale, ScaleMode, TouchPhase,
+    TouchPoint, WindowOptions,
+};
 // use MenuItem;
 use crate::buffer_helper;
 use crate::icon::Icon;
 use crate::mouse_handler;
+use crate::present::PresentCallbacks;
 use crate::window_flags;
 use crate::InputCallback;
-use crate::{CursorStyle, MenuHandle, MenuItem, MenuItemHandle};
+use crate::{
+    BorderFill, CloseReason, CursorStyle, MenuHandle, MenuItem, MenuItemHandle, MenuSource, Monitor,
+};
 // use menu::Menu;
 
-use std::ffi::CString;
+use std::borrow::Cow;
+use std::ffi::{CStr, CString};
 use std::mem;
 use std::os::raw;
 use std::os::raw::{c_char, c_uchar, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
 use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Once;
 
 // Table taken from GLFW and slightly modified
 
@@ -162,11 +179,14 @@ extern "C" {
         height: u32,
         flags: u32,
         scale: i32,
+        buffer_count: i32,
         view_handle: *mut *const c_void,
     ) -> *mut c_void;
     fn mfb_set_title(window: *mut c_void, title: *const c_char);
     fn mfb_close(window: *mut c_void);
+    fn mfb_destroy(window: *mut c_void);
     fn mfb_update(window: *mut c_void);
+    fn mfb_poll_events(window: *mut c_void);
     fn mfb_update_with_buffer(
         window: *mut c_void,
         buffer: *const c_uchar,
@@ -175,7 +195,10 @@ extern "C" {
         buf_stride: u32,
     );
     fn mfb_set_position(window: *mut c_void, x: i32, y: i32);
+    fn mfb_set_aspect_ratio(window: *mut c_void, numerator: u32, denominator: u32);
+    fn mfb_set_size(window: *mut c_void, width: i32, height: i32);
     fn mfb_get_position(window: *const c_void, x: *mut i32, y: *mut i32);
+    fn mfb_get_outer_size(window: *const c_void, width: *mut i32, height: *mut i32);
     fn mfb_set_key_callback(
         window: *mut c_void,
         target: *mut c_void,
@@ -185,18 +208,82 @@ extern "C" {
     fn mfb_set_mouse_data(window_handle: *mut c_void, shared_data: *mut SharedData);
     fn mfb_set_cursor_style(window: *mut c_void, cursor: u32);
     fn mfb_set_cursor_visibility(window: *mut c_void, visibility: bool);
+    fn mfb_create_cursor(
+        data: *const u32,
+        width: u32,
+        height: u32,
+        hot_x: u32,
+        hot_y: u32,
+    ) -> *mut c_void;
+    fn mfb_set_cursor(window: *mut c_void, cursor: *mut c_void);
+    fn mfb_destroy_cursor(cursor: *mut c_void);
+    fn mfb_get_cursor_hotspot(cursor: *mut c_void, style: i32, x: *mut i32, y: *mut i32);
+    fn mfb_confine_cursor(window: *mut c_void);
+    fn mfb_set_cursor_position(window: *mut c_void, x: f32, y: f32);
+    fn mfb_set_keyboard_grab(grab: bool);
+    fn mfb_current_event_time() -> f64;
     fn mfb_should_close(window: *mut c_void) -> i32;
+    fn mfb_set_close_requested_handling(window: *mut c_void, auto_close: bool);
+    fn mfb_close_requested(window: *mut c_void) -> i32;
     fn mfb_get_screen_size() -> u32;
+    fn mfb_get_key_repeat(delay: *mut f32, rate: *mut f32);
     fn mfb_is_active(window: *mut c_void) -> u32;
+    fn mfb_is_occluded(window: *mut c_void) -> u32;
+    fn mfb_get_content_scale(window: *mut c_void) -> f32;
+    fn mfb_is_main_thread() -> u32;
+    fn mfb_get_screen_id(window: *mut c_void) -> u32;
+    fn mfb_get_monitor(
+        window: *mut c_void,
+        name: *mut c_char,
+        name_len: i32,
+        x: *mut i32,
+        y: *mut i32,
+        width: *mut i32,
+        height: *mut i32,
+        refresh_rate: *mut f32,
+    ) -> u32;
+    fn mfb_get_screen_count() -> u32;
+    fn mfb_get_screen_monitor(
+        index: u32,
+        name: *mut c_char,
+        name_len: i32,
+        x: *mut i32,
+        y: *mut i32,
+        width: *mut i32,
+        height: *mut i32,
+        refresh_rate: *mut f32,
+    ) -> u32;
+    fn mfb_is_zoomed(window: *mut c_void) -> u32;
+    fn mfb_set_zoomed(window: *mut c_void, zoomed: bool);
+    fn mfb_get_keyboard_layout(name: *mut c_char, name_len: i32) -> u32;
     fn mfb_add_menu(window: *mut c_void, menu: *mut c_void) -> u64;
     fn mfb_add_sub_menu(parent_menu: *mut c_void, name: *const c_char, menu: *mut c_void);
-    fn mfb_active_menu(window: *mut c_void) -> i32;
+    fn mfb_active_menu(window: *mut c_void, from_shortcut: *mut bool) -> i32;
 
     fn mfb_create_menu(name: *const c_char) -> *mut c_void;
     fn mfb_remove_menu_at(window: *mut c_void, index: i32);
+    fn mfb_replace_menu_at(window: *mut c_void, index: i32, menu: *mut c_void);
+    fn mfb_clear_menu(menu: *mut c_void);
+    fn mfb_show_context_menu(window: *mut c_void, menu: *mut c_void, x: i32, y: i32);
+    fn mfb_set_app_menu(menu: *mut c_void);
 
     /// Sets the whether or not the window is the topmost window
     fn mfb_topmost(window: *mut c_void, topmost: bool);
+    fn mfb_set_visible(window: *mut c_void, visible: bool);
+    fn mfb_set_window_level(window: *mut c_void, level: u32);
+    fn mfb_beep();
+    fn mfb_set_skip_taskbar(window: *mut c_void, skip: bool);
+    fn mfb_get_mouse_screen_pos(x: *mut f32, y: *mut f32);
+    fn mfb_attach_to_parent(window: *mut c_void, parent_view: *mut c_void);
+    fn mfb_set_caption_buttons(window: *mut c_void, minimize: bool, maximize: bool, close: bool);
+    fn mfb_set_shadow(window: *mut c_void, enabled: bool);
+    fn mfb_set_document_edited(window: *mut c_void, edited: bool);
+    fn mfb_set_represented_file(window: *mut c_void, path: *const c_char);
+    fn mfb_is_key_down(key: u16) -> bool;
+    fn mfb_gl_create(window: *mut c_void) -> *mut c_void;
+    fn mfb_gl_swap(context: *mut c_void);
+    fn mfb_gl_destroy(context: *mut c_void);
+    fn mfb_gl_get_proc_address(name: *const c_char) -> *const c_void;
 
     fn mfb_add_menu_item(
         menu_item: *mut c_void,
@@ -207,6 +294,60 @@ extern "C" {
         modifier: u32,
     ) -> u64;
     fn mfb_remove_menu_item(menu: *mut c_void, item_handle: u64);
+    fn mfb_set_menu_item_icon(item: u64, data: *const u32, width: u32, height: u32);
+
+    fn mfb_tray_create(icon: *const u64, icon_len: u32, tooltip: *const c_char) -> *mut c_void;
+    fn mfb_tray_set_tooltip(tray: *mut c_void, tooltip: *const c_char);
+    fn mfb_tray_set_menu(tray: *mut c_void, menu: *mut c_void);
+    fn mfb_tray_active_menu(tray: *mut c_void) -> i32;
+    fn mfb_tray_clicked(tray: *mut c_void) -> bool;
+    fn mfb_tray_update();
+    fn mfb_tray_destroy(tray: *mut c_void);
+}
+
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGDisplayRegisterReconfigurationCallback(
+        callback: extern "C" fn(display: u32, flags: u32, user_info: *mut c_void),
+        user_info: *mut c_void,
+    ) -> i32;
+    fn CGSetDisplayTransferByTable(
+        display: u32,
+        table_size: u32,
+        red: *const f32,
+        green: *const f32,
+        blue: *const f32,
+    ) -> i32;
+    fn CGGetDisplayTransferByTable(
+        display: u32,
+        capacity: u32,
+        red: *mut f32,
+        green: *mut f32,
+        blue: *mut f32,
+        sample_count: *mut u32,
+    ) -> i32;
+}
+
+const CG_DISPLAY_BEGIN_CONFIGURATION_FLAG: u32 = 1;
+
+// Counts the display reconfigurations, each window compares it with the count it last saw
+static DISPLAY_CHANGES: AtomicUsize = AtomicUsize::new(0);
+static DISPLAY_CALLBACK: Once = Once::new();
+
+extern "C" fn display_reconfigured(_display: u32, flags: u32, _user_info: *mut c_void) {
+    // The callback runs both before and after a change, only count the finished ones
+    if flags & CG_DISPLAY_BEGIN_CONFIGURATION_FLAG == 0 {
+        DISPLAY_CHANGES.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+#[repr(C)]
+pub struct TouchEvent {
+    pub id: u64,
+    pub x: f32,
+    pub y: f32,
+    pub phase: i32,
 }
 
 #[derive(Default)]
@@ -221,6 +362,15 @@ pub struct SharedData {
     pub scroll_x: f32,
     pub scroll_y: f32,
     pub state: [u8; 8],
+    // Same size as MAX_TOUCH_EVENTS in shared_data.h
+    pub touch_events: [TouchEvent; 32],
+    pub touch_event_count: u32,
+    pub pen_x: f32,
+    pub pen_y: f32,
+    pub pen_pressure: f32,
+    pub pen_tilt_x: f32,
+    pub pen_tilt_y: f32,
+    pub pen_state: u32,
 }
 
 pub struct Window {
@@ -229,9 +379,39 @@ pub struct Window {
     scale_factor: usize,
     pub shared_data: SharedData,
     key_handler: KeyHandler,
+    touch_handler: TouchHandler,
+    cursor_auto_hide: mouse_handler::CursorAutoHide,
+    mouse_delta: mouse_handler::MouseDelta,
+    scroll_accumulator: mouse_handler::ScrollAccumulator,
+    mouse_history: mouse_handler::MouseHistory,
+    present_callbacks: PresentCallbacks,
+    button_modifiers: mouse_handler::ButtonModifiers,
+    // Value of `DISPLAY_CHANGES` when the displays were last counted
+    display_changes: usize,
+    // Set once `poll_input` has started the input of the next update
+    input_polled: bool,
+    cursor_confined: bool,
+    screen_id: u32,
+    moved_to_monitor: bool,
+    // Displays and the transfer tables they had before `set_gamma_ramp` changed them
+    gamma_restore: Vec<(u32, GammaTable)>,
+    scale_mode: ScaleMode,
+    pixel_format: PixelFormat,
+    // Holds the buffer when it had to be converted, the view draws from it until the next update
+    converted_buffer: Vec<u32>,
+    buffer_size: (usize, usize), // size of the last buffer drawn
     update_rate: UpdateRate,
     pub has_set_data: bool,
     menus: Vec<MenuHandle>,
+    menu_source: Option<MenuSource>,
+    closed: bool,
+    close_reason: Option<CloseReason>,
+    // Only set for windows created with `WindowOptions::gl`
+    gl_context: *mut c_void,
+    cursor: CursorStyle,
+    // Set with `set_animated_cursor`, shown instead of `cursor`
+    animated_cursor: Option<mouse_handler::AnimatedCursor<*mut c_void>>,
+    title: String,
 }
 
 unsafe extern "C" fn key_callback(window: *mut c_void, key: i32, state: i32) {
@@ -239,6 +419,15 @@ unsafe extern "C" fn key_callback(window: *mut c_void, key: i32, state: i32) {
 
     let s = state == 1;
 
+    let time = mfb_current_event_time();
+    if time >= 0.0 {
+        (*win)
+            .key_handler
+            .set_event_time(std::time::Duration::from_secs_f64(time));
+    }
+
+    (*win).key_handler.set_scancode_state(key as u32, s);
+
     if key > 128 {
         (*win).key_handler.set_key_state(Key::Unknown, s);
     } else {
@@ -256,9 +445,7 @@ unsafe extern "C" fn char_callback(window: *mut c_void, code_point: u32) {
         return;
     }
 
-    if let Some(ref mut callback) = (*win).key_handler.key_callback {
-        callback.add_char(code_point);
-    }
+    (*win).key_handler.add_char(code_point);
 }
 
 unsafe impl raw_window_handle::HasRawWindowHandle for Window {
@@ -270,8 +457,52 @@ unsafe impl raw_window_handle::HasRawWindowHandle for Window {
     }
 }
 
+#[cfg(feature = "rwh_06")]
+impl Window {
+    pub fn raw_window_handle_06(&self) -> Option<raw_window_handle_06::RawWindowHandle> {
+        // The view is released together with the window
+        if self.closed {
+            return None;
+        }
+
+        let view = ptr::NonNull::new(self.view_handle as *mut c_void)?;
+        let handle = raw_window_handle_06::AppKitWindowHandle::new(view);
+        Some(raw_window_handle_06::RawWindowHandle::AppKit(handle))
+    }
+
+    pub fn raw_display_handle_06(&self) -> Option<raw_window_handle_06::RawDisplayHandle> {
+        let handle = raw_window_handle_06::AppKitDisplayHandle::new();
+        Some(raw_window_handle_06::RawDisplayHandle::AppKit(handle))
+    }
+}
+
 impl Window {
     pub fn new(name: &str, width: usize, height: usize, opts: WindowOptions) -> Result<Window> {
+        Self::create(name, width, height, opts, ptr::null_mut())
+    }
+
+    pub fn new_child(
+        parent: *mut raw::c_void,
+        name: &str,
+        width: usize,
+        height: usize,
+        opts: WindowOptions,
+    ) -> Result<Window> {
+        Self::create(name, width, height, opts, parent)
+    }
+
+    fn create(
+        name: &str,
+        width: usize,
+        height: usize,
+        opts: WindowOptions,
+        parent_view: *mut raw::c_void,
+    ) -> Result<Window> {
+        // AppKit isn't thread safe, using it from another thread fails in unpredictable ways
+        if unsafe { mfb_is_main_thread() } == 0 {
+            panic!("minifb: on macOS a Window has to be created on the main thread");
+        }
+
         let n = match CString::new(name) {
             Err(_) => {
                 println!("Unable to convert {} to c_string", name);
@@ -289,6 +520,7 @@ impl Window {
                 height as u32,
                 window_flags::get_flags(opts),
                 scale_factor as i32,
+                i32::from(opts.buffer_count.clamp(2, 3)),
                 &mut view_handle,
             );
 
@@ -300,7 +532,24 @@ impl Window {
                 return Err(Error::WindowCreate("Unable to open Window".to_owned()));
             }
 
-            Ok(Window {
+            if !parent_view.is_null() {
+                mfb_attach_to_parent(handle, parent_view);
+            }
+
+            let gl_context = if opts.gl {
+                let context = mfb_gl_create(handle);
+                if context.is_null() {
+                    mfb_destroy(handle);
+                    return Err(Error::WindowCreate(
+                        "Unable to create GL context".to_owned(),
+                    ));
+                }
+                context
+            } else {
+                ptr::null_mut()
+            };
+
+            let mut window = Window {
                 window_handle: handle,
                 view_handle,
                 scale_factor,
@@ -312,24 +561,101 @@ impl Window {
                     ..SharedData::default()
                 },
                 key_handler: KeyHandler::new(),
+                touch_handler: TouchHandler::new(),
+                cursor_auto_hide: mouse_handler::CursorAutoHide::new(),
+                mouse_delta: mouse_handler::MouseDelta::default(),
+                scroll_accumulator: mouse_handler::ScrollAccumulator::default(),
+                mouse_history: mouse_handler::MouseHistory::new(),
+                present_callbacks: PresentCallbacks::default(),
+                button_modifiers: mouse_handler::ButtonModifiers::default(),
+                display_changes: DISPLAY_CHANGES.load(Ordering::Relaxed),
+                input_polled: false,
+                cursor_confined: false,
+                screen_id: mfb_get_screen_id(handle),
+                moved_to_monitor: false,
+                gamma_restore: Vec::new(),
+                scale_mode: opts.scale_mode,
+                pixel_format: opts.pixel_format,
+                converted_buffer: Vec::new(),
+                buffer_size: (width, height),
                 update_rate: UpdateRate::new(),
                 has_set_data: false,
                 menus: Vec::new(),
-            })
+                menu_source: None,
+                closed: false,
+                close_reason: None,
+                gl_context,
+                cursor: CursorStyle::Arrow,
+                animated_cursor: None,
+                title: name.to_owned(),
+            };
+
+            window.report_held_keys();
+
+            DISPLAY_CALLBACK.call_once(|| {
+                CGDisplayRegisterReconfigurationCallback(display_reconfigured, ptr::null_mut());
+            });
+            window.key_handler.set_display_count(window.display_count());
+
+            Ok(window)
+        }
+    }
+
+    // Recounts the displays when the reconfiguration callback ran since the last update
+    fn check_displays(&mut self) {
+        let changes = DISPLAY_CHANGES.load(Ordering::Relaxed);
+
+        if changes != self.display_changes {
+            self.display_changes = changes;
+            self.key_handler.set_display_count(self.display_count());
         }
     }
 
+    // Keys that are already down when the window opens count as held rather than pressed
+    fn report_held_keys(&mut self) {
+        self.key_handler.begin_held_keys();
+
+        for (code, &key) in KEY_MAPPINGS.iter().enumerate() {
+            if key != Key::Unknown && unsafe { mfb_is_key_down(code as u16) } {
+                self.key_handler.set_key_state(key, true);
+            }
+        }
+
+        self.key_handler.end_held_keys();
+    }
+
     #[inline]
     pub fn set_title(&mut self, title: &str) {
         unsafe {
             let t = CString::new(title).unwrap();
             mfb_set_title(self.window_handle, t.as_ptr());
         }
+        self.title = title.to_owned();
     }
 
     #[inline]
-    pub fn set_icon(&mut self, _icon: Icon) {
-        unimplemented!("Currently not implemented on MacOS!")
+    pub fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    #[inline]
+    pub fn set_document_edited(&mut self, edited: bool) {
+        unsafe { mfb_set_document_edited(self.window_handle, edited) }
+    }
+
+    pub fn set_represented_file(&mut self, path: &Path) {
+        if let Ok(path) = CString::new(path.as_os_str().as_bytes()) {
+            unsafe { mfb_set_represented_file(self.window_handle, path.as_ptr()) }
+        }
+    }
+
+    #[inline]
+    pub fn set_icon(&mut self, _icon: Icon) -> Result<()> {
+        // windows have no icons of their own, the Dock shows the application's
+        Err(Error::Unsupported {
+            operation: "set_icon",
+            platform: "macOS",
+        })
     }
 
     #[inline]
@@ -342,12 +668,45 @@ impl Window {
         self.update_rate.update();
     }
 
+    #[inline]
+    pub fn set_target_fps(&mut self, fps: u32) {
+        self.update_rate.set_target_fps(fps);
+    }
+
+    #[inline]
+    pub fn get_frame_time(&self) -> std::time::Duration {
+        self.update_rate.frame_time()
+    }
+
+    #[inline]
+    pub fn backend_name(&self) -> &'static str {
+        "cocoa"
+    }
+
     #[inline]
     pub fn get_window_handle(&self) -> *mut raw::c_void {
         self.window_handle as *mut raw::c_void
     }
 
     #[inline]
+    fn process_touch_events(&mut self) {
+        let data = &mut self.shared_data;
+        let count = (data.touch_event_count as usize).min(data.touch_events.len());
+
+        for event in data.touch_events[..count].iter() {
+            let phase = match event.phase {
+                0 => TouchPhase::Begin,
+                1 => TouchPhase::Move,
+                _ => TouchPhase::End,
+            };
+
+            self.touch_handler
+                .set_touch(event.id, event.x, event.y, phase);
+        }
+
+        data.touch_event_count = 0;
+    }
+
     unsafe fn set_mouse_data(&mut self) {
         mfb_set_mouse_data(self.window_handle, &mut self.shared_data);
     }
@@ -357,8 +716,61 @@ impl Window {
         self.shared_data.bg_color = color;
     }
 
+    pub fn set_border_fill(&mut self, _fill: BorderFill) {
+        // The borders are drawn by the shader, which only supports the background color
+    }
+
     #[inline]
     pub fn set_cursor_visibility(&mut self, visibility: bool) {
+        self.cursor_auto_hide.set_visible(visibility);
+        self.show_cursor(visibility);
+    }
+
+    #[inline]
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_auto_hide.is_visible()
+    }
+
+    pub fn set_cursor_auto_hide(&mut self, timeout: Option<std::time::Duration>) {
+        if let Some(visible) = self.cursor_auto_hide.set_timeout(timeout) {
+            self.show_cursor(visible);
+        }
+    }
+
+    pub fn set_cursor_confined(&mut self, confined: bool) {
+        // Applied on every update as the cursor can only be warped back into the window
+        self.cursor_confined = confined;
+    }
+
+    pub fn set_gameplay_cursor_mode(&mut self, enabled: bool) {
+        if enabled == self.mouse_delta.is_enabled() {
+            return;
+        }
+
+        if enabled {
+            self.mouse_delta
+                .save_cursor(self.is_cursor_visible(), self.cursor_confined);
+            self.set_cursor_visibility(false);
+            self.set_cursor_confined(true);
+        } else {
+            let (visible, confined) = self.mouse_delta.saved_cursor();
+            self.set_cursor_visibility(visible);
+            self.set_cursor_confined(confined);
+        }
+
+        self.mouse_delta.set_enabled(enabled);
+    }
+
+    #[inline]
+    pub fn get_mouse_delta(&self) -> (f32, f32) {
+        self.mouse_delta.get()
+    }
+
+    pub fn set_keyboard_grab(&mut self, grab: bool) {
+        unsafe { mfb_set_keyboard_grab(grab) }
+    }
+
+    fn show_cursor(&mut self, visibility: bool) {
         unsafe {
             mfb_set_cursor_visibility(self.window_handle, visibility);
         }
@@ -371,18 +783,36 @@ impl Window {
         buf_height: usize,
         buf_stride: usize,
     ) -> Result<()> {
-        self.key_handler.update();
+        if self.closed {
+            return Ok(());
+        }
+
+        self.begin_input();
+        // The view draws in sync with the display, the last frame is shown by the next update
+        self.present_callbacks.frame_shown();
+        // The update clears the scrolling, keep what was polled since the last one
+        let polled_scroll = (self.shared_data.scroll_x, self.shared_data.scroll_y);
 
         buffer_helper::check_buffer_size(buf_width, buf_height, buf_stride, buffer)?;
+        self.buffer_size = (buf_width, buf_height);
+
+        let buffer = match buffer_helper::to_argb(buffer, self.pixel_format) {
+            Cow::Borrowed(buffer) => buffer.as_ptr(),
+            Cow::Owned(converted) => {
+                self.converted_buffer = converted;
+                self.converted_buffer.as_ptr()
+            }
+        };
 
         unsafe {
             mfb_update_with_buffer(
                 self.window_handle,
-                buffer.as_ptr() as *const u8,
+                buffer as *const u8,
                 buf_width as u32,
                 buf_height as u32,
                 buf_stride as u32,
             );
+            self.present_callbacks.frame_presented();
             Self::set_mouse_data(self);
             mfb_set_key_callback(
                 self.window_handle,
@@ -392,11 +822,146 @@ impl Window {
             );
         }
 
+        self.shared_data.scroll_x += polled_scroll.0;
+        self.shared_data.scroll_y += polled_scroll.1;
+        self.input_polled = false;
+
+        self.key_handler.update_just_pressed();
+        self.key_handler
+            .set_keyboard_layout(self.get_keyboard_layout());
+        self.check_displays();
+        self.scroll_accumulator
+            .add(self.shared_data.scroll_x, self.shared_data.scroll_y);
+        self.update_button_modifiers();
+
+        if self.cursor_confined {
+            unsafe { mfb_confine_cursor(self.window_handle) };
+        }
+        self.update_mouse_delta();
+        // Mouse events aren't forwarded one by one, only the position at each update is known
+        self.mouse_history
+            .push(self.shared_data.mouse_x, self.shared_data.mouse_y, None);
+
+        if let Some(visible) = self
+            .cursor_auto_hide
+            .update(self.shared_data.mouse_x, self.shared_data.mouse_y)
+        {
+            self.show_cursor(visible);
+        }
+
+        self.process_touch_events();
+        self.update_monitor();
+        self.update_cursor_animation();
+
         Ok(())
     }
 
-    pub fn update(&mut self) {
+    pub fn set_present_callback(&mut self, callback: Box<dyn FnOnce()>) {
+        self.present_callbacks.add(callback);
+    }
+
+    pub fn cancel_present_callbacks(&mut self) {
+        self.present_callbacks.clear_pending();
+    }
+
+    pub fn set_damage(&mut self, _rects: &[Rect]) {
+        // the whole texture is always uploaded
+    }
+
+    pub fn gl_get_proc_address(&self, name: &str) -> *const raw::c_void {
+        match CString::new(name) {
+            Ok(name) if !self.gl_context.is_null() => unsafe {
+                mfb_gl_get_proc_address(name.as_ptr())
+            },
+            _ => ptr::null(),
+        }
+    }
+
+    pub fn gl_swap_buffers(&mut self) {
+        if !self.gl_context.is_null() {
+            unsafe { mfb_gl_swap(self.gl_context) };
+        }
+    }
+
+    pub fn update_with_buffer_vsync(
+        &mut self,
+        buffer: &[u32],
+        buf_width: usize,
+        buf_height: usize,
+        buf_stride: usize,
+    ) -> Result<()> {
+        // The view is redrawn on its own display link so there's nothing to wait for here, the rate
+        // limiter paces the updates
+        self.update_rate.update();
+        self.update_with_buffer_stride(buffer, buf_width, buf_height, buf_stride)
+    }
+
+    pub fn poll_input(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        self.begin_input();
+        self.input_polled = true;
+
+        unsafe {
+            Self::set_mouse_data(self);
+            mfb_set_key_callback(
+                self.window_handle,
+                mem::transmute(self),
+                key_callback,
+                char_callback,
+            );
+            mfb_poll_events(self.window_handle);
+        }
+
+        self.update_mouse_delta();
+        // Mouse events aren't forwarded one by one, only the position at each update is known
+        self.mouse_history
+            .push(self.shared_data.mouse_x, self.shared_data.mouse_y, None);
+    }
+
+    // Clears the input state of the previous update before processing new events, only once
+    // when `poll_input` is called in between updates
+    fn begin_input(&mut self) {
+        if self.input_polled {
+            return;
+        }
+
         self.key_handler.update();
+        self.touch_handler.update();
+        self.shared_data.scroll_x = 0.0;
+        self.shared_data.scroll_y = 0.0;
+        self.mouse_delta.reset();
+        self.mouse_history.reset();
+    }
+
+    // Measures the movement for the gameplay cursor mode and moves the cursor back to the center
+    fn update_mouse_delta(&mut self) {
+        let center = (
+            (self.shared_data.width / 2) as f32,
+            (self.shared_data.height / 2) as f32,
+        );
+        let active = unsafe { mfb_is_active(self.window_handle) != 0 };
+        let pos = (self.shared_data.mouse_x, self.shared_data.mouse_y);
+
+        if self.mouse_delta.update(active, pos, center) {
+            unsafe { mfb_set_cursor_position(self.window_handle, center.0, center.1) };
+            self.shared_data.mouse_x = center.0;
+            self.shared_data.mouse_y = center.1;
+        }
+    }
+
+    pub fn update(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        self.begin_input();
+        // The view draws in sync with the display, the last frame is shown by the next update
+        self.present_callbacks.frame_shown();
+        // The update clears the scrolling, keep what was polled since the last one
+        let polled_scroll = (self.shared_data.scroll_x, self.shared_data.scroll_y);
 
         unsafe {
             mfb_update(self.window_handle);
@@ -408,6 +973,186 @@ impl Window {
                 char_callback,
             );
         }
+
+        self.shared_data.scroll_x += polled_scroll.0;
+        self.shared_data.scroll_y += polled_scroll.1;
+        self.input_polled = false;
+
+        self.key_handler.update_just_pressed();
+        self.key_handler
+            .set_keyboard_layout(self.get_keyboard_layout());
+        self.check_displays();
+        self.scroll_accumulator
+            .add(self.shared_data.scroll_x, self.shared_data.scroll_y);
+        self.update_button_modifiers();
+
+        if self.cursor_confined {
+            unsafe { mfb_confine_cursor(self.window_handle) };
+        }
+        self.update_mouse_delta();
+        // Mouse events aren't forwarded one by one, only the position at each update is known
+        self.mouse_history
+            .push(self.shared_data.mouse_x, self.shared_data.mouse_y, None);
+
+        if let Some(visible) = self
+            .cursor_auto_hide
+            .update(self.shared_data.mouse_x, self.shared_data.mouse_y)
+        {
+            self.show_cursor(visible);
+        }
+
+        self.process_touch_events();
+        self.update_monitor();
+        self.update_cursor_animation();
+    }
+
+    fn update_monitor(&mut self) {
+        let screen_id = unsafe { mfb_get_screen_id(self.window_handle) };
+        self.moved_to_monitor = screen_id != self.screen_id;
+        self.screen_id = screen_id;
+    }
+
+    pub fn get_buffer_scale(&self) -> (f32, f32) {
+        buffer_helper::buffer_scale(
+            self.scale_mode,
+            self.shared_data.width as usize,
+            self.shared_data.height as usize,
+            self.buffer_size.0,
+            self.buffer_size.1,
+        )
+    }
+
+    pub fn get_content_scale(&self) -> f32 {
+        unsafe { mfb_get_content_scale(self.window_handle) }
+    }
+
+    #[inline]
+    pub fn get_native_pixel_format(&self) -> Option<PixelFormat> {
+        // The texture is BGRA8, which is 0xAARRGGBB on little-endian machines
+        Some(PixelFormat::Argb8888)
+    }
+
+    pub fn is_transparency_active(&self) -> bool {
+        // Transparent windows aren't supported on macOS yet
+        false
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        unsafe { mfb_is_zoomed(self.window_handle) != 0 }
+    }
+
+    pub fn set_maximized(&mut self, maximized: bool) {
+        unsafe { mfb_set_zoomed(self.window_handle, maximized) }
+    }
+
+    pub fn display_count(&self) -> usize {
+        unsafe { mfb_get_screen_count() as usize }
+    }
+
+    pub fn get_monitors(&self) -> Vec<Monitor> {
+        let count = unsafe { mfb_get_screen_count() };
+
+        (0..count)
+            .filter_map(|index| {
+                read_monitor(|name, name_len, x, y, width, height, refresh_rate| unsafe {
+                    mfb_get_screen_monitor(index, name, name_len, x, y, width, height, refresh_rate)
+                })
+            })
+            .collect()
+    }
+
+    pub fn get_keyboard_layout(&self) -> Option<String> {
+        let mut name = [0 as c_char; 256];
+
+        unsafe {
+            if mfb_get_keyboard_layout(name.as_mut_ptr(), name.len() as i32) == 0 {
+                return None;
+            }
+
+            Some(CStr::from_ptr(name.as_ptr()).to_string_lossy().into_owned())
+        }
+    }
+
+    pub fn current_monitor(&self) -> Option<Monitor> {
+        read_monitor(|name, name_len, x, y, width, height, refresh_rate| unsafe {
+            mfb_get_monitor(
+                self.window_handle,
+                name,
+                name_len,
+                x,
+                y,
+                width,
+                height,
+                refresh_rate,
+            )
+        })
+    }
+
+    pub fn set_gamma_ramp(&mut self, ramp: &GammaRamp) -> Result<()> {
+        let display = unsafe { mfb_get_screen_id(self.window_handle) };
+        if display == 0 {
+            return Err(Error::GammaRamp("The window isn't on a screen".to_owned()));
+        }
+
+        // Keep the table the display had before the first change, it's restored on drop
+        if !self.gamma_restore.iter().any(|&(d, _)| d == display) {
+            if let Some(original) = GammaTable::get(display) {
+                self.gamma_restore.push((display, original));
+            }
+        }
+
+        let to_table = |channel: &[u16; 256]| {
+            let mut table = [0.0; 256];
+            for (value, &level) in table.iter_mut().zip(channel.iter()) {
+                *value = level as f32 / 65535.0;
+            }
+            table
+        };
+
+        let table = GammaTable {
+            red: to_table(&ramp.red),
+            green: to_table(&ramp.green),
+            blue: to_table(&ramp.blue),
+            size: 256,
+        };
+
+        if table.set(display) {
+            Ok(())
+        } else {
+            Err(Error::GammaRamp(
+                "CGSetDisplayTransferByTable failed".to_owned(),
+            ))
+        }
+    }
+
+    pub fn get_gamma_ramp(&self) -> Option<GammaRamp> {
+        let display = unsafe { mfb_get_screen_id(self.window_handle) };
+        let table = GammaTable::get(display)?;
+
+        if table.size < 2 {
+            return None;
+        }
+
+        // The table can have fewer entries than the ramp, the levels are resampled
+        let to_ramp = |channel: &[f32; 256]| {
+            let mut ramp = [0u16; 256];
+            for (level, value) in ramp.iter_mut().enumerate() {
+                let i = level * (table.size as usize - 1) / 255;
+                *value = (channel[i].clamp(0.0, 1.0) * 65535.0) as u16;
+            }
+            ramp
+        };
+
+        Some(GammaRamp {
+            red: to_ramp(&table.red),
+            green: to_ramp(&table.green),
+            blue: to_ramp(&table.blue),
+        })
+    }
+
+    #[inline]
+    pub fn moved_to_monitor(&self) -> bool {
+        self.moved_to_monitor
     }
 
     #[inline]
@@ -424,11 +1169,58 @@ impl Window {
         (x as isize, y as isize)
     }
 
+    pub fn get_outer_position(&self) -> (isize, isize) {
+        // The position is already taken from the frame
+        self.get_position()
+    }
+
+    pub fn get_outer_size(&self) -> (usize, usize) {
+        let (mut width, mut height) = (0, 0);
+        unsafe {
+            mfb_get_outer_size(self.window_handle, &mut width, &mut height);
+        }
+        (width as usize, height as usize)
+    }
+
     #[inline]
     pub fn topmost(&self, topmost: bool) {
         unsafe { mfb_topmost(self.window_handle, topmost) }
     }
 
+    pub fn set_visible(&mut self, visible: bool) {
+        if !self.closed {
+            unsafe { mfb_set_visible(self.window_handle, visible) }
+        }
+    }
+
+    pub fn set_always_on_bottom(&mut self, enabled: bool) {
+        self.set_window_level(if enabled {
+            WindowLevel::Bottom
+        } else {
+            WindowLevel::Normal
+        });
+    }
+
+    pub fn set_window_level(&mut self, level: WindowLevel) {
+        unsafe { mfb_set_window_level(self.window_handle, level as u32) }
+    }
+
+    pub fn set_skip_taskbar(&mut self, skip: bool) {
+        unsafe { mfb_set_skip_taskbar(self.window_handle, skip) }
+    }
+
+    pub fn set_caption_buttons(&mut self, minimize: bool, maximize: bool, close: bool) {
+        unsafe { mfb_set_caption_buttons(self.window_handle, minimize, maximize, close) }
+    }
+
+    pub fn set_shadow(&mut self, enabled: bool) {
+        unsafe { mfb_set_shadow(self.window_handle, enabled) }
+    }
+
+    pub fn beep(&self) {
+        unsafe { mfb_beep() }
+    }
+
     pub fn get_size(&self) -> (usize, usize) {
         (
             self.shared_data.width as usize,
@@ -436,6 +1228,19 @@ impl Window {
         )
     }
 
+    pub fn get_unscaled_size(&self) -> (usize, usize) {
+        self.get_size()
+    }
+
+    pub fn set_size(&mut self, width: usize, height: usize) {
+        unsafe { mfb_set_size(self.window_handle, width as i32, height as i32) }
+    }
+
+    pub fn set_aspect_ratio(&mut self, ratio: Option<(u32, u32)>) {
+        let (numerator, denominator) = ratio.unwrap_or((0, 0));
+        unsafe { mfb_set_aspect_ratio(self.window_handle, numerator, denominator) }
+    }
+
     pub fn get_scroll_wheel(&self) -> Option<(f32, f32)> {
         let sx = self.shared_data.scroll_x;
         let sy = self.shared_data.scroll_y;
@@ -447,6 +1252,16 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn get_scroll_accumulated(&self) -> (f32, f32) {
+        self.scroll_accumulator.get()
+    }
+
+    #[inline]
+    pub fn reset_scroll_accumulator(&mut self) {
+        self.scroll_accumulator.reset()
+    }
+
     pub fn get_mouse_down(&self, button: MouseButton) -> bool {
         match button {
             MouseButton::Left => self.shared_data.state[0] > 0,
@@ -455,6 +1270,30 @@ impl Window {
         }
     }
 
+    // The button state is only read after the events have been processed on MacOS, so the
+    // modifiers are those held at the end of the update in which the button went down
+    fn update_button_modifiers(&mut self) {
+        let modifiers = self.key_handler.modifiers();
+        for &button in &[MouseButton::Left, MouseButton::Middle, MouseButton::Right] {
+            let is_down = self.get_mouse_down(button);
+            self.button_modifiers
+                .set_button_state(button, is_down, modifiers);
+        }
+    }
+
+    pub fn get_mouse_down_with_modifiers(&self, button: MouseButton) -> Option<Modifiers> {
+        if !self.get_mouse_down(button) {
+            return None;
+        }
+
+        // Buttons that were already down when the window got focus have nothing captured
+        Some(
+            self.button_modifiers
+                .get(button)
+                .unwrap_or_else(|| self.key_handler.modifiers()),
+        )
+    }
+
     pub fn get_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
         let s = self.scale_factor as f32;
         let w = self.shared_data.width as f32;
@@ -470,6 +1309,33 @@ impl Window {
         )
     }
 
+    pub fn get_mouse_history(&self) -> Vec<(f32, f32, std::time::Duration)> {
+        self.mouse_history.get(self.scale_factor as f32)
+    }
+
+    pub fn get_pen(&self) -> Option<PenState> {
+        let data = &self.shared_data;
+        let s = self.scale_factor as f32;
+
+        if data.pen_state == 0 {
+            return None;
+        }
+
+        Some(PenState {
+            x: data.pen_x / s,
+            y: data.pen_y / s,
+            pressure: data.pen_pressure,
+            tilt_x: data.pen_tilt_x,
+            tilt_y: data.pen_tilt_y,
+            in_range: data.pen_state == 2,
+        })
+    }
+
+    pub fn get_touch_points(&self) -> Vec<TouchPoint> {
+        self.touch_handler
+            .get_touch_points(self.scale_factor as f32)
+    }
+
     pub fn get_unscaled_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
         let s = 1.0;
         let w = self.shared_data.width as f32;
@@ -485,11 +1351,80 @@ impl Window {
         )
     }
 
+    pub fn get_mouse_pos_on_screen(&self) -> Option<(f32, f32)> {
+        let mut x = 0.0;
+        let mut y = 0.0;
+
+        unsafe {
+            mfb_get_mouse_screen_pos(&mut x, &mut y);
+        }
+
+        Some((x, y))
+    }
+
     #[inline]
     pub fn set_cursor_style(&mut self, cursor: CursorStyle) {
         unsafe {
+            self.free_animated_cursor();
             mfb_set_cursor_style(self.window_handle, cursor as u32);
         }
+        self.cursor = cursor;
+    }
+
+    #[inline]
+    pub fn get_cursor_style(&self) -> CursorStyle {
+        self.cursor
+    }
+
+    pub fn get_cursor_hotspot(&self) -> (i32, i32) {
+        let cursor = match self.animated_cursor {
+            Some(ref animated) => *animated.current(),
+            None => ptr::null_mut(),
+        };
+
+        let mut x = 0;
+        let mut y = 0;
+        unsafe { mfb_get_cursor_hotspot(cursor, self.cursor as i32, &mut x, &mut y) };
+        (x, y)
+    }
+
+    pub fn set_animated_cursor(
+        &mut self,
+        frames: &[(&[u32], u32, u32)],
+        frame_duration: std::time::Duration,
+        hot_x: u32,
+        hot_y: u32,
+    ) {
+        unsafe {
+            let cursors = frames
+                .iter()
+                .map(|&(buffer, width, height)| {
+                    mfb_create_cursor(buffer.as_ptr(), width, height, hot_x, hot_y)
+                })
+                .collect();
+
+            self.free_animated_cursor();
+            let animated = mouse_handler::AnimatedCursor::new(cursors, frame_duration);
+            mfb_set_cursor(self.window_handle, *animated.current());
+            self.animated_cursor = Some(animated);
+        }
+    }
+
+    // Called once per update to show the next frame of the animated cursor
+    fn update_cursor_animation(&mut self) {
+        if let Some(animated) = self.animated_cursor.as_mut() {
+            if let Some(&cursor) = animated.update() {
+                unsafe { mfb_set_cursor(self.window_handle, cursor) };
+            }
+        }
+    }
+
+    unsafe fn free_animated_cursor(&mut self) {
+        if let Some(animated) = self.animated_cursor.take() {
+            for &cursor in animated.frames() {
+                mfb_destroy_cursor(cursor);
+            }
+        }
     }
 
     #[inline]
@@ -497,6 +1432,11 @@ impl Window {
         self.key_handler.get_keys()
     }
 
+    #[inline]
+    pub fn keys_down(&self) -> &[Key] {
+        self.key_handler.keys_down()
+    }
+
     #[inline]
     pub fn get_keys_pressed(&self, repeat: KeyRepeat) -> Vec<Key> {
         self.key_handler.get_keys_pressed(repeat)
@@ -507,11 +1447,31 @@ impl Window {
         self.key_handler.get_keys_released()
     }
 
+    #[inline]
+    pub fn get_keys_just_pressed(&self) -> Vec<Key> {
+        self.key_handler.get_keys_just_pressed()
+    }
+
+    #[inline]
+    pub fn get_text_input(&self) -> String {
+        self.key_handler.get_text_input()
+    }
+
     #[inline]
     pub fn is_key_down(&self, key: Key) -> bool {
         self.key_handler.is_key_down(key)
     }
 
+    #[inline]
+    pub fn get_input_timestamp(&self) -> Option<std::time::Duration> {
+        self.key_handler.get_event_time()
+    }
+
+    #[inline]
+    pub fn get_key_timestamp(&self, key: Key) -> Option<std::time::Duration> {
+        self.key_handler.get_key_time(key)
+    }
+
     #[inline]
     pub fn set_key_repeat_delay(&mut self, delay: f32) {
         self.key_handler.set_key_repeat_delay(delay)
@@ -522,11 +1482,43 @@ impl Window {
         self.key_handler.set_key_repeat_rate(rate)
     }
 
+    #[inline]
+    pub fn set_key_map(&mut self, key_map: KeyMap) {
+        self.key_handler.set_key_map(key_map)
+    }
+
+    #[inline]
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.key_handler.is_action_down(action)
+    }
+
+    #[inline]
+    pub fn set_key_repeat_for(&mut self, keys: &[Key], delay: f32, rate: f32) {
+        self.key_handler.set_key_repeat_for(keys, delay, rate)
+    }
+
+    pub fn use_os_key_repeat(&mut self) {
+        let mut delay = 0.0;
+        let mut rate = 0.0;
+
+        unsafe { mfb_get_key_repeat(&mut delay, &mut rate) };
+
+        if delay > 0.0 && rate > 0.0 {
+            self.key_handler.set_key_repeat_delay(delay);
+            self.key_handler.set_key_repeat_rate(rate);
+        }
+    }
+
     #[inline]
     pub fn is_key_pressed(&self, key: Key, repeat: KeyRepeat) -> bool {
         self.key_handler.is_key_pressed(key, repeat)
     }
 
+    #[inline]
+    pub fn is_key_repeat(&self, key: Key) -> bool {
+        self.key_handler.is_key_repeat(key)
+    }
+
     #[inline]
     pub fn is_key_released(&self, key: Key) -> bool {
         self.key_handler.is_key_released(key)
@@ -538,15 +1530,27 @@ impl Window {
     }
 
     pub fn is_menu_pressed(&mut self) -> Option<usize> {
-        let menu_id = unsafe { mfb_active_menu(self.window_handle) };
+        let mut from_shortcut = false;
+        let menu_id = unsafe { mfb_active_menu(self.window_handle, &mut from_shortcut) };
 
         if menu_id < 0 {
+            self.menu_source = None;
             None
         } else {
+            self.menu_source = Some(if from_shortcut {
+                MenuSource::Shortcut
+            } else {
+                MenuSource::Click
+            });
             Some(menu_id as usize)
         }
     }
 
+    #[inline]
+    pub fn menu_activation_source(&self) -> Option<MenuSource> {
+        self.menu_source
+    }
+
     pub fn add_menu(&mut self, menu: &Menu) -> MenuHandle {
         unsafe {
             let handle = MenuHandle(mfb_add_menu(self.window_handle, menu.menu_handle));
@@ -568,9 +1572,82 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn get_menu_height(&self) -> usize {
+        // The menu bar is shown at the top of the screen and not inside the window
+        0
+    }
+
+    pub fn update_menu(&mut self, handle: MenuHandle, menu: &Menu) {
+        if let Some(i) = self.menus.iter().position(|h| *h == handle) {
+            unsafe {
+                // + 1 here as we always have a default menu
+                mfb_replace_menu_at(self.window_handle, (i + 1) as i32, menu.menu_handle);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn set_app_menu(&mut self, menu: &Menu) {
+        unsafe { mfb_set_app_menu(menu.menu_handle) }
+    }
+
+    pub fn show_context_menu(&mut self, menu: &Menu, x: i32, y: i32) {
+        if self.closed {
+            return;
+        }
+
+        let scale = self.scale_factor as i32;
+        unsafe {
+            mfb_show_context_menu(self.window_handle, menu.menu_handle, x * scale, y * scale);
+        }
+    }
+
     #[inline]
     pub fn is_open(&self) -> bool {
-        unsafe { mfb_should_close(self.window_handle) == 0 }
+        !self.closed && unsafe { mfb_should_close(self.window_handle) == 0 }
+    }
+
+    #[inline]
+    pub fn is_occluded(&self) -> bool {
+        self.closed || unsafe { mfb_is_occluded(self.window_handle) != 0 }
+    }
+
+    pub fn close(&mut self) {
+        if !self.closed {
+            // Remember if the user closed the window before it's released
+            self.close_reason = self.close_reason();
+            unsafe { mfb_close(self.window_handle) };
+            self.closed = true;
+        }
+    }
+
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        if self.closed {
+            return Some(self.close_reason.unwrap_or(CloseReason::ProgrammaticClose));
+        }
+
+        // The user closing the window is only known through the native window
+        if unsafe { mfb_should_close(self.window_handle) } != 0 {
+            Some(CloseReason::UserRequested)
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    pub fn set_close_requested_handling(&mut self, auto: bool) {
+        unsafe { mfb_set_close_requested_handling(self.window_handle, auto) }
+    }
+
+    #[inline]
+    pub fn set_default_accelerators(&mut self, _enabled: bool) {
+        // The standard menu shortcuts such as Cmd+Q are kept, as macOS apps are expected to have them
+    }
+
+    #[inline]
+    pub fn close_requested(&self) -> bool {
+        unsafe { mfb_close_requested(self.window_handle) != 0 }
     }
 
     #[inline]
@@ -578,6 +1655,15 @@ impl Window {
         unsafe { mfb_is_active(self.window_handle) == 0 }
     }
 
+    #[inline]
+    pub fn get_primary_selection(&self) -> Option<String> {
+        // There is no primary selection on macOS
+        None
+    }
+
+    #[inline]
+    pub fn set_primary_selection(&mut self, _text: &str) {}
+
     unsafe fn get_scale_factor(width: usize, height: usize, scale: Scale) -> i32 {
         let factor: i32 = match scale {
             Scale::X1 => 1,
@@ -749,14 +1835,26 @@ impl Menu {
             let item_name = CString::new(item.label.as_str()).unwrap();
             let conv_key = Self::map_key_to_menu_key(item.key);
 
-            MenuItemHandle(mfb_add_menu_item(
+            let handle = mfb_add_menu_item(
                 self.menu_handle,
                 item.id as i32,
                 item_name.as_ptr(),
                 item.enabled,
                 conv_key,
                 item.modifier as u32,
-            ))
+            );
+
+            // Separators have no handle
+            if let Some(icon) = item.icon.as_ref().filter(|_| handle != 0) {
+                mfb_set_menu_item_icon(
+                    handle,
+                    icon.data.as_ptr(),
+                    icon.width as u32,
+                    icon.height as u32,
+                );
+            }
+
+            MenuItemHandle(handle)
         }
     }
 
@@ -765,12 +1863,178 @@ impl Menu {
             mfb_remove_menu_item(self.menu_handle, handle.0);
         }
     }
+
+    pub fn clear(&mut self) {
+        unsafe { mfb_clear_menu(self.menu_handle) }
+    }
 }
 
 impl Drop for Window {
     fn drop(&mut self) {
+        self.close();
+        unsafe {
+            if !self.gl_context.is_null() {
+                mfb_gl_destroy(self.gl_context);
+            }
+            self.free_animated_cursor();
+            mfb_destroy(self.window_handle);
+        }
+
+        for (display, original) in self.gamma_restore.drain(..) {
+            original.set(display);
+        }
+    }
+}
+
+/// The transfer table of a display, as used by CoreGraphics.
+struct GammaTable {
+    red: [f32; 256],
+    green: [f32; 256],
+    blue: [f32; 256],
+    size: u32,
+}
+
+impl GammaTable {
+    fn get(display: u32) -> Option<GammaTable> {
+        let mut table = GammaTable {
+            red: [0.0; 256],
+            green: [0.0; 256],
+            blue: [0.0; 256],
+            size: 0,
+        };
+
+        let result = unsafe {
+            CGGetDisplayTransferByTable(
+                display,
+                256,
+                table.red.as_mut_ptr(),
+                table.green.as_mut_ptr(),
+                table.blue.as_mut_ptr(),
+                &mut table.size,
+            )
+        };
+
+        if result == 0 {
+            Some(table)
+        } else {
+            None
+        }
+    }
+
+    fn set(&self, display: u32) -> bool {
+        unsafe {
+            CGSetDisplayTransferByTable(
+                display,
+                self.size,
+                self.red.as_ptr(),
+                self.green.as_ptr(),
+                self.blue.as_ptr(),
+            ) == 0
+        }
+    }
+}
+
+pub struct TrayIcon {
+    tray_handle: *mut c_void,
+}
+
+impl TrayIcon {
+    pub fn new(icon: Icon, tooltip: &str) -> Result<TrayIcon> {
+        // a path has nothing to load here, the tooltip is shown instead
+        let (icon_ptr, icon_len) = match icon {
+            Icon::Buffer(ptr, len) => (ptr, len),
+            Icon::Path(_) => (ptr::null(), 0),
+        };
+
+        let tooltip = CString::new(tooltip).unwrap_or_default();
+
         unsafe {
-            mfb_close(self.window_handle);
+            let tray_handle = mfb_tray_create(icon_ptr, icon_len, tooltip.as_ptr());
+
+            if tray_handle.is_null() {
+                return Err(Error::WindowCreate(
+                    "Unable to create status item".to_owned(),
+                ));
+            }
+
+            Ok(TrayIcon { tray_handle })
+        }
+    }
+
+    pub fn set_tooltip(&mut self, tooltip: &str) {
+        if let Ok(tooltip) = CString::new(tooltip) {
+            unsafe { mfb_tray_set_tooltip(self.tray_handle, tooltip.as_ptr()) }
+        }
+    }
+
+    #[inline]
+    pub fn set_menu(&mut self, menu: &Menu) -> Result<()> {
+        unsafe { mfb_tray_set_menu(self.tray_handle, menu.menu_handle) };
+        Ok(())
+    }
+
+    #[inline]
+    pub fn update(&mut self) {
+        unsafe { mfb_tray_update() }
+    }
+
+    pub fn is_menu_pressed(&mut self) -> Option<usize> {
+        let menu_id = unsafe { mfb_tray_active_menu(self.tray_handle) };
+
+        if menu_id < 0 {
+            None
+        } else {
+            Some(menu_id as usize)
         }
     }
+
+    #[inline]
+    pub fn is_clicked(&mut self) -> bool {
+        unsafe { mfb_tray_clicked(self.tray_handle) }
+    }
+}
+
+impl Drop for TrayIcon {
+    fn drop(&mut self) {
+        unsafe { mfb_tray_destroy(self.tray_handle) }
+    }
+}
+
+/// Reads a monitor from one of the native functions that fill in its name, area and refresh rate.
+fn read_monitor<F>(fill: F) -> Option<Monitor>
+where
+    F: FnOnce(*mut c_char, i32, *mut i32, *mut i32, *mut i32, *mut i32, *mut f32) -> u32,
+{
+    let mut name = [0 as c_char; 256];
+    let (mut x, mut y, mut width, mut height) = (0, 0, 0, 0);
+    let mut refresh_rate = 0.0;
+
+    if fill(
+        name.as_mut_ptr(),
+        name.len() as i32,
+        &mut x,
+        &mut y,
+        &mut width,
+        &mut height,
+        &mut refresh_rate,
+    ) == 0
+    {
+        return None;
+    }
+
+    Some(Monitor {
+        name: unsafe { CStr::from_ptr(name.as_ptr()) }
+            .to_string_lossy()
+            .into_owned(),
+        x: x as isize,
+        y: y as isize,
+        width: width as usize,
+        height: height as usize,
+        // Built-in displays usually report 0
+        refresh_rate: if refresh_rate > 0.0 {
+            Some(refresh_rate)
+        } else {
+            None
+        },
+    })
 }
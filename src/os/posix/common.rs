@@ -27,6 +27,7 @@ impl Menu {
             enabled: true,
             key: Key::Unknown,
             modifier: 0,
+            icon: None,
         });
     }
 
@@ -46,6 +47,7 @@ impl Menu {
             enabled: item.enabled,
             key: item.key,
             modifier: item.modifier,
+            icon: item.icon.clone(),
         });
         item_handle
     }
@@ -53,4 +55,8 @@ impl Menu {
     pub fn remove_item(&mut self, handle: &MenuItemHandle) {
         self.internal.items.retain(|item| item.handle.0 != handle.0);
     }
+
+    pub fn clear(&mut self) {
+        self.internal.items.clear();
+    }
 }
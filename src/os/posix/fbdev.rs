@@ -0,0 +1,1473 @@
+//! Draws straight to the Linux framebuffer device, for kiosks and embedded boards that run
+//! without X11 or Wayland. Input is read from the evdev devices in `/dev/input`.
+
+use crate::buffer_helper;
+use crate::error::Error;
+use crate::icon::Icon;
+use crate::key_handler::KeyHandler;
+use crate::mouse_handler;
+use crate::present::PresentCallbacks;
+use crate::rate::UpdateRate;
+use crate::Rect;
+use crate::Result;
+use crate::WindowLevel;
+use crate::{BorderFill, CloseReason, CursorStyle, GammaRamp, MenuHandle, Monitor, UnixMenu};
+use crate::{
+    InputCallback, Key, KeyMap, KeyRepeat, Modifiers, MouseButton, MouseMode, PenState,
+    PixelFormat, ScaleMode, TouchPoint, WindowOptions,
+};
+
+use super::common::Menu;
+
+use std::env;
+use std::ffi::CString;
+use std::fs;
+use std::io;
+use std::mem;
+use std::os::raw::{self, c_char, c_int, c_ulong};
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use std::ptr;
+use std::time::Duration;
+
+extern "C" {
+    fn Image_upper_left(
+        target: *mut u32,
+        source: *const u32,
+        source_w: u32,
+        source_h: u32,
+        source_stride: u32,
+        dest_width: u32,
+        dest_height: u32,
+        bg_color: u32,
+    );
+
+    fn Image_center(
+        target: *mut u32,
+        source: *const u32,
+        source_w: u32,
+        source_h: u32,
+        source_stride: u32,
+        dest_width: u32,
+        dest_height: u32,
+        bg_color: u32,
+    );
+
+    fn Image_resize_linear_aspect_fill_c(
+        target: *mut u32,
+        source: *const u32,
+        source_w: u32,
+        source_h: u32,
+        source_stride: u32,
+        dest_width: u32,
+        dest_height: u32,
+        bg_color: u32,
+    );
+
+    fn Image_resize_linear_c(
+        target: *mut u32,
+        source: *const u32,
+        source_w: u32,
+        source_h: u32,
+        source_stride: u32,
+        dest_width: u32,
+        dest_height: u32,
+    );
+}
+
+// ioctls from linux/fb.h, linux/kd.h and linux/input.h
+const FBIOGET_VSCREENINFO: c_ulong = 0x4600;
+const FBIOGET_FSCREENINFO: c_ulong = 0x4602;
+const FBIO_WAITFORVSYNC: c_ulong = 0x4004_4620;
+const KDSETMODE: c_ulong = 0x4b3a;
+const KD_TEXT: c_int = 0;
+const KD_GRAPHICS: c_int = 1;
+const KDGKBMODE: c_ulong = 0x4b44;
+const KDSKBMODE: c_ulong = 0x4b45;
+const K_OFF: c_int = 0x04;
+const EVIOCGRAB: c_ulong = 0x4004_4590;
+// EVIOCGABS(ABS_X) and EVIOCGABS(ABS_Y)
+const EVIOCGABS_X: c_ulong = 0x8018_4540;
+const EVIOCGABS_Y: c_ulong = 0x8018_4541;
+
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const EV_ABS: u16 = 0x03;
+const SYN_REPORT: u16 = 0x00;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+const ABS_X: u16 = 0x00;
+const ABS_Y: u16 = 0x01;
+const BTN_LEFT: u16 = 0x110;
+const BTN_RIGHT: u16 = 0x111;
+const BTN_MIDDLE: u16 = 0x112;
+const BTN_TOUCH: u16 = 0x14a;
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct FbBitfield {
+    offset: u32,
+    length: u32,
+    msb_right: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct FbVarScreeninfo {
+    xres: u32,
+    yres: u32,
+    xres_virtual: u32,
+    yres_virtual: u32,
+    xoffset: u32,
+    yoffset: u32,
+    bits_per_pixel: u32,
+    grayscale: u32,
+    red: FbBitfield,
+    green: FbBitfield,
+    blue: FbBitfield,
+    transp: FbBitfield,
+    nonstd: u32,
+    activate: u32,
+    height: u32,
+    width: u32,
+    accel_flags: u32,
+    pixclock: u32,
+    left_margin: u32,
+    right_margin: u32,
+    upper_margin: u32,
+    lower_margin: u32,
+    hsync_len: u32,
+    vsync_len: u32,
+    sync: u32,
+    vmode: u32,
+    rotate: u32,
+    colorspace: u32,
+    reserved: [u32; 4],
+}
+
+#[repr(C)]
+struct FbFixScreeninfo {
+    id: [c_char; 16],
+    smem_start: c_ulong,
+    smem_len: u32,
+    type_: u32,
+    type_aux: u32,
+    visual: u32,
+    xpanstep: u16,
+    ypanstep: u16,
+    ywrapstep: u16,
+    line_length: u32,
+    mmio_start: c_ulong,
+    mmio_len: u32,
+    accel: u32,
+    capabilities: u16,
+    reserved: [u16; 2],
+}
+
+#[repr(C)]
+struct InputEvent {
+    time: libc::timeval,
+    type_: u16,
+    code: u16,
+    value: i32,
+}
+
+#[repr(C)]
+#[derive(Default)]
+struct InputAbsinfo {
+    value: i32,
+    minimum: i32,
+    maximum: i32,
+    fuzz: i32,
+    flat: i32,
+    resolution: i32,
+}
+
+struct InputDevice {
+    fd: c_int,
+    // Range of the absolute axes of touch screens and tablets
+    abs_x: Option<(i32, i32)>,
+    abs_y: Option<(i32, i32)>,
+}
+
+impl InputDevice {
+    unsafe fn open(path: &Path) -> Option<InputDevice> {
+        let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+        let fd = libc::open(
+            path.as_ptr(),
+            libc::O_RDONLY | libc::O_NONBLOCK | libc::O_CLOEXEC,
+        );
+
+        if fd < 0 {
+            return None;
+        }
+
+        // Without the grab the console gets the keys as well and passes them to the shell
+        libc::ioctl(fd, EVIOCGRAB as _, 1 as c_int);
+
+        let abs_range = |request: c_ulong| {
+            let mut info = InputAbsinfo::default();
+            if libc::ioctl(fd, request as _, &mut info) == 0 && info.maximum > info.minimum {
+                Some((info.minimum, info.maximum))
+            } else {
+                None
+            }
+        };
+
+        Some(InputDevice {
+            fd,
+            abs_x: abs_range(EVIOCGABS_X),
+            abs_y: abs_range(EVIOCGABS_Y),
+        })
+    }
+}
+
+impl Drop for InputDevice {
+    fn drop(&mut self) {
+        unsafe {
+            libc::ioctl(self.fd, EVIOCGRAB as _, 0 as c_int);
+            libc::close(self.fd);
+        }
+    }
+}
+
+pub struct Window {
+    fb_fd: c_int,
+    fb_mem: *mut u8,
+    fb_len: usize,
+    var_info: FbVarScreeninfo,
+    line_length: usize,
+    name: String,
+    // The console the application runs on, switched to graphics mode while the window is open
+    tty_fd: Option<c_int>,
+    // Keyboard mode of the console before it was turned off
+    tty_keyboard_mode: Option<c_int>,
+    devices: Vec<InputDevice>,
+
+    width: u32,
+    height: u32,
+    title: String,
+    draw_buffer: Vec<u32>,
+    buffer_size: (usize, usize),
+    scale_mode: ScaleMode,
+    bg_color: u32,
+    border_fill: Option<BorderFill>,
+    pixel_format: PixelFormat,
+
+    key_handler: KeyHandler,
+    update_rate: UpdateRate,
+    mouse_x: f32,
+    mouse_y: f32,
+    buttons: [bool; 3],
+    button_modifiers: mouse_handler::ButtonModifiers,
+    scroll_x: f32,
+    scroll_y: f32,
+    scroll_accumulator: mouse_handler::ScrollAccumulator,
+    mouse_delta: mouse_handler::MouseDelta,
+    mouse_history: mouse_handler::MouseHistory,
+    present_callbacks: PresentCallbacks,
+    cursor: CursorStyle,
+    cursor_visible: bool,
+    input_polled: bool,
+
+    is_open: bool,
+    close_reason: Option<CloseReason>,
+    primary_selection: Option<String>,
+    menu_counter: MenuHandle,
+    menus: Vec<UnixMenu>,
+    context_menu: Option<(UnixMenu, i32, i32)>,
+}
+
+impl Window {
+    pub fn new(name: &str, width: usize, height: usize, opts: WindowOptions) -> Result<Window> {
+        if opts.gl {
+            return Err(Error::Unsupported {
+                operation: "WindowOptions::gl",
+                platform: "framebuffer",
+            });
+        }
+
+        // FRAMEBUFFER is also used by other framebuffer applications to pick the device
+        let path = env::var_os("FRAMEBUFFER").unwrap_or_else(|| "/dev/fb0".into());
+        let open_error = |what: &str| {
+            Error::WindowCreate(format!(
+                "Unable to {} {}: {}",
+                what,
+                Path::new(&path).display(),
+                io::Error::last_os_error()
+            ))
+        };
+
+        unsafe {
+            let c_path = CString::new(path.as_bytes())
+                .map_err(|_| Error::WindowCreate("Invalid framebuffer path".to_owned()))?;
+            let fb_fd = libc::open(c_path.as_ptr(), libc::O_RDWR | libc::O_CLOEXEC);
+
+            if fb_fd < 0 {
+                return Err(open_error("open"));
+            }
+
+            let mut var_info = FbVarScreeninfo::default();
+            let mut fix_info: FbFixScreeninfo = mem::zeroed();
+
+            if libc::ioctl(fb_fd, FBIOGET_VSCREENINFO as _, &mut var_info) != 0
+                || libc::ioctl(fb_fd, FBIOGET_FSCREENINFO as _, &mut fix_info) != 0
+            {
+                let error = open_error("query");
+                libc::close(fb_fd);
+                return Err(error);
+            }
+
+            if ![16, 24, 32].contains(&var_info.bits_per_pixel) {
+                libc::close(fb_fd);
+                return Err(Error::WindowCreate(format!(
+                    "Unsupported framebuffer depth of {} bits",
+                    var_info.bits_per_pixel
+                )));
+            }
+
+            let fb_len = fix_info.smem_len as usize;
+            let fb_mem = libc::mmap(
+                ptr::null_mut(),
+                fb_len,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_SHARED,
+                fb_fd,
+                0,
+            );
+
+            if fb_mem == libc::MAP_FAILED {
+                let error = open_error("map");
+                libc::close(fb_fd);
+                return Err(error);
+            }
+
+            let id = &fix_info.id;
+            let name_len = id.iter().position(|&c| c == 0).unwrap_or(id.len());
+            let fb_name =
+                String::from_utf8_lossy(&*(&id[..name_len] as *const [c_char] as *const [u8]))
+                    .into_owned();
+
+            // Keep the console from drawing its text and cursor over the image
+            let tty_fd = libc::open(b"/dev/tty\0".as_ptr() as *const c_char, libc::O_RDWR);
+            let tty_fd = if tty_fd >= 0 && libc::ioctl(tty_fd, KDSETMODE as _, KD_GRAPHICS) == 0 {
+                Some(tty_fd)
+            } else {
+                if tty_fd >= 0 {
+                    libc::close(tty_fd);
+                }
+                None
+            };
+
+            // The console stops turning key presses into input while the window is open
+            let tty_keyboard_mode = tty_fd.and_then(|tty_fd| {
+                let mut mode: c_int = 0;
+                if libc::ioctl(tty_fd, KDGKBMODE as _, &mut mode) == 0
+                    && libc::ioctl(tty_fd, KDSKBMODE as _, K_OFF) == 0
+                {
+                    Some(mode)
+                } else {
+                    None
+                }
+            });
+
+            let devices = fs::read_dir("/dev/input")
+                .map(|entries| {
+                    entries
+                        .filter_map(|entry| entry.ok())
+                        .filter(|entry| entry.file_name().as_bytes().starts_with(b"event"))
+                        .filter_map(|entry| InputDevice::open(&entry.path()))
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            let screen_width = var_info.xres;
+            let screen_height = var_info.yres;
+
+            let mut window = Window {
+                fb_fd,
+                fb_mem: fb_mem as *mut u8,
+                fb_len,
+                var_info,
+                line_length: fix_info.line_length as usize,
+                name: fb_name,
+                tty_fd,
+                tty_keyboard_mode,
+                devices,
+
+                width: screen_width,
+                height: screen_height,
+                title: name.to_owned(),
+                draw_buffer: vec![0; (screen_width * screen_height) as usize],
+                buffer_size: (width, height),
+                scale_mode: opts.scale_mode,
+                bg_color: 0,
+                border_fill: None,
+                pixel_format: opts.pixel_format,
+
+                key_handler: KeyHandler::new(),
+                update_rate: UpdateRate::new(),
+                mouse_x: (screen_width / 2) as f32,
+                mouse_y: (screen_height / 2) as f32,
+                buttons: [false; 3],
+                button_modifiers: mouse_handler::ButtonModifiers::default(),
+                scroll_x: 0.0,
+                scroll_y: 0.0,
+                scroll_accumulator: mouse_handler::ScrollAccumulator::default(),
+                mouse_delta: mouse_handler::MouseDelta::default(),
+                mouse_history: mouse_handler::MouseHistory::new(),
+                present_callbacks: PresentCallbacks::default(),
+                cursor: CursorStyle::Arrow,
+                cursor_visible: true,
+                input_polled: false,
+
+                is_open: true,
+                close_reason: None,
+                primary_selection: None,
+                menu_counter: MenuHandle(0),
+                menus: Vec::new(),
+                context_menu: None,
+            };
+
+            window.present();
+
+            Ok(window)
+        }
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        // There's nowhere to show the title, it's only kept for the getter
+        self.title = title.to_owned();
+    }
+
+    pub fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    pub fn set_document_edited(&mut self, _edited: bool) {
+        // the framebuffer has no document state
+    }
+
+    pub fn set_represented_file(&mut self, _path: &Path) {
+        // the framebuffer has no proxy icons
+    }
+
+    pub fn set_icon(&mut self, _icon: Icon) -> Result<()> {
+        Err(Error::Unsupported {
+            operation: "set_icon",
+            platform: "framebuffer",
+        })
+    }
+
+    pub fn update_with_buffer_stride(
+        &mut self,
+        buffer: &[u32],
+        buf_width: usize,
+        buf_height: usize,
+        buf_stride: usize,
+    ) -> Result<()> {
+        if !self.is_open {
+            return Ok(());
+        }
+
+        buffer_helper::check_buffer_size(buf_width, buf_height, buf_stride, buffer)?;
+
+        let buffer = buffer_helper::to_argb(buffer, self.pixel_format);
+        self.buffer_size = (buf_width, buf_height);
+        unsafe { self.scale_buffer(&buffer, buf_width, buf_height, buf_stride) };
+        self.present();
+        self.present_callbacks.frame_presented();
+        self.present_callbacks.frame_shown();
+
+        self.update();
+
+        Ok(())
+    }
+
+    pub fn set_present_callback(&mut self, callback: Box<dyn FnOnce()>) {
+        self.present_callbacks.add(callback);
+    }
+
+    pub fn cancel_present_callbacks(&mut self) {
+        self.present_callbacks.clear_pending();
+    }
+
+    pub fn set_damage(&mut self, _rects: &[Rect]) {
+        // the whole screen is always redrawn
+    }
+
+    pub fn gl_get_proc_address(&self, _name: &str) -> *const raw::c_void {
+        ptr::null()
+    }
+
+    pub fn gl_swap_buffers(&mut self) {}
+
+    pub fn update_with_buffer_vsync(
+        &mut self,
+        buffer: &[u32],
+        buf_width: usize,
+        buf_height: usize,
+        buf_stride: usize,
+    ) -> Result<()> {
+        // Not every driver can wait for the vertical blank, the rate limiter is used then
+        let mut crtc: u32 = 0;
+        if unsafe { libc::ioctl(self.fb_fd, FBIO_WAITFORVSYNC as _, &mut crtc) } == 0 {
+            self.update_rate.update_unthrottled();
+        } else {
+            self.update_rate.update();
+        }
+
+        self.update_with_buffer_stride(buffer, buf_width, buf_height, buf_stride)
+    }
+
+    pub fn poll_input(&mut self) {
+        if !self.is_open {
+            return;
+        }
+
+        self.begin_input();
+        self.input_polled = true;
+        self.process_events();
+    }
+
+    pub fn update(&mut self) {
+        if !self.is_open {
+            return;
+        }
+
+        self.begin_input();
+        self.process_events();
+        self.input_polled = false;
+        self.key_handler.update_just_pressed();
+    }
+
+    // Clears the input state of the previous update before processing new events, only once
+    // when `poll_input` is called in between updates
+    fn begin_input(&mut self) {
+        if self.input_polled {
+            return;
+        }
+
+        self.key_handler.update();
+        self.scroll_x = 0.0;
+        self.scroll_y = 0.0;
+        self.mouse_delta.reset();
+        self.mouse_history.reset();
+    }
+
+    pub fn get_window_handle(&self) -> *mut raw::c_void {
+        ptr::null_mut()
+    }
+
+    pub fn set_background_color(&mut self, bg_color: u32) {
+        self.bg_color = bg_color;
+        self.border_fill = None;
+    }
+
+    pub fn set_border_fill(&mut self, fill: BorderFill) {
+        self.border_fill = Some(fill);
+    }
+
+    pub fn set_cursor_visibility(&mut self, visibility: bool) {
+        // No cursor is drawn, the visibility is only kept for the getter
+        self.cursor_visible = visibility;
+    }
+
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    pub fn set_cursor_confined(&mut self, _confined: bool) {
+        // the pointer never leaves the screen
+    }
+
+    pub fn set_gameplay_cursor_mode(&mut self, enabled: bool) {
+        // The relative motion of the mice is reported as is, the pointer doesn't need recentering
+        self.mouse_delta.set_enabled(enabled);
+    }
+
+    pub fn get_mouse_delta(&self) -> (f32, f32) {
+        self.mouse_delta.get()
+    }
+
+    pub fn set_keyboard_grab(&mut self, _grab: bool) {
+        // all keyboards are always read
+    }
+
+    pub fn set_cursor_auto_hide(&mut self, _timeout: Option<std::time::Duration>) {
+        // no cursor is drawn
+    }
+
+    pub fn set_position(&mut self, _x: isize, _y: isize) {
+        // the image always covers the whole screen
+    }
+
+    pub fn get_position(&self) -> (isize, isize) {
+        (0, 0)
+    }
+
+    pub fn get_outer_position(&self) -> (isize, isize) {
+        (0, 0)
+    }
+
+    pub fn get_outer_size(&self) -> (usize, usize) {
+        self.get_size()
+    }
+
+    pub fn set_visible(&mut self, _visible: bool) {
+        // the screen can't be hidden
+    }
+
+    pub fn set_always_on_bottom(&mut self, _enabled: bool) {
+        // there are no other windows
+    }
+
+    pub fn set_window_level(&mut self, _level: WindowLevel) {
+        // there are no other windows
+    }
+
+    pub fn set_skip_taskbar(&mut self, _skip: bool) {
+        // there is no taskbar
+    }
+
+    pub fn set_caption_buttons(&mut self, _minimize: bool, _maximize: bool, _close: bool) {
+        // there is no title bar
+    }
+
+    pub fn set_shadow(&mut self, _enabled: bool) {
+        // there are no window shadows
+    }
+
+    pub fn beep(&self) {
+        // there is no system sound
+    }
+
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.width as usize, self.height as usize)
+    }
+
+    pub fn get_unscaled_size(&self) -> (usize, usize) {
+        self.get_size()
+    }
+
+    pub fn get_buffer_scale(&self) -> (f32, f32) {
+        buffer_helper::buffer_scale(
+            self.scale_mode,
+            self.width as usize,
+            self.height as usize,
+            self.buffer_size.0,
+            self.buffer_size.1,
+        )
+    }
+
+    pub fn get_content_scale(&self) -> f32 {
+        // The console has no notion of scaling
+        1.0
+    }
+
+    pub fn get_native_pixel_format(&self) -> Option<PixelFormat> {
+        if self.is_native_xrgb() {
+            Some(PixelFormat::Xrgb8888)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_transparency_active(&self) -> bool {
+        // there's nothing behind the image
+        false
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        false
+    }
+
+    pub fn set_maximized(&mut self, _maximized: bool) {
+        // the image always covers the whole screen
+    }
+
+    pub fn get_monitors(&self) -> Vec<Monitor> {
+        vec![self.monitor()]
+    }
+
+    pub fn get_keyboard_layout(&self) -> Option<String> {
+        // Keys are mapped with the US layout
+        None
+    }
+
+    pub fn set_gamma_ramp(&mut self, _ramp: &GammaRamp) -> Result<()> {
+        Err(Error::Unsupported {
+            operation: "set_gamma_ramp",
+            platform: "framebuffer",
+        })
+    }
+
+    pub fn get_gamma_ramp(&self) -> Option<GammaRamp> {
+        None
+    }
+
+    pub fn display_count(&self) -> usize {
+        // Each framebuffer device drives a single display
+        1
+    }
+
+    pub fn current_monitor(&self) -> Option<Monitor> {
+        Some(self.monitor())
+    }
+
+    pub fn moved_to_monitor(&self) -> bool {
+        false
+    }
+
+    pub fn set_size(&mut self, _width: usize, _height: usize) {
+        // the size of the framebuffer is set by the console
+    }
+
+    pub fn set_aspect_ratio(&mut self, _ratio: Option<(u32, u32)>) {
+        // the size of the framebuffer is set by the console
+    }
+
+    pub fn get_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
+        let w = self.width as f32;
+        let h = self.height as f32;
+
+        mouse_handler::get_pos(mode, self.mouse_x, self.mouse_y, 1.0, w, h)
+    }
+
+    pub fn get_mouse_history(&self) -> Vec<(f32, f32, std::time::Duration)> {
+        self.mouse_history.get(1.0)
+    }
+
+    pub fn get_pen(&self) -> Option<PenState> {
+        // Pens are reported as a mouse
+        None
+    }
+
+    pub fn get_touch_points(&self) -> Vec<TouchPoint> {
+        // Touch screens are reported as a mouse
+        Vec::new()
+    }
+
+    pub fn get_unscaled_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
+        self.get_mouse_pos(mode)
+    }
+
+    pub fn get_mouse_pos_on_screen(&self) -> Option<(f32, f32)> {
+        Some((self.mouse_x, self.mouse_y))
+    }
+
+    pub fn get_mouse_down(&self, button: MouseButton) -> bool {
+        match button {
+            MouseButton::Left => self.buttons[0],
+            MouseButton::Middle => self.buttons[1],
+            MouseButton::Right => self.buttons[2],
+        }
+    }
+
+    pub fn get_mouse_down_with_modifiers(&self, button: MouseButton) -> Option<Modifiers> {
+        if !self.get_mouse_down(button) {
+            return None;
+        }
+
+        Some(
+            self.button_modifiers
+                .get(button)
+                .unwrap_or_else(|| self.key_handler.modifiers()),
+        )
+    }
+
+    pub fn get_scroll_wheel(&self) -> Option<(f32, f32)> {
+        if self.scroll_x.abs() > 0.0 || self.scroll_y.abs() > 0.0 {
+            Some((self.scroll_x, self.scroll_y))
+        } else {
+            None
+        }
+    }
+
+    pub fn get_scroll_accumulated(&self) -> (f32, f32) {
+        self.scroll_accumulator.get()
+    }
+
+    pub fn reset_scroll_accumulator(&mut self) {
+        self.scroll_accumulator.reset()
+    }
+
+    pub fn set_cursor_style(&mut self, cursor: CursorStyle) {
+        // No cursor is drawn, the style is only kept for the getter
+        self.cursor = cursor;
+    }
+
+    pub fn get_cursor_style(&self) -> CursorStyle {
+        self.cursor
+    }
+
+    pub fn get_cursor_hotspot(&self) -> (i32, i32) {
+        (0, 0)
+    }
+
+    pub fn set_animated_cursor(
+        &mut self,
+        _frames: &[(&[u32], u32, u32)],
+        _frame_duration: std::time::Duration,
+        _hot_x: u32,
+        _hot_y: u32,
+    ) {
+        // no cursor is drawn
+    }
+
+    #[inline]
+    pub fn set_rate(&mut self, rate: Option<std::time::Duration>) {
+        self.update_rate.set_rate(rate);
+    }
+
+    #[inline]
+    pub fn update_rate(&mut self) {
+        self.update_rate.update();
+    }
+
+    #[inline]
+    pub fn set_target_fps(&mut self, fps: u32) {
+        self.update_rate.set_target_fps(fps);
+    }
+
+    #[inline]
+    pub fn get_frame_time(&self) -> std::time::Duration {
+        self.update_rate.frame_time()
+    }
+
+    #[inline]
+    pub fn get_keys(&self) -> Vec<Key> {
+        self.key_handler.get_keys()
+    }
+
+    #[inline]
+    pub fn keys_down(&self) -> &[Key] {
+        self.key_handler.keys_down()
+    }
+
+    #[inline]
+    pub fn get_keys_pressed(&self, repeat: KeyRepeat) -> Vec<Key> {
+        self.key_handler.get_keys_pressed(repeat)
+    }
+
+    #[inline]
+    pub fn get_keys_just_pressed(&self) -> Vec<Key> {
+        self.key_handler.get_keys_just_pressed()
+    }
+
+    #[inline]
+    pub fn get_text_input(&self) -> String {
+        self.key_handler.get_text_input()
+    }
+
+    #[inline]
+    pub fn get_keys_released(&self) -> Vec<Key> {
+        self.key_handler.get_keys_released()
+    }
+
+    #[inline]
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.key_handler.is_key_down(key)
+    }
+
+    #[inline]
+    pub fn get_input_timestamp(&self) -> Option<std::time::Duration> {
+        self.key_handler.get_event_time()
+    }
+
+    #[inline]
+    pub fn get_key_timestamp(&self, key: Key) -> Option<std::time::Duration> {
+        self.key_handler.get_key_time(key)
+    }
+
+    #[inline]
+    pub fn set_key_repeat_delay(&mut self, delay: f32) {
+        self.key_handler.set_key_repeat_delay(delay)
+    }
+
+    #[inline]
+    pub fn set_key_repeat_rate(&mut self, rate: f32) {
+        self.key_handler.set_key_repeat_rate(rate)
+    }
+
+    #[inline]
+    pub fn set_key_map(&mut self, key_map: KeyMap) {
+        self.key_handler.set_key_map(key_map)
+    }
+
+    #[inline]
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.key_handler.is_action_down(action)
+    }
+
+    #[inline]
+    pub fn set_key_repeat_for(&mut self, keys: &[Key], delay: f32, rate: f32) {
+        self.key_handler.set_key_repeat_for(keys, delay, rate)
+    }
+
+    pub fn is_occluded(&self) -> bool {
+        false
+    }
+
+    pub fn close(&mut self) {
+        self.is_open = false;
+        self.close_reason
+            .get_or_insert(CloseReason::ProgrammaticClose);
+    }
+
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        self.close_reason
+    }
+
+    pub fn set_close_requested_handling(&mut self, _auto: bool) {
+        // there's no close button to handle
+    }
+
+    pub fn set_default_accelerators(&mut self, _enabled: bool) {
+        // minifb doesn't handle any keys itself on the framebuffer
+    }
+
+    pub fn close_requested(&self) -> bool {
+        false
+    }
+
+    pub fn use_os_key_repeat(&mut self) {
+        // The console's repeat settings aren't exposed to evdev readers, keep the current values
+    }
+
+    #[inline]
+    pub fn is_key_pressed(&self, key: Key, repeat: KeyRepeat) -> bool {
+        self.key_handler.is_key_pressed(key, repeat)
+    }
+
+    #[inline]
+    pub fn is_key_repeat(&self, key: Key) -> bool {
+        self.key_handler.is_key_repeat(key)
+    }
+
+    #[inline]
+    pub fn is_key_released(&self, key: Key) -> bool {
+        self.key_handler.is_key_released(key)
+    }
+
+    #[inline]
+    pub fn set_input_callback(&mut self, callback: Box<dyn InputCallback>) {
+        self.key_handler.set_input_callback(callback)
+    }
+
+    #[inline]
+    pub fn is_open(&self) -> bool {
+        self.is_open
+    }
+
+    pub fn is_active(&mut self) -> bool {
+        // The application owns the screen and every input device
+        true
+    }
+
+    pub fn get_primary_selection(&self) -> Option<String> {
+        // There are no other applications to share the selection with
+        self.primary_selection.clone()
+    }
+
+    pub fn set_primary_selection(&mut self, text: &str) {
+        self.primary_selection = Some(text.to_owned());
+    }
+
+    fn next_menu_handle(&mut self) -> MenuHandle {
+        let handle = self.menu_counter;
+        self.menu_counter.0 += 1;
+        handle
+    }
+
+    pub fn add_menu(&mut self, menu: &Menu) -> MenuHandle {
+        let handle = self.next_menu_handle();
+        let mut menu = menu.internal.clone();
+        menu.handle = handle;
+        self.menus.push(menu);
+        handle
+    }
+
+    pub fn get_posix_menus(&self) -> Option<&Vec<UnixMenu>> {
+        Some(&self.menus)
+    }
+
+    pub fn remove_menu(&mut self, handle: MenuHandle) {
+        self.menus.retain(|menu| menu.handle != handle);
+    }
+
+    pub fn update_menu(&mut self, handle: MenuHandle, menu: &Menu) {
+        if let Some(existing) = self.menus.iter_mut().find(|m| m.handle == handle) {
+            *existing = menu.internal.clone();
+            existing.handle = handle;
+        }
+    }
+
+    pub fn show_context_menu(&mut self, menu: &Menu, x: i32, y: i32) {
+        self.context_menu = Some((menu.internal.clone(), x, y));
+    }
+
+    pub fn take_posix_context_menu(&mut self) -> Option<(UnixMenu, i32, i32)> {
+        self.context_menu.take()
+    }
+
+    pub fn sync(&mut self) {
+        // writes to the framebuffer show up right away
+    }
+
+    pub fn is_menu_pressed(&mut self) -> Option<usize> {
+        None
+    }
+
+    fn monitor(&self) -> Monitor {
+        let info = &self.var_info;
+        let total_width = info.xres + info.left_margin + info.right_margin + info.hsync_len;
+        let total_height = info.yres + info.upper_margin + info.lower_margin + info.vsync_len;
+
+        // The pixel clock is the length of a pixel in picoseconds, not every driver sets it
+        let refresh_rate = if info.pixclock != 0 {
+            Some(1e12 / (info.pixclock as f32 * total_width as f32 * total_height as f32))
+        } else {
+            None
+        };
+
+        Monitor {
+            name: self.name.clone(),
+            x: 0,
+            y: 0,
+            width: self.width as usize,
+            height: self.height as usize,
+            refresh_rate,
+        }
+    }
+
+    // True if the framebuffer pixels are laid out as 0x00RRGGBB and can be copied as they are
+    fn is_native_xrgb(&self) -> bool {
+        let info = &self.var_info;
+        info.bits_per_pixel == 32
+            && (info.red.offset, info.red.length) == (16, 8)
+            && (info.green.offset, info.green.length) == (8, 8)
+            && (info.blue.offset, info.blue.length) == (0, 8)
+    }
+
+    unsafe fn scale_buffer(
+        &mut self,
+        buffer: &[u32],
+        buf_width: usize,
+        buf_height: usize,
+        buf_stride: usize,
+    ) {
+        match self.scale_mode {
+            ScaleMode::Stretch => {
+                Image_resize_linear_c(
+                    self.draw_buffer.as_mut_ptr(),
+                    buffer.as_ptr(),
+                    buf_width as u32,
+                    buf_height as u32,
+                    buf_stride as u32,
+                    self.width,
+                    self.height,
+                );
+            }
+
+            ScaleMode::AspectRatioStretch => {
+                Image_resize_linear_aspect_fill_c(
+                    self.draw_buffer.as_mut_ptr(),
+                    buffer.as_ptr(),
+                    buf_width as u32,
+                    buf_height as u32,
+                    buf_stride as u32,
+                    self.width,
+                    self.height,
+                    self.bg_color,
+                );
+            }
+
+            ScaleMode::Center => {
+                Image_center(
+                    self.draw_buffer.as_mut_ptr(),
+                    buffer.as_ptr(),
+                    buf_width as u32,
+                    buf_height as u32,
+                    buf_stride as u32,
+                    self.width,
+                    self.height,
+                    self.bg_color,
+                );
+            }
+
+            ScaleMode::UpperLeft => {
+                Image_upper_left(
+                    self.draw_buffer.as_mut_ptr(),
+                    buffer.as_ptr(),
+                    buf_width as u32,
+                    buf_height as u32,
+                    buf_stride as u32,
+                    self.width,
+                    self.height,
+                    self.bg_color,
+                );
+            }
+        }
+
+        if let Some(fill) = &self.border_fill {
+            let image = buffer_helper::image_rect(
+                self.scale_mode,
+                self.width as usize,
+                self.height as usize,
+                buf_width,
+                buf_height,
+            );
+            buffer_helper::fill_border(
+                &mut self.draw_buffer,
+                self.width as usize,
+                self.height as usize,
+                image,
+                fill,
+            );
+        }
+    }
+
+    /// Copies the draw buffer to the visible part of the framebuffer, converting the pixels to
+    /// its layout.
+    fn present(&mut self) {
+        let info = self.var_info;
+        let width = self.width as usize;
+        let bytes_per_pixel = info.bits_per_pixel as usize / 8;
+        let native = self.is_native_xrgb();
+
+        for (y, row) in self.draw_buffer.chunks_exact(width).enumerate() {
+            let offset = (y + info.yoffset as usize) * self.line_length
+                + info.xoffset as usize * bytes_per_pixel;
+
+            if offset + width * bytes_per_pixel > self.fb_len {
+                break;
+            }
+
+            unsafe {
+                let target = self.fb_mem.add(offset);
+
+                if native {
+                    ptr::copy_nonoverlapping(row.as_ptr() as *const u8, target, width * 4);
+                    continue;
+                }
+
+                for (x, &pixel) in row.iter().enumerate() {
+                    let value = convert_pixel(pixel, &info);
+                    let target = target.add(x * bytes_per_pixel);
+
+                    match bytes_per_pixel {
+                        4 => ptr::write_unaligned(target as *mut u32, value),
+                        2 => ptr::write_unaligned(target as *mut u16, value as u16),
+                        // 24-bit framebuffers are always stored little endian
+                        _ => ptr::copy_nonoverlapping(value.to_le_bytes().as_ptr(), target, 3),
+                    }
+                }
+            }
+        }
+    }
+
+    fn process_events(&mut self) {
+        const BATCH: usize = 64;
+        let mut events: [InputEvent; BATCH] = unsafe { mem::zeroed() };
+        let mut index = 0;
+
+        while index < self.devices.len() {
+            let fd = self.devices[index].fd;
+            let read = unsafe {
+                libc::read(
+                    fd,
+                    events.as_mut_ptr() as *mut raw::c_void,
+                    mem::size_of_val(&events),
+                )
+            };
+
+            if read < 0 {
+                // The device was unplugged, anything else just means there's nothing to read
+                if io::Error::last_os_error().raw_os_error() == Some(libc::ENODEV) {
+                    self.devices.remove(index);
+                } else {
+                    index += 1;
+                }
+                continue;
+            }
+
+            let count = read as usize / mem::size_of::<InputEvent>();
+            for event in &events[..count] {
+                self.process_event(index, event);
+            }
+
+            if count < BATCH {
+                index += 1;
+            }
+        }
+    }
+
+    fn process_event(&mut self, device: usize, event: &InputEvent) {
+        self.key_handler.set_event_time(Duration::new(
+            event.time.tv_sec as u64,
+            event.time.tv_usec as u32 * 1000,
+        ));
+
+        match (event.type_, event.code) {
+            (EV_SYN, SYN_REPORT) => {
+                self.mouse_history.push(
+                    self.mouse_x,
+                    self.mouse_y,
+                    self.key_handler.get_event_time(),
+                );
+            }
+
+            (EV_KEY, code) => {
+                // 2 is an auto repeat, the key handler does its own repeating
+                if event.value == 2 {
+                    return;
+                }
+
+                let is_down = event.value != 0;
+                let button = match code {
+                    BTN_LEFT | BTN_TOUCH => Some((0, MouseButton::Left)),
+                    BTN_MIDDLE => Some((1, MouseButton::Middle)),
+                    BTN_RIGHT => Some((2, MouseButton::Right)),
+                    _ => None,
+                };
+
+                if let Some((index, button)) = button {
+                    self.buttons[index] = is_down;
+                    self.button_modifiers.set_button_state(
+                        button,
+                        is_down,
+                        self.key_handler.modifiers(),
+                    );
+                    return;
+                }
+
+                self.key_handler
+                    .set_scancode_state(u32::from(code), is_down);
+
+                if let Some(key) = map_key(code) {
+                    self.key_handler.set_key_state(key, is_down);
+
+                    if is_down {
+                        if let Some(c) = key.to_char(self.key_handler.modifiers().shift) {
+                            self.key_handler.add_char(c as u32);
+                        }
+                    }
+                }
+            }
+
+            (EV_REL, REL_X) => self.move_mouse(event.value as f32, 0.0),
+            (EV_REL, REL_Y) => self.move_mouse(0.0, event.value as f32),
+
+            (EV_REL, REL_WHEEL) => {
+                self.scroll_y += event.value as f32;
+                self.scroll_accumulator.add(0.0, event.value as f32);
+            }
+
+            (EV_REL, REL_HWHEEL) => {
+                self.scroll_x -= event.value as f32;
+                self.scroll_accumulator.add(-event.value as f32, 0.0);
+            }
+
+            (EV_ABS, ABS_X) => {
+                if let Some(range) = self.devices[device].abs_x {
+                    self.mouse_x = abs_to_screen(event.value, range, self.width);
+                }
+            }
+
+            (EV_ABS, ABS_Y) => {
+                if let Some(range) = self.devices[device].abs_y {
+                    self.mouse_y = abs_to_screen(event.value, range, self.height);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    fn move_mouse(&mut self, dx: f32, dy: f32) {
+        if self.mouse_delta.is_enabled() {
+            self.mouse_delta.add(dx, dy);
+        }
+
+        self.mouse_x = (self.mouse_x + dx).clamp(0.0, (self.width - 1) as f32);
+        self.mouse_y = (self.mouse_y + dy).clamp(0.0, (self.height - 1) as f32);
+    }
+}
+
+impl Drop for Window {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(tty_fd) = self.tty_fd {
+                if let Some(mode) = self.tty_keyboard_mode {
+                    libc::ioctl(tty_fd, KDSKBMODE as _, mode);
+                }
+                libc::ioctl(tty_fd, KDSETMODE as _, KD_TEXT);
+                libc::close(tty_fd);
+            }
+
+            // Releases the grabs of the input devices
+            self.devices.clear();
+
+            libc::munmap(self.fb_mem as *mut raw::c_void, self.fb_len);
+            libc::close(self.fb_fd);
+        }
+    }
+}
+
+unsafe impl raw_window_handle::HasRawWindowHandle for Window {
+    fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+        // raw-window-handle 0.4 has no way to say there's no window, so hand out a Wayland handle
+        // without a surface that no consumer can mistake for a real one
+        raw_window_handle::RawWindowHandle::Wayland(raw_window_handle::WaylandHandle::empty())
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+impl Window {
+    pub fn raw_window_handle_06(&self) -> Option<raw_window_handle_06::RawWindowHandle> {
+        None
+    }
+
+    pub fn raw_display_handle_06(&self) -> Option<raw_window_handle_06::RawDisplayHandle> {
+        None
+    }
+}
+
+/// Converts a `0xAARRGGBB` pixel to the channel layout of the framebuffer.
+fn convert_pixel(pixel: u32, info: &FbVarScreeninfo) -> u32 {
+    let channel = |shift: u32, field: &FbBitfield| {
+        let value = (pixel >> shift) & 0xff;
+        let value = if field.length < 8 {
+            value >> (8 - field.length)
+        } else {
+            value << (field.length - 8)
+        };
+        value << field.offset
+    };
+
+    channel(16, &info.red) | channel(8, &info.green) | channel(0, &info.blue)
+}
+
+fn abs_to_screen(value: i32, (min, max): (i32, i32), size: u32) -> f32 {
+    let t = (value - min) as f32 / (max - min) as f32;
+    (t * (size - 1) as f32).clamp(0.0, (size - 1) as f32)
+}
+
+/// Maps evdev key codes, from linux/input-event-codes.h, to minifb keys.
+fn map_key(code: u16) -> Option<Key> {
+    let key = match code {
+        1 => Key::Escape,
+        2 => Key::Key1,
+        3 => Key::Key2,
+        4 => Key::Key3,
+        5 => Key::Key4,
+        6 => Key::Key5,
+        7 => Key::Key6,
+        8 => Key::Key7,
+        9 => Key::Key8,
+        10 => Key::Key9,
+        11 => Key::Key0,
+        12 => Key::Minus,
+        13 => Key::Equal,
+        14 => Key::Backspace,
+        15 => Key::Tab,
+        16 => Key::Q,
+        17 => Key::W,
+        18 => Key::E,
+        19 => Key::R,
+        20 => Key::T,
+        21 => Key::Y,
+        22 => Key::U,
+        23 => Key::I,
+        24 => Key::O,
+        25 => Key::P,
+        26 => Key::LeftBracket,
+        27 => Key::RightBracket,
+        28 => Key::Enter,
+        29 => Key::LeftCtrl,
+        30 => Key::A,
+        31 => Key::S,
+        32 => Key::D,
+        33 => Key::F,
+        34 => Key::G,
+        35 => Key::H,
+        36 => Key::J,
+        37 => Key::K,
+        38 => Key::L,
+        39 => Key::Semicolon,
+        40 => Key::Apostrophe,
+        41 => Key::Backquote,
+        42 => Key::LeftShift,
+        43 => Key::Backslash,
+        44 => Key::Z,
+        45 => Key::X,
+        46 => Key::C,
+        47 => Key::V,
+        48 => Key::B,
+        49 => Key::N,
+        50 => Key::M,
+        51 => Key::Comma,
+        52 => Key::Period,
+        53 => Key::Slash,
+        54 => Key::RightShift,
+        55 => Key::NumPadAsterisk,
+        56 => Key::LeftAlt,
+        57 => Key::Space,
+        58 => Key::CapsLock,
+        59 => Key::F1,
+        60 => Key::F2,
+        61 => Key::F3,
+        62 => Key::F4,
+        63 => Key::F5,
+        64 => Key::F6,
+        65 => Key::F7,
+        66 => Key::F8,
+        67 => Key::F9,
+        68 => Key::F10,
+        69 => Key::NumLock,
+        70 => Key::ScrollLock,
+        71 => Key::NumPad7,
+        72 => Key::NumPad8,
+        73 => Key::NumPad9,
+        74 => Key::NumPadMinus,
+        75 => Key::NumPad4,
+        76 => Key::NumPad5,
+        77 => Key::NumPad6,
+        78 => Key::NumPadPlus,
+        79 => Key::NumPad1,
+        80 => Key::NumPad2,
+        81 => Key::NumPad3,
+        82 => Key::NumPad0,
+        83 => Key::NumPadDot,
+        87 => Key::F11,
+        88 => Key::F12,
+        96 => Key::NumPadEnter,
+        97 => Key::RightCtrl,
+        98 => Key::NumPadSlash,
+        100 => Key::RightAlt,
+        102 => Key::Home,
+        103 => Key::Up,
+        104 => Key::PageUp,
+        105 => Key::Left,
+        106 => Key::Right,
+        107 => Key::End,
+        108 => Key::Down,
+        109 => Key::PageDown,
+        110 => Key::Insert,
+        111 => Key::Delete,
+        119 => Key::Pause,
+        125 => Key::LeftSuper,
+        126 => Key::RightSuper,
+        127 => Key::Menu,
+        183 => Key::F13,
+        184 => Key::F14,
+        185 => Key::F15,
+        _ => return None,
+    };
+
+    Some(key)
+}
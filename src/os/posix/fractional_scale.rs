@@ -0,0 +1,23 @@
+//! Client side of the `wp_fractional_scale_v1` protocol, generated by the build script since the
+//! wayland-protocols crate doesn't include it yet.
+
+#![allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+#![allow(non_upper_case_globals, non_snake_case, unused_imports)]
+#![allow(missing_docs, clippy::all)]
+// The generated code takes references to the `static mut` interface descriptions
+#![allow(unknown_lints, static_mut_refs)]
+
+pub mod client {
+    pub(crate) use wayland_client::protocol::wl_surface;
+    pub(crate) use wayland_client::sys;
+    pub(crate) use wayland_client::{AnonymousObject, Attached, Main, Proxy, ProxyMap};
+    pub(crate) use wayland_commons::map::{Object, ObjectMetadata};
+    pub(crate) use wayland_commons::smallvec;
+    pub(crate) use wayland_commons::wire::{Argument, ArgumentType, Message, MessageDesc};
+    pub(crate) use wayland_commons::{Interface, MessageGroup};
+
+    include!(concat!(
+        env!("OUT_DIR"),
+        "/fractional_scale_v1_client_api.rs"
+    ));
+}
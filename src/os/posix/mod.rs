@@ -9,6 +9,12 @@
 #![allow(non_upper_case_globals)]
 
 mod common;
+#[cfg(feature = "fbdev")]
+mod fbdev;
+#[cfg(feature = "wayland")]
+mod fractional_scale;
+#[cfg(feature = "x11")]
+mod tray;
 #[cfg(feature = "wayland")]
 mod wayland;
 #[cfg(feature = "x11")]
@@ -18,48 +24,103 @@ mod xkb_ffi;
 #[cfg(feature = "wayland")]
 mod xkb_keysyms;
 
+use crate::error::Error;
 use crate::icon::Icon;
+use crate::Rect;
 use crate::Result;
-use crate::{CursorStyle, MenuHandle, UnixMenu};
-use crate::{InputCallback, Key, KeyRepeat, MouseButton, MouseMode, WindowOptions};
+use crate::UnixMenu;
+use crate::WindowLevel;
+use crate::{BorderFill, CloseReason, CursorStyle, GammaRamp, MenuHandle, MenuSource, Monitor};
+use crate::{
+    InputCallback, Key, KeyMap, KeyRepeat, Modifiers, MouseButton, MouseMode, PenState,
+    PixelFormat, PosixBackend, TouchPoint, WindowOptions,
+};
 pub use common::Menu;
+#[cfg(feature = "x11")]
+pub use tray::TrayIcon;
 
 use std::os::raw;
+use std::path::Path;
+
+// Name of the platform reported by the operations that need X11
+#[cfg(all(not(feature = "x11"), feature = "wayland"))]
+const PLATFORM: &str = "Wayland";
+#[cfg(all(not(feature = "x11"), not(feature = "wayland")))]
+const PLATFORM: &str = "framebuffer";
 
-// Differentiate between Wayland and X11 at run-time
+// Differentiate between Wayland, X11 and the framebuffer at run-time
 #[allow(clippy::large_enum_variant)]
 pub enum Window {
     #[cfg(feature = "x11")]
     X11(x11::Window),
     #[cfg(feature = "wayland")]
     Wayland(wayland::Window),
+    #[cfg(feature = "fbdev")]
+    Fbdev(fbdev::Window),
 }
 
 impl Window {
-    #[cfg(all(feature = "x11", feature = "wayland"))]
     pub fn new(name: &str, width: usize, height: usize, opts: WindowOptions) -> Result<Window> {
-        // Try to create Wayland display first
-        let wl_window = wayland::Window::new(name, width, height, opts);
-        match wl_window {
-            Ok(w) => Ok(Window::Wayland(w)),
-            Err(_) => {
-                // Create X11 Window when Wayland fails
-                let window = Window::X11(x11::Window::new(name, width, height, opts)?);
-                Ok(window)
-            }
-        }
+        let backend = opts.backend.unwrap_or(PosixBackend::Auto);
+        #[cfg(any(feature = "wayland", feature = "x11"))]
+        let wants = |b| backend == PosixBackend::Auto || backend == b;
+
+        // Replaced by the error of the last backend that was tried
+        let result = Err(Error::WindowCreate(match backend {
+            PosixBackend::Auto => "No backend available".to_owned(),
+            _ => format!("The {:?} backend isn't enabled", backend),
+        }));
+
+        // Try to create Wayland display first, then X11. The framebuffer is only used when asked
+        // for, it grabs every input device and the console, which would take them away from the
+        // session when a program is merely started without a display
+        #[cfg(feature = "wayland")]
+        let result = if wants(PosixBackend::Wayland) {
+            result.or_else(|_| wayland::Window::new(name, width, height, opts).map(Window::Wayland))
+        } else {
+            result
+        };
+        #[cfg(feature = "x11")]
+        let result = if wants(PosixBackend::X11) {
+            result.or_else(|_| x11::Window::new(name, width, height, opts).map(Window::X11))
+        } else {
+            result
+        };
+        #[cfg(feature = "fbdev")]
+        let result = if backend == PosixBackend::Fbdev {
+            result.or_else(|_| fbdev::Window::new(name, width, height, opts).map(Window::Fbdev))
+        } else {
+            result
+        };
+
+        result
     }
 
-    #[cfg(all(feature = "wayland", not(feature = "x11")))]
-    pub fn new(name: &str, width: usize, height: usize, opts: WindowOptions) -> Result<Window> {
-        let wl_window = wayland::Window::new(name, width, height, opts)?;
-        Ok(Window::Wayland(wl_window))
+    // The parent is always an X11 window, Wayland has no handles to embed into
+    #[cfg(feature = "x11")]
+    pub fn new_child(
+        parent: *mut raw::c_void,
+        name: &str,
+        width: usize,
+        height: usize,
+        opts: WindowOptions,
+    ) -> Result<Window> {
+        let window = x11::Window::new_child(parent as raw::c_ulong, name, width, height, opts)?;
+        Ok(Window::X11(window))
     }
 
-    #[cfg(all(feature = "x11", not(feature = "wayland")))]
-    pub fn new(name: &str, width: usize, height: usize, opts: WindowOptions) -> Result<Window> {
-        let window = Window::X11(x11::Window::new(name, width, height, opts)?);
-        Ok(window)
+    #[cfg(not(feature = "x11"))]
+    pub fn new_child(
+        _parent: *mut raw::c_void,
+        _name: &str,
+        _width: usize,
+        _height: usize,
+        _opts: WindowOptions,
+    ) -> Result<Window> {
+        Err(Error::Unsupported {
+            operation: "new_child",
+            platform: PLATFORM,
+        })
     }
 
     pub fn set_title(&mut self, title: &str) {
@@ -68,17 +129,57 @@ impl Window {
             Window::X11(ref mut w) => w.set_title(title),
             #[cfg(feature = "wayland")]
             Window::Wayland(ref mut w) => w.set_title(title),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_title(title),
+        }
+    }
+
+    pub fn get_title(&self) -> String {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_title(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_title(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_title(),
+        }
+    }
+
+    pub fn set_document_edited(&mut self, edited: bool) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_document_edited(edited),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_document_edited(edited),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_document_edited(edited),
         }
     }
 
-    pub fn set_icon(&mut self, icon: Icon) {
+    pub fn set_represented_file(&mut self, path: &Path) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_represented_file(path),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_represented_file(path),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_represented_file(path),
+        }
+    }
+
+    #[cfg_attr(not(any(feature = "x11", feature = "fbdev")), allow(unused_variables))]
+    pub fn set_icon(&mut self, icon: Icon) -> Result<()> {
         match *self {
             #[cfg(feature = "x11")]
             Window::X11(ref mut w) => w.set_icon(icon),
+            // Wayland takes the icon from the .desktop file matching the app id
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref mut _w) => {
-                unimplemented!("Cannot set icons at runtime on Wayland, create a .desktop file!")
-            }
+            Window::Wayland(ref mut _w) => Err(Error::Unsupported {
+                operation: "set_icon",
+                platform: "Wayland",
+            }),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_icon(icon),
         }
     }
 
@@ -98,6 +199,99 @@ impl Window {
             Window::Wayland(ref mut w) => {
                 w.update_with_buffer_stride(buffer, buf_width, buf_height, buf_stride)
             }
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => {
+                w.update_with_buffer_stride(buffer, buf_width, buf_height, buf_stride)
+            }
+        }
+    }
+
+    pub fn set_present_callback(&mut self, callback: Box<dyn FnOnce()>) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_present_callback(callback),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_present_callback(callback),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_present_callback(callback),
+        }
+    }
+
+    pub fn cancel_present_callbacks(&mut self) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.cancel_present_callbacks(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.cancel_present_callbacks(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.cancel_present_callbacks(),
+        }
+    }
+
+    pub fn set_damage(&mut self, rects: &[Rect]) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_damage(rects),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_damage(rects),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_damage(rects),
+        }
+    }
+
+    pub fn gl_get_proc_address(&self, name: &str) -> *const raw::c_void {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.gl_get_proc_address(name),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.gl_get_proc_address(name),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.gl_get_proc_address(name),
+        }
+    }
+
+    pub fn gl_swap_buffers(&mut self) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.gl_swap_buffers(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.gl_swap_buffers(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.gl_swap_buffers(),
+        }
+    }
+
+    pub fn update_with_buffer_vsync(
+        &mut self,
+        buffer: &[u32],
+        buf_width: usize,
+        buf_height: usize,
+        buf_stride: usize,
+    ) -> Result<()> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => {
+                w.update_with_buffer_vsync(buffer, buf_width, buf_height, buf_stride)
+            }
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => {
+                w.update_with_buffer_vsync(buffer, buf_width, buf_height, buf_stride)
+            }
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => {
+                w.update_with_buffer_vsync(buffer, buf_width, buf_height, buf_stride)
+            }
+        }
+    }
+
+    pub fn poll_input(&mut self) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.poll_input(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.poll_input(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.poll_input(),
         }
     }
 
@@ -107,6 +301,19 @@ impl Window {
             Window::X11(ref mut w) => w.update(),
             #[cfg(feature = "wayland")]
             Window::Wayland(ref mut w) => w.update(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.update(),
+        }
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(_) => "x11",
+            #[cfg(feature = "wayland")]
+            Window::Wayland(_) => "wayland",
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(_) => "fbdev",
         }
     }
 
@@ -116,6 +323,8 @@ impl Window {
             Window::X11(ref w) => w.get_window_handle(),
             #[cfg(feature = "wayland")]
             Window::Wayland(ref w) => w.get_window_handle(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_window_handle(),
         }
     }
 
@@ -125,6 +334,19 @@ impl Window {
             Window::X11(ref mut w) => w.set_background_color(bg_color),
             #[cfg(feature = "wayland")]
             Window::Wayland(ref mut w) => w.set_background_color(bg_color),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_background_color(bg_color),
+        }
+    }
+
+    pub fn set_border_fill(&mut self, fill: BorderFill) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_border_fill(fill),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_border_fill(fill),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_border_fill(fill),
         }
     }
 
@@ -134,6 +356,74 @@ impl Window {
             Window::X11(ref mut w) => w.set_cursor_visibility(visibility),
             #[cfg(feature = "wayland")]
             Window::Wayland(ref mut w) => w.set_cursor_visibility(visibility),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_cursor_visibility(visibility),
+        }
+    }
+
+    pub fn is_cursor_visible(&self) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.is_cursor_visible(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.is_cursor_visible(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.is_cursor_visible(),
+        }
+    }
+
+    pub fn set_cursor_confined(&mut self, confined: bool) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_cursor_confined(confined),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_cursor_confined(confined),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_cursor_confined(confined),
+        }
+    }
+
+    pub fn set_gameplay_cursor_mode(&mut self, enabled: bool) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_gameplay_cursor_mode(enabled),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_gameplay_cursor_mode(enabled),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_gameplay_cursor_mode(enabled),
+        }
+    }
+
+    pub fn get_mouse_delta(&self) -> (f32, f32) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_mouse_delta(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_mouse_delta(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_mouse_delta(),
+        }
+    }
+
+    pub fn set_keyboard_grab(&mut self, grab: bool) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_keyboard_grab(grab),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_keyboard_grab(grab),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_keyboard_grab(grab),
+        }
+    }
+
+    pub fn set_cursor_auto_hide(&mut self, timeout: Option<std::time::Duration>) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_cursor_auto_hide(timeout),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_cursor_auto_hide(timeout),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_cursor_auto_hide(timeout),
         }
     }
 
@@ -143,6 +433,8 @@ impl Window {
             Window::X11(ref mut w) => w.set_position(x, y),
             #[cfg(feature = "wayland")]
             Window::Wayland(ref mut w) => w.set_position(x, y),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_position(x, y),
         }
     }
 
@@ -152,6 +444,30 @@ impl Window {
             Window::X11(ref w) => w.get_position(),
             #[cfg(feature = "wayland")]
             Window::Wayland(ref w) => w.get_position(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_position(),
+        }
+    }
+
+    pub fn get_outer_position(&self) -> (isize, isize) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_outer_position(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_outer_position(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_outer_position(),
+        }
+    }
+
+    pub fn get_outer_size(&self) -> (usize, usize) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_outer_size(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_outer_size(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_outer_size(),
         }
     }
 
@@ -159,221 +475,974 @@ impl Window {
         // We will just do nothing until it is implemented so that nothing breaks
     }
 
-    pub fn get_size(&self) -> (usize, usize) {
+    pub fn set_visible(&mut self, visible: bool) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.get_size(),
+            Window::X11(ref mut w) => w.set_visible(visible),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.get_size(),
+            Window::Wayland(ref mut w) => w.set_visible(visible),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_visible(visible),
         }
     }
 
-    pub fn get_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
+    pub fn set_always_on_bottom(&mut self, enabled: bool) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.get_mouse_pos(mode),
+            Window::X11(ref mut w) => w.set_always_on_bottom(enabled),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.get_mouse_pos(mode),
+            Window::Wayland(ref mut w) => w.set_always_on_bottom(enabled),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_always_on_bottom(enabled),
         }
     }
 
-    pub fn get_unscaled_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
+    pub fn set_window_level(&mut self, level: WindowLevel) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.get_unscaled_mouse_pos(mode),
+            Window::X11(ref mut w) => w.set_window_level(level),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.get_unscaled_mouse_pos(mode),
+            Window::Wayland(ref mut w) => w.set_window_level(level),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_window_level(level),
         }
     }
 
-    pub fn get_mouse_down(&self, button: MouseButton) -> bool {
+    pub fn set_skip_taskbar(&mut self, skip: bool) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.get_mouse_down(button),
+            Window::X11(ref mut w) => w.set_skip_taskbar(skip),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.get_mouse_down(button),
+            Window::Wayland(ref mut w) => w.set_skip_taskbar(skip),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_skip_taskbar(skip),
         }
     }
 
-    pub fn get_scroll_wheel(&self) -> Option<(f32, f32)> {
+    pub fn set_caption_buttons(&mut self, minimize: bool, maximize: bool, close: bool) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.get_scroll_wheel(),
+            Window::X11(ref mut w) => w.set_caption_buttons(minimize, maximize, close),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.get_scroll_wheel(),
+            Window::Wayland(ref mut w) => w.set_caption_buttons(minimize, maximize, close),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_caption_buttons(minimize, maximize, close),
         }
     }
 
-    pub fn set_cursor_style(&mut self, cursor: CursorStyle) {
+    pub fn set_shadow(&mut self, enabled: bool) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref mut w) => w.set_cursor_style(cursor),
+            Window::X11(ref mut w) => w.set_shadow(enabled),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref mut w) => w.set_cursor_style(cursor),
+            Window::Wayland(ref mut w) => w.set_shadow(enabled),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_shadow(enabled),
         }
     }
 
-    pub fn set_rate(&mut self, rate: Option<std::time::Duration>) {
+    pub fn beep(&self) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref mut w) => w.set_rate(rate),
+            Window::X11(ref w) => w.beep(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref mut w) => w.set_rate(rate),
+            Window::Wayland(ref w) => w.beep(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.beep(),
         }
     }
 
-    pub fn update_rate(&mut self) {
+    pub fn get_size(&self) -> (usize, usize) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref mut w) => w.update_rate(),
+            Window::X11(ref w) => w.get_size(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref mut w) => w.update_rate(),
+            Window::Wayland(ref w) => w.get_size(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_size(),
         }
     }
 
-    pub fn get_keys(&self) -> Vec<Key> {
+    pub fn get_unscaled_size(&self) -> (usize, usize) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.get_keys(),
+            Window::X11(ref w) => w.get_unscaled_size(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.get_keys(),
+            Window::Wayland(ref w) => w.get_unscaled_size(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_unscaled_size(),
         }
     }
 
-    pub fn get_keys_pressed(&self, repeat: KeyRepeat) -> Vec<Key> {
+    pub fn get_buffer_scale(&self) -> (f32, f32) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.get_keys_pressed(repeat),
+            Window::X11(ref w) => w.get_buffer_scale(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.get_keys_pressed(repeat),
+            Window::Wayland(ref w) => w.get_buffer_scale(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_buffer_scale(),
         }
     }
 
-    pub fn get_keys_released(&self) -> Vec<Key> {
+    pub fn get_content_scale(&self) -> f32 {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.get_keys_released(),
+            Window::X11(ref w) => w.get_content_scale(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.get_keys_released(),
+            Window::Wayland(ref w) => w.get_content_scale(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_content_scale(),
         }
     }
 
-    pub fn is_key_down(&self, key: Key) -> bool {
+    pub fn get_native_pixel_format(&self) -> Option<PixelFormat> {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.is_key_down(key),
+            Window::X11(ref w) => w.get_native_pixel_format(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.is_key_down(key),
+            Window::Wayland(ref w) => w.get_native_pixel_format(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_native_pixel_format(),
         }
     }
 
-    pub fn set_key_repeat_delay(&mut self, delay: f32) {
+    pub fn is_transparency_active(&self) -> bool {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref mut w) => w.set_key_repeat_delay(delay),
+            Window::X11(ref w) => w.is_transparency_active(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref mut w) => w.set_key_repeat_delay(delay),
+            Window::Wayland(ref w) => w.is_transparency_active(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.is_transparency_active(),
         }
     }
 
-    pub fn set_key_repeat_rate(&mut self, rate: f32) {
+    pub fn is_maximized(&self) -> bool {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref mut w) => w.set_key_repeat_rate(rate),
+            Window::X11(ref w) => w.is_maximized(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref mut w) => w.set_key_repeat_rate(rate),
+            Window::Wayland(ref w) => w.is_maximized(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.is_maximized(),
         }
     }
 
-    pub fn is_key_pressed(&self, key: Key, repeat: KeyRepeat) -> bool {
+    pub fn set_maximized(&mut self, maximized: bool) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.is_key_pressed(key, repeat),
+            Window::X11(ref mut w) => w.set_maximized(maximized),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.is_key_pressed(key, repeat),
+            Window::Wayland(ref mut w) => w.set_maximized(maximized),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_maximized(maximized),
         }
     }
 
-    pub fn is_key_released(&self, key: Key) -> bool {
+    pub fn get_monitors(&self) -> Vec<Monitor> {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.is_key_released(key),
+            Window::X11(ref w) => w.get_monitors(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.is_key_released(key),
+            Window::Wayland(ref w) => w.get_monitors(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_monitors(),
         }
     }
 
-    pub fn set_input_callback(&mut self, callback: Box<dyn InputCallback>) {
+    pub fn get_keyboard_layout(&self) -> Option<String> {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref mut w) => w.set_input_callback(callback),
+            Window::X11(ref w) => w.get_keyboard_layout(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref mut w) => w.set_input_callback(callback),
+            Window::Wayland(ref w) => w.get_keyboard_layout(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_keyboard_layout(),
         }
     }
 
-    pub fn is_open(&self) -> bool {
+    pub fn display_count(&self) -> usize {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.is_open(),
+            Window::X11(ref w) => w.display_count(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.is_open(),
+            Window::Wayland(ref w) => w.display_count(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.display_count(),
         }
     }
 
-    pub fn is_active(&mut self) -> bool {
+    pub fn set_gamma_ramp(&mut self, ramp: &GammaRamp) -> Result<()> {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref mut w) => w.is_active(),
+            Window::X11(ref mut w) => w.set_gamma_ramp(ramp),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref mut w) => w.is_active(),
+            Window::Wayland(ref mut w) => w.set_gamma_ramp(ramp),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_gamma_ramp(ramp),
         }
     }
 
-    pub fn add_menu(&mut self, menu: &Menu) -> MenuHandle {
+    pub fn get_gamma_ramp(&self) -> Option<GammaRamp> {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref mut w) => w.add_menu(menu),
+            Window::X11(ref w) => w.get_gamma_ramp(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref mut w) => w.add_menu(menu),
+            Window::Wayland(ref w) => w.get_gamma_ramp(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_gamma_ramp(),
         }
     }
 
-    pub fn get_posix_menus(&self) -> Option<&Vec<UnixMenu>> {
+    pub fn current_monitor(&self) -> Option<Monitor> {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.get_posix_menus(),
+            Window::X11(ref w) => w.current_monitor(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.get_posix_menus(),
+            Window::Wayland(ref w) => w.current_monitor(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.current_monitor(),
         }
     }
 
-    pub fn remove_menu(&mut self, handle: MenuHandle) {
+    pub fn moved_to_monitor(&self) -> bool {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref mut w) => w.remove_menu(handle),
+            Window::X11(ref w) => w.moved_to_monitor(),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref mut w) => w.remove_menu(handle),
+            Window::Wayland(ref w) => w.moved_to_monitor(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.moved_to_monitor(),
         }
     }
 
-    pub fn is_menu_pressed(&mut self) -> Option<usize> {
+    pub fn set_size(&mut self, width: usize, height: usize) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref mut w) => w.is_menu_pressed(),
+            Window::X11(ref mut w) => w.set_size(width, height),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref mut w) => w.is_menu_pressed(),
+            Window::Wayland(ref mut w) => w.set_size(width, height),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_size(width, height),
         }
     }
-}
 
-unsafe impl raw_window_handle::HasRawWindowHandle for Window {
-    fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+    pub fn set_aspect_ratio(&mut self, ratio: Option<(u32, u32)>) {
         match *self {
             #[cfg(feature = "x11")]
-            Window::X11(ref w) => w.raw_window_handle(),
+            Window::X11(ref mut w) => w.set_aspect_ratio(ratio),
             #[cfg(feature = "wayland")]
-            Window::Wayland(ref w) => w.raw_window_handle(),
+            Window::Wayland(ref mut w) => w.set_aspect_ratio(ratio),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_aspect_ratio(ratio),
+        }
+    }
+
+    pub fn get_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_mouse_pos(mode),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_mouse_pos(mode),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_mouse_pos(mode),
+        }
+    }
+
+    pub fn get_mouse_history(&self) -> Vec<(f32, f32, std::time::Duration)> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_mouse_history(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_mouse_history(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_mouse_history(),
+        }
+    }
+
+    pub fn get_pen(&self) -> Option<PenState> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_pen(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_pen(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_pen(),
+        }
+    }
+
+    pub fn get_touch_points(&self) -> Vec<TouchPoint> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_touch_points(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_touch_points(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_touch_points(),
         }
     }
+
+    pub fn get_unscaled_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_unscaled_mouse_pos(mode),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_unscaled_mouse_pos(mode),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_unscaled_mouse_pos(mode),
+        }
+    }
+
+    pub fn get_mouse_pos_on_screen(&self) -> Option<(f32, f32)> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_mouse_pos_on_screen(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_mouse_pos_on_screen(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_mouse_pos_on_screen(),
+        }
+    }
+
+    pub fn get_mouse_down(&self, button: MouseButton) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_mouse_down(button),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_mouse_down(button),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_mouse_down(button),
+        }
+    }
+
+    pub fn get_mouse_down_with_modifiers(&self, button: MouseButton) -> Option<Modifiers> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_mouse_down_with_modifiers(button),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_mouse_down_with_modifiers(button),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_mouse_down_with_modifiers(button),
+        }
+    }
+
+    pub fn get_scroll_wheel(&self) -> Option<(f32, f32)> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_scroll_wheel(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_scroll_wheel(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_scroll_wheel(),
+        }
+    }
+
+    pub fn get_scroll_accumulated(&self) -> (f32, f32) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_scroll_accumulated(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_scroll_accumulated(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_scroll_accumulated(),
+        }
+    }
+
+    pub fn reset_scroll_accumulator(&mut self) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.reset_scroll_accumulator(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.reset_scroll_accumulator(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.reset_scroll_accumulator(),
+        }
+    }
+
+    pub fn set_cursor_style(&mut self, cursor: CursorStyle) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_cursor_style(cursor),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_cursor_style(cursor),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_cursor_style(cursor),
+        }
+    }
+
+    pub fn get_cursor_style(&self) -> CursorStyle {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_cursor_style(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_cursor_style(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_cursor_style(),
+        }
+    }
+
+    pub fn get_cursor_hotspot(&self) -> (i32, i32) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_cursor_hotspot(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_cursor_hotspot(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_cursor_hotspot(),
+        }
+    }
+
+    pub fn set_animated_cursor(
+        &mut self,
+        frames: &[(&[u32], u32, u32)],
+        frame_duration: std::time::Duration,
+        hot_x: u32,
+        hot_y: u32,
+    ) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_animated_cursor(frames, frame_duration, hot_x, hot_y),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => {
+                w.set_animated_cursor(frames, frame_duration, hot_x, hot_y)
+            }
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_animated_cursor(frames, frame_duration, hot_x, hot_y),
+        }
+    }
+
+    pub fn set_rate(&mut self, rate: Option<std::time::Duration>) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_rate(rate),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_rate(rate),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_rate(rate),
+        }
+    }
+
+    pub fn update_rate(&mut self) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.update_rate(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.update_rate(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.update_rate(),
+        }
+    }
+
+    pub fn set_target_fps(&mut self, fps: u32) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_target_fps(fps),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_target_fps(fps),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_target_fps(fps),
+        }
+    }
+
+    pub fn get_frame_time(&self) -> std::time::Duration {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_frame_time(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_frame_time(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_frame_time(),
+        }
+    }
+
+    pub fn get_keys(&self) -> Vec<Key> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_keys(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_keys(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_keys(),
+        }
+    }
+
+    pub fn keys_down(&self) -> &[Key] {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.keys_down(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.keys_down(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.keys_down(),
+        }
+    }
+
+    pub fn get_keys_pressed(&self, repeat: KeyRepeat) -> Vec<Key> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_keys_pressed(repeat),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_keys_pressed(repeat),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_keys_pressed(repeat),
+        }
+    }
+
+    pub fn get_keys_just_pressed(&self) -> Vec<Key> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_keys_just_pressed(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_keys_just_pressed(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_keys_just_pressed(),
+        }
+    }
+
+    pub fn get_text_input(&self) -> String {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_text_input(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_text_input(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_text_input(),
+        }
+    }
+
+    pub fn get_keys_released(&self) -> Vec<Key> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_keys_released(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_keys_released(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_keys_released(),
+        }
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.is_key_down(key),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.is_key_down(key),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.is_key_down(key),
+        }
+    }
+
+    pub fn get_input_timestamp(&self) -> Option<std::time::Duration> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_input_timestamp(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_input_timestamp(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_input_timestamp(),
+        }
+    }
+
+    pub fn get_key_timestamp(&self, key: Key) -> Option<std::time::Duration> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_key_timestamp(key),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_key_timestamp(key),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_key_timestamp(key),
+        }
+    }
+
+    pub fn set_key_repeat_delay(&mut self, delay: f32) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_key_repeat_delay(delay),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_key_repeat_delay(delay),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_key_repeat_delay(delay),
+        }
+    }
+
+    pub fn set_key_repeat_rate(&mut self, rate: f32) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_key_repeat_rate(rate),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_key_repeat_rate(rate),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_key_repeat_rate(rate),
+        }
+    }
+
+    pub fn set_key_map(&mut self, key_map: KeyMap) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_key_map(key_map),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_key_map(key_map),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_key_map(key_map),
+        }
+    }
+
+    pub fn is_action_down(&self, action: &str) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.is_action_down(action),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.is_action_down(action),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.is_action_down(action),
+        }
+    }
+
+    pub fn set_key_repeat_for(&mut self, keys: &[Key], delay: f32, rate: f32) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_key_repeat_for(keys, delay, rate),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_key_repeat_for(keys, delay, rate),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_key_repeat_for(keys, delay, rate),
+        }
+    }
+
+    pub fn is_occluded(&self) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.is_occluded(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.is_occluded(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.is_occluded(),
+        }
+    }
+
+    pub fn close(&mut self) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.close(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.close(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.close(),
+        }
+    }
+
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.close_reason(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.close_reason(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.close_reason(),
+        }
+    }
+
+    pub fn set_close_requested_handling(&mut self, auto: bool) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_close_requested_handling(auto),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_close_requested_handling(auto),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_close_requested_handling(auto),
+        }
+    }
+
+    pub fn set_default_accelerators(&mut self, enabled: bool) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_default_accelerators(enabled),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_default_accelerators(enabled),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_default_accelerators(enabled),
+        }
+    }
+
+    pub fn close_requested(&self) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.close_requested(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.close_requested(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.close_requested(),
+        }
+    }
+
+    pub fn use_os_key_repeat(&mut self) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.use_os_key_repeat(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.use_os_key_repeat(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.use_os_key_repeat(),
+        }
+    }
+
+    pub fn is_key_pressed(&self, key: Key, repeat: KeyRepeat) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.is_key_pressed(key, repeat),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.is_key_pressed(key, repeat),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.is_key_pressed(key, repeat),
+        }
+    }
+
+    pub fn is_key_repeat(&self, key: Key) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.is_key_repeat(key),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.is_key_repeat(key),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.is_key_repeat(key),
+        }
+    }
+
+    pub fn is_key_released(&self, key: Key) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.is_key_released(key),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.is_key_released(key),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.is_key_released(key),
+        }
+    }
+
+    pub fn set_input_callback(&mut self, callback: Box<dyn InputCallback>) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_input_callback(callback),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_input_callback(callback),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_input_callback(callback),
+        }
+    }
+
+    pub fn is_open(&self) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.is_open(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.is_open(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.is_open(),
+        }
+    }
+
+    pub fn is_active(&mut self) -> bool {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.is_active(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.is_active(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.is_active(),
+        }
+    }
+
+    pub fn get_primary_selection(&self) -> Option<String> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_primary_selection(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_primary_selection(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_primary_selection(),
+        }
+    }
+
+    pub fn set_primary_selection(&mut self, text: &str) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.set_primary_selection(text),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.set_primary_selection(text),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.set_primary_selection(text),
+        }
+    }
+
+    pub fn add_menu(&mut self, menu: &Menu) -> MenuHandle {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.add_menu(menu),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.add_menu(menu),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.add_menu(menu),
+        }
+    }
+
+    pub fn get_posix_menus(&self) -> Option<&Vec<UnixMenu>> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.get_posix_menus(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.get_posix_menus(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.get_posix_menus(),
+        }
+    }
+
+    pub fn remove_menu(&mut self, handle: MenuHandle) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.remove_menu(handle),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.remove_menu(handle),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.remove_menu(handle),
+        }
+    }
+
+    pub fn update_menu(&mut self, handle: MenuHandle, menu: &Menu) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.update_menu(handle, menu),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.update_menu(handle, menu),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.update_menu(handle, menu),
+        }
+    }
+
+    #[inline]
+    pub fn set_app_menu(&mut self, _menu: &Menu) {
+        // There is no application menu on X11 or Wayland
+    }
+
+    pub fn show_context_menu(&mut self, menu: &Menu, x: i32, y: i32) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.show_context_menu(menu, x, y),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.show_context_menu(menu, x, y),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.show_context_menu(menu, x, y),
+        }
+    }
+
+    pub fn take_posix_context_menu(&mut self) -> Option<(UnixMenu, i32, i32)> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.take_posix_context_menu(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.take_posix_context_menu(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.take_posix_context_menu(),
+        }
+    }
+
+    pub fn sync(&mut self) {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.sync(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.sync(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.sync(),
+        }
+    }
+
+    pub fn get_menu_height(&self) -> usize {
+        // Menus aren't shown by the posix backends
+        0
+    }
+
+    pub fn menu_activation_source(&self) -> Option<MenuSource> {
+        // Menus aren't shown by the posix backends
+        None
+    }
+
+    pub fn is_menu_pressed(&mut self) -> Option<usize> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref mut w) => w.is_menu_pressed(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref mut w) => w.is_menu_pressed(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref mut w) => w.is_menu_pressed(),
+        }
+    }
+}
+
+unsafe impl raw_window_handle::HasRawWindowHandle for Window {
+    fn raw_window_handle(&self) -> raw_window_handle::RawWindowHandle {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.raw_window_handle(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.raw_window_handle(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.raw_window_handle(),
+        }
+    }
+}
+
+#[cfg(feature = "rwh_06")]
+impl Window {
+    pub fn raw_window_handle_06(&self) -> Option<raw_window_handle_06::RawWindowHandle> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.raw_window_handle_06(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.raw_window_handle_06(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.raw_window_handle_06(),
+        }
+    }
+
+    pub fn raw_display_handle_06(&self) -> Option<raw_window_handle_06::RawDisplayHandle> {
+        match *self {
+            #[cfg(feature = "x11")]
+            Window::X11(ref w) => w.raw_display_handle_06(),
+            #[cfg(feature = "wayland")]
+            Window::Wayland(ref w) => w.raw_display_handle_06(),
+            #[cfg(feature = "fbdev")]
+            Window::Fbdev(ref w) => w.raw_display_handle_06(),
+        }
+    }
+}
+
+// The tray protocol is X11 only
+#[cfg(not(feature = "x11"))]
+pub struct TrayIcon;
+
+#[cfg(not(feature = "x11"))]
+impl TrayIcon {
+    pub fn new(_icon: Icon, _tooltip: &str) -> Result<TrayIcon> {
+        Err(Error::Unsupported {
+            operation: "TrayIcon",
+            platform: PLATFORM,
+        })
+    }
+
+    pub fn set_tooltip(&mut self, _tooltip: &str) {}
+
+    pub fn set_menu(&mut self, _menu: &Menu) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn update(&mut self) {}
+
+    pub fn is_menu_pressed(&mut self) -> Option<usize> {
+        None
+    }
+
+    pub fn is_clicked(&mut self) -> bool {
+        false
+    }
 }
@@ -0,0 +1,292 @@
+//! System tray icon for X11, docked into the tray with the XEmbed based system tray protocol.
+
+use crate::error::Error;
+use crate::icon::Icon;
+use crate::Result;
+
+use super::common::Menu;
+
+use x11_dl::xlib;
+
+use std::ffi::CString;
+use std::mem;
+use std::os::raw::{c_char, c_int, c_long, c_uint};
+use std::ptr;
+use std::slice;
+
+// Opcode of the dock request sent to the tray
+const SYSTEM_TRAY_REQUEST_DOCK: c_long = 0;
+// Flag in _XEMBED_INFO that asks the embedder to map the window
+const XEMBED_MAPPED: c_long = 1;
+// Size used until the tray resizes the icon
+const DEFAULT_SIZE: u32 = 22;
+
+pub struct TrayIcon {
+    lib: xlib::Xlib,
+    display: *mut xlib::Display,
+    handle: xlib::Window,
+    gc: xlib::GC,
+    width: u32,
+    height: u32,
+    icon_width: usize,
+    icon_height: usize,
+    icon: Vec<u32>,
+    clicked: bool,
+}
+
+impl TrayIcon {
+    pub fn new(icon: Icon, tooltip: &str) -> Result<TrayIcon> {
+        let (icon_width, icon_height, icon) = match icon {
+            Icon::Buffer(ptr, len) => {
+                unsafe { first_image(slice::from_raw_parts(ptr, len as usize)) }
+                    .ok_or_else(|| Error::WindowCreate("Invalid icon buffer".to_owned()))?
+            }
+            Icon::Path(_) => {
+                return Err(Error::Unsupported {
+                    operation: "TrayIcon from a file",
+                    platform: "X11",
+                })
+            }
+        };
+
+        unsafe {
+            let lib = xlib::Xlib::open()
+                .map_err(|e| Error::WindowCreate(format!("failed to load Xlib: {:?}", e)))?;
+
+            let display = (lib.XOpenDisplay)(ptr::null());
+
+            if display.is_null() {
+                return Err(Error::WindowCreate("XOpenDisplay failed".to_owned()));
+            }
+
+            let screen = (lib.XDefaultScreen)(display);
+            let selection = CString::new(format!("_NET_SYSTEM_TRAY_S{}", screen)).unwrap();
+            let selection = (lib.XInternAtom)(display, selection.as_ptr(), xlib::False);
+            let tray = (lib.XGetSelectionOwner)(display, selection);
+
+            if tray == 0 {
+                (lib.XCloseDisplay)(display);
+                return Err(Error::WindowCreate("No system tray is running".to_owned()));
+            }
+
+            let root = (lib.XDefaultRootWindow)(display);
+            let handle =
+                (lib.XCreateSimpleWindow)(display, root, 0, 0, DEFAULT_SIZE, DEFAULT_SIZE, 0, 0, 0);
+
+            // Let the tray background show through the transparent parts of the icon
+            (lib.XSetWindowBackgroundPixmap)(display, handle, xlib::ParentRelative as _);
+            (lib.XSelectInput)(
+                display,
+                handle,
+                xlib::ExposureMask | xlib::StructureNotifyMask | xlib::ButtonReleaseMask,
+            );
+
+            let xembed_info = (lib.XInternAtom)(
+                display,
+                b"_XEMBED_INFO\0".as_ptr() as *const c_char,
+                xlib::False,
+            );
+            let info: [c_long; 2] = [0, XEMBED_MAPPED];
+            (lib.XChangeProperty)(
+                display,
+                handle,
+                xembed_info,
+                xembed_info,
+                32,
+                xlib::PropModeReplace,
+                info.as_ptr() as *const u8,
+                info.len() as c_int,
+            );
+
+            let gc = (lib.XCreateGC)(display, handle, 0, ptr::null_mut());
+
+            let mut ev: xlib::XEvent = mem::zeroed();
+            ev.client_message.type_ = xlib::ClientMessage;
+            ev.client_message.window = tray;
+            ev.client_message.message_type = (lib.XInternAtom)(
+                display,
+                b"_NET_SYSTEM_TRAY_OPCODE\0".as_ptr() as *const c_char,
+                xlib::False,
+            );
+            ev.client_message.format = 32;
+            ev.client_message
+                .data
+                .set_long(0, xlib::CurrentTime as c_long);
+            ev.client_message.data.set_long(1, SYSTEM_TRAY_REQUEST_DOCK);
+            ev.client_message.data.set_long(2, handle as c_long);
+            (lib.XSendEvent)(display, tray, xlib::False, xlib::NoEventMask, &mut ev);
+
+            let mut tray_icon = TrayIcon {
+                lib,
+                display,
+                handle,
+                gc,
+                width: DEFAULT_SIZE,
+                height: DEFAULT_SIZE,
+                icon_width,
+                icon_height,
+                icon,
+                clicked: false,
+            };
+
+            tray_icon.set_tooltip(tooltip);
+
+            Ok(tray_icon)
+        }
+    }
+
+    pub fn set_tooltip(&mut self, tooltip: &str) {
+        // Trays show the window name as the tooltip
+        if let Ok(name) = CString::new(tooltip) {
+            unsafe {
+                (self.lib.XStoreName)(self.display, self.handle, name.as_ptr());
+                (self.lib.XFlush)(self.display);
+            }
+        }
+    }
+
+    pub fn set_menu(&mut self, _menu: &Menu) -> Result<()> {
+        // The tray protocol has no menus, and minifb doesn't draw its own popups
+        Err(Error::Unsupported {
+            operation: "TrayIcon::set_menu",
+            platform: "X11",
+        })
+    }
+
+    pub fn update(&mut self) {
+        unsafe {
+            while (self.lib.XPending)(self.display) > 0 {
+                let mut event: xlib::XEvent = mem::zeroed();
+                (self.lib.XNextEvent)(self.display, &mut event);
+
+                match event.get_type() {
+                    xlib::Expose if event.expose.count == 0 => self.draw(),
+                    xlib::ConfigureNotify => {
+                        let width = event.configure.width.max(1) as u32;
+                        let height = event.configure.height.max(1) as u32;
+                        if width != self.width || height != self.height {
+                            self.width = width;
+                            self.height = height;
+                            self.draw();
+                        }
+                    }
+                    xlib::ButtonRelease if event.button.button == xlib::Button1 => {
+                        self.clicked = true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub fn is_menu_pressed(&mut self) -> Option<usize> {
+        None
+    }
+
+    #[inline]
+    pub fn is_clicked(&mut self) -> bool {
+        mem::replace(&mut self.clicked, false)
+    }
+
+    /// Draws the icon scaled to the window, only the opaque pixels are drawn.
+    unsafe fn draw(&mut self) {
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let stride = width.div_ceil(8);
+
+        let mut pixels = vec![0u32; width * height];
+        let mut mask = vec![0u8; stride * height];
+
+        for y in 0..height {
+            let src_y = y * self.icon_height / height;
+            for x in 0..width {
+                let src_x = x * self.icon_width / width;
+                let color = self.icon[src_y * self.icon_width + src_x];
+                pixels[y * width + x] = color & 0x00ff_ffff;
+                if color >> 24 >= 0x80 {
+                    mask[y * stride + x / 8] |= 1 << (x % 8);
+                }
+            }
+        }
+
+        let screen = (self.lib.XDefaultScreen)(self.display);
+        let image = (self.lib.XCreateImage)(
+            self.display,
+            (self.lib.XDefaultVisual)(self.display, screen),
+            (self.lib.XDefaultDepth)(self.display, screen) as c_uint,
+            xlib::ZPixmap,
+            0,
+            pixels.as_mut_ptr() as *mut c_char,
+            self.width,
+            self.height,
+            32,
+            (width * 4) as c_int,
+        );
+
+        if image.is_null() {
+            return;
+        }
+
+        (*image).byte_order = if cfg!(target_endian = "little") {
+            xlib::LSBFirst
+        } else {
+            xlib::MSBFirst
+        };
+
+        let clip = (self.lib.XCreateBitmapFromData)(
+            self.display,
+            self.handle,
+            mask.as_ptr() as *const c_char,
+            self.width,
+            self.height,
+        );
+
+        (self.lib.XClearWindow)(self.display, self.handle);
+        (self.lib.XSetClipMask)(self.display, self.gc, clip);
+        (self.lib.XPutImage)(
+            self.display,
+            self.handle,
+            self.gc,
+            image,
+            0,
+            0,
+            0,
+            0,
+            self.width,
+            self.height,
+        );
+        (self.lib.XSetClipMask)(self.display, self.gc, 0);
+        (self.lib.XFreePixmap)(self.display, clip);
+        (self.lib.XFlush)(self.display);
+
+        // The pixels are owned by the vector
+        (*image).data = ptr::null_mut();
+        (self.lib.XDestroyImage)(image);
+    }
+}
+
+impl Drop for TrayIcon {
+    fn drop(&mut self) {
+        unsafe {
+            (self.lib.XFreeGC)(self.display, self.gc);
+            (self.lib.XDestroyWindow)(self.display, self.handle);
+            (self.lib.XCloseDisplay)(self.display);
+        }
+    }
+}
+
+/// Takes the first image out of a `_NET_WM_ICON` style buffer.
+fn first_image(data: &[u64]) -> Option<(usize, usize, Vec<u32>)> {
+    let width = *data.first()? as usize;
+    let height = *data.get(1)? as usize;
+    // The size comes from the caller's buffer, so it can be anything
+    let len = width.checked_mul(height)?.checked_add(2)?;
+    let pixels = data.get(2..len)?;
+
+    if width == 0 || height == 0 {
+        return None;
+    }
+
+    Some((width, height, pixels.iter().map(|&p| p as u32).collect()))
+}
@@ -1,14 +1,24 @@
 use crate::buffer_helper;
 use crate::key_handler::KeyHandler;
 use crate::mouse_handler;
+use crate::present::PresentCallbacks;
 use crate::rate::UpdateRate;
-use crate::{CursorStyle, MenuHandle, UnixMenu};
+use crate::touch_handler::TouchHandler;
+use crate::BorderFill;
+use crate::Modifiers;
+use crate::PixelFormat;
+use crate::Rect;
+use crate::WindowLevel;
+use crate::{CloseReason, CursorStyle, GammaRamp, MenuHandle, Monitor, UnixMenu};
 use crate::{Error, Result};
 use crate::{
-    InputCallback, Key, KeyRepeat, MouseButton, MouseMode, Scale, ScaleMode, WindowOptions,
+    InputCallback, Key, KeyMap, KeyRepeat, MouseButton, MouseMode, PenState, Scale, ScaleMode,
+    TouchPhase, TouchPoint, WindowOptions,
 };
 
 use super::common::Menu;
+use super::fractional_scale::client::wp_fractional_scale_manager_v1::WpFractionalScaleManagerV1;
+use super::fractional_scale::client::wp_fractional_scale_v1::{self, WpFractionalScaleV1};
 use super::xkb_ffi;
 #[cfg(feature = "dlopen")]
 use super::xkb_ffi::XKBCOMMON_HANDLE as XKBH;
@@ -20,36 +30,52 @@ use wayland_client::protocol::wl_compositor::WlCompositor;
 use wayland_client::protocol::wl_display::WlDisplay;
 use wayland_client::protocol::wl_keyboard::{KeymapFormat, WlKeyboard};
 use wayland_client::protocol::wl_pointer::WlPointer;
-use wayland_client::protocol::wl_seat::WlSeat;
+use wayland_client::protocol::wl_seat::{self, WlSeat};
 use wayland_client::protocol::wl_shm::{Format, WlShm};
 use wayland_client::protocol::wl_shm_pool::WlShmPool;
 use wayland_client::protocol::wl_surface::WlSurface;
-use wayland_client::protocol::{wl_keyboard, wl_pointer};
+use wayland_client::protocol::wl_touch::WlTouch;
+use wayland_client::protocol::{wl_callback, wl_keyboard, wl_pointer, wl_touch};
 use wayland_client::{Attached, Display, EventQueue, GlobalManager, Main};
+use wayland_protocols::unstable::keyboard_shortcuts_inhibit::v1::client::zwp_keyboard_shortcuts_inhibit_manager_v1::ZwpKeyboardShortcutsInhibitManagerV1;
+use wayland_protocols::unstable::keyboard_shortcuts_inhibit::v1::client::zwp_keyboard_shortcuts_inhibitor_v1::ZwpKeyboardShortcutsInhibitorV1;
+use wayland_protocols::unstable::pointer_constraints::v1::client::zwp_confined_pointer_v1::ZwpConfinedPointerV1;
+use wayland_protocols::unstable::pointer_constraints::v1::client::zwp_locked_pointer_v1::ZwpLockedPointerV1;
+use wayland_protocols::unstable::pointer_constraints::v1::client::zwp_pointer_constraints_v1::{
+    Lifetime, ZwpPointerConstraintsV1,
+};
+use wayland_protocols::unstable::relative_pointer::v1::client::zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1;
+use wayland_protocols::unstable::relative_pointer::v1::client::zwp_relative_pointer_v1::ZwpRelativePointerV1;
 use wayland_protocols::unstable::xdg_decoration::v1::client::zxdg_decoration_manager_v1::ZxdgDecorationManagerV1;
 use wayland_protocols::xdg_shell::client::xdg_surface::XdgSurface;
 use wayland_protocols::xdg_shell::client::xdg_toplevel::XdgToplevel;
 use wayland_protocols::xdg_shell::client::xdg_wm_base::XdgWmBase;
 
 use std::cell::RefCell;
-use std::ffi::c_void;
+use std::collections::VecDeque;
+use std::ffi::{c_void, CStr};
 use std::fs::File;
 use std::io::{self, Seek, SeekFrom, Write};
 use std::mem;
 use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::Path;
 use std::ptr;
 use std::rc::Rc;
 use std::slice;
 use std::sync::mpsc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 const KEY_XKB_OFFSET: u32 = 8;
+// How long `update_with_buffer_vsync` waits for a frame callback before falling back to the rate
+// limiter
+const FRAME_CALLBACK_TIMEOUT: Duration = Duration::from_millis(100);
 const KEY_MOUSE_BTN1: u32 = 272;
 const KEY_MOUSE_BTN2: u32 = 273;
 const KEY_MOUSE_BTN3: u32 = 274;
 
 type ToplevelResolution = Rc<RefCell<Option<(i32, i32)>>>;
 type ToplevelClosed = Rc<RefCell<bool>>;
+type ToplevelMaximized = Rc<RefCell<bool>>;
 
 // These functions are implemented in C in order to always have
 // optimizations on (`-O3`), allowing debug builds to run fast as well.
@@ -107,21 +133,32 @@ struct Buffer {
     fb_size: (i32, i32),
 }
 
+// Buffer and size of each frame of an animated cursor
+type AnimatedCursor = mouse_handler::AnimatedCursor<(Main<WlBuffer>, (i32, i32))>;
+
 struct BufferPool {
     pool: Vec<Buffer>,
     shm: Main<WlShm>,
     format: Format,
+    // Number of buffers to keep before waiting for the compositor to release one
+    buffer_count: usize,
 }
 
 impl BufferPool {
-    fn new(shm: Main<WlShm>, format: Format) -> Self {
+    fn new(shm: Main<WlShm>, format: Format, buffer_count: usize) -> Self {
         Self {
             pool: Vec::new(),
             shm,
             format,
+            buffer_count,
         }
     }
 
+    /// True when a buffer can be drawn into without growing the pool beyond `buffer_count`.
+    fn has_free_buffer(&self) -> bool {
+        self.pool.len() < self.buffer_count || self.pool.iter().any(|e| *e.buffer_state.borrow())
+    }
+
     fn create_shm_buffer(
         shm_pool: &Main<WlShmPool>,
         size: (i32, i32),
@@ -198,6 +235,8 @@ impl BufferPool {
 
 struct DisplayInfo {
     attached_display: Attached<WlDisplay>,
+    // Keeps the list of globals up to date, only used to count the outputs
+    globals: GlobalManager,
     surface: Main<WlSurface>,
     xdg_surface: Main<XdgSurface>,
     toplevel: Main<XdgToplevel>,
@@ -205,6 +244,15 @@ struct DisplayInfo {
     xdg_config: Rc<RefCell<Option<u32>>>,
     cursor: wayland_cursor::CursorTheme,
     cursor_surface: Main<WlSurface>,
+    // Frames set with `set_animated_cursor` and the pool their buffers are in
+    animated_cursor: Option<(AnimatedCursor, Main<WlShmPool>)>,
+    cursor_hotspot: (i32, i32),
+    pointer_constraints: Option<Main<ZwpPointerConstraintsV1>>,
+    relative_pointer_manager: Option<Main<ZwpRelativePointerManagerV1>>,
+    shortcuts_inhibit: Option<Main<ZwpKeyboardShortcutsInhibitManagerV1>>,
+    fractional_scale: Option<Main<WpFractionalScaleV1>>,
+    // The preferred scale of the surface in 120ths, updated by `fractional_scale`
+    preferred_scale: Rc<RefCell<u32>>,
     _display: Display,
     buf_pool: BufferPool,
 }
@@ -212,7 +260,12 @@ struct DisplayInfo {
 impl DisplayInfo {
     /// Accepts the size of the surface to be created, whether or not the alpha channel will be
     /// rendered, and whether or not server-side decorations will be used.
-    fn new(size: (i32, i32), alpha: bool, decorate: bool) -> Result<(Self, WaylandInput)> {
+    fn new(
+        size: (i32, i32),
+        alpha: bool,
+        decorate: bool,
+        buffer_count: usize,
+    ) -> Result<(Self, WaylandInput)> {
         // Get the wayland display
         let display = Display::connect_to_env().map_err(|e| {
             Error::WindowCreate(format!("Failed to connect to the Wayland display: {:?}", e))
@@ -251,7 +304,7 @@ impl DisplayInfo {
         };
 
         // Retrive shm buffer for writing
-        let mut buf_pool = BufferPool::new(shm.clone(), format);
+        let mut buf_pool = BufferPool::new(shm.clone(), format, buffer_count);
         let (mut tempfile, buffer) = buf_pool
             .get_buffer(size)
             .map_err(|e| Error::WindowCreate(format!("Failed to retrieve Buffer: {:?}", e)))?;
@@ -317,7 +370,7 @@ impl DisplayInfo {
 
         // Give the buffer to the surface and commit
         surface.attach(Some(buffer), 0, 0);
-        surface.damage(0, 0, i32::max_value(), i32::max_value());
+        surface.damage(0, 0, i32::MAX, i32::MAX);
         surface.commit();
 
         let xdg_config = Rc::new(RefCell::new(None));
@@ -335,10 +388,38 @@ impl DisplayInfo {
         let cursor = wayland_cursor::CursorTheme::load(16, &shm);
         let cursor_surface = compositor.create_surface();
 
+        // Only needed to confine the pointer, which isn't supported by every compositor
+        let pointer_constraints = globals.instantiate_exact::<ZwpPointerConstraintsV1>(1).ok();
+        let relative_pointer_manager = globals
+            .instantiate_exact::<ZwpRelativePointerManagerV1>(1)
+            .ok();
+        let shortcuts_inhibit = globals
+            .instantiate_exact::<ZwpKeyboardShortcutsInhibitManagerV1>(1)
+            .ok();
+
+        // Compositors without fractional scaling don't tell the preferred scale this way
+        let preferred_scale = Rc::new(RefCell::new(120));
+        let fractional_scale = globals
+            .instantiate_exact::<WpFractionalScaleManagerV1>(1)
+            .ok()
+            .map(|manager| {
+                let fractional_scale = manager.get_fractional_scale(&surface);
+                let preferred_scale = preferred_scale.clone();
+                fractional_scale.quick_assign(move |_, event, _| {
+                    let wp_fractional_scale_v1::Event::PreferredScale { scale } = event;
+                    *preferred_scale.borrow_mut() = scale;
+                });
+
+                // Destroying the manager leaves the objects created from it alone
+                manager.destroy();
+                fractional_scale
+            });
+
         Ok((
             Self {
                 _display: display,
                 attached_display,
+                globals,
                 surface,
                 xdg_surface,
                 toplevel: xdg_toplevel,
@@ -346,12 +427,28 @@ impl DisplayInfo {
                 xdg_config,
                 cursor,
                 cursor_surface,
+                animated_cursor: None,
+                cursor_hotspot: (0, 0),
+                pointer_constraints,
+                relative_pointer_manager,
+                shortcuts_inhibit,
+                fractional_scale,
+                preferred_scale,
                 buf_pool,
             },
             input_devices,
         ))
     }
 
+    /// Returns the number of `wl_output` globals, one for each connected monitor.
+    fn output_count(&self) -> usize {
+        self.globals
+            .list()
+            .iter()
+            .filter(|(_, interface, _)| interface == "wl_output")
+            .count()
+    }
+
     fn set_geometry(&self, pos: (i32, i32), size: (i32, i32)) {
         self.xdg_surface
             .set_window_geometry(pos.0, pos.1, size.0, size.1);
@@ -361,25 +458,200 @@ impl DisplayInfo {
         self.toplevel.set_title(title.to_owned());
     }
 
+    fn set_app_id(&self, app_id: &str) {
+        self.toplevel.set_app_id(app_id.to_owned());
+    }
+
+    fn unmap(&self) {
+        // Committing a null buffer unmaps the toplevel
+        self.surface.attach(None, 0, 0);
+        self.surface.commit();
+
+        if let Err(e) = self.event_queue.display().flush() {
+            if e.kind() != io::ErrorKind::WouldBlock {
+                eprintln!("Error while trying to flush the wayland socket: {:?}", e);
+            }
+        }
+    }
+
+    // Asks for a callback once the compositor wants the next frame, which applies to the next
+    // commit. The returned flag is set when it arrives.
+    fn request_frame(&self) -> Rc<RefCell<bool>> {
+        let done = Rc::new(RefCell::new(false));
+        let done_clone = done.clone();
+
+        self.surface.frame().quick_assign(move |_, event, _| {
+            if let wl_callback::Event::Done { .. } = event {
+                *done_clone.borrow_mut() = true;
+            }
+        });
+
+        done
+    }
+
     fn set_no_resize(&self, size: (i32, i32)) {
         self.toplevel.set_max_size(size.0, size.1);
         self.toplevel.set_min_size(size.0, size.1);
     }
 
+    // Confines the pointer to the surface, the compositor lifts it while the surface is unfocused
+    fn confine_pointer(&self, pointer: &WlPointer) -> Option<Main<ZwpConfinedPointerV1>> {
+        self.pointer_constraints.as_ref().map(|constraints| {
+            let confined =
+                constraints.confine_pointer(&self.surface, pointer, None, Lifetime::Persistent);
+            self.surface.commit();
+            confined
+        })
+    }
+
+    // Keeps the pointer in place, the compositor lifts it while the surface is unfocused
+    fn lock_pointer(&self, pointer: &WlPointer) -> Option<Main<ZwpLockedPointerV1>> {
+        self.pointer_constraints.as_ref().map(|constraints| {
+            let locked =
+                constraints.lock_pointer(&self.surface, pointer, None, Lifetime::Persistent);
+            self.surface.commit();
+            locked
+        })
+    }
+
+    // Adds the movement of the pointer to `motion`, which is also reported while it's locked
+    fn relative_pointer(
+        &self,
+        pointer: &WlPointer,
+        motion: Rc<RefCell<(f32, f32)>>,
+    ) -> Option<Main<ZwpRelativePointerV1>> {
+        self.relative_pointer_manager.as_ref().map(|manager| {
+            let relative = manager.get_relative_pointer(pointer);
+
+            relative.quick_assign(move |_, event, _| {
+                use wayland_protocols::unstable::relative_pointer::v1::client::zwp_relative_pointer_v1::Event;
+
+                if let Event::RelativeMotion { dx, dy, .. } = event {
+                    let mut motion = motion.borrow_mut();
+                    motion.0 += dx as f32;
+                    motion.1 += dy as f32;
+                }
+            });
+
+            relative
+        })
+    }
+
+    // Asks the compositor to pass its shortcuts to the surface, it only does so while focused
+    fn inhibit_shortcuts(&self, seat: &WlSeat) -> Option<Main<ZwpKeyboardShortcutsInhibitorV1>> {
+        self.shortcuts_inhibit.as_ref().map(|inhibit| {
+            let inhibitor = inhibit.inhibit_shortcuts(&self.surface, seat);
+            self.surface.commit();
+            inhibitor
+        })
+    }
+
     // Sets a specific cursor style
     fn update_cursor(&mut self, cursor: &str) -> std::result::Result<(), ()> {
-        let cursor = self.cursor.get_cursor(cursor);
-        if let Some(cursor) = cursor {
-            let img = &cursor[0];
-            self.cursor_surface.attach(Some(&*img), 0, 0);
-            self.cursor_surface.damage(0, 0, 32, 32);
-            self.cursor_surface.commit();
+        let cursor = self.cursor.get_cursor(cursor).ok_or(())?;
+        let img = &cursor[0];
+        let (hot_x, hot_y) = img.hotspot();
+        self.cursor_hotspot = (hot_x as i32, hot_y as i32);
+        self.cursor_surface.attach(Some(img), 0, 0);
+        self.cursor_surface.damage(0, 0, 32, 32);
+        self.cursor_surface.commit();
+        Ok(())
+    }
+
+    // Not every cursor theme has the newer cursors, the cursor is kept if both are missing
+    fn update_cursor_style(&mut self, cursor: CursorStyle) {
+        let _ = self
+            .update_cursor(Window::decode_cursor(cursor))
+            .or_else(|_| self.update_cursor(Window::decode_cursor(cursor.fallback())));
+    }
+
+    // Shows the animated cursor if there is one, otherwise the given style
+    fn restore_cursor(&mut self, cursor: CursorStyle) {
+        match self.animated_cursor {
+            Some((ref animated, _)) => {
+                let (buffer, size) = animated.current();
+                self.attach_cursor(buffer, *size);
+            }
+            None => self.update_cursor_style(cursor),
+        }
+    }
+
+    fn attach_cursor(&self, buffer: &WlBuffer, size: (i32, i32)) {
+        self.cursor_surface.attach(Some(buffer), 0, 0);
+        self.cursor_surface.damage(0, 0, size.0, size.1);
+        self.cursor_surface.commit();
+    }
+
+    // All frames are put in one pool, one after the other
+    fn set_animated_cursor(
+        &mut self,
+        frames: &[(&[u32], u32, u32)],
+        frame_duration: Duration,
+        hotspot: (i32, i32),
+    ) -> io::Result<()> {
+        let mut tempfile = tempfile::tempfile()?;
+        for &(buffer, width, height) in frames {
+            let pixels: Vec<u8> = buffer[..(width * height) as usize]
+                .iter()
+                .flat_map(|&pixel| buffer_helper::premultiply(pixel).to_ne_bytes())
+                .collect();
+            tempfile.write_all(&pixels)?;
         }
+        tempfile.flush()?;
+
+        let pool_size = tempfile.metadata()?.len() as i32;
+        let pool = self
+            .buf_pool
+            .shm
+            .create_pool(tempfile.as_raw_fd(), pool_size);
+
+        let mut offset = 0;
+        let buffers = frames
+            .iter()
+            .map(|&(_, width, height)| {
+                let size = (width as i32, height as i32);
+                let buffer =
+                    pool.create_buffer(offset, size.0, size.1, size.0 * 4, Format::Argb8888);
+                offset += size.0 * size.1 * 4;
+                (buffer, size)
+            })
+            .collect();
+
+        self.clear_animated_cursor();
+        self.animated_cursor = Some((AnimatedCursor::new(buffers, frame_duration), pool));
+        self.cursor_hotspot = hotspot;
         Ok(())
     }
 
+    fn clear_animated_cursor(&mut self) {
+        if let Some((animated, pool)) = self.animated_cursor.take() {
+            for (buffer, _) in animated.frames() {
+                buffer.destroy();
+            }
+            pool.destroy();
+        }
+
+        self.cursor_hotspot = (0, 0);
+    }
+
+    // Called once per update to show the next frame of the animated cursor
+    fn update_cursor_animation(&mut self) {
+        if let Some((ref mut animated, _)) = self.animated_cursor {
+            if let Some((buffer, size)) = animated.update() {
+                self.cursor_surface.attach(Some(buffer), 0, 0);
+                self.cursor_surface.damage(0, 0, size.0, size.1);
+                self.cursor_surface.commit();
+            }
+        }
+    }
+
     // Resizes when buffer is bigger or less
-    fn update_framebuffer(&mut self, buffer: &[u32], size: (i32, i32)) -> std::io::Result<()> {
+    fn update_framebuffer(
+        &mut self,
+        buffer: &[u32],
+        size: (i32, i32),
+        damage: Option<&[(i32, i32, i32, i32)]>,
+    ) -> std::io::Result<()> {
         let (mut fd, buf) = self.buf_pool.get_buffer(size)?;
 
         fd.seek(SeekFrom::Start(0))?;
@@ -387,7 +659,7 @@ impl DisplayInfo {
         let slice = unsafe {
             slice::from_raw_parts(
                 buffer[..].as_ptr() as *const u8,
-                buffer.len() * mem::size_of::<u32>(),
+                std::mem::size_of_val(buffer),
             )
         };
 
@@ -400,39 +672,83 @@ impl DisplayInfo {
         }
 
         self.surface.attach(Some(buf), 0, 0);
-        self.surface
-            .damage(0, 0, i32::max_value(), i32::max_value());
+        match damage {
+            Some(rects) => {
+                for &(x, y, width, height) in rects {
+                    self.surface.damage_buffer(x, y, width, height);
+                }
+            }
+            None => self.surface.damage(0, 0, i32::MAX, i32::MAX),
+        }
         self.surface.commit();
 
         Ok(())
     }
 
-    fn get_toplevel_info(&self) -> (ToplevelResolution, ToplevelClosed) {
+    fn get_toplevel_info(&self) -> (ToplevelResolution, ToplevelClosed, ToplevelMaximized) {
         let resolution = Rc::new(RefCell::new(None));
         let closed = Rc::new(RefCell::new(false));
+        let maximized = Rc::new(RefCell::new(false));
 
         let resolution_clone = resolution.clone();
         let closed_clone = closed.clone();
+        let maximized_clone = maximized.clone();
 
         self.toplevel.quick_assign(move |_, event, _| {
-            use wayland_protocols::xdg_shell::client::xdg_toplevel::Event;
+            use wayland_protocols::xdg_shell::client::xdg_toplevel::{Event, State};
 
-            if let Event::Configure { width, height, .. } = event {
+            if let Event::Configure {
+                width,
+                height,
+                states,
+            } = event
+            {
                 *resolution_clone.borrow_mut() = Some((width, height));
+                // The states are sent as an array of native endian u32
+                *maximized_clone.borrow_mut() = states.chunks_exact(4).any(|s| {
+                    u32::from_ne_bytes([s[0], s[1], s[2], s[3]]) == State::Maximized as u32
+                });
             } else if let Event::Close = event {
                 *closed_clone.borrow_mut() = true;
             }
         });
 
-        (resolution, closed)
+        (resolution, closed, maximized)
+    }
+}
+
+impl Drop for DisplayInfo {
+    fn drop(&mut self) {
+        // The server frees everything once the connection is closed, destroying the objects first
+        // tears the window down in the order the protocol expects
+        for buffer in self.buf_pool.pool.iter() {
+            buffer.buffer.destroy();
+            buffer.pool.destroy();
+        }
+
+        if let Some(fractional_scale) = &self.fractional_scale {
+            fractional_scale.destroy();
+        }
+
+        self.clear_animated_cursor();
+        self.cursor_surface.destroy();
+        self.toplevel.destroy();
+        self.xdg_surface.destroy();
+        self.surface.destroy();
+
+        let _ = self.event_queue.display().flush();
     }
 }
 
 struct WaylandInput {
     kb_events: mpsc::Receiver<wl_keyboard::Event>,
     pt_events: mpsc::Receiver<wl_pointer::Event>,
+    tc_events: mpsc::Receiver<wl_touch::Event>,
     _keyboard: Main<WlKeyboard>,
     pointer: Main<WlPointer>,
+    // Created once the seat says it has a touch screen
+    _touch: Rc<RefCell<Option<Main<WlTouch>>>>,
+    seat: Main<WlSeat>,
 }
 
 impl WaylandInput {
@@ -450,11 +766,33 @@ impl WaylandInput {
             pt_sender.send(event).unwrap();
         });
 
+        // Asking a seat without touch capability for a touch object is a protocol error
+        let (tc_sender, tc_receiver) = mpsc::sync_channel(1024);
+        let touch = Rc::new(RefCell::new(None));
+        let seat_touch = touch.clone();
+
+        seat.quick_assign(move |seat, event, _| {
+            if let wl_seat::Event::Capabilities { capabilities } = event {
+                let mut touch = seat_touch.borrow_mut();
+                if capabilities.contains(wl_seat::Capability::Touch) && touch.is_none() {
+                    let tc_sender = tc_sender.clone();
+                    let new_touch = seat.get_touch();
+                    new_touch.quick_assign(move |_, event, _| {
+                        tc_sender.send(event).unwrap();
+                    });
+                    *touch = Some(new_touch);
+                }
+            }
+        });
+
         Self {
             kb_events: kb_receiver,
             pt_events: pt_receiver,
+            tc_events: tc_receiver,
             _keyboard: keyboard,
             pointer,
+            _touch: touch,
+            seat: seat.clone(),
         }
     }
 
@@ -462,13 +800,21 @@ impl WaylandInput {
         &self.pointer
     }
 
-    fn iter_keyboard_events(&self) -> mpsc::TryIter<wl_keyboard::Event> {
+    fn get_seat(&self) -> &Main<WlSeat> {
+        &self.seat
+    }
+
+    fn iter_keyboard_events(&self) -> mpsc::TryIter<'_, wl_keyboard::Event> {
         self.kb_events.try_iter()
     }
 
-    fn iter_pointer_events(&self) -> mpsc::TryIter<wl_pointer::Event> {
+    fn iter_pointer_events(&self) -> mpsc::TryIter<'_, wl_pointer::Event> {
         self.pt_events.try_iter()
     }
+
+    fn iter_touch_events(&self) -> mpsc::TryIter<'_, wl_touch::Event> {
+        self.tc_events.try_iter()
+    }
 }
 
 pub struct Window {
@@ -479,7 +825,13 @@ pub struct Window {
 
     scale: i32,
     bg_color: u32,
+    // Drawn over the background color outside of the buffer when set
+    border_fill: Option<BorderFill>,
     scale_mode: ScaleMode,
+    buffer_size: (usize, usize), // size of the last buffer drawn
+    // Damage for the next present, and the buffer and window size of the last one
+    damage: Option<Vec<Rect>>,
+    presented_sizes: Option<((usize, usize), (i32, i32))>,
 
     mouse_x: f32,
     mouse_y: f32,
@@ -487,11 +839,36 @@ pub struct Window {
     scroll_y: f32,
     buttons: [bool; 8], // Linux kernel defines 8 mouse buttons
     prev_cursor: CursorStyle,
+    title: String,
 
     should_close: bool,
+    closed: bool,
+    close_reason: Option<CloseReason>,
+    auto_close: bool,
+    close_requested: bool,
     active: bool,
 
     key_handler: KeyHandler,
+    touch_handler: TouchHandler,
+    cursor_auto_hide: mouse_handler::CursorAutoHide,
+    scroll_accumulator: mouse_handler::ScrollAccumulator,
+    mouse_history: mouse_handler::MouseHistory,
+    present_callbacks: PresentCallbacks,
+    // Frame callbacks of the commits that have present callbacks, oldest first
+    present_frames: VecDeque<Rc<RefCell<bool>>>,
+    button_modifiers: mouse_handler::ButtonModifiers,
+    // Set once `poll_input` has started the input of the next update
+    input_polled: bool,
+    confined_pointer: Option<Main<ZwpConfinedPointerV1>>,
+    // Used instead of the confinement in the gameplay cursor mode
+    locked_pointer: Option<Main<ZwpLockedPointerV1>>,
+    relative_pointer: Option<Main<ZwpRelativePointerV1>>,
+    relative_motion: Rc<RefCell<(f32, f32)>>,
+    mouse_delta: mouse_handler::MouseDelta,
+    shortcuts_inhibitor: Option<Main<ZwpKeyboardShortcutsInhibitorV1>>,
+    // Repeat (delay, rate) in seconds as reported by the compositor
+    os_key_repeat: Option<(f32, f32)>,
+    use_os_key_repeat: bool,
 
     xkb_context: *mut xkb_ffi::xkb_context,
     xkb_keymap: *mut xkb_ffi::xkb_keymap,
@@ -500,17 +877,30 @@ pub struct Window {
     update_rate: UpdateRate,
     menu_counter: MenuHandle,
     menus: Vec<UnixMenu>,
+    context_menu: Option<(UnixMenu, i32, i32)>,
     input: WaylandInput,
     resizable: bool,
+    transparency: bool,
+    pixel_format: PixelFormat,
     // Temporary buffer
     buffer: Vec<u32>,
     // Resolution, closed
-    toplevel_info: (ToplevelResolution, ToplevelClosed),
+    toplevel_info: (ToplevelResolution, ToplevelClosed, ToplevelMaximized),
     pointer_visibility: bool,
+    // Serial of the last pointer enter event, needed to change the cursor
+    pointer_serial: Option<u32>,
 }
 
 impl Window {
     pub fn new(name: &str, width: usize, height: usize, opts: WindowOptions) -> Result<Self> {
+        // There is no EGL here, GL windows are left to X11
+        if opts.gl {
+            return Err(Error::Unsupported {
+                operation: "WindowOptions::gl",
+                platform: "Wayland",
+            });
+        }
+
         let scale: i32 = match opts.scale {
             // Relies on the fact that this is done by the server
             // https://docs.rs/winit/0.22.0/winit/dpi/index.html#how-is-the-scale-factor-calculated
@@ -524,20 +914,27 @@ impl Window {
             Scale::X32 => 32,
         };
 
-        let (display, input) = DisplayInfo::new(
+        let (mut display, input) = DisplayInfo::new(
             (width as i32 * scale, height as i32 * scale),
             opts.transparency,
             !opts.borderless || opts.none,
+            opts.buffer_count.clamp(2, 3) as usize,
         )?;
 
+        // Loaded up front so the hotspot is known before the pointer enters
+        display.update_cursor_style(CursorStyle::Arrow);
+
         if opts.title {
             display.set_title(name);
         }
+        if let Some(app_id) = opts.app_id {
+            display.set_app_id(app_id);
+        }
         if !opts.resize || opts.none {
             display.set_no_resize((width as i32 * scale, height as i32 * scale));
         }
 
-        let (resolution, closed) = display.get_toplevel_info();
+        let (resolution, closed, maximized) = display.get_toplevel_info();
 
         #[cfg(feature = "dlopen")]
         {
@@ -568,7 +965,11 @@ impl Window {
 
             scale,
             bg_color: 0,
+            border_fill: None,
             scale_mode: opts.scale_mode,
+            buffer_size: (width, height),
+            damage: None,
+            presented_sizes: None,
 
             mouse_x: 0.,
             mouse_y: 0.,
@@ -576,11 +977,32 @@ impl Window {
             scroll_y: 0.,
             buttons: [false; 8],
             prev_cursor: CursorStyle::Arrow,
+            title: name.to_owned(),
 
             should_close: false,
+            closed: false,
+            close_reason: None,
+            auto_close: true,
+            close_requested: false,
             active: false,
 
             key_handler: KeyHandler::new(),
+            touch_handler: TouchHandler::new(),
+            cursor_auto_hide: mouse_handler::CursorAutoHide::new(),
+            scroll_accumulator: mouse_handler::ScrollAccumulator::default(),
+            mouse_history: mouse_handler::MouseHistory::new(),
+            present_callbacks: PresentCallbacks::default(),
+            present_frames: VecDeque::new(),
+            button_modifiers: mouse_handler::ButtonModifiers::default(),
+            input_polled: false,
+            confined_pointer: None,
+            locked_pointer: None,
+            relative_pointer: None,
+            relative_motion: Rc::new(RefCell::new((0.0, 0.0))),
+            mouse_delta: mouse_handler::MouseDelta::default(),
+            shortcuts_inhibitor: None,
+            os_key_repeat: None,
+            use_os_key_repeat: false,
 
             xkb_context: context,
             xkb_keymap: ptr::null_mut(),
@@ -589,30 +1011,181 @@ impl Window {
             update_rate: UpdateRate::new(),
             menu_counter: MenuHandle(0),
             menus: Vec::new(),
+            context_menu: None,
             input,
             resizable: opts.resize && !opts.none,
+            transparency: opts.transparency,
+            pixel_format: opts.pixel_format,
             buffer: Vec::with_capacity(width * height * scale as usize * scale as usize),
-            toplevel_info: (resolution, closed),
+            toplevel_info: (resolution, closed, maximized),
             pointer_visibility: true,
+            pointer_serial: None,
         })
     }
 
     pub fn set_title(&mut self, title: &str) {
         self.display.set_title(title);
+        self.title = title.to_owned();
+    }
+
+    #[inline]
+    pub fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    pub fn set_document_edited(&mut self, _edited: bool) {
+        // xdg-shell has no document state, applications show it in the title
+    }
+
+    pub fn set_represented_file(&mut self, _path: &Path) {
+        // compositors don't show the file a window belongs to
     }
 
     pub fn set_background_color(&mut self, bg_color: u32) {
         self.bg_color = bg_color;
+        self.border_fill = None;
+    }
+
+    pub fn set_border_fill(&mut self, fill: BorderFill) {
+        self.border_fill = Some(fill);
     }
 
     pub fn set_cursor_visibility(&mut self, visibility: bool) {
+        self.cursor_auto_hide.set_visible(visibility);
+        self.show_cursor(visibility);
+    }
+
+    #[inline]
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_auto_hide.is_visible()
+    }
+
+    pub fn set_cursor_auto_hide(&mut self, timeout: Option<Duration>) {
+        if let Some(visible) = self.cursor_auto_hide.set_timeout(timeout) {
+            self.show_cursor(visible);
+        }
+    }
+
+    pub fn set_cursor_confined(&mut self, confined: bool) {
+        if confined {
+            // A locked pointer can't be confined as well, and it can't leave the window anyway
+            if self.confined_pointer.is_none() && self.locked_pointer.is_none() {
+                self.confined_pointer = self.display.confine_pointer(self.input.get_pointer());
+            }
+        } else if let Some(confined_pointer) = self.confined_pointer.take() {
+            confined_pointer.destroy();
+        }
+    }
+
+    pub fn set_gameplay_cursor_mode(&mut self, enabled: bool) {
+        if enabled == self.mouse_delta.is_enabled() {
+            return;
+        }
+
+        if enabled {
+            self.mouse_delta
+                .save_cursor(self.is_cursor_visible(), self.confined_pointer.is_some());
+            self.set_cursor_visibility(false);
+        }
+
+        if let Some(locked_pointer) = self.locked_pointer.take() {
+            locked_pointer.destroy();
+        }
+        if let Some(relative_pointer) = self.relative_pointer.take() {
+            relative_pointer.destroy();
+        }
+
+        if enabled {
+            self.relative_pointer = self
+                .display
+                .relative_pointer(self.input.get_pointer(), self.relative_motion.clone());
+        }
+
+        // Without relative motion the movement is taken from the position, which only works while
+        // the pointer is free to move within the window
+        if enabled && self.relative_pointer.is_some() {
+            self.set_cursor_confined(false);
+            self.locked_pointer = self.display.lock_pointer(self.input.get_pointer());
+        } else if enabled {
+            self.set_cursor_confined(true);
+        } else {
+            let (visible, confined) = self.mouse_delta.saved_cursor();
+            self.set_cursor_visibility(visible);
+            self.set_cursor_confined(confined);
+        }
+
+        *self.relative_motion.borrow_mut() = (0.0, 0.0);
+        self.mouse_delta.set_enabled(enabled);
+    }
+
+    #[inline]
+    pub fn get_mouse_delta(&self) -> (f32, f32) {
+        self.mouse_delta.get()
+    }
+
+    pub fn set_keyboard_grab(&mut self, grab: bool) {
+        if grab {
+            if self.shortcuts_inhibitor.is_none() {
+                self.shortcuts_inhibitor = self.display.inhibit_shortcuts(self.input.get_seat());
+            }
+        } else if let Some(inhibitor) = self.shortcuts_inhibitor.take() {
+            inhibitor.destroy();
+        }
+    }
+
+    fn show_cursor(&mut self, visibility: bool) {
         self.pointer_visibility = visibility;
+
+        if let Some(serial) = self.pointer_serial {
+            if visibility {
+                self.input.get_pointer().set_cursor(
+                    serial,
+                    Some(&self.display.cursor_surface),
+                    self.display.cursor_hotspot.0,
+                    self.display.cursor_hotspot.1,
+                );
+            } else {
+                self.input.get_pointer().set_cursor(serial, None, 0, 0);
+            }
+        }
     }
 
     pub fn is_open(&self) -> bool {
         !self.should_close
     }
 
+    pub fn is_occluded(&self) -> bool {
+        // Wayland doesn't tell clients if their surface is visible
+        false
+    }
+
+    pub fn close(&mut self) {
+        if !self.closed {
+            self.display.unmap();
+            self.closed = true;
+        }
+        self.should_close = true;
+        self.close_reason
+            .get_or_insert(CloseReason::ProgrammaticClose);
+    }
+
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        self.close_reason
+    }
+
+    pub fn set_close_requested_handling(&mut self, auto: bool) {
+        self.auto_close = auto;
+    }
+
+    #[inline]
+    pub fn set_default_accelerators(&mut self, _enabled: bool) {
+        // minifb doesn't handle any keys itself, shortcuts of the compositor always apply
+    }
+
+    pub fn close_requested(&self) -> bool {
+        self.close_requested
+    }
+
     pub fn get_window_handle(&self) -> *mut c_void {
         self.display.surface.as_ref().c_ptr() as *mut c_void
     }
@@ -621,10 +1194,158 @@ impl Window {
         (self.width as usize, self.height as usize)
     }
 
+    pub fn get_unscaled_size(&self) -> (usize, usize) {
+        // The window is already measured in screen pixels
+        (self.width as usize, self.height as usize)
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        *self.toplevel_info.2.borrow()
+    }
+
+    pub fn set_maximized(&mut self, maximized: bool) {
+        if maximized {
+            self.display.toplevel.set_maximized();
+        } else {
+            self.display.toplevel.unset_maximized();
+        }
+    }
+
+    pub fn get_buffer_scale(&self) -> (f32, f32) {
+        buffer_helper::buffer_scale(
+            self.scale_mode,
+            self.width as usize,
+            self.height as usize,
+            self.buffer_size.0,
+            self.buffer_size.1,
+        )
+    }
+
+    pub fn get_content_scale(&self) -> f32 {
+        *self.display.preferred_scale.borrow() as f32 / 120.0
+    }
+
+    #[inline]
+    pub fn get_native_pixel_format(&self) -> Option<PixelFormat> {
+        // The shm buffers are 0xAARRGGBB, the alpha is only used for transparent windows
+        Some(if self.transparency {
+            PixelFormat::Argb8888
+        } else {
+            PixelFormat::Xrgb8888
+        })
+    }
+
+    pub fn is_transparency_active(&self) -> bool {
+        // Wayland compositors always blend surfaces with an alpha channel
+        self.transparency
+    }
+
+    pub fn get_monitors(&self) -> Vec<Monitor> {
+        // Outputs aren't tracked
+        Vec::new()
+    }
+
+    pub fn set_gamma_ramp(&mut self, _ramp: &GammaRamp) -> Result<()> {
+        Err(Error::Unsupported {
+            operation: "set_gamma_ramp",
+            platform: "Wayland",
+        })
+    }
+
+    pub fn get_gamma_ramp(&self) -> Option<GammaRamp> {
+        None
+    }
+
+    pub fn display_count(&self) -> usize {
+        self.display.output_count()
+    }
+
+    pub fn get_keyboard_layout(&self) -> Option<String> {
+        if self.xkb_keymap.is_null() || self.xkb_state.is_null() {
+            return None;
+        }
+
+        unsafe {
+            let index = ffi_dispatch!(
+                XKBH,
+                xkb_state_serialize_layout,
+                self.xkb_state,
+                xkb_ffi::XKB_STATE_LAYOUT_EFFECTIVE
+            );
+            let name = ffi_dispatch!(XKBH, xkb_keymap_layout_get_name, self.xkb_keymap, index);
+
+            if name.is_null() {
+                None
+            } else {
+                Some(CStr::from_ptr(name).to_string_lossy().into_owned())
+            }
+        }
+    }
+
+    pub fn current_monitor(&self) -> Option<Monitor> {
+        // Outputs aren't tracked and Wayland doesn't let clients know where their surfaces are
+        None
+    }
+
+    pub fn moved_to_monitor(&self) -> bool {
+        false
+    }
+
+    pub fn set_size(&mut self, width: usize, height: usize) {
+        if self.closed || width == 0 || height == 0 {
+            return;
+        }
+
+        // A window that can't be resized has its size pinned by the min and max sizes
+        if !self.resizable {
+            self.display.set_no_resize((width as i32, height as i32));
+        }
+
+        // Clients pick their own size on Wayland, the next frame is simply drawn at the new size
+        self.width = width as i32;
+        self.height = height as i32;
+    }
+
+    pub fn set_visible(&mut self, _visible: bool) {
+        // xdg-shell toplevels can't be hidden without being destroyed
+    }
+
+    pub fn set_always_on_bottom(&mut self, _enabled: bool) {
+        // xdg-shell has no way to ask for a stacking order
+    }
+
+    pub fn set_window_level(&mut self, _level: WindowLevel) {
+        // xdg-shell has no way to ask for a stacking order
+    }
+
+    pub fn set_skip_taskbar(&mut self, _skip: bool) {
+        // xdg-shell leaves the taskbar to the compositor
+    }
+
+    pub fn set_caption_buttons(&mut self, _minimize: bool, _maximize: bool, _close: bool) {
+        // the decorations are drawn by the compositor
+    }
+
+    pub fn set_shadow(&mut self, _enabled: bool) {
+        // shadows are drawn by the compositor
+    }
+
+    pub fn beep(&self) {
+        // Wayland has no core protocol for the system bell
+    }
+
+    pub fn set_aspect_ratio(&mut self, _ratio: Option<(u32, u32)>) {
+        // xdg-shell has no way to ask the compositor for an aspect ratio
+    }
+
     pub fn get_keys(&self) -> Vec<Key> {
         self.key_handler.get_keys()
     }
 
+    pub fn keys_down(&self) -> &[Key] {
+        self.key_handler.keys_down()
+    }
+
     pub fn get_keys_pressed(&self, repeat: KeyRepeat) -> Vec<Key> {
         self.key_handler.get_keys_pressed(repeat)
     }
@@ -633,6 +1354,14 @@ impl Window {
         self.key_handler.get_keys_released()
     }
 
+    pub fn get_keys_just_pressed(&self) -> Vec<Key> {
+        self.key_handler.get_keys_just_pressed()
+    }
+
+    pub fn get_text_input(&self) -> String {
+        self.key_handler.get_text_input()
+    }
+
     pub fn get_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
         mouse_handler::get_pos(
             mode,
@@ -644,6 +1373,10 @@ impl Window {
         )
     }
 
+    pub fn get_mouse_history(&self) -> Vec<(f32, f32, std::time::Duration)> {
+        self.mouse_history.get(self.scale as f32)
+    }
+
     pub fn get_mouse_down(&self, button: MouseButton) -> bool {
         match button {
             MouseButton::Left => self.buttons[0],
@@ -652,6 +1385,28 @@ impl Window {
         }
     }
 
+    pub fn get_mouse_down_with_modifiers(&self, button: MouseButton) -> Option<Modifiers> {
+        if !self.get_mouse_down(button) {
+            return None;
+        }
+
+        // Buttons that were already down when the window got focus have nothing captured
+        Some(
+            self.button_modifiers
+                .get(button)
+                .unwrap_or_else(|| self.key_handler.modifiers()),
+        )
+    }
+
+    pub fn get_pen(&self) -> Option<PenState> {
+        // The tablet protocol isn't supported yet
+        None
+    }
+
+    pub fn get_touch_points(&self) -> Vec<TouchPoint> {
+        self.touch_handler.get_touch_points(self.scale as f32)
+    }
+
     pub fn get_unscaled_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
         mouse_handler::get_pos(
             mode,
@@ -663,6 +1418,11 @@ impl Window {
         )
     }
 
+    pub fn get_mouse_pos_on_screen(&self) -> Option<(f32, f32)> {
+        // Wayland doesn't expose global coordinates to clients
+        None
+    }
+
     pub fn get_scroll_wheel(&self) -> Option<(f32, f32)> {
         if self.scroll_x.abs() > 0.0 || self.scroll_y.abs() > 0.0 {
             Some((self.scroll_x, self.scroll_y))
@@ -671,10 +1431,28 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn get_scroll_accumulated(&self) -> (f32, f32) {
+        self.scroll_accumulator.get()
+    }
+
+    #[inline]
+    pub fn reset_scroll_accumulator(&mut self) {
+        self.scroll_accumulator.reset()
+    }
+
     pub fn is_key_down(&self, key: Key) -> bool {
         self.key_handler.is_key_down(key)
     }
 
+    pub fn get_input_timestamp(&self) -> Option<std::time::Duration> {
+        self.key_handler.get_event_time()
+    }
+
+    pub fn get_key_timestamp(&self, key: Key) -> Option<std::time::Duration> {
+        self.key_handler.get_key_time(key)
+    }
+
     pub fn set_position(&mut self, x: isize, y: isize) {
         self.display
             .set_geometry((x as i32, y as i32), (self.width, self.height));
@@ -687,18 +1465,52 @@ impl Window {
         (x as isize, y as isize)
     }
 
+    pub fn get_outer_position(&self) -> (isize, isize) {
+        // Wayland doesn't tell clients where their windows are
+        self.get_position()
+    }
+
+    pub fn get_outer_size(&self) -> (usize, usize) {
+        // The compositor draws the decorations and doesn't tell their size
+        self.get_size()
+    }
+
     pub fn set_rate(&mut self, rate: Option<Duration>) {
         self.update_rate.set_rate(rate);
     }
 
     pub fn set_key_repeat_rate(&mut self, rate: f32) {
-        self.key_handler.set_key_repeat_delay(rate);
+        self.key_handler.set_key_repeat_rate(rate);
+    }
+
+    #[inline]
+    pub fn set_key_map(&mut self, key_map: KeyMap) {
+        self.key_handler.set_key_map(key_map)
+    }
+
+    #[inline]
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.key_handler.is_action_down(action)
+    }
+
+    pub fn set_key_repeat_for(&mut self, keys: &[Key], delay: f32, rate: f32) {
+        self.key_handler.set_key_repeat_for(keys, delay, rate)
     }
 
     pub fn set_key_repeat_delay(&mut self, delay: f32) {
         self.key_handler.set_key_repeat_delay(delay);
     }
 
+    pub fn use_os_key_repeat(&mut self) {
+        // The compositor may only send the repeat info after the keyboard has been set up, so
+        // remember the request and apply the values whenever they arrive.
+        self.use_os_key_repeat = true;
+        if let Some((delay, rate)) = self.os_key_repeat {
+            self.key_handler.set_key_repeat_delay(delay);
+            self.key_handler.set_key_repeat_rate(rate);
+        }
+    }
+
     pub fn set_input_callback(&mut self, callback: Box<dyn InputCallback>) {
         self.key_handler.set_input_callback(callback);
     }
@@ -707,6 +1519,10 @@ impl Window {
         self.key_handler.is_key_pressed(key, repeat)
     }
 
+    pub fn is_key_repeat(&self, key: Key) -> bool {
+        self.key_handler.is_key_repeat(key)
+    }
+
     pub fn is_key_released(&self, key: Key) -> bool {
         !self.key_handler.is_key_released(key)
     }
@@ -715,10 +1531,29 @@ impl Window {
         self.update_rate.update();
     }
 
+    #[inline]
+    pub fn set_target_fps(&mut self, fps: u32) {
+        self.update_rate.set_target_fps(fps);
+    }
+
+    #[inline]
+    pub fn get_frame_time(&self) -> std::time::Duration {
+        self.update_rate.frame_time()
+    }
+
     pub fn is_active(&self) -> bool {
         self.active
     }
 
+    #[inline]
+    pub fn get_primary_selection(&self) -> Option<String> {
+        // The primary selection protocols aren't supported yet
+        None
+    }
+
+    #[inline]
+    pub fn set_primary_selection(&mut self, _text: &str) {}
+
     fn next_menu_handle(&mut self) -> MenuHandle {
         let handle = self.menu_counter;
         self.menu_counter.0 += 1;
@@ -744,6 +1579,29 @@ impl Window {
         self.menus.retain(|menu| menu.handle != handle);
     }
 
+    pub fn update_menu(&mut self, handle: MenuHandle, menu: &Menu) {
+        if let Some(existing) = self.menus.iter_mut().find(|m| m.handle == handle) {
+            *existing = menu.internal.clone();
+            existing.handle = handle;
+        }
+    }
+
+    pub fn show_context_menu(&mut self, menu: &Menu, x: i32, y: i32) {
+        self.context_menu = Some((menu.internal.clone(), x, y));
+    }
+
+    pub fn take_posix_context_menu(&mut self) -> Option<(UnixMenu, i32, i32)> {
+        self.context_menu.take()
+    }
+
+    pub fn sync(&mut self) {
+        if let Err(e) = self.display.event_queue.display().flush() {
+            if e.kind() != io::ErrorKind::WouldBlock {
+                eprintln!("Error while trying to flush the wayland socket: {:?}", e);
+            }
+        }
+    }
+
     pub fn is_menu_pressed(&mut self) -> Option<usize> {
         //FIXME
         unimplemented!()
@@ -764,30 +1622,47 @@ impl Window {
                         "Error while trying to read from the wayland socket: {:?}",
                         e
                     );
+                    self.display_lost();
+                    return;
                 }
             }
         }
 
-        self.display
+        if let Err(e) = self
+            .display
             .event_queue
             .dispatch_pending(&mut (), |_, _, _| {})
-            .map_err(|e| Error::WindowCreate(format!("Event dispatch failed: {:?}", e)))
-            .unwrap();
+        {
+            eprintln!("Event dispatch failed: {:?}", e);
+            self.display_lost();
+        }
     }
 
-    pub fn update(&mut self) {
-        self.try_dispatch_events();
+    /// Closes the window after the connection to the compositor broke, nothing can be sent
+    /// over it anymore so the surfaces are left alone.
+    fn display_lost(&mut self) {
+        self.closed = true;
+        self.should_close = true;
+        self.close_reason = Some(CloseReason::DisplayError);
+    }
 
-        if let Some(resize) = (*self.toplevel_info.0.borrow_mut()).take() {
-            // Don't try to resize to 0x0
-            if self.resizable && resize != (0, 0) {
-                self.width = resize.0;
-                self.height = resize.1;
-            }
+    pub fn poll_input(&mut self) {
+        if self.closed {
+            return;
         }
-        if *self.toplevel_info.1.borrow() {
-            self.should_close = true;
+
+        self.try_dispatch_events();
+        if !self.input_polled {
+            self.touch_handler.update();
         }
+        self.process_input_events();
+        self.input_polled = true;
+    }
+
+    fn process_input_events(&mut self) {
+        // Outputs are added and removed as globals when monitors are connected or disconnected
+        let count = self.display.output_count();
+        self.key_handler.set_display_count(count);
 
         for event in self.input.iter_keyboard_events() {
             use wayland_client::protocol::wl_keyboard::Event;
@@ -797,22 +1672,41 @@ impl Window {
                     let keymap = Self::handle_keymap(self.xkb_context, format, fd, size).unwrap();
                     self.xkb_keymap = keymap;
                     self.xkb_state = unsafe { ffi_dispatch!(XKBH, xkb_state_new, keymap) };
+                    let layout = self.get_keyboard_layout();
+                    self.key_handler.set_keyboard_layout(layout);
                 }
-                Event::Enter { .. } => {
+                Event::Enter { keys, .. } => {
                     self.active = true;
+
+                    // Keys that are already down when the window gets focus, for example the
+                    // one that launched it, count as held rather than pressed
+                    if !self.xkb_state.is_null() {
+                        self.key_handler.begin_held_keys();
+                        for key in keys.chunks_exact(4) {
+                            let key = u32::from_ne_bytes([key[0], key[1], key[2], key[3]]);
+                            Self::handle_key(
+                                self.xkb_state,
+                                key + KEY_XKB_OFFSET,
+                                wl_keyboard::KeyState::Pressed,
+                                &mut self.key_handler,
+                            );
+                        }
+                        self.key_handler.end_held_keys();
+                    }
                 }
                 Event::Leave { .. } => {
                     self.active = false;
                 }
-                Event::Key { key, state, .. } => {
-                    if !self.xkb_state.is_null() {
-                        Self::handle_key(
-                            self.xkb_state,
-                            key + KEY_XKB_OFFSET,
-                            state,
-                            &mut self.key_handler,
-                        );
-                    }
+                Event::Key {
+                    key, state, time, ..
+                } if !self.xkb_state.is_null() => {
+                    self.key_handler.set_event_time_ms(time);
+                    Self::handle_key(
+                        self.xkb_state,
+                        key + KEY_XKB_OFFSET,
+                        state,
+                        &mut self.key_handler,
+                    );
                 }
                 Event::Modifiers {
                     mods_depressed,
@@ -820,29 +1714,51 @@ impl Window {
                     mods_locked,
                     group,
                     ..
-                } => {
-                    if !self.xkb_state.is_null() {
-                        unsafe {
-                            ffi_dispatch!(
-                                XKBH,
-                                xkb_state_update_mask,
-                                self.xkb_state,
-                                mods_depressed,
-                                mods_latched,
-                                mods_locked,
-                                0,
-                                0,
-                                group
-                            )
-                        };
+                } if !self.xkb_state.is_null() => {
+                    unsafe {
+                        ffi_dispatch!(
+                            XKBH,
+                            xkb_state_update_mask,
+                            self.xkb_state,
+                            mods_depressed,
+                            mods_latched,
+                            mods_locked,
+                            0,
+                            0,
+                            group
+                        )
+                    };
+                    let layout = self.get_keyboard_layout();
+                    self.key_handler.set_keyboard_layout(layout);
+                }
+                Event::RepeatInfo { rate, delay } => {
+                    // A rate of zero means that the compositor has disabled key repeat
+                    let rate = if rate > 0 {
+                        1.0 / rate as f32
+                    } else {
+                        f32::INFINITY
+                    };
+                    let delay = delay as f32 / 1000.0;
+                    self.os_key_repeat = Some((delay, rate));
+                    if self.use_os_key_repeat {
+                        self.key_handler.set_key_repeat_delay(delay);
+                        self.key_handler.set_key_repeat_rate(rate);
                     }
                 }
                 _ => {}
             }
         }
 
-        self.scroll_x = 0.;
-        self.scroll_y = 0.;
+        // Keep the scrolling of earlier `poll_input` calls until the update
+        if !self.input_polled {
+            self.scroll_x = 0.;
+            self.scroll_y = 0.;
+            self.mouse_delta.reset();
+            self.mouse_history.reset();
+        }
+
+        let (dx, dy) = self.relative_motion.replace((0.0, 0.0));
+        self.mouse_delta.add(dx, dy);
 
         for event in self.input.iter_pointer_events() {
             use wayland_client::protocol::wl_pointer::Event;
@@ -856,80 +1772,110 @@ impl Window {
                 } => {
                     self.mouse_x = surface_x as f32;
                     self.mouse_y = surface_y as f32;
+                    self.pointer_serial = Some(serial);
 
                     self.input.get_pointer().set_cursor(
                         serial,
                         Some(&self.display.cursor_surface),
-                        0,
-                        0,
+                        self.display.cursor_hotspot.0,
+                        self.display.cursor_hotspot.1,
                     );
-                    self.display
-                        .update_cursor(Self::decode_cursor(self.prev_cursor))
-                        .unwrap();
+                    self.display.restore_cursor(self.prev_cursor);
 
                     if self.pointer_visibility {
                         self.input.get_pointer().set_cursor(
                             serial,
                             Some(&self.display.cursor_surface),
-                            0,
-                            0,
+                            self.display.cursor_hotspot.0,
+                            self.display.cursor_hotspot.1,
                         );
                     } else {
                         self.input.get_pointer().set_cursor(serial, None, 0, 0);
                     }
                 }
                 Event::Motion {
+                    time,
                     surface_x,
                     surface_y,
-                    ..
                 } => {
+                    self.key_handler.set_event_time_ms(time);
+
+                    if self.mouse_delta.is_enabled() && self.relative_pointer.is_none() {
+                        self.mouse_delta.add(
+                            surface_x as f32 - self.mouse_x,
+                            surface_y as f32 - self.mouse_y,
+                        );
+                    }
+
                     self.mouse_x = surface_x as f32;
                     self.mouse_y = surface_y as f32;
+                    self.mouse_history.push(
+                        self.mouse_x,
+                        self.mouse_y,
+                        self.key_handler.get_event_time(),
+                    );
                 }
                 Event::Button {
                     button,
                     state,
                     serial,
-                    ..
+                    time,
                 } => {
                     use wayland_client::protocol::wl_pointer::ButtonState;
 
+                    self.key_handler.set_event_time_ms(time);
+
                     let pressed = state == ButtonState::Pressed;
 
-                    match button {
-                        // Left mouse button
-                        KEY_MOUSE_BTN1 => self.buttons[0] = pressed,
-                        // Right mouse button
-                        KEY_MOUSE_BTN2 => self.buttons[1] = pressed,
-                        // Middle mouse button
-                        KEY_MOUSE_BTN3 => self.buttons[2] = pressed,
+                    let button = match button {
+                        KEY_MOUSE_BTN1 => Some((0, MouseButton::Left)),
+                        KEY_MOUSE_BTN2 => Some((1, MouseButton::Right)),
+                        KEY_MOUSE_BTN3 => Some((2, MouseButton::Middle)),
                         _ => {
                             // TODO: handle more mouse buttons (see: linux/input-event-codes.h from
                             // the Linux kernel)
+                            None
                         }
+                    };
+
+                    if let Some((index, button)) = button {
+                        self.buttons[index] = pressed;
+                        self.button_modifiers.set_button_state(
+                            button,
+                            pressed,
+                            self.key_handler.modifiers(),
+                        );
                     }
 
                     if self.pointer_visibility {
                         self.input.get_pointer().set_cursor(
                             serial,
                             Some(&self.display.cursor_surface),
-                            0,
-                            0,
+                            self.display.cursor_hotspot.0,
+                            self.display.cursor_hotspot.1,
                         );
                     } else {
                         self.input.get_pointer().set_cursor(serial, None, 0, 0);
                     }
                 }
-                Event::Axis { axis, value, .. } => {
+                Event::Axis { axis, value, time } => {
                     use wayland_client::protocol::wl_pointer::Axis;
 
+                    self.key_handler.set_event_time_ms(time);
+
                     match axis {
-                        Axis::VerticalScroll => self.scroll_y = value as f32,
-                        Axis::HorizontalScroll => self.scroll_x = value as f32,
+                        Axis::VerticalScroll => {
+                            self.scroll_y = value as f32;
+                            self.scroll_accumulator.add(0.0, value as f32);
+                        }
+                        Axis::HorizontalScroll => {
+                            self.scroll_x = value as f32;
+                            self.scroll_accumulator.add(value as f32, 0.0);
+                        }
                         _ => {}
                     }
                 }
-                Event::Frame {} => {
+                Event::Frame => {
                     // TODO
                 }
                 Event::AxisSource { axis_source } => {
@@ -950,12 +1896,14 @@ impl Window {
                     // TODO
                 }
                 Event::Leave { serial, .. } => {
+                    self.pointer_serial = None;
+
                     if self.pointer_visibility {
                         self.input.get_pointer().set_cursor(
                             serial,
                             Some(&self.display.cursor_surface),
-                            0,
-                            0,
+                            self.display.cursor_hotspot.0,
+                            self.display.cursor_hotspot.1,
                         );
                     } else {
                         self.input.get_pointer().set_cursor(serial, None, 0, 0);
@@ -965,7 +1913,90 @@ impl Window {
             }
         }
 
+        for event in self.input.iter_touch_events() {
+            use wayland_client::protocol::wl_touch::Event;
+
+            match event {
+                Event::Down { id, x, y, .. } => {
+                    self.touch_handler
+                        .set_touch(id as u64, x as f32, y as f32, TouchPhase::Begin);
+                }
+                Event::Motion { id, x, y, .. } => {
+                    self.touch_handler
+                        .set_touch(id as u64, x as f32, y as f32, TouchPhase::Move);
+                }
+                Event::Up { id, .. } => {
+                    // Up has no position, the touch ends where it last was
+                    if let Some(point) = self
+                        .touch_handler
+                        .get_touch_points(1.0)
+                        .into_iter()
+                        .find(|point| point.id == id as u64)
+                    {
+                        self.touch_handler
+                            .set_touch(point.id, point.x, point.y, TouchPhase::End);
+                    }
+                }
+                Event::Cancel => {
+                    // The compositor took over the touches, for example for a gesture
+                    for point in self.touch_handler.get_touch_points(1.0) {
+                        self.touch_handler
+                            .set_touch(point.id, point.x, point.y, TouchPhase::End);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn update(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        self.try_dispatch_events();
+
+        // Frame callbacks arrive in the order of the commits
+        while let Some(done) = self.present_frames.front() {
+            if !*done.borrow() {
+                break;
+            }
+
+            self.present_frames.pop_front();
+            self.present_callbacks.frame_shown();
+        }
+
+        if let Some(resize) = (*self.toplevel_info.0.borrow_mut()).take() {
+            // Don't try to resize to 0x0
+            if self.resizable && resize != (0, 0) {
+                self.width = resize.0;
+                self.height = resize.1;
+            }
+        }
+        self.close_requested = false;
+        if self.toplevel_info.1.replace(false) {
+            if self.auto_close {
+                self.should_close = true;
+                self.close_reason = Some(CloseReason::UserRequested);
+            } else {
+                self.close_requested = true;
+            }
+        }
+
+        if !self.input_polled {
+            self.touch_handler.update();
+        }
+        self.process_input_events();
+        self.input_polled = false;
+
         self.key_handler.update();
+        self.key_handler.update_just_pressed();
+
+        if let Some(visible) = self.cursor_auto_hide.update(self.mouse_x, self.mouse_y) {
+            self.show_cursor(visible);
+        }
+
+        self.display.update_cursor_animation();
     }
 
     fn handle_key(
@@ -975,6 +2006,8 @@ impl Window {
         key_handler: &mut KeyHandler,
     ) {
         let is_down = state == wl_keyboard::KeyState::Pressed;
+        key_handler.set_scancode_state(key, is_down);
+
         let key_xkb = unsafe { ffi_dispatch!(XKBH, xkb_state_key_get_one_sym, keymap_state, key) };
         if key_xkb != 0 {
             use super::xkb_keysyms as key;
@@ -983,9 +2016,7 @@ impl Window {
                 // Taken from GLFW
                 let code_point = unsafe { ffi_dispatch!(XKBH, xkb_keysym_to_utf32, key_xkb) };
                 if !(code_point < 32 || (code_point > 126 && code_point < 160)) {
-                    if let Some(ref mut callback) = key_handler.key_callback {
-                        callback.add_char(code_point);
-                    }
+                    key_handler.add_char(code_point);
                 }
             }
 
@@ -1168,18 +2199,58 @@ impl Window {
             CursorStyle::ResizeLeftRight => "sb_h_double_arrow",
             CursorStyle::ResizeUpDown => "sb_v_double_arrow",
             CursorStyle::ResizeAll => "diamond_cross",
+            CursorStyle::Wait => "watch",
+            CursorStyle::Progress => "left_ptr_watch",
+            CursorStyle::Help => "question_arrow",
+            CursorStyle::NotAllowed => "crossed_circle",
+            CursorStyle::ZoomIn => "zoom-in",
+            CursorStyle::ZoomOut => "zoom-out",
         }
     }
 
     pub fn set_cursor_style(&mut self, cursor: CursorStyle) {
-        if self.prev_cursor != cursor {
-            self.display
-                .update_cursor(Self::decode_cursor(cursor))
-                .unwrap();
+        if self.prev_cursor != cursor || self.display.animated_cursor.is_some() {
+            self.display.clear_animated_cursor();
+            self.display.update_cursor_style(cursor);
+            self.refresh_pointer_cursor();
             self.prev_cursor = cursor;
         }
     }
 
+    #[inline]
+    pub fn get_cursor_style(&self) -> CursorStyle {
+        self.prev_cursor
+    }
+
+    #[inline]
+    pub fn get_cursor_hotspot(&self) -> (i32, i32) {
+        self.display.cursor_hotspot
+    }
+
+    pub fn set_animated_cursor(
+        &mut self,
+        frames: &[(&[u32], u32, u32)],
+        frame_duration: Duration,
+        hot_x: u32,
+        hot_y: u32,
+    ) {
+        let hotspot = (hot_x as i32, hot_y as i32);
+        if self
+            .display
+            .set_animated_cursor(frames, frame_duration, hotspot)
+            .is_ok()
+        {
+            self.display.restore_cursor(self.prev_cursor);
+            self.refresh_pointer_cursor();
+        }
+    }
+
+    // The hotspot is given with the surface, so the pointer has to be told about a new one
+    fn refresh_pointer_cursor(&mut self) {
+        let visibility = self.pointer_visibility;
+        self.show_cursor(visibility);
+    }
+
     pub fn update_with_buffer_stride(
         &mut self,
         buffer: &[u32],
@@ -1187,18 +2258,140 @@ impl Window {
         buf_height: usize,
         buf_stride: usize,
     ) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+
         buffer_helper::check_buffer_size(buf_width, buf_height, buf_width, buffer)?;
 
-        unsafe { self.scale_buffer(buffer, buf_width, buf_height, buf_stride) };
+        let buffer = buffer_helper::to_argb(buffer, self.pixel_format);
+        unsafe { self.scale_buffer(&buffer, buf_width, buf_height, buf_stride) };
+        self.buffer_size = (buf_width, buf_height);
+
+        // Gives up after a while so a compositor that holds on to the buffers of a hidden window
+        // doesn't block the application, the pool grows instead
+        self.wait_until(|display| display.buf_pool.has_free_buffer());
 
+        if self.present_callbacks.has_pending() {
+            self.present_frames.push_back(self.display.request_frame());
+            self.present_callbacks.frame_presented();
+        }
+
+        let damage = self.take_damage();
         self.display
-            .update_framebuffer(&self.buffer[..], (self.width as i32, self.height as i32))
+            .update_framebuffer(
+                &self.buffer[..],
+                (self.width, self.height),
+                damage.as_deref(),
+            )
             .map_err(|e| Error::UpdateFailed(format!("Error updating framebuffer: {:?}", e)))?;
+        self.presented_sizes = Some((self.buffer_size, (self.width, self.height)));
         self.update();
 
         Ok(())
     }
 
+    pub fn set_present_callback(&mut self, callback: Box<dyn FnOnce()>) {
+        self.present_callbacks.add(callback);
+    }
+
+    pub fn cancel_present_callbacks(&mut self) {
+        self.present_callbacks.clear_pending();
+    }
+
+    pub fn set_damage(&mut self, rects: &[Rect]) {
+        // Frames that weren't presented, for example while the window was occluded, add up
+        self.damage
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(rects);
+    }
+
+    pub fn gl_get_proc_address(&self, _name: &str) -> *const c_void {
+        ptr::null()
+    }
+
+    pub fn gl_swap_buffers(&mut self) {}
+
+    // Takes the damage set for this present in window pixels, the whole window is damaged when
+    // no damage was set or the buffer or window size changed since the last present
+    fn take_damage(&mut self) -> Option<Vec<(i32, i32, i32, i32)>> {
+        let rects = self.damage.take()?;
+        if self.presented_sizes != Some((self.buffer_size, (self.width, self.height))) {
+            return None;
+        }
+
+        Some(
+            rects
+                .into_iter()
+                .filter_map(|rect| {
+                    buffer_helper::damage_rect(
+                        self.scale_mode,
+                        self.width as usize,
+                        self.height as usize,
+                        self.buffer_size.0,
+                        self.buffer_size.1,
+                        rect,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    pub fn update_with_buffer_vsync(
+        &mut self,
+        buffer: &[u32],
+        buf_width: usize,
+        buf_height: usize,
+        buf_stride: usize,
+    ) -> Result<()> {
+        let frame_done = self.display.request_frame();
+        self.update_with_buffer_stride(buffer, buf_width, buf_height, buf_stride)?;
+
+        if self.wait_for_frame(&frame_done) {
+            self.update_rate.update_unthrottled();
+        } else {
+            self.update_rate.update();
+        }
+
+        Ok(())
+    }
+
+    // Waits for the frame callback of the last commit. Compositors stop sending these while the
+    // surface is hidden, so this gives up after a while and returns false.
+    fn wait_for_frame(&mut self, frame_done: &RefCell<bool>) -> bool {
+        self.wait_until(|_| *frame_done.borrow())
+    }
+
+    /// Dispatches events until `done` returns true, returns false if that didn't happen within
+    /// `FRAME_CALLBACK_TIMEOUT`.
+    fn wait_until<F: Fn(&DisplayInfo) -> bool>(&mut self, done: F) -> bool {
+        let deadline = Instant::now() + FRAME_CALLBACK_TIMEOUT;
+        let fd = self.display.event_queue.display().get_connection_fd();
+
+        loop {
+            self.try_dispatch_events();
+
+            if done(&self.display) {
+                return true;
+            }
+
+            let now = Instant::now();
+            if self.closed || now >= deadline {
+                return false;
+            }
+
+            let mut poll_fd = libc::pollfd {
+                fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let timeout = (deadline - now).as_millis() as libc::c_int;
+            if unsafe { libc::poll(&mut poll_fd, 1, timeout.max(1)) } <= 0 {
+                return false;
+            }
+        }
+    }
+
     unsafe fn scale_buffer(
         &mut self,
         buffer: &[u32],
@@ -1260,6 +2453,23 @@ impl Window {
                 );
             }
         }
+
+        if let Some(fill) = &self.border_fill {
+            let image = buffer_helper::image_rect(
+                self.scale_mode,
+                self.width as usize,
+                self.height as usize,
+                buf_width,
+                buf_height,
+            );
+            buffer_helper::fill_border(
+                &mut self.buffer,
+                self.width as usize,
+                self.height as usize,
+                image,
+                fill,
+            );
+        }
     }
 }
 
@@ -1279,6 +2489,27 @@ unsafe impl raw_window_handle::HasRawWindowHandle for Window {
     }
 }
 
+#[cfg(feature = "rwh_06")]
+impl Window {
+    pub fn raw_window_handle_06(&self) -> Option<raw_window_handle_06::RawWindowHandle> {
+        let surface = self.display.surface.as_ref().c_ptr() as *mut c_void;
+        let handle = raw_window_handle_06::WaylandWindowHandle::new(ptr::NonNull::new(surface)?);
+        Some(raw_window_handle_06::RawWindowHandle::Wayland(handle))
+    }
+
+    pub fn raw_display_handle_06(&self) -> Option<raw_window_handle_06::RawDisplayHandle> {
+        let display = self
+            .display
+            .attached_display
+            .clone()
+            .detach()
+            .as_ref()
+            .c_ptr() as *mut c_void;
+        let handle = raw_window_handle_06::WaylandDisplayHandle::new(ptr::NonNull::new(display)?);
+        Some(raw_window_handle_06::RawDisplayHandle::Wayland(handle))
+    }
+}
+
 impl Drop for Window {
     fn drop(&mut self) {
         unsafe {
@@ -1,26 +1,39 @@
 use crate::key_handler::KeyHandler;
 use crate::rate::UpdateRate;
+use crate::touch_handler::TouchHandler;
 use crate::{
-    InputCallback, Key, KeyRepeat, MouseButton, MouseMode, Scale, ScaleMode, WindowOptions,
+    BorderFill, InputCallback, Key, KeyMap, KeyRepeat, MouseButton, MouseMode, PenState,
+    PixelFormat, Scale, ScaleMode, TouchPhase, TouchPoint, WindowOptions,
 };
+use x11_dl::glx;
 use x11_dl::keysym::*;
 use x11_dl::xcursor;
+use x11_dl::xinput2;
 use x11_dl::xlib;
+use x11_dl::xrandr;
 
 use crate::error::Error;
+use crate::Modifiers;
+use crate::Rect;
 use crate::Result;
-use crate::{CursorStyle, MenuHandle, UnixMenu};
+use crate::WindowLevel;
+use crate::{CloseReason, CursorStyle, GammaRamp, MenuHandle, Monitor, UnixMenu};
 
 use std::convert::TryFrom;
 use std::ffi::{c_void, CStr, CString};
 use std::mem;
 use std::os::raw;
 use std::os::raw::{c_char, c_int, c_long, c_uchar, c_uint, c_ulong};
+use std::path::Path;
 use std::ptr;
+use std::slice;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use crate::buffer_helper;
 use crate::icon::Icon;
 use crate::mouse_handler;
+use crate::present::PresentCallbacks;
 
 use super::common::Menu;
 use x11_dl::xlib::{
@@ -33,6 +46,12 @@ use x11_dl::xlib::{
 const Button6: c_uint = xlib::Button5 + 1;
 const Button7: c_uint = xlib::Button5 + 2;
 
+// NOTE: the x11-dl crate does not define XkbUseCoreKbd either
+const XKB_USE_CORE_KBD: c_uint = 0x0100;
+
+// How long to wait for the owner of a selection to hand over its contents
+const SELECTION_TIMEOUT: Duration = Duration::from_millis(500);
+
 // These functions are implemented in C in order to always have
 // optimizations on (`-O3`), allowing debug builds to run fast as well.
 extern "C" {
@@ -89,6 +108,40 @@ struct MwmHints {
     status: c_ulong,
 }
 
+// Fields of `MwmHints` that are set, and the bits of the functions and decorations fields
+const MWM_HINTS_FUNCTIONS: c_ulong = 1 << 0;
+const MWM_HINTS_DECORATIONS: c_ulong = 1 << 1;
+
+const MWM_FUNC_RESIZE: c_ulong = 1 << 1;
+const MWM_FUNC_MOVE: c_ulong = 1 << 2;
+const MWM_FUNC_MINIMIZE: c_ulong = 1 << 3;
+const MWM_FUNC_MAXIMIZE: c_ulong = 1 << 4;
+const MWM_FUNC_CLOSE: c_ulong = 1 << 5;
+
+const MWM_DECOR_BORDER: c_ulong = 1 << 1;
+const MWM_DECOR_RESIZEH: c_ulong = 1 << 2;
+const MWM_DECOR_TITLE: c_ulong = 1 << 3;
+const MWM_DECOR_MENU: c_ulong = 1 << 4;
+const MWM_DECOR_MINIMIZE: c_ulong = 1 << 5;
+const MWM_DECOR_MAXIMIZE: c_ulong = 1 << 6;
+
+/// The GLX context of a window created with `WindowOptions::gl`
+struct GlContext {
+    glx: glx::Glx,
+    context: glx::GLXContext,
+}
+
+// Valuator number and range
+type Valuator = (c_int, f64, f64);
+
+/// Where the pen values are found in the valuators of a tablet
+#[derive(Clone, Copy)]
+struct PenValuators {
+    pressure: Valuator,
+    tilt_x: Option<Valuator>,
+    tilt_y: Option<Valuator>,
+}
+
 struct DisplayInfo {
     lib: x11_dl::xlib::Xlib,
     display: *mut xlib::Display,
@@ -96,13 +149,46 @@ struct DisplayInfo {
     visual: *mut xlib::Visual,
     gc: xlib::GC,
     depth: i32,
+    // The pixel layout of the visual and where its channels go when it isn't 0xAARRGGBB, see
+    // `channel_shifts`
+    native_format: Option<PixelFormat>,
+    channel_shifts: Option<[u32; 4]>,
     screen_width: usize,
     screen_height: usize,
     _context: xlib::XContext,
     cursor_lib: x11_dl::xcursor::Xcursor,
-    cursors: [xlib::Cursor; 8],
+    cursors: [xlib::Cursor; 14],
+    cursor_hotspots: [(i32, i32); 14],
     keyb_ext: bool,
+    xkb_event: c_int,
+    // XInput2 is only used for sub-pixel mouse motion and touch, so it's fine if it's missing
+    xinput2: Option<xinput2::XInput2>,
+    xinput2_opcode: c_int,
+    xinput2_touch: bool, // touch events need XInput 2.2
+    // RandR is only used to find the monitors and to hear about new ones
+    xrandr: Option<xrandr::Xrandr>,
+    xrandr_event: c_int,
     wm_delete_window: xlib::Atom,
+    abs_pressure: xlib::Atom,
+    abs_tilt_x: xlib::Atom,
+    abs_tilt_y: xlib::Atom,
+    utf8_string: xlib::Atom,
+    targets: xlib::Atom,
+    selection_property: xlib::Atom,
+    net_wm_state: xlib::Atom,
+    net_wm_state_maximized_horz: xlib::Atom,
+    net_wm_state_maximized_vert: xlib::Atom,
+    net_wm_state_below: xlib::Atom,
+    net_wm_state_above: xlib::Atom,
+    net_wm_state_skip_taskbar: xlib::Atom,
+    net_wm_state_skip_pager: xlib::Atom,
+    compton_shadow: xlib::Atom,
+    net_frame_extents: xlib::Atom,
+    net_wm_window_type: xlib::Atom,
+    net_wm_window_type_normal: xlib::Atom,
+    net_wm_window_type_desktop: xlib::Atom,
+    net_wm_window_type_dialog: xlib::Atom,
+    xkb_rules_names: xlib::Atom,
 }
 
 impl DisplayInfo {
@@ -158,10 +244,32 @@ impl DisplayInfo {
                 depth = vinfo.depth;
             } else {
                 screen = (lib.XDefaultScreen)(display);
-                visual = (lib.XDefaultVisual)(display, screen);
-                depth = (lib.XDefaultDepth)(display, screen);
+                let default_visual = (lib.XDefaultVisual)(display, screen);
+                let default_depth = (lib.XDefaultDepth)(display, screen);
+
+                // Prefer a visual in the layout of the buffer so the pixels don't have to be
+                // moved around on every update
+                let matching = if Self::channel_shifts(default_visual).is_some() {
+                    Self::match_rgb_visual(&lib, display, screen)
+                } else {
+                    None
+                };
+
+                match matching {
+                    Some(vinfo) => {
+                        visual = vinfo.visual;
+                        depth = vinfo.depth;
+                    }
+                    None => {
+                        visual = default_visual;
+                        depth = default_depth;
+                    }
+                }
             }
 
+            let channel_shifts = Self::channel_shifts(visual);
+            let native_format = Self::native_format(visual, depth);
+
             let gc = (lib.XDefaultGC)(display, screen);
 
             let screen_width = usize::try_from((lib.XDisplayWidth)(display, screen))
@@ -180,18 +288,146 @@ impl DisplayInfo {
                 visual,
                 gc,
                 depth,
+                native_format,
+                channel_shifts,
                 screen_width,
                 screen_height,
                 _context: context,
                 cursor_lib,
                 // the following are determined later...
-                cursors: [0; 8],
+                cursors: [0; 14],
+                cursor_hotspots: [(0, 0); 14],
                 keyb_ext: false,
+                xkb_event: 0,
+                xinput2: None,
+                xinput2_opcode: 0,
+                xinput2_touch: false,
+                xrandr: None,
+                xrandr_event: 0,
                 wm_delete_window: 0,
+                abs_pressure: 0,
+                abs_tilt_x: 0,
+                abs_tilt_y: 0,
+                utf8_string: 0,
+                targets: 0,
+                selection_property: 0,
+                net_wm_state: 0,
+                net_wm_state_maximized_horz: 0,
+                net_wm_state_maximized_vert: 0,
+                net_wm_state_below: 0,
+                net_wm_state_above: 0,
+                net_wm_state_skip_taskbar: 0,
+                net_wm_state_skip_pager: 0,
+                compton_shadow: 0,
+                net_frame_extents: 0,
+                net_wm_window_type: 0,
+                net_wm_window_type_normal: 0,
+                net_wm_window_type_desktop: 0,
+                net_wm_window_type_dialog: 0,
+                xkb_rules_names: 0,
             })
         }
     }
 
+    // Where each channel of a 0xAARRGGBB pixel has to go for the visual, None if it's already in
+    // that layout or its channels aren't 8 bits each
+    /// Switches to a double buffered visual that GL can render to, the returned info is needed to
+    /// create the context and has to be freed with `XFree`.
+    unsafe fn use_gl_visual(&mut self, glx: &glx::Glx) -> Result<*mut xlib::XVisualInfo> {
+        let mut attributes = [
+            glx::GLX_RGBA,
+            glx::GLX_DOUBLEBUFFER,
+            glx::GLX_DEPTH_SIZE,
+            24,
+            0,
+        ];
+        let vinfo = (glx.glXChooseVisual)(self.display, self.screen, attributes.as_mut_ptr());
+        if vinfo.is_null() {
+            return Err(Error::WindowCreate(
+                "glXChooseVisual found no usable visual".to_owned(),
+            ));
+        }
+
+        self.visual = (*vinfo).visual;
+        self.depth = (*vinfo).depth;
+        self.channel_shifts = Self::channel_shifts(self.visual);
+        self.native_format = Self::native_format(self.visual, self.depth);
+
+        Ok(vinfo)
+    }
+
+    unsafe fn channel_shifts(visual: *mut xlib::Visual) -> Option<[u32; 4]> {
+        let masks = [
+            (*visual).red_mask,
+            (*visual).green_mask,
+            (*visual).blue_mask,
+        ];
+
+        if masks == [0xff_0000, 0xff00, 0xff] {
+            return None;
+        }
+
+        let mut shifts = [0; 3];
+        for (shift, &mask) in shifts.iter_mut().zip(masks.iter()) {
+            *shift = mask.trailing_zeros();
+            if *shift > 24 || mask >> *shift != 0xff {
+                return None;
+            }
+        }
+
+        // The alpha goes in whichever byte is left
+        let alpha = [24, 16, 8, 0]
+            .iter()
+            .copied()
+            .find(|s| !shifts.contains(s))?;
+
+        Some([alpha, shifts[0], shifts[1], shifts[2]])
+    }
+
+    unsafe fn native_format(visual: *mut xlib::Visual, depth: i32) -> Option<PixelFormat> {
+        match Self::channel_shifts(visual) {
+            None if (*visual).red_mask == 0xff_0000 => Some(if depth == 32 {
+                PixelFormat::Argb8888
+            } else {
+                PixelFormat::Xrgb8888
+            }),
+            Some([0, 8, 16, 24]) => Some(PixelFormat::Bgra8888),
+            _ => None,
+        }
+    }
+
+    // Looks for a TrueColor visual with the channels in 0x00RRGGBB order
+    unsafe fn match_rgb_visual(
+        lib: &xlib::Xlib,
+        display: *mut xlib::Display,
+        screen: i32,
+    ) -> Option<xlib::XVisualInfo> {
+        let mut template: xlib::XVisualInfo = mem::zeroed();
+        template.screen = screen;
+        template.depth = 24;
+        template.class = xlib::TrueColor;
+        template.red_mask = 0xff_0000;
+        template.green_mask = 0xff00;
+        template.blue_mask = 0xff;
+
+        let mask = xlib::VisualScreenMask
+            | xlib::VisualDepthMask
+            | xlib::VisualClassMask
+            | xlib::VisualRedMaskMask
+            | xlib::VisualGreenMaskMask
+            | xlib::VisualBlueMaskMask;
+
+        let mut count = 0;
+        let infos = (lib.XGetVisualInfo)(display, mask, &mut template, &mut count);
+        if infos.is_null() {
+            return None;
+        }
+
+        let vinfo = if count > 0 { Some(*infos) } else { None };
+        (lib.XFree)(infos as *mut _);
+        vinfo
+    }
+
     fn check_formats(&mut self) -> Result<()> {
         // We only support 32-bit right now
 
@@ -239,31 +475,532 @@ impl DisplayInfo {
             ) != xlib::False
             {
                 self.keyb_ext = true;
+                self.xkb_event = event;
+            }
+
+            let name = CString::new("XInputExtension").expect("static data");
+            if (self.lib.XQueryExtension)(
+                self.display,
+                name.as_ptr(),
+                &mut opcode,
+                &mut event,
+                &mut error,
+            ) != xlib::False
+            {
+                if let Ok(lib) = xinput2::XInput2::open() {
+                    let mut major: c_int = 2;
+                    let mut minor: c_int = 2;
+
+                    // The server answers with the highest version it supports up to 2.2
+                    if (lib.XIQueryVersion)(self.display, &mut major, &mut minor)
+                        == xlib::Success as c_int
+                    {
+                        self.xinput2 = Some(lib);
+                        self.xinput2_opcode = opcode;
+                        self.xinput2_touch = major > 2 || (major == 2 && minor >= 2);
+                    }
+                }
+            }
+
+            if let Ok(lib) = xrandr::Xrandr::open() {
+                if (lib.XRRQueryExtension)(self.display, &mut event, &mut error) != xlib::False {
+                    self.xrandr = Some(lib);
+                    self.xrandr_event = event;
+                }
             }
         }
 
         Ok(())
     }
 
+    /// Returns the monitors, one for each active RandR CRTC.
+    unsafe fn get_monitors(&self) -> Vec<Monitor> {
+        self.get_crtcs()
+            .into_iter()
+            .map(|(_, monitor)| monitor)
+            .collect()
+    }
+
+    /// Returns the active RandR CRTCs and the monitors they drive.
+    unsafe fn get_crtcs(&self) -> Vec<(xrandr::RRCrtc, Monitor)> {
+        let mut monitors = Vec::new();
+
+        let lib = match &self.xrandr {
+            Some(lib) => lib,
+            None => return monitors,
+        };
+
+        let root = (self.lib.XDefaultRootWindow)(self.display);
+        let resources = (lib.XRRGetScreenResourcesCurrent)(self.display, root);
+        if resources.is_null() {
+            return monitors;
+        }
+
+        let crtcs = raw_slice((*resources).crtcs, (*resources).ncrtc);
+        let modes = raw_slice((*resources).modes, (*resources).nmode);
+
+        for &crtc in crtcs {
+            let info = (lib.XRRGetCrtcInfo)(self.display, resources, crtc);
+            if info.is_null() {
+                continue;
+            }
+
+            // CRTCs without a mode or an output aren't driving a monitor
+            if (*info).mode != 0 && (*info).noutput > 0 {
+                let output = (lib.XRRGetOutputInfo)(self.display, resources, *(*info).outputs);
+                let name = if output.is_null() {
+                    String::new()
+                } else {
+                    let bytes = raw_slice((*output).name as *const u8, (*output).nameLen as c_int);
+                    let name = String::from_utf8_lossy(bytes).into_owned();
+                    (lib.XRRFreeOutputInfo)(output);
+                    name
+                };
+
+                let refresh_rate = modes
+                    .iter()
+                    .find(|mode| mode.id == (*info).mode)
+                    .filter(|mode| mode.hTotal != 0 && mode.vTotal != 0)
+                    .map(|mode| mode.dotClock as f32 / (mode.hTotal as f32 * mode.vTotal as f32));
+
+                monitors.push((
+                    crtc,
+                    Monitor {
+                        name,
+                        x: (*info).x as isize,
+                        y: (*info).y as isize,
+                        width: (*info).width as usize,
+                        height: (*info).height as usize,
+                        refresh_rate,
+                    },
+                ));
+            }
+
+            (lib.XRRFreeCrtcInfo)(info);
+        }
+
+        (lib.XRRFreeScreenResources)(resources);
+
+        monitors
+    }
+
+    /// Asks for XInput2 motion events, which unlike core events have sub-pixel positions, and
+    /// touch events.
+    unsafe fn select_xinput2_events(&mut self, handle: xlib::Window) {
+        if let Some(lib) = &self.xinput2 {
+            let mut mask = [0u8; 3];
+            xinput2::XISetMask(&mut mask, xinput2::XI_Motion);
+
+            if self.xinput2_touch {
+                xinput2::XISetMask(&mut mask, xinput2::XI_TouchBegin);
+                xinput2::XISetMask(&mut mask, xinput2::XI_TouchUpdate);
+                xinput2::XISetMask(&mut mask, xinput2::XI_TouchEnd);
+            }
+
+            let mut event_mask = xinput2::XIEventMask {
+                deviceid: xinput2::XIAllMasterDevices,
+                mask_len: mask.len() as c_int,
+                mask: mask.as_mut_ptr(),
+            };
+
+            (lib.XISelectEvents)(self.display, handle, &mut event_mask, 1);
+        }
+    }
+
     fn init_cursors(&mut self) {
-        self.cursors[0] = self.load_cursor("arrow");
-        self.cursors[1] = self.load_cursor("xterm");
-        self.cursors[2] = self.load_cursor("crosshair");
-        self.cursors[3] = self.load_cursor("hand2");
-        self.cursors[4] = self.load_cursor("hand2");
-        self.cursors[5] = self.load_cursor("sb_h_double_arrow");
-        self.cursors[6] = self.load_cursor("sb_v_double_arrow");
-        self.cursors[7] = self.load_cursor("diamond_cross");
+        self.load_cursor(0, "arrow");
+        self.load_cursor(1, "xterm");
+        self.load_cursor(2, "crosshair");
+        self.load_cursor(3, "hand2");
+        self.load_cursor(4, "hand2");
+        self.load_cursor(5, "sb_h_double_arrow");
+        self.load_cursor(6, "sb_v_double_arrow");
+        self.load_cursor(7, "diamond_cross");
+        self.load_cursor(8, "watch");
+        self.load_cursor(9, "left_ptr_watch");
+        self.load_cursor(10, "question_arrow");
+        self.load_cursor(11, "crossed_circle");
+        self.load_cursor(12, "zoom-in");
+        self.load_cursor(13, "zoom-out");
+
+        // Not every cursor theme has the newer cursors
+        let newer = [
+            CursorStyle::Wait,
+            CursorStyle::Progress,
+            CursorStyle::Help,
+            CursorStyle::NotAllowed,
+            CursorStyle::ZoomIn,
+            CursorStyle::ZoomOut,
+        ];
+
+        for style in newer.iter() {
+            if self.cursors[*style as usize] == 0 {
+                self.cursors[*style as usize] = self.cursors[style.fallback() as usize];
+                self.cursor_hotspots[*style as usize] =
+                    self.cursor_hotspots[style.fallback() as usize];
+            }
+        }
     }
 
-    fn load_cursor(&mut self, name: &'static str) -> xlib::Cursor {
+    fn load_cursor(&mut self, index: usize, name: &'static str) {
         let name = CString::new(name).expect("static data");
 
-        unsafe { (self.cursor_lib.XcursorLibraryLoadCursor)(self.display, name.as_ptr()) }
+        unsafe {
+            self.cursors[index] =
+                (self.cursor_lib.XcursorLibraryLoadCursor)(self.display, name.as_ptr());
+
+            // The server doesn't tell the hotspot of a cursor, so it's read from the theme's image
+            let image = (self.cursor_lib.XcursorLibraryLoadImage)(
+                name.as_ptr(),
+                (self.cursor_lib.XcursorGetTheme)(self.display),
+                (self.cursor_lib.XcursorGetDefaultSize)(self.display),
+            );
+            if !image.is_null() {
+                self.cursor_hotspots[index] = ((*image).xhot as i32, (*image).yhot as i32);
+                (self.cursor_lib.XcursorImageDestroy)(image);
+            }
+        }
     }
 
     fn init_atoms(&mut self) {
         self.wm_delete_window = self.intern_atom("WM_DELETE_WINDOW", false);
+        self.utf8_string = self.intern_atom("UTF8_STRING", false);
+        // Valuator labels used by tablet drivers, these don't exist without a tablet
+        self.abs_pressure = self.intern_atom("Abs Pressure", true);
+        self.abs_tilt_x = self.intern_atom("Abs Tilt X", true);
+        self.abs_tilt_y = self.intern_atom("Abs Tilt Y", true);
+        self.targets = self.intern_atom("TARGETS", false);
+        self.selection_property = self.intern_atom("MINIFB_SELECTION", false);
+        self.net_wm_state = self.intern_atom("_NET_WM_STATE", false);
+        self.net_wm_state_maximized_horz = self.intern_atom("_NET_WM_STATE_MAXIMIZED_HORZ", false);
+        self.net_wm_state_maximized_vert = self.intern_atom("_NET_WM_STATE_MAXIMIZED_VERT", false);
+        self.net_wm_state_below = self.intern_atom("_NET_WM_STATE_BELOW", false);
+        self.net_wm_state_above = self.intern_atom("_NET_WM_STATE_ABOVE", false);
+        self.net_wm_state_skip_taskbar = self.intern_atom("_NET_WM_STATE_SKIP_TASKBAR", false);
+        self.net_wm_state_skip_pager = self.intern_atom("_NET_WM_STATE_SKIP_PAGER", false);
+        // Not a standard hint, only picom and compton look at it
+        self.compton_shadow = self.intern_atom("_COMPTON_SHADOW", false);
+        self.net_frame_extents = self.intern_atom("_NET_FRAME_EXTENTS", false);
+        self.net_wm_window_type = self.intern_atom("_NET_WM_WINDOW_TYPE", false);
+        self.net_wm_window_type_normal = self.intern_atom("_NET_WM_WINDOW_TYPE_NORMAL", false);
+        self.net_wm_window_type_desktop = self.intern_atom("_NET_WM_WINDOW_TYPE_DESKTOP", false);
+        self.net_wm_window_type_dialog = self.intern_atom("_NET_WM_WINDOW_TYPE_DIALOG", false);
+        // Set on the root window by the X server or setxkbmap
+        self.xkb_rules_names = self.intern_atom("_XKB_RULES_NAMES", true);
+    }
+
+    /// Returns the active keyboard layout as named in the XKB rules, for example "us".
+    unsafe fn get_keyboard_layout(&self) -> Option<String> {
+        if !self.keyb_ext || self.xkb_rules_names == 0 {
+            return None;
+        }
+
+        let mut state: xlib::XkbStateRec = mem::zeroed();
+        if (self.lib.XkbGetState)(self.display, XKB_USE_CORE_KBD, &mut state)
+            != xlib::Success as c_int
+        {
+            return None;
+        }
+
+        let mut actual_type: xlib::Atom = 0;
+        let mut actual_format: c_int = 0;
+        let mut item_count: c_ulong = 0;
+        let mut bytes_after: c_ulong = 0;
+        let mut data: *mut c_uchar = ptr::null_mut();
+
+        let status = (self.lib.XGetWindowProperty)(
+            self.display,
+            (self.lib.XDefaultRootWindow)(self.display),
+            self.xkb_rules_names,
+            0,
+            1024,
+            xlib::False,
+            xlib::XA_STRING,
+            &mut actual_type,
+            &mut actual_format,
+            &mut item_count,
+            &mut bytes_after,
+            &mut data,
+        );
+
+        if status != xlib::Success as c_int || data.is_null() {
+            return None;
+        }
+
+        // The rules, model, layouts, variants and options separated by nul bytes, where the
+        // layouts are a comma separated list with one layout per group
+        let layout = if actual_format == 8 {
+            raw_slice(data, item_count as c_int)
+                .split(|&b| b == 0)
+                .nth(2)
+                .and_then(|layouts| layouts.split(|&b| b == b',').nth(state.group as usize))
+                .filter(|layout| !layout.is_empty())
+                .map(|layout| String::from_utf8_lossy(layout).into_owned())
+        } else {
+            None
+        };
+
+        (self.lib.XFree)(data as *mut c_void);
+
+        layout
+    }
+
+    /// Returns the `_NET_WM_STATE` atoms the window manager has set on a window.
+    unsafe fn get_wm_state(&self, handle: xlib::Window) -> Vec<xlib::Atom> {
+        let mut actual_type: xlib::Atom = 0;
+        let mut actual_format: c_int = 0;
+        let mut item_count: c_ulong = 0;
+        let mut bytes_after: c_ulong = 0;
+        let mut data: *mut c_uchar = ptr::null_mut();
+
+        let status = (self.lib.XGetWindowProperty)(
+            self.display,
+            handle,
+            self.net_wm_state,
+            0,
+            1024,
+            xlib::False,
+            xlib::XA_ATOM,
+            &mut actual_type,
+            &mut actual_format,
+            &mut item_count,
+            &mut bytes_after,
+            &mut data,
+        );
+
+        if status != xlib::Success as c_int || data.is_null() {
+            return Vec::new();
+        }
+
+        // Format 32 properties are returned as an array of longs
+        let state = if actual_format == 32 {
+            raw_slice(data as *const xlib::Atom, item_count as c_int).to_vec()
+        } else {
+            Vec::new()
+        };
+
+        (self.lib.XFree)(data as *mut c_void);
+
+        state
+    }
+
+    /// Returns the size of the decorations around a window as left, right, top and bottom. These
+    /// are all 0 when the window manager doesn't decorate the window or doesn't report it.
+    unsafe fn get_frame_extents(&self, handle: xlib::Window) -> [c_long; 4] {
+        let mut actual_type: xlib::Atom = 0;
+        let mut actual_format: c_int = 0;
+        let mut item_count: c_ulong = 0;
+        let mut bytes_after: c_ulong = 0;
+        let mut data: *mut c_uchar = ptr::null_mut();
+
+        let status = (self.lib.XGetWindowProperty)(
+            self.display,
+            handle,
+            self.net_frame_extents,
+            0,
+            4,
+            xlib::False,
+            xlib::XA_CARDINAL,
+            &mut actual_type,
+            &mut actual_format,
+            &mut item_count,
+            &mut bytes_after,
+            &mut data,
+        );
+
+        if status != xlib::Success as c_int || data.is_null() {
+            return [0; 4];
+        }
+
+        let mut extents = [0; 4];
+        if actual_format == 32 && item_count == 4 {
+            extents.copy_from_slice(raw_slice(data as *const c_long, 4));
+        }
+
+        (self.lib.XFree)(data as *mut c_void);
+
+        extents
+    }
+
+    /// Asks the window manager to add or remove up to two `_NET_WM_STATE` atoms on a mapped
+    /// window, pass 0 for `second` to only change one.
+    unsafe fn send_wm_state(
+        &self,
+        handle: xlib::Window,
+        add: bool,
+        first: xlib::Atom,
+        second: xlib::Atom,
+    ) {
+        let mut ev: xlib::XEvent = mem::zeroed();
+        ev.client_message.type_ = xlib::ClientMessage;
+        ev.client_message.window = handle;
+        ev.client_message.message_type = self.net_wm_state;
+        ev.client_message.format = 32;
+        // _NET_WM_STATE_ADD is 1 and _NET_WM_STATE_REMOVE is 0
+        ev.client_message.data.set_long(0, add as c_long);
+        ev.client_message.data.set_long(1, first as c_long);
+        ev.client_message.data.set_long(2, second as c_long);
+        // The request comes from a normal application
+        ev.client_message.data.set_long(3, 1);
+
+        let root = (self.lib.XDefaultRootWindow)(self.display);
+        (self.lib.XSendEvent)(
+            self.display,
+            root,
+            xlib::False,
+            xlib::SubstructureRedirectMask | xlib::SubstructureNotifyMask,
+            &mut ev,
+        );
+        (self.lib.XFlush)(self.display);
+    }
+
+    /// Looks up the pressure and tilt valuators of a device, returns None if it isn't a pen.
+    unsafe fn query_pen_valuators(&self, device_id: c_int) -> Option<PenValuators> {
+        let lib = self.xinput2.as_ref()?;
+
+        let mut count: c_int = 0;
+        let info = (lib.XIQueryDevice)(self.display, device_id, &mut count);
+        if info.is_null() {
+            return None;
+        }
+
+        let mut pressure = None;
+        let mut tilt_x = None;
+        let mut tilt_y = None;
+
+        if count > 0 {
+            let device = &*info;
+
+            for i in 0..device.num_classes as usize {
+                let class = *device.classes.add(i);
+                if (*class)._type != xinput2::XIValuatorClass {
+                    continue;
+                }
+
+                let valuator = &*(class as *const xinput2::XIValuatorClassInfo);
+                let range = Some((valuator.number, valuator.min, valuator.max));
+
+                if valuator.label == 0 {
+                    continue;
+                } else if valuator.label == self.abs_pressure {
+                    pressure = range;
+                } else if valuator.label == self.abs_tilt_x {
+                    tilt_x = range;
+                } else if valuator.label == self.abs_tilt_y {
+                    tilt_y = range;
+                }
+            }
+        }
+
+        (lib.XIFreeDeviceInfo)(info);
+
+        Some(PenValuators {
+            pressure: pressure?,
+            tilt_x,
+            tilt_y,
+        })
+    }
+
+    /// Stops the window from being resized by setting the same minimum and maximum size.
+    unsafe fn set_fixed_size(&mut self, handle: xlib::Window, width: usize, height: usize) {
+        let mut size_hints: xlib::XSizeHints = mem::zeroed();
+
+        size_hints.flags = xlib::PMinSize | xlib::PMaxSize;
+        size_hints.min_width = width as i32;
+        size_hints.max_width = width as i32;
+        size_hints.min_height = height as i32;
+        size_hints.max_height = height as i32;
+
+        (self.lib.XSetWMNormalHints)(
+            self.display,
+            handle,
+            &mut size_hints as *mut xlib::XSizeHints,
+        );
+    }
+
+    /// Asks the window manager to keep the window at the given aspect ratio while it's resized,
+    /// or lifts the constraint with `None`.
+    unsafe fn set_aspect_ratio(&mut self, handle: xlib::Window, ratio: Option<(u32, u32)>) {
+        let mut size_hints: xlib::XSizeHints = mem::zeroed();
+
+        if let Some((numerator, denominator)) = ratio {
+            size_hints.flags = xlib::PAspect;
+            size_hints.min_aspect.x = numerator as c_int;
+            size_hints.min_aspect.y = denominator as c_int;
+            size_hints.max_aspect = size_hints.min_aspect;
+        }
+
+        (self.lib.XSetWMNormalHints)(
+            self.display,
+            handle,
+            &mut size_hints as *mut xlib::XSizeHints,
+        );
+    }
+
+    /// Sets the instance and class names of `WM_CLASS`, which desktops match against the name of
+    /// `.desktop` files and window managers use in their rules.
+    unsafe fn set_class_hint(&mut self, handle: xlib::Window, instance: &str, class: &str) {
+        let (instance, class) = match (CString::new(instance), CString::new(class)) {
+            (Ok(instance), Ok(class)) => (instance, class),
+            _ => return,
+        };
+
+        let mut hint = xlib::XClassHint {
+            res_name: instance.as_ptr() as *mut c_char,
+            res_class: class.as_ptr() as *mut c_char,
+        };
+        (self.lib.XSetClassHint)(self.display, handle, &mut hint);
+    }
+
+    /// Sets the Motif hints, which most window managers use to decide which decorations and
+    /// functions a window gets.
+    unsafe fn set_motif_hints(&mut self, handle: xlib::Window, hints: &MwmHints) {
+        let hints_property = (self.lib.XInternAtom)(
+            self.display,
+            "_MOTIF_WM_HINTS\0" as *const _ as *const c_char,
+            0,
+        );
+        assert!(hints_property != 0);
+        (self.lib.XChangeProperty)(
+            self.display,
+            handle,
+            hints_property,
+            hints_property,
+            32,
+            xlib::PropModeReplace,
+            hints as *const _ as *const c_uchar,
+            5,
+        );
+    }
+
+    /// Sets both the legacy (Latin-1) and the EWMH (UTF-8) window and icon names so window
+    /// managers show non-ASCII titles correctly. Returns false if the title is too long.
+    unsafe fn set_window_title(&mut self, handle: xlib::Window, title: &CStr) -> bool {
+        let (title_data, title_len) = match utf8_title_property(title) {
+            Some(property) => property,
+            None => return false,
+        };
+
+        (self.lib.XStoreName)(self.display, handle, title.as_ptr());
+        (self.lib.XSetIconName)(self.display, handle, title.as_ptr());
+
+        let utf8_string = self.utf8_string;
+
+        for atom_name in &["_NET_WM_NAME", "_NET_WM_ICON_NAME"] {
+            let atom = self.intern_atom(atom_name, false);
+            (self.lib.XChangeProperty)(
+                self.display,
+                handle,
+                atom,
+                utf8_string,
+                8,
+                xlib::PropModeReplace,
+                title_data.as_ptr() as *const c_uchar,
+                title_len,
+            );
+        }
+
+        true
     }
 
     fn intern_atom(&mut self, name: &'static str, only_if_exists: bool) -> xlib::Atom {
@@ -312,22 +1049,69 @@ pub struct Window {
 
     scale: i32,
     bg_color: u32,
+    // Drawn over the background color outside of the buffer when set
+    border_fill: Option<BorderFill>,
     scale_mode: ScaleMode,
+    buffer_size: (usize, usize), // size of the last buffer drawn
+    // Damage for the next present, and the buffer and window size of the last one
+    damage: Option<Vec<Rect>>,
+    presented_sizes: Option<((usize, usize), (u32, u32))>,
 
     mouse_x: f32,
     mouse_y: f32,
+    // Position from the last XInput2 motion event
+    precise_mouse_pos: Option<(f32, f32)>,
+    // Position relative to the root window, None while the pointer is on another screen
+    mouse_on_screen: Option<(f32, f32)>,
+    borderless: bool,
+    pen: Option<PenState>,
+    // Pen valuators of the input devices seen so far, by device id
+    pen_devices: Vec<(c_int, Option<PenValuators>)>,
     scroll_x: f32,
     scroll_y: f32,
     buttons: [u8; 3],
     prev_cursor: CursorStyle,
+    // Cursor set with `set_animated_cursor`, 0 while one of the styles is used
+    animated_cursor: xlib::Cursor,
+    animated_cursor_hotspot: (i32, i32),
     active: bool,
+    obscured: bool,
+    mapped: bool,
+    resizable: bool,
+    // Whether the window got a 32-bit visual for the transparency it asked for
+    transparency: bool,
+    pixel_format: PixelFormat,
 
     should_close: bool, // received delete window message from X server
+    closed: bool,       // hidden by `close`, the window is only destroyed when dropped
+    close_reason: Option<CloseReason>,
+    auto_close: bool,
+    close_requested: bool,
 
     key_handler: KeyHandler,
+    touch_handler: TouchHandler,
+    cursor_auto_hide: mouse_handler::CursorAutoHide,
+    mouse_delta: mouse_handler::MouseDelta,
+    scroll_accumulator: mouse_handler::ScrollAccumulator,
+    mouse_history: mouse_handler::MouseHistory,
+    button_modifiers: mouse_handler::ButtonModifiers,
+    // Set once `poll_input` has started the input of the next update
+    input_polled: bool,
+    cursor_confined: bool,
+    keyboard_grabbed: bool,
     update_rate: UpdateRate,
     menu_counter: MenuHandle,
     menus: Vec<UnixMenu>,
+    context_menu: Option<(UnixMenu, i32, i32)>,
+    primary_selection: Option<String>, // set while we own the PRIMARY selection
+    monitor: Option<Monitor>,
+    monitor_changed: bool,
+    moved_to_monitor: bool,
+    // Gamma ramps the CRTCs had before `set_gamma_ramp` changed them
+    gamma_restore: Vec<(xrandr::RRCrtc, *mut xrandr::XRRCrtcGamma)>,
+    present_callbacks: PresentCallbacks,
+    gl: Option<GlContext>,
+    title: String,
 }
 
 unsafe impl raw_window_handle::HasRawWindowHandle for Window {
@@ -339,8 +1123,46 @@ unsafe impl raw_window_handle::HasRawWindowHandle for Window {
     }
 }
 
+#[cfg(feature = "rwh_06")]
+impl Window {
+    pub fn raw_window_handle_06(&self) -> Option<raw_window_handle_06::RawWindowHandle> {
+        if self.handle == 0 {
+            return None;
+        }
+
+        let handle = raw_window_handle_06::XlibWindowHandle::new(self.handle);
+        Some(raw_window_handle_06::RawWindowHandle::Xlib(handle))
+    }
+
+    pub fn raw_display_handle_06(&self) -> Option<raw_window_handle_06::RawDisplayHandle> {
+        let display = ptr::NonNull::new(self.d.display as *mut c_void);
+        let handle = raw_window_handle_06::XlibDisplayHandle::new(display, self.d.screen);
+        Some(raw_window_handle_06::RawDisplayHandle::Xlib(handle))
+    }
+}
+
 impl Window {
     pub fn new(name: &str, width: usize, height: usize, opts: WindowOptions) -> Result<Window> {
+        Self::create(name, width, height, opts, None)
+    }
+
+    pub fn new_child(
+        parent: xlib::Window,
+        name: &str,
+        width: usize,
+        height: usize,
+        opts: WindowOptions,
+    ) -> Result<Window> {
+        Self::create(name, width, height, opts, Some(parent))
+    }
+
+    fn create(
+        name: &str,
+        width: usize,
+        height: usize,
+        opts: WindowOptions,
+        parent: Option<xlib::Window>,
+    ) -> Result<Window> {
         let name = match CString::new(name) {
             Err(_) => {
                 println!("Unable to convert {} to c_string", name);
@@ -354,6 +1176,15 @@ impl Window {
 
         let mut d = DisplayInfo::new(opts.transparency)?;
 
+        let gl_visual = if opts.gl {
+            let glx = glx::Glx::open()
+                .map_err(|e| Error::WindowCreate(format!("failed to load GLX: {:?}", e)))?;
+            let vinfo = unsafe { d.use_gl_visual(&glx)? };
+            Some((glx, vinfo))
+        } else {
+            None
+        };
+
         let scale =
             Self::get_scale_factor(width, height, d.screen_width, d.screen_height, opts.scale);
 
@@ -367,19 +1198,20 @@ impl Window {
 
             attributes.border_pixel = (d.lib.XBlackPixel)(d.display, d.screen);
             attributes.background_pixel = attributes.border_pixel;
-            if opts.transparency {
+            if opts.transparency || d.visual != (d.lib.XDefaultVisual)(d.display, d.screen) {
                 attributes.colormap =
                     (d.lib.XCreateColormap)(d.display, root, d.visual, xlib::AllocNone);
             }
 
             attributes.backing_store = xlib::NotUseful;
 
-            let x = if d.screen_width > width {
+            // Child windows go in the top left corner of their parent
+            let x = if parent.is_none() && d.screen_width > width {
                 (d.screen_width - width) / 2
             } else {
                 0
             };
-            let y = if d.screen_height > height {
+            let y = if parent.is_none() && d.screen_height > height {
                 (d.screen_height - height) / 2
             } else {
                 0
@@ -387,7 +1219,7 @@ impl Window {
 
             let handle = (d.lib.XCreateWindow)(
                 d.display,
-                root,
+                parent.unwrap_or(root),
                 x as i32,
                 y as i32,
                 width as u32,
@@ -443,24 +1275,16 @@ impl Window {
                 return Err(Error::WindowCreate("Unable to open Window".to_owned()));
             }
 
-            (d.lib.XStoreName)(d.display, handle, name.as_ptr());
-            if let Ok(name_len) = c_int::try_from(name.to_bytes().len()) {
-                let net_wm_name = d.intern_atom("_NET_WM_NAME", false);
-                let utf8_string = d.intern_atom("UTF8_STRING", false);
-                (d.lib.XChangeProperty)(
-                    d.display,
-                    handle,
-                    net_wm_name,
-                    utf8_string,
-                    8,
-                    xlib::PropModeReplace,
-                    name.as_ptr() as *const c_uchar,
-                    name_len,
-                );
-            } else {
+            if !d.set_window_title(handle, &name) {
                 return Err(Error::WindowCreate("Window name too long".to_owned()));
             }
 
+            if let Some((instance, class)) = opts.wm_class {
+                d.set_class_hint(handle, instance, class);
+            } else if let Some(app_id) = opts.app_id {
+                d.set_class_hint(handle, app_id, app_id);
+            }
+
             (d.lib.XSelectInput)(
                 d.display,
                 handle,
@@ -469,49 +1293,39 @@ impl Window {
                     | xlib::KeyReleaseMask
                     | xlib::ButtonPressMask
                     | xlib::ButtonReleaseMask
-                    | xlib::FocusChangeMask,
+                    | xlib::PointerMotionMask
+                    | xlib::FocusChangeMask
+                    | xlib::VisibilityChangeMask,
             );
 
-            if !opts.resize || opts.none {
-                let mut size_hints: xlib::XSizeHints = mem::zeroed();
-
-                size_hints.flags = xlib::PMinSize | xlib::PMaxSize;
-                size_hints.min_width = width as i32;
-                size_hints.max_width = width as i32;
-                size_hints.min_height = height as i32;
-                size_hints.max_height = height as i32;
+            d.select_xinput2_events(handle);
 
-                (d.lib.XSetWMNormalHints)(
+            // Tells when the user switches to another keyboard layout
+            if d.keyb_ext {
+                (d.lib.XkbSelectEventDetails)(
                     d.display,
-                    handle,
-                    &mut size_hints as *mut xlib::XSizeHints,
+                    XKB_USE_CORE_KBD,
+                    xlib::XkbStateNotify as c_uint,
+                    xlib::XkbGroupStateMask,
+                    xlib::XkbGroupStateMask,
                 );
             }
 
+            if !opts.resize || opts.none {
+                d.set_fixed_size(handle, width, height);
+            }
+
             if opts.borderless || opts.none {
-                let hints_property = (d.lib.XInternAtom)(
-                    d.display,
-                    "_MOTIF_WM_HINTS\0" as *const _ as *const c_char,
-                    0,
-                );
-                assert!(hints_property != 0);
                 let mut hints: MwmHints = std::mem::zeroed();
-                hints.flags = 2;
+                hints.flags = MWM_HINTS_DECORATIONS;
                 hints.decorations = 0;
-                (d.lib.XChangeProperty)(
-                    d.display,
-                    handle,
-                    hints_property,
-                    hints_property,
-                    32,
-                    xlib::PropModeReplace,
-                    &hints as *const _ as *const c_uchar,
-                    5,
-                );
+                d.set_motif_hints(handle, &hints);
             }
 
             (d.lib.XClearWindow)(d.display, handle);
-            (d.lib.XMapRaised)(d.display, handle);
+            if opts.visible {
+                (d.lib.XMapRaised)(d.display, handle);
+            }
             (d.lib.XSetWMProtocols)(d.display, handle, &mut d.wm_delete_window, 1);
             (d.lib.XFlush)(d.display);
 
@@ -527,7 +1341,23 @@ impl Window {
                 }
             };
 
-            Ok(Window {
+            let transparency = opts.transparency && d.depth == 32;
+
+            let gl = match gl_visual {
+                Some((glx, vinfo)) => Some(Self::create_gl_context(&d, handle, glx, vinfo)?),
+                None => None,
+            };
+
+            let mut key_handler = KeyHandler::new();
+            key_handler.set_keyboard_layout(d.get_keyboard_layout());
+            key_handler.set_display_count(d.get_monitors().len());
+
+            // Screen changes are sent to every window that asks for them, not just the root
+            if let Some(lib) = &d.xrandr {
+                (lib.XRRSelectInput)(d.display, handle, xrandr::RRScreenChangeNotifyMask);
+            }
+
+            let mut window = Window {
                 d,
                 handle,
                 xim,
@@ -538,20 +1368,100 @@ impl Window {
                 height: height as u32,
                 scale: scale as i32,
                 mouse_x: 0.0,
+                precise_mouse_pos: None,
+                mouse_on_screen: None,
+                borderless: opts.borderless || opts.none,
+                pen: None,
+                pen_devices: Vec::new(),
                 mouse_y: 0.0,
                 scroll_x: 0.0,
                 scroll_y: 0.0,
                 bg_color: 0,
+                border_fill: None,
                 scale_mode: opts.scale_mode,
+                buffer_size: (width / scale, height / scale),
+                damage: None,
+                presented_sizes: None,
                 buttons: [0, 0, 0],
                 prev_cursor: CursorStyle::Arrow,
+                animated_cursor: 0,
+                animated_cursor_hotspot: (0, 0),
                 should_close: false,
+                closed: false,
+                close_reason: None,
+                auto_close: true,
+                close_requested: false,
                 active: false,
-                key_handler: KeyHandler::new(),
+                obscured: false,
+                mapped: opts.visible,
+                resizable: opts.resize && !opts.none,
+                transparency,
+                pixel_format: opts.pixel_format,
+                key_handler,
+                touch_handler: TouchHandler::new(),
+                cursor_auto_hide: mouse_handler::CursorAutoHide::new(),
+                mouse_delta: mouse_handler::MouseDelta::default(),
+                scroll_accumulator: mouse_handler::ScrollAccumulator::default(),
+                mouse_history: mouse_handler::MouseHistory::new(),
+                button_modifiers: mouse_handler::ButtonModifiers::default(),
+                input_polled: false,
+                cursor_confined: false,
+                keyboard_grabbed: false,
                 update_rate: UpdateRate::new(),
                 menu_counter: MenuHandle(0),
                 menus: Vec::new(),
-            })
+                context_menu: None,
+                primary_selection: None,
+                monitor: None,
+                monitor_changed: true,
+                moved_to_monitor: false,
+                gamma_restore: Vec::new(),
+                present_callbacks: PresentCallbacks::default(),
+                gl,
+                title: name.to_string_lossy().into_owned(),
+            };
+
+            window.report_held_keys();
+
+            Ok(window)
+        }
+    }
+
+    /// Creates a GL context for the visual picked by `use_gl_visual` and makes it current.
+    unsafe fn create_gl_context(
+        d: &DisplayInfo,
+        handle: xlib::Window,
+        glx: glx::Glx,
+        vinfo: *mut xlib::XVisualInfo,
+    ) -> Result<GlContext> {
+        let context = (glx.glXCreateContext)(d.display, vinfo, ptr::null_mut(), xlib::True);
+        (d.lib.XFree)(vinfo as *mut _);
+
+        if context.is_null() {
+            (d.lib.XDestroyWindow)(d.display, handle);
+            return Err(Error::WindowCreate("glXCreateContext failed".to_owned()));
+        }
+
+        (glx.glXMakeCurrent)(d.display, handle, context);
+
+        Ok(GlContext { glx, context })
+    }
+
+    pub fn gl_get_proc_address(&self, name: &str) -> *const raw::c_void {
+        let (gl, name) = match (&self.gl, CString::new(name)) {
+            (Some(gl), Ok(name)) => (gl, name),
+            _ => return ptr::null(),
+        };
+
+        match unsafe { (gl.glx.glXGetProcAddress)(name.as_ptr() as *const c_uchar) } {
+            Some(f) => f as *const raw::c_void,
+            None => ptr::null(),
+        }
+    }
+
+    pub fn gl_swap_buffers(&mut self) {
+        if let Some(gl) = &self.gl {
+            unsafe { (gl.glx.glXSwapBuffers)(self.d.display, self.handle) };
         }
     }
 
@@ -580,28 +1490,104 @@ impl Window {
         if image.is_null() {
             None
         } else {
+            // The pixels are in the byte order of this machine, Xlib swaps them if the server
+            // uses the other one
+            (*image).byte_order = if cfg!(target_endian = "little") {
+                xlib::LSBFirst
+            } else {
+                xlib::MSBFirst
+            };
             Some(image)
         }
     }
 
-    unsafe fn free_image(&mut self) {
-        (*self.ximage).data = ptr::null_mut();
-        (self.d.lib.XDestroyImage)(self.ximage);
-        self.ximage = ptr::null_mut();
-    }
+    /// Releases the native window and its resources. Safe to call more than once.
+    unsafe fn destroy_window(&mut self) {
+        if self.handle == 0 {
+            return;
+        }
 
-    pub fn set_title(&mut self, title: &str) {
-        match CString::new(title) {
-            Err(_) => {
-                println!("Unable to convert {} to c_string", title);
+        self.free_image();
+        self.free_animated_cursor();
+
+        if let Some(lib) = &self.d.xrandr {
+            for (crtc, gamma) in self.gamma_restore.drain(..) {
+                (lib.XRRSetCrtcGamma)(self.d.display, crtc, gamma);
+                (lib.XRRFreeGamma)(gamma);
+            }
+        }
+
+        // TODO  [ andrewj: right now DisplayInfo is not shared, so doing this is
+        //                  probably pointless ]
+        // XSaveContext(s_display, info->window, s_context, (XPointer)0);
+
+        if let Some(gl) = self.gl.take() {
+            (gl.glx.glXMakeCurrent)(self.d.display, 0, ptr::null_mut());
+            (gl.glx.glXDestroyContext)(self.d.display, gl.context);
+        }
+
+        (self.d.lib.XDestroyIC)(self.xic);
+        (self.d.lib.XCloseIM)(self.xim);
+        (self.d.lib.XFreeGC)(self.d.display, self.d.gc);
+        (self.d.lib.XDestroyWindow)(self.d.display, self.handle);
+        (self.d.lib.XFlush)(self.d.display);
+
+        self.handle = 0;
+    }
+
+    /// Recreates the image when the window size changed.
+    unsafe fn resize_image(&mut self, width: u32, height: u32) {
+        // ConfigureNotify is also sent when the window is only moved or restacked, so only
+        // recreate the image when the size actually changed
+        if width != self.width || height != self.height {
+            self.width = width;
+            self.height = height;
+            self.free_image();
+            self.ximage = Self::alloc_image(
+                &self.d,
+                self.width as usize,
+                self.height as usize,
+                &mut self.draw_buffer,
+            )
+            .expect("todo");
+        }
+    }
+
+    unsafe fn free_image(&mut self) {
+        (*self.ximage).data = ptr::null_mut();
+        (self.d.lib.XDestroyImage)(self.ximage);
+        self.ximage = ptr::null_mut();
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        match CString::new(title) {
+            Err(_) => {
+                println!("Unable to convert {} to c_string", title);
             }
 
             Ok(t) => unsafe {
-                (self.d.lib.XStoreName)(self.d.display, self.handle, t.as_ptr());
+                if self.d.set_window_title(self.handle, &t) {
+                    self.title = title.to_owned();
+                } else {
+                    println!("Unable to set title {}, it is too long", title);
+                }
             },
         };
     }
 
+    #[inline]
+    pub fn get_title(&self) -> String {
+        self.title.clone()
+    }
+
+    pub fn set_document_edited(&mut self, _edited: bool) {
+        // X11 has no document state, applications show it in the title
+    }
+
+    pub fn set_represented_file(&mut self, _path: &Path) {
+        // window managers don't show the file a window belongs to
+    }
+
     pub fn update_with_buffer_stride(
         &mut self,
         buffer: &[u32],
@@ -609,48 +1595,209 @@ impl Window {
         buf_height: usize,
         buf_stride: usize,
     ) -> Result<()> {
+        if self.closed {
+            return Ok(());
+        }
+
         buffer_helper::check_buffer_size(buf_width, buf_height, buf_stride, buffer)?;
 
-        unsafe { self.raw_blit_buffer(buffer, buf_width, buf_height, buf_stride) };
+        let buffer = buffer_helper::to_argb(buffer, self.pixel_format);
+        self.buffer_size = (buf_width, buf_height);
+        let damage = self.take_damage();
+        unsafe { self.raw_blit_buffer(&buffer, buf_width, buf_height, buf_stride, damage) };
+        self.presented_sizes = Some((self.buffer_size, (self.width, self.height)));
+
+        // The server has drawn the frame once it handled the requests, a compositor may show it
+        // later
+        if self.present_callbacks.has_pending() {
+            unsafe { (self.d.lib.XSync)(self.d.display, xlib::False) };
+            self.present_callbacks.frame_presented();
+            self.present_callbacks.frame_shown();
+        }
 
         self.update();
 
         Ok(())
     }
 
-    pub fn update(&mut self) {
+    pub fn set_present_callback(&mut self, callback: Box<dyn FnOnce()>) {
+        self.present_callbacks.add(callback);
+    }
+
+    pub fn cancel_present_callbacks(&mut self) {
+        self.present_callbacks.clear_pending();
+    }
+
+    pub fn set_damage(&mut self, rects: &[Rect]) {
+        // Frames that weren't presented, for example while the window was occluded, add up
+        self.damage
+            .get_or_insert_with(Vec::new)
+            .extend_from_slice(rects);
+    }
+
+    // Takes the damage set for this present in window pixels, the whole window is damaged when
+    // no damage was set or the buffer or window size changed since the last present
+    fn take_damage(&mut self) -> Option<Vec<(i32, i32, i32, i32)>> {
+        let rects = self.damage.take()?;
+        if self.presented_sizes != Some((self.buffer_size, (self.width, self.height))) {
+            return None;
+        }
+
+        Some(
+            rects
+                .into_iter()
+                .filter_map(|rect| {
+                    buffer_helper::damage_rect(
+                        self.scale_mode,
+                        self.width as usize,
+                        self.height as usize,
+                        self.buffer_size.0,
+                        self.buffer_size.1,
+                        rect,
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    pub fn update_with_buffer_vsync(
+        &mut self,
+        buffer: &[u32],
+        buf_width: usize,
+        buf_height: usize,
+        buf_stride: usize,
+    ) -> Result<()> {
+        // There's no vsync to wait for without a GL context, the rate limiter paces the updates
+        self.update_rate.update();
+        self.update_with_buffer_stride(buffer, buf_width, buf_height, buf_stride)
+    }
+
+    pub fn poll_input(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        self.begin_input();
+        self.input_polled = true;
+
+        unsafe {
+            self.raw_get_mouse_pos();
+            self.raw_process_events();
+        }
+
+        self.update_mouse_delta();
+    }
+
+    // Clears the input state of the previous update before processing new events, only once
+    // when `poll_input` is called in between updates
+    fn begin_input(&mut self) {
+        if self.input_polled {
+            return;
+        }
+
         self.key_handler.update();
+        self.touch_handler.update();
 
-        // clear before processing new events
         self.scroll_x = 0.0;
         self.scroll_y = 0.0;
+        self.mouse_delta.reset();
+        self.mouse_history.reset();
+        self.close_requested = false;
+        self.moved_to_monitor = false;
+    }
+
+    // Measures the movement for the gameplay cursor mode and moves the pointer back to the center
+    fn update_mouse_delta(&mut self) {
+        let center = ((self.width / 2) as f32, (self.height / 2) as f32);
+
+        if !self
+            .mouse_delta
+            .update(self.active, (self.mouse_x, self.mouse_y), center)
+        {
+            return;
+        }
+
+        unsafe {
+            (self.d.lib.XWarpPointer)(
+                self.d.display,
+                0,
+                self.handle,
+                0,
+                0,
+                0,
+                0,
+                center.0 as c_int,
+                center.1 as c_int,
+            );
+            (self.d.lib.XFlush)(self.d.display);
+        }
+
+        self.mouse_x = center.0;
+        self.mouse_y = center.1;
+    }
+
+    pub fn update(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        self.begin_input();
 
         unsafe {
             self.raw_get_mouse_pos();
             self.raw_process_events();
         }
+
+        self.update_mouse_delta();
+        self.input_polled = false;
+        self.key_handler.update_just_pressed();
+
+        // Only look the monitor up again after the window was moved or resized
+        if self.monitor_changed && self.handle != 0 {
+            self.monitor_changed = false;
+
+            let monitor = self.current_monitor();
+            if monitor != self.monitor {
+                self.moved_to_monitor = self.monitor.is_some();
+                self.monitor = monitor;
+            }
+        }
+
+        if let Some(visible) = self.cursor_auto_hide.update(self.mouse_x, self.mouse_y) {
+            self.show_cursor(visible);
+        }
     }
 
     #[inline]
-    pub fn set_icon(&mut self, icon: Icon) {
+    pub fn set_icon(&mut self, icon: Icon) -> Result<()> {
         // XChangeProperty
         let net_string_ptr = b"_NET_WM_ICON\0".as_ptr() as _;
         let cardinal_ptr = b"CARDINAL\0".as_ptr() as _;
 
-        unsafe {
-            if let Icon::Buffer(ptr, len) = icon {
-                let _ = (self.d.lib.XChangeProperty)(
-                    self.d.display,
-                    self.handle,
-                    (self.d.lib.XInternAtom)(self.d.display, net_string_ptr, xlib::False),
-                    (self.d.lib.XInternAtom)(self.d.display, cardinal_ptr, xlib::False),
-                    32,
-                    xlib::PropModeReplace,
-                    ptr as *const u8,
-                    len as c_int,
-                );
+        let (ptr, len) = match icon {
+            Icon::Buffer(ptr, len) => (ptr, len),
+            Icon::Path(_) => {
+                return Err(Error::Unsupported {
+                    operation: "set_icon from a file",
+                    platform: "X11",
+                })
             }
+        };
+
+        unsafe {
+            (self.d.lib.XChangeProperty)(
+                self.d.display,
+                self.handle,
+                (self.d.lib.XInternAtom)(self.d.display, net_string_ptr, xlib::False),
+                (self.d.lib.XInternAtom)(self.d.display, cardinal_ptr, xlib::False),
+                32,
+                xlib::PropModeReplace,
+                ptr as *const u8,
+                len as c_int,
+            );
         }
+
+        Ok(())
     }
 
     #[inline]
@@ -661,17 +1808,125 @@ impl Window {
     #[inline]
     pub fn set_background_color(&mut self, bg_color: u32) {
         self.bg_color = bg_color;
+        self.border_fill = None;
+    }
+
+    pub fn set_border_fill(&mut self, fill: BorderFill) {
+        self.border_fill = Some(fill);
     }
 
     #[inline]
     pub fn set_cursor_visibility(&mut self, visibility: bool) {
+        self.cursor_auto_hide.set_visible(visibility);
+        self.show_cursor(visibility);
+    }
+
+    #[inline]
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_auto_hide.is_visible()
+    }
+
+    pub fn set_cursor_auto_hide(&mut self, timeout: Option<Duration>) {
+        if let Some(visible) = self.cursor_auto_hide.set_timeout(timeout) {
+            self.show_cursor(visible);
+        }
+    }
+
+    pub fn set_cursor_confined(&mut self, confined: bool) {
+        if confined {
+            if self.active {
+                self.grab_pointer(true);
+            }
+        } else if self.cursor_confined {
+            self.grab_pointer(false);
+        }
+
+        self.cursor_confined = confined;
+    }
+
+    pub fn set_gameplay_cursor_mode(&mut self, enabled: bool) {
+        if enabled == self.mouse_delta.is_enabled() {
+            return;
+        }
+
+        if enabled {
+            self.mouse_delta
+                .save_cursor(self.is_cursor_visible(), self.cursor_confined);
+            self.set_cursor_visibility(false);
+            self.set_cursor_confined(true);
+        } else {
+            let (visible, confined) = self.mouse_delta.saved_cursor();
+            self.set_cursor_visibility(visible);
+            self.set_cursor_confined(confined);
+        }
+
+        self.mouse_delta.set_enabled(enabled);
+    }
+
+    #[inline]
+    pub fn get_mouse_delta(&self) -> (f32, f32) {
+        self.mouse_delta.get()
+    }
+
+    fn grab_pointer(&mut self, grab: bool) {
         unsafe {
-            if visibility {
-                (self.d.lib.XDefineCursor)(
+            if grab {
+                // Events are still reported as usual, the grab is only used to confine the pointer
+                (self.d.lib.XGrabPointer)(
                     self.d.display,
                     self.handle,
-                    self.d.cursors[self.prev_cursor as usize],
+                    xlib::True,
+                    (xlib::ButtonPressMask | xlib::ButtonReleaseMask | xlib::PointerMotionMask)
+                        as c_uint,
+                    xlib::GrabModeAsync,
+                    xlib::GrabModeAsync,
+                    self.handle,
+                    0,
+                    xlib::CurrentTime,
                 );
+            } else {
+                (self.d.lib.XUngrabPointer)(self.d.display, xlib::CurrentTime);
+            }
+
+            (self.d.lib.XFlush)(self.d.display);
+        }
+    }
+
+    pub fn set_keyboard_grab(&mut self, grab: bool) {
+        if grab {
+            if self.active {
+                self.grab_keyboard(true);
+            }
+        } else if self.keyboard_grabbed {
+            self.grab_keyboard(false);
+        }
+
+        self.keyboard_grabbed = grab;
+    }
+
+    fn grab_keyboard(&mut self, grab: bool) {
+        unsafe {
+            if grab {
+                (self.d.lib.XGrabKeyboard)(
+                    self.d.display,
+                    self.handle,
+                    xlib::True,
+                    xlib::GrabModeAsync,
+                    xlib::GrabModeAsync,
+                    xlib::CurrentTime,
+                );
+            } else {
+                (self.d.lib.XUngrabKeyboard)(self.d.display, xlib::CurrentTime);
+            }
+
+            (self.d.lib.XFlush)(self.d.display);
+        }
+    }
+
+    fn show_cursor(&mut self, visibility: bool) {
+        unsafe {
+            if visibility {
+                (self.d.lib.XDefineCursor)(self.d.display, self.handle, self.current_cursor());
             } else {
                 static empty: [c_char; 8] = [0; 8];
                 let mut color = std::mem::zeroed();
@@ -692,6 +1947,11 @@ impl Window {
                     0,
                 );
                 (self.d.lib.XDefineCursor)(self.d.display, self.handle, cursor);
+
+                // The window keeps its own reference, so these don't pile up while hiding the
+                // cursor repeatedly
+                (self.d.lib.XFreeCursor)(self.d.display, cursor);
+                (self.d.lib.XFreePixmap)(self.d.display, pixmap);
             }
         }
     }
@@ -704,42 +1964,433 @@ impl Window {
         }
     }
 
+    pub fn is_maximized(&self) -> bool {
+        let state = unsafe { self.d.get_wm_state(self.handle) };
+
+        state.contains(&self.d.net_wm_state_maximized_horz)
+            && state.contains(&self.d.net_wm_state_maximized_vert)
+    }
+
+    pub fn set_maximized(&mut self, maximized: bool) {
+        unsafe {
+            self.d.send_wm_state(
+                self.handle,
+                maximized,
+                self.d.net_wm_state_maximized_horz,
+                self.d.net_wm_state_maximized_vert,
+            );
+        }
+    }
+
+    pub fn get_buffer_scale(&self) -> (f32, f32) {
+        buffer_helper::buffer_scale(
+            self.scale_mode,
+            self.width as usize,
+            self.height as usize,
+            self.buffer_size.0,
+            self.buffer_size.1,
+        )
+    }
+
+    pub fn get_content_scale(&self) -> f32 {
+        // Desktops scale X11 applications through the Xft.dpi resource, 96 is unscaled
+        let resources = unsafe { (self.d.lib.XResourceManagerString)(self.d.display) };
+        if resources.is_null() {
+            return 1.0;
+        }
+
+        let resources = unsafe { CStr::from_ptr(resources) }.to_string_lossy();
+        resources
+            .lines()
+            .find_map(|line| line.strip_prefix("Xft.dpi:"))
+            .and_then(|dpi| dpi.trim().parse::<f32>().ok())
+            .map_or(1.0, |dpi| dpi / 96.0)
+    }
+
+    #[inline]
+    pub fn get_native_pixel_format(&self) -> Option<PixelFormat> {
+        self.d.native_format
+    }
+
+    pub fn is_transparency_active(&self) -> bool {
+        if !self.transparency {
+            return false;
+        }
+
+        // A compositing manager owns the _NET_WM_CM_S<screen> selection while it runs, without
+        // one the alpha channel of a 32-bit visual is simply ignored.
+        let selection = CString::new(format!("_NET_WM_CM_S{}", self.d.screen)).unwrap();
+
+        unsafe {
+            let atom = (self.d.lib.XInternAtom)(self.d.display, selection.as_ptr(), xlib::False);
+            (self.d.lib.XGetSelectionOwner)(self.d.display, atom) != 0
+        }
+    }
+
+    pub fn get_monitors(&self) -> Vec<Monitor> {
+        unsafe { self.d.get_monitors() }
+    }
+
+    pub fn display_count(&self) -> usize {
+        self.get_monitors().len()
+    }
+
+    pub fn get_keyboard_layout(&self) -> Option<String> {
+        unsafe { self.d.get_keyboard_layout() }
+    }
+
+    pub fn current_monitor(&self) -> Option<Monitor> {
+        self.current_crtc().map(|(_, monitor)| monitor)
+    }
+
+    /// Returns the CRTC of the monitor that contains the center of the window.
+    fn current_crtc(&self) -> Option<(xrandr::RRCrtc, Monitor)> {
+        let (mut x, mut y) = (0, 0);
+        let mut child: xlib::Window = 0;
+
+        unsafe {
+            let root = (self.d.lib.XDefaultRootWindow)(self.d.display);
+
+            (self.d.lib.XTranslateCoordinates)(
+                self.d.display,
+                self.handle,
+                root,
+                self.width as i32 / 2,
+                self.height as i32 / 2,
+                &mut x,
+                &mut y,
+                &mut child,
+            );
+
+            let (x, y) = (x as isize, y as isize);
+
+            self.d.get_crtcs().into_iter().find(|(_, m)| {
+                x >= m.x && y >= m.y && x < m.x + m.width as isize && y < m.y + m.height as isize
+            })
+        }
+    }
+
+    pub fn set_gamma_ramp(&mut self, ramp: &GammaRamp) -> Result<()> {
+        let (crtc, _) = self
+            .current_crtc()
+            .ok_or_else(|| Error::GammaRamp("The window isn't on a RandR monitor".to_owned()))?;
+        let lib = self.d.xrandr.as_ref().expect("found a crtc without RandR");
+
+        unsafe {
+            let size = (lib.XRRGetCrtcGammaSize)(self.d.display, crtc);
+            if size < 2 {
+                return Err(Error::GammaRamp("The monitor has no gamma ramp".to_owned()));
+            }
+
+            // Keep the ramp the CRTC had before the first change, it's restored on drop
+            if !self.gamma_restore.iter().any(|&(c, _)| c == crtc) {
+                let original = (lib.XRRGetCrtcGamma)(self.d.display, crtc);
+                if !original.is_null() {
+                    self.gamma_restore.push((crtc, original));
+                }
+            }
+
+            // CRTCs can have any number of entries, the ramp is resampled to fit
+            let gamma = (lib.XRRAllocGamma)(size);
+            for i in 0..size as usize {
+                let level = i * 255 / (size as usize - 1);
+                *(*gamma).red.add(i) = ramp.red[level];
+                *(*gamma).green.add(i) = ramp.green[level];
+                *(*gamma).blue.add(i) = ramp.blue[level];
+            }
+
+            (lib.XRRSetCrtcGamma)(self.d.display, crtc, gamma);
+            (lib.XRRFreeGamma)(gamma);
+            (self.d.lib.XFlush)(self.d.display);
+        }
+
+        Ok(())
+    }
+
+    pub fn get_gamma_ramp(&self) -> Option<GammaRamp> {
+        let (crtc, _) = self.current_crtc()?;
+        let lib = self.d.xrandr.as_ref()?;
+
+        unsafe {
+            let gamma = (lib.XRRGetCrtcGamma)(self.d.display, crtc);
+            if gamma.is_null() {
+                return None;
+            }
+
+            let size = (*gamma).size as usize;
+            let ramp = if size >= 2 {
+                let mut ramp = GammaRamp {
+                    red: [0; 256],
+                    green: [0; 256],
+                    blue: [0; 256],
+                };
+
+                for level in 0..256 {
+                    let i = level * (size - 1) / 255;
+                    ramp.red[level] = *(*gamma).red.add(i);
+                    ramp.green[level] = *(*gamma).green.add(i);
+                    ramp.blue[level] = *(*gamma).blue.add(i);
+                }
+
+                Some(ramp)
+            } else {
+                None
+            };
+
+            (lib.XRRFreeGamma)(gamma);
+            ramp
+        }
+    }
+
+    #[inline]
+    pub fn moved_to_monitor(&self) -> bool {
+        self.moved_to_monitor
+    }
+
     #[inline]
     pub fn get_position(&self) -> (isize, isize) {
-        let (x, y);
         let (mut nx, mut ny) = (0, 0);
 
-        // Create dummy window for child_return value
-        let mut dummy_window: mem::MaybeUninit<Window> = mem::MaybeUninit::uninit();
-        let mut attributes: mem::MaybeUninit<XWindowAttributes> = mem::MaybeUninit::uninit();
+        // Dummy window for the child_return value
+        let mut dummy_window: c_ulong = 0;
 
         unsafe {
             let root = (self.d.lib.XDefaultRootWindow)(self.d.display);
 
-            (self.d.lib.XGetWindowAttributes)(self.d.display, self.handle, attributes.as_mut_ptr());
-            x = attributes.assume_init().x;
-            y = attributes.assume_init().y;
+            let mut attributes: XWindowAttributes = mem::zeroed();
+            if (self.d.lib.XGetWindowAttributes)(self.d.display, self.handle, &mut attributes) == 0
+            {
+                return (0, 0);
+            }
 
             (self.d.lib.XTranslateCoordinates)(
                 self.d.display,
                 self.handle,
                 root,
-                x,
-                y,
+                attributes.x,
+                attributes.y,
                 &mut nx,
                 &mut ny,
-                dummy_window.as_mut_ptr() as *mut c_ulong,
+                &mut dummy_window,
             );
         }
 
         (nx as isize, ny as isize)
     }
 
+    pub fn get_outer_position(&self) -> (isize, isize) {
+        let (mut x, mut y) = (0, 0);
+        let mut child: xlib::Window = 0;
+
+        let [left, _, top, _] = unsafe {
+            let root = (self.d.lib.XDefaultRootWindow)(self.d.display);
+
+            (self.d.lib.XTranslateCoordinates)(
+                self.d.display,
+                self.handle,
+                root,
+                0,
+                0,
+                &mut x,
+                &mut y,
+                &mut child,
+            );
+
+            self.d.get_frame_extents(self.handle)
+        };
+
+        (x as isize - left as isize, y as isize - top as isize)
+    }
+
+    pub fn get_outer_size(&self) -> (usize, usize) {
+        let [left, right, top, bottom] = unsafe { self.d.get_frame_extents(self.handle) };
+
+        (
+            self.width as usize + (left + right) as usize,
+            self.height as usize + (top + bottom) as usize,
+        )
+    }
+
     #[inline]
     pub fn get_size(&self) -> (usize, usize) {
         (self.width as usize, self.height as usize)
     }
 
+    #[inline]
+    pub fn get_unscaled_size(&self) -> (usize, usize) {
+        // The window is already measured in screen pixels
+        (self.width as usize, self.height as usize)
+    }
+
+    pub fn set_size(&mut self, width: usize, height: usize) {
+        if self.handle == 0 || width == 0 || height == 0 {
+            return;
+        }
+
+        unsafe {
+            // A window that can't be resized has its size pinned by the size hints
+            if !self.resizable {
+                self.d.set_fixed_size(self.handle, width, height);
+            }
+
+            (self.d.lib.XResizeWindow)(self.d.display, self.handle, width as u32, height as u32);
+            (self.d.lib.XFlush)(self.d.display);
+
+            // Use the new size right away instead of waiting for ConfigureNotify, so the next
+            // update already scales the buffer to it
+            self.resize_image(width as u32, height as u32);
+        }
+    }
+
+    pub fn set_aspect_ratio(&mut self, ratio: Option<(u32, u32)>) {
+        // The size hints of a window that can't be resized already pin its size
+        if self.handle == 0 || !self.resizable {
+            return;
+        }
+
+        unsafe {
+            self.d.set_aspect_ratio(self.handle, ratio);
+            (self.d.lib.XFlush)(self.d.display);
+        }
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        if self.closed {
+            return;
+        }
+
+        // `mapped` follows once the server reports the change
+        unsafe {
+            if visible {
+                (self.d.lib.XMapRaised)(self.d.display, self.handle);
+            } else {
+                (self.d.lib.XUnmapWindow)(self.d.display, self.handle);
+            }
+            (self.d.lib.XFlush)(self.d.display);
+        }
+    }
+
+    pub fn set_always_on_bottom(&mut self, enabled: bool) {
+        self.set_window_level(if enabled {
+            WindowLevel::Bottom
+        } else {
+            WindowLevel::Normal
+        });
+    }
+
+    pub fn set_window_level(&mut self, level: WindowLevel) {
+        if self.handle == 0 {
+            return;
+        }
+
+        // X11 has no levels between the normal ones and "above", the window type is what sets
+        // desktop and dialog windows apart
+        let (window_type, below, above) = match level {
+            WindowLevel::Bottom => (self.d.net_wm_window_type_desktop, true, false),
+            WindowLevel::Normal => (self.d.net_wm_window_type_normal, false, false),
+            WindowLevel::Floating | WindowLevel::ScreenSaver => {
+                (self.d.net_wm_window_type_normal, false, true)
+            }
+            WindowLevel::ModalPanel => (self.d.net_wm_window_type_dialog, false, true),
+        };
+
+        // Most window managers only look at the type when the window is mapped, the state is
+        // applied right away
+        unsafe {
+            (self.d.lib.XChangeProperty)(
+                self.d.display,
+                self.handle,
+                self.d.net_wm_window_type,
+                xlib::XA_ATOM,
+                32,
+                xlib::PropModeReplace,
+                &window_type as *const xlib::Atom as *const c_uchar,
+                1,
+            );
+            self.d
+                .send_wm_state(self.handle, below, self.d.net_wm_state_below, 0);
+            self.d
+                .send_wm_state(self.handle, above, self.d.net_wm_state_above, 0);
+        }
+    }
+
+    pub fn set_skip_taskbar(&mut self, skip: bool) {
+        if self.handle == 0 {
+            return;
+        }
+
+        unsafe {
+            self.d.send_wm_state(
+                self.handle,
+                skip,
+                self.d.net_wm_state_skip_taskbar,
+                self.d.net_wm_state_skip_pager,
+            );
+        }
+    }
+
+    pub fn set_caption_buttons(&mut self, minimize: bool, maximize: bool, close: bool) {
+        let mut functions = MWM_FUNC_RESIZE | MWM_FUNC_MOVE;
+        let mut decorations =
+            MWM_DECOR_BORDER | MWM_DECOR_RESIZEH | MWM_DECOR_TITLE | MWM_DECOR_MENU;
+
+        if minimize {
+            functions |= MWM_FUNC_MINIMIZE;
+            decorations |= MWM_DECOR_MINIMIZE;
+        }
+        if maximize {
+            functions |= MWM_FUNC_MAXIMIZE;
+            decorations |= MWM_DECOR_MAXIMIZE;
+        }
+        if close {
+            functions |= MWM_FUNC_CLOSE;
+        }
+
+        // A borderless window has no buttons to hide, keep it without decorations
+        if self.borderless {
+            decorations = 0;
+        }
+
+        unsafe {
+            let mut hints: MwmHints = mem::zeroed();
+            hints.flags = MWM_HINTS_FUNCTIONS | MWM_HINTS_DECORATIONS;
+            hints.functions = functions;
+            hints.decorations = decorations;
+            self.d.set_motif_hints(self.handle, &hints);
+            (self.d.lib.XFlush)(self.d.display);
+        }
+    }
+
+    pub fn set_shadow(&mut self, enabled: bool) {
+        if self.handle == 0 {
+            return;
+        }
+
+        let value = c_ulong::from(enabled);
+
+        unsafe {
+            (self.d.lib.XChangeProperty)(
+                self.d.display,
+                self.handle,
+                self.d.compton_shadow,
+                xlib::XA_CARDINAL,
+                32,
+                xlib::PropModeReplace,
+                &value as *const c_ulong as *const c_uchar,
+                1,
+            );
+            (self.d.lib.XFlush)(self.d.display);
+        }
+    }
+
+    pub fn beep(&self) {
+        unsafe {
+            // 0 uses the volume set by the user
+            (self.d.lib.XBell)(self.d.display, 0);
+            (self.d.lib.XFlush)(self.d.display);
+        }
+    }
+
     pub fn get_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
         let s = self.scale as f32;
         let w = self.width as f32;
@@ -748,6 +2399,23 @@ impl Window {
         mouse_handler::get_pos(mode, self.mouse_x, self.mouse_y, s, w, h)
     }
 
+    pub fn get_mouse_history(&self) -> Vec<(f32, f32, std::time::Duration)> {
+        self.mouse_history.get(self.scale as f32)
+    }
+
+    pub fn get_pen(&self) -> Option<PenState> {
+        let s = self.scale as f32;
+        self.pen.map(|pen| PenState {
+            x: pen.x / s,
+            y: pen.y / s,
+            ..pen
+        })
+    }
+
+    pub fn get_touch_points(&self) -> Vec<TouchPoint> {
+        self.touch_handler.get_touch_points(self.scale as f32)
+    }
+
     pub fn get_unscaled_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
         let w = self.width as f32;
         let h = self.height as f32;
@@ -755,6 +2423,10 @@ impl Window {
         mouse_handler::get_pos(mode, self.mouse_x, self.mouse_y, 1.0, w, h)
     }
 
+    pub fn get_mouse_pos_on_screen(&self) -> Option<(f32, f32)> {
+        self.mouse_on_screen
+    }
+
     pub fn get_mouse_down(&self, button: MouseButton) -> bool {
         match button {
             MouseButton::Left => self.buttons[0] > 0,
@@ -763,6 +2435,19 @@ impl Window {
         }
     }
 
+    pub fn get_mouse_down_with_modifiers(&self, button: MouseButton) -> Option<Modifiers> {
+        if !self.get_mouse_down(button) {
+            return None;
+        }
+
+        // Buttons that were already down when the window got focus have nothing captured
+        Some(
+            self.button_modifiers
+                .get(button)
+                .unwrap_or_else(|| self.key_handler.modifiers()),
+        )
+    }
+
     pub fn get_scroll_wheel(&self) -> Option<(f32, f32)> {
         if self.scroll_x.abs() > 0.0 || self.scroll_y.abs() > 0.0 {
             Some((self.scroll_x, self.scroll_y))
@@ -771,10 +2456,21 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn get_scroll_accumulated(&self) -> (f32, f32) {
+        self.scroll_accumulator.get()
+    }
+
+    #[inline]
+    pub fn reset_scroll_accumulator(&mut self) {
+        self.scroll_accumulator.reset()
+    }
+
     #[inline]
     pub fn set_cursor_style(&mut self, cursor: CursorStyle) {
-        if self.prev_cursor != cursor {
+        if self.prev_cursor != cursor || self.animated_cursor != 0 {
             unsafe {
+                self.free_animated_cursor();
                 (self.d.lib.XDefineCursor)(
                     self.d.display,
                     self.handle,
@@ -786,6 +2482,93 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn get_cursor_style(&self) -> CursorStyle {
+        self.prev_cursor
+    }
+
+    #[inline]
+    pub fn get_cursor_hotspot(&self) -> (i32, i32) {
+        if self.animated_cursor != 0 {
+            self.animated_cursor_hotspot
+        } else {
+            self.d.cursor_hotspots[self.prev_cursor as usize]
+        }
+    }
+
+    pub fn set_animated_cursor(
+        &mut self,
+        frames: &[(&[u32], u32, u32)],
+        frame_duration: Duration,
+        hot_x: u32,
+        hot_y: u32,
+    ) {
+        unsafe {
+            // Xcursor plays the animation itself
+            let images = (self.d.cursor_lib.XcursorImagesCreate)(frames.len() as c_int);
+            if images.is_null() {
+                return;
+            }
+
+            for &(buffer, width, height) in frames {
+                let image = (self.d.cursor_lib.XcursorImageCreate)(width as c_int, height as c_int);
+                if image.is_null() {
+                    continue;
+                }
+
+                (*image).xhot = hot_x.min(width.saturating_sub(1));
+                (*image).yhot = hot_y.min(height.saturating_sub(1));
+                (*image).delay = frame_duration.as_millis() as u32;
+
+                let pixels = slice::from_raw_parts_mut((*image).pixels, (width * height) as usize);
+                for (dst, &src) in pixels.iter_mut().zip(buffer) {
+                    // Xcursor images have premultiplied alpha
+                    *dst = buffer_helper::premultiply(src);
+                }
+
+                *(*images).images.add((*images).nimage as usize) = image;
+                (*images).nimage += 1;
+            }
+
+            let cursor = (self.d.cursor_lib.XcursorImagesLoadCursor)(self.d.display, images);
+            (self.d.cursor_lib.XcursorImagesDestroy)(images);
+
+            if cursor == 0 {
+                return;
+            }
+
+            self.free_animated_cursor();
+            self.animated_cursor = cursor;
+            // Clamped like the first frame's
+            let (_, width, height) = frames[0];
+            self.animated_cursor_hotspot = (
+                hot_x.min(width.saturating_sub(1)) as i32,
+                hot_y.min(height.saturating_sub(1)) as i32,
+            );
+
+            if self.cursor_auto_hide.is_visible() {
+                (self.d.lib.XDefineCursor)(self.d.display, self.handle, cursor);
+                (self.d.lib.XFlush)(self.d.display);
+            }
+        }
+    }
+
+    // The cursor shown while the cursor is visible
+    fn current_cursor(&self) -> xlib::Cursor {
+        if self.animated_cursor != 0 {
+            self.animated_cursor
+        } else {
+            self.d.cursors[self.prev_cursor as usize]
+        }
+    }
+
+    unsafe fn free_animated_cursor(&mut self) {
+        if self.animated_cursor != 0 {
+            (self.d.lib.XFreeCursor)(self.d.display, self.animated_cursor);
+            self.animated_cursor = 0;
+        }
+    }
+
     #[inline]
     pub fn set_rate(&mut self, rate: Option<std::time::Duration>) {
         self.update_rate.set_rate(rate);
@@ -797,53 +2580,169 @@ impl Window {
     }
 
     #[inline]
-    pub fn get_keys(&self) -> Vec<Key> {
-        self.key_handler.get_keys()
+    pub fn set_target_fps(&mut self, fps: u32) {
+        self.update_rate.set_target_fps(fps);
+    }
+
+    #[inline]
+    pub fn get_frame_time(&self) -> std::time::Duration {
+        self.update_rate.frame_time()
+    }
+
+    #[inline]
+    pub fn get_keys(&self) -> Vec<Key> {
+        self.key_handler.get_keys()
+    }
+
+    #[inline]
+    pub fn keys_down(&self) -> &[Key] {
+        self.key_handler.keys_down()
+    }
+
+    #[inline]
+    pub fn get_keys_pressed(&self, repeat: KeyRepeat) -> Vec<Key> {
+        self.key_handler.get_keys_pressed(repeat)
+    }
+
+    #[inline]
+    pub fn get_keys_released(&self) -> Vec<Key> {
+        self.key_handler.get_keys_released()
+    }
+
+    #[inline]
+    pub fn get_keys_just_pressed(&self) -> Vec<Key> {
+        self.key_handler.get_keys_just_pressed()
+    }
+
+    #[inline]
+    pub fn get_text_input(&self) -> String {
+        self.key_handler.get_text_input()
+    }
+
+    #[inline]
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.key_handler.is_key_down(key)
+    }
+
+    #[inline]
+    pub fn get_input_timestamp(&self) -> Option<std::time::Duration> {
+        self.key_handler.get_event_time()
+    }
+
+    #[inline]
+    pub fn get_key_timestamp(&self, key: Key) -> Option<std::time::Duration> {
+        self.key_handler.get_key_time(key)
+    }
+
+    #[inline]
+    pub fn set_key_repeat_delay(&mut self, delay: f32) {
+        self.key_handler.set_key_repeat_delay(delay)
+    }
+
+    #[inline]
+    pub fn set_key_repeat_rate(&mut self, rate: f32) {
+        self.key_handler.set_key_repeat_rate(rate)
+    }
+
+    #[inline]
+    pub fn set_key_map(&mut self, key_map: KeyMap) {
+        self.key_handler.set_key_map(key_map)
+    }
+
+    #[inline]
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.key_handler.is_action_down(action)
+    }
+
+    #[inline]
+    pub fn set_key_repeat_for(&mut self, keys: &[Key], delay: f32, rate: f32) {
+        self.key_handler.set_key_repeat_for(keys, delay, rate)
+    }
+
+    pub fn use_os_key_repeat(&mut self) {
+        let mut delay_ms = 0;
+        let mut interval_ms = 0;
+
+        let ok = unsafe {
+            (self.d.lib.XkbGetAutoRepeatRate)(
+                self.d.display,
+                XKB_USE_CORE_KBD,
+                &mut delay_ms,
+                &mut interval_ms,
+            )
+        };
+
+        if ok != 0 && interval_ms != 0 {
+            self.key_handler
+                .set_key_repeat_delay(delay_ms as f32 / 1000.0);
+            self.key_handler
+                .set_key_repeat_rate(interval_ms as f32 / 1000.0);
+        }
+    }
+
+    #[inline]
+    pub fn is_key_pressed(&self, key: Key, repeat: KeyRepeat) -> bool {
+        self.key_handler.is_key_pressed(key, repeat)
+    }
+
+    #[inline]
+    pub fn is_key_repeat(&self, key: Key) -> bool {
+        self.key_handler.is_key_repeat(key)
     }
 
     #[inline]
-    pub fn get_keys_pressed(&self, repeat: KeyRepeat) -> Vec<Key> {
-        self.key_handler.get_keys_pressed(repeat)
+    pub fn is_key_released(&self, key: Key) -> bool {
+        self.key_handler.is_key_released(key)
     }
 
     #[inline]
-    pub fn get_keys_released(&self) -> Vec<Key> {
-        self.key_handler.get_keys_released()
+    pub fn set_input_callback(&mut self, callback: Box<dyn InputCallback>) {
+        self.key_handler.set_input_callback(callback)
     }
 
     #[inline]
-    pub fn is_key_down(&self, key: Key) -> bool {
-        self.key_handler.is_key_down(key)
+    pub fn is_open(&self) -> bool {
+        !self.should_close
     }
 
     #[inline]
-    pub fn set_key_repeat_delay(&mut self, delay: f32) {
-        self.key_handler.set_key_repeat_delay(delay)
+    pub fn is_occluded(&self) -> bool {
+        self.obscured || !self.mapped
     }
 
-    #[inline]
-    pub fn set_key_repeat_rate(&mut self, rate: f32) {
-        self.key_handler.set_key_repeat_rate(rate)
+    pub fn close(&mut self) {
+        // The window is only hidden so the other functions can keep using it, it's destroyed
+        // when dropped. Unmapping it also releases the pointer and keyboard grabs.
+        if !self.closed {
+            unsafe {
+                (self.d.lib.XUnmapWindow)(self.d.display, self.handle);
+                (self.d.lib.XFlush)(self.d.display);
+            }
+            self.closed = true;
+        }
+        self.should_close = true;
+        self.close_reason
+            .get_or_insert(CloseReason::ProgrammaticClose);
     }
 
     #[inline]
-    pub fn is_key_pressed(&self, key: Key, repeat: KeyRepeat) -> bool {
-        self.key_handler.is_key_pressed(key, repeat)
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        self.close_reason
     }
 
     #[inline]
-    pub fn is_key_released(&self, key: Key) -> bool {
-        self.key_handler.is_key_released(key)
+    pub fn set_close_requested_handling(&mut self, auto: bool) {
+        self.auto_close = auto;
     }
 
     #[inline]
-    pub fn set_input_callback(&mut self, callback: Box<dyn InputCallback>) {
-        self.key_handler.set_input_callback(callback)
+    pub fn set_default_accelerators(&mut self, _enabled: bool) {
+        // minifb doesn't handle any keys itself, shortcuts of the window manager always apply
     }
 
     #[inline]
-    pub fn is_open(&self) -> bool {
-        !self.should_close
+    pub fn close_requested(&self) -> bool {
+        self.close_requested
     }
 
     #[inline]
@@ -851,6 +2750,189 @@ impl Window {
         self.active
     }
 
+    pub fn get_primary_selection(&self) -> Option<String> {
+        if self.handle == 0 {
+            return None;
+        }
+
+        if let Some(text) = &self.primary_selection {
+            return Some(text.clone());
+        }
+
+        unsafe {
+            let display = self.d.display;
+
+            if (self.d.lib.XGetSelectionOwner)(display, xlib::XA_PRIMARY) == 0 {
+                return None;
+            }
+
+            (self.d.lib.XConvertSelection)(
+                display,
+                xlib::XA_PRIMARY,
+                self.d.utf8_string,
+                self.d.selection_property,
+                self.handle,
+                xlib::CurrentTime,
+            );
+            (self.d.lib.XFlush)(display);
+
+            // Only the SelectionNotify event is taken from the queue here, everything else is
+            // left for the next update
+            let mut ev: xlib::XEvent = mem::zeroed();
+            let start = Instant::now();
+            while (self.d.lib.XCheckTypedWindowEvent)(
+                display,
+                self.handle,
+                xlib::SelectionNotify,
+                &mut ev,
+            ) == 0
+            {
+                if start.elapsed() > SELECTION_TIMEOUT {
+                    return None;
+                }
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            // The owner couldn't convert the selection to text
+            if ev.selection.property == 0 {
+                return None;
+            }
+
+            self.read_selection_property()
+        }
+    }
+
+    unsafe fn read_selection_property(&self) -> Option<String> {
+        let mut actual_type: xlib::Atom = 0;
+        let mut actual_format: c_int = 0;
+        let mut item_count: c_ulong = 0;
+        let mut bytes_after: c_ulong = 0;
+        let mut data: *mut c_uchar = ptr::null_mut();
+
+        let status = (self.d.lib.XGetWindowProperty)(
+            self.d.display,
+            self.handle,
+            self.d.selection_property,
+            0,
+            c_long::MAX / 4,
+            xlib::True, // delete the property once it has been read
+            xlib::AnyPropertyType as xlib::Atom,
+            &mut actual_type,
+            &mut actual_format,
+            &mut item_count,
+            &mut bytes_after,
+            &mut data,
+        );
+
+        if status != xlib::Success as c_int || data.is_null() {
+            return None;
+        }
+
+        // Large selections are sent in chunks using the INCR protocol, which isn't supported
+        let bytes = std::slice::from_raw_parts(data, item_count as usize);
+        let text = if actual_format != 8 {
+            None
+        } else if actual_type == self.d.utf8_string {
+            Some(String::from_utf8_lossy(bytes).into_owned())
+        } else if actual_type == xlib::XA_STRING {
+            // Latin-1 maps directly onto the first 256 code points
+            Some(bytes.iter().map(|&b| char::from(b)).collect())
+        } else {
+            None
+        };
+
+        (self.d.lib.XFree)(data as *mut c_void);
+
+        text
+    }
+
+    pub fn set_primary_selection(&mut self, text: &str) {
+        if self.handle == 0 {
+            return;
+        }
+
+        unsafe {
+            (self.d.lib.XSetSelectionOwner)(
+                self.d.display,
+                xlib::XA_PRIMARY,
+                self.handle,
+                xlib::CurrentTime,
+            );
+
+            if (self.d.lib.XGetSelectionOwner)(self.d.display, xlib::XA_PRIMARY) == self.handle {
+                self.primary_selection = Some(text.to_owned());
+            } else {
+                self.primary_selection = None;
+            }
+
+            (self.d.lib.XFlush)(self.d.display);
+        }
+    }
+
+    /// Answers another client asking for the contents of the PRIMARY selection we own.
+    unsafe fn process_selection_request(&mut self, request: &xlib::XSelectionRequestEvent) {
+        // Obsolete clients don't give a property, in which case the target is used instead
+        let property = if request.property == 0 {
+            request.target
+        } else {
+            request.property
+        };
+
+        let mut reply = xlib::XSelectionEvent {
+            type_: xlib::SelectionNotify,
+            serial: 0,
+            send_event: xlib::True,
+            display: self.d.display,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            property: 0, // None unless the conversion below succeeds
+            time: request.time,
+        };
+
+        if let (xlib::XA_PRIMARY, Some(text)) = (request.selection, &self.primary_selection) {
+            if request.target == self.d.targets {
+                let targets = [self.d.targets, self.d.utf8_string, xlib::XA_STRING];
+                (self.d.lib.XChangeProperty)(
+                    self.d.display,
+                    request.requestor,
+                    property,
+                    xlib::XA_ATOM,
+                    32,
+                    xlib::PropModeReplace,
+                    targets.as_ptr() as *const c_uchar,
+                    targets.len() as c_int,
+                );
+                reply.property = property;
+            } else if request.target == self.d.utf8_string || request.target == xlib::XA_STRING {
+                // STRING is Latin-1, only UTF8_STRING can hold all of the text
+                let latin1;
+                let data = if request.target == xlib::XA_STRING {
+                    latin1 = to_latin1(text);
+                    &latin1[..]
+                } else {
+                    text.as_bytes()
+                };
+
+                (self.d.lib.XChangeProperty)(
+                    self.d.display,
+                    request.requestor,
+                    property,
+                    request.target,
+                    8,
+                    xlib::PropModeReplace,
+                    data.as_ptr(),
+                    data.len() as c_int,
+                );
+                reply.property = property;
+            }
+        }
+
+        let mut ev = xlib::XEvent { selection: reply };
+        (self.d.lib.XSendEvent)(self.d.display, request.requestor, xlib::False, 0, &mut ev);
+        (self.d.lib.XFlush)(self.d.display);
+    }
+
     fn get_scale_factor(
         width: usize,
         height: usize,
@@ -910,6 +2992,27 @@ impl Window {
         self.menus.retain(|menu| menu.handle != handle);
     }
 
+    pub fn update_menu(&mut self, handle: MenuHandle, menu: &Menu) {
+        if let Some(existing) = self.menus.iter_mut().find(|m| m.handle == handle) {
+            *existing = menu.internal.clone();
+            existing.handle = handle;
+        }
+    }
+
+    pub fn show_context_menu(&mut self, menu: &Menu, x: i32, y: i32) {
+        self.context_menu = Some((menu.internal.clone(), x, y));
+    }
+
+    pub fn take_posix_context_menu(&mut self) -> Option<(UnixMenu, i32, i32)> {
+        self.context_menu.take()
+    }
+
+    pub fn sync(&mut self) {
+        unsafe {
+            (self.d.lib.XSync)(self.d.display, xlib::False);
+        }
+    }
+
     pub fn is_menu_pressed(&mut self) -> Option<usize> {
         None
     }
@@ -922,6 +3025,7 @@ impl Window {
         buf_width: usize,
         buf_height: usize,
         buf_stride: usize,
+        damage: Option<Vec<(i32, i32, i32, i32)>>,
     ) {
         match self.scale_mode {
             ScaleMode::Stretch => {
@@ -931,8 +3035,8 @@ impl Window {
                     buf_width as u32,
                     buf_height as u32,
                     buf_stride as u32,
-                    self.width as u32,
-                    self.height as u32,
+                    self.width,
+                    self.height,
                 );
             }
 
@@ -943,8 +3047,8 @@ impl Window {
                     buf_width as u32,
                     buf_height as u32,
                     buf_stride as u32,
-                    self.width as u32,
-                    self.height as u32,
+                    self.width,
+                    self.height,
                     self.bg_color,
                 );
             }
@@ -956,8 +3060,8 @@ impl Window {
                     buf_width as u32,
                     buf_height as u32,
                     buf_stride as u32,
-                    self.width as u32,
-                    self.height as u32,
+                    self.width,
+                    self.height,
                     self.bg_color,
                 );
             }
@@ -969,25 +3073,52 @@ impl Window {
                     buf_width as u32,
                     buf_height as u32,
                     buf_stride as u32,
-                    self.width as u32,
-                    self.height as u32,
+                    self.width,
+                    self.height,
                     self.bg_color,
                 );
             }
         }
 
-        (self.d.lib.XPutImage)(
-            self.d.display,
-            self.handle,
-            self.d.gc,
-            self.ximage,
-            0,
-            0,
-            0,
-            0,
-            self.width,
-            self.height,
-        );
+        if let Some(fill) = &self.border_fill {
+            let image = buffer_helper::image_rect(
+                self.scale_mode,
+                self.width as usize,
+                self.height as usize,
+                buf_width,
+                buf_height,
+            );
+            buffer_helper::fill_border(
+                &mut self.draw_buffer,
+                self.width as usize,
+                self.height as usize,
+                image,
+                fill,
+            );
+        }
+
+        if let Some(shifts) = self.d.channel_shifts {
+            swizzle_argb(&mut self.draw_buffer, shifts);
+        }
+
+        // Only the damaged parts are sent to the server when they are known
+        let full = [(0, 0, self.width as i32, self.height as i32)];
+        let rects = damage.as_deref().unwrap_or(&full);
+
+        for &(x, y, width, height) in rects {
+            (self.d.lib.XPutImage)(
+                self.d.display,
+                self.handle,
+                self.d.gc,
+                self.ximage,
+                x,
+                y,
+                x,
+                y,
+                width as u32,
+                height as u32,
+            );
+        }
         (self.d.lib.XFlush)(self.d.display);
     }
 
@@ -1016,6 +3147,18 @@ impl Window {
         {
             self.mouse_x = child_x as f32;
             self.mouse_y = child_y as f32;
+            self.mouse_on_screen = Some((root_x as f32, root_y as f32));
+
+            // XQueryPointer only reports whole pixels, keep the sub-pixel position from the last
+            // motion event as long as the pointer hasn't moved since
+            if let Some((x, y)) = self.precise_mouse_pos {
+                if x.floor() as i32 == child_x && y.floor() as i32 == child_y {
+                    self.mouse_x = x;
+                    self.mouse_y = y;
+                }
+            }
+        } else {
+            self.mouse_on_screen = None;
         }
     }
 
@@ -1033,6 +3176,29 @@ impl Window {
                 continue;
             }
 
+            // XKB events don't have a window either, the only ones selected are layout changes
+            if self.d.keyb_ext && event.type_ == self.d.xkb_event {
+                let layout = self.d.get_keyboard_layout();
+                self.key_handler.set_keyboard_layout(layout);
+                continue;
+            }
+
+            // The screen changes when a monitor is connected or disconnected
+            if let Some(lib) = &self.d.xrandr {
+                if event.type_ == self.d.xrandr_event + xrandr::RRScreenChangeNotify {
+                    (lib.XRRUpdateConfiguration)(&mut event);
+                    let count = self.d.get_monitors().len();
+                    self.key_handler.set_display_count(count);
+                    continue;
+                }
+            }
+
+            // Extension events don't have a window, so they can't go through the check below
+            if event.type_ == xlib::GenericEvent {
+                self.process_generic_event(&mut event);
+                continue;
+            }
+
             // Don't process any more messages if we hit a termination event
             if self.raw_process_one_event(event) == ProcessEventResult::Termination {
                 return;
@@ -1040,6 +3206,102 @@ impl Window {
         }
     }
 
+    unsafe fn process_generic_event(&mut self, ev: &mut xlib::XEvent) {
+        if self.d.xinput2.is_none() {
+            return;
+        }
+
+        let cookie = &mut ev.generic_event_cookie;
+        if cookie.extension != self.d.xinput2_opcode
+            || (self.d.lib.XGetEventData)(self.d.display, cookie) == 0
+        {
+            return;
+        }
+
+        let event = &*(cookie.data as *const xinput2::XIDeviceEvent);
+        let pos = (event.event_x as f32, event.event_y as f32);
+
+        if event.event == self.handle {
+            self.key_handler.set_event_time_ms(event.time as u32);
+
+            match cookie.evtype {
+                xinput2::XI_Motion => {
+                    self.mouse_x = pos.0;
+                    self.mouse_y = pos.1;
+                    self.precise_mouse_pos = Some(pos);
+                    self.mouse_history
+                        .push(pos.0, pos.1, self.key_handler.get_event_time());
+                    self.process_pen_motion(event, pos);
+                }
+                xinput2::XI_TouchBegin | xinput2::XI_TouchUpdate | xinput2::XI_TouchEnd => {
+                    let phase = match cookie.evtype {
+                        xinput2::XI_TouchBegin => TouchPhase::Begin,
+                        xinput2::XI_TouchUpdate => TouchPhase::Move,
+                        _ => TouchPhase::End,
+                    };
+
+                    self.touch_handler
+                        .set_touch(event.detail as u64, pos.0, pos.1, phase);
+
+                    // Selecting touch events stops the server from emulating the mouse for this
+                    // window, so do that here to keep touch screens working as before
+                    if event.flags & xinput2::XITouchEmulatingPointer != 0 {
+                        self.mouse_x = pos.0;
+                        self.mouse_y = pos.1;
+                        self.precise_mouse_pos = Some(pos);
+                        self.buttons[0] = if phase == TouchPhase::End { 0 } else { 1 };
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        (self.d.lib.XFreeEventData)(self.d.display, cookie);
+    }
+
+    unsafe fn process_pen_motion(&mut self, event: &xinput2::XIDeviceEvent, pos: (f32, f32)) {
+        let valuators = match self.pen_devices.iter().find(|d| d.0 == event.sourceid) {
+            Some(device) => device.1,
+            None => {
+                let valuators = self.d.query_pen_valuators(event.sourceid);
+                self.pen_devices.push((event.sourceid, valuators));
+                valuators
+            }
+        };
+
+        let valuators = match valuators {
+            Some(valuators) => valuators,
+            None => return,
+        };
+
+        // Only valuators that changed are sent, so start out with the previous values
+        let mut pen = self.pen.unwrap_or(PenState {
+            x: 0.0,
+            y: 0.0,
+            pressure: 0.0,
+            tilt_x: 0.0,
+            tilt_y: 0.0,
+            in_range: true,
+        });
+
+        pen.x = pos.0;
+        pen.y = pos.1;
+        // XInput2 has no proximity events, so a pen that has been seen stays in range
+        pen.in_range = true;
+
+        if let Some(value) = valuator_fraction(&event.valuators, Some(valuators.pressure)) {
+            pen.pressure = value;
+        }
+        if let Some(value) = valuator_fraction(&event.valuators, valuators.tilt_x) {
+            pen.tilt_x = value * 180.0 - 90.0;
+        }
+        if let Some(value) = valuator_fraction(&event.valuators, valuators.tilt_y) {
+            pen.tilt_y = value * 180.0 - 90.0;
+        }
+
+        self.pen = Some(pen);
+    }
+
     unsafe fn raw_process_one_event(&mut self, mut ev: xlib::XEvent) -> ProcessEventResult {
         // FIXME: we cannot handle multiple windows here!
         if ev.any.window != self.handle {
@@ -1047,19 +3309,26 @@ impl Window {
         }
 
         match ev.type_ {
-            xlib::ClientMessage => {
-                // TODO : check for message_type == wm_protocols, as per x11-rs example
-                if ev.client_message.format == 32 /* i.e. longs */ &&
-                   ev.client_message.data.get_long(0) as xlib::Atom == self.d.wm_delete_window
-                {
-                    self.should_close = true;
-                    return ProcessEventResult::Termination;
+            // TODO : check for message_type == wm_protocols, as per x11-rs example
+            xlib::ClientMessage
+                if ev.client_message.format == 32 /* i.e. longs */
+                    && ev.client_message.data.get_long(0) as xlib::Atom
+                        == self.d.wm_delete_window =>
+            {
+                if !self.auto_close {
+                    self.close_requested = true;
+                    return ProcessEventResult::Ok;
                 }
+
+                self.should_close = true;
+                self.close_reason = Some(CloseReason::UserRequested);
+                return ProcessEventResult::Termination;
             }
 
             xlib::KeyPress => {
+                self.key_handler.set_event_time_ms(ev.key.time as u32);
                 self.process_key(ev, true /* is_down */);
-                self.emit_code_point_chars_to_callback(&mut ev.key);
+                self.emit_code_point_chars(&mut ev.key);
             }
 
             xlib::KeyRelease => {
@@ -1086,35 +3355,75 @@ impl Window {
                 }
                  */
 
+                self.key_handler.set_event_time_ms(ev.key.time as u32);
                 self.process_key(ev, false /* is_down */);
             }
 
             xlib::ButtonPress => {
+                self.key_handler.set_event_time_ms(ev.button.time as u32);
                 self.process_button(ev, true /* is_down */);
             }
 
             xlib::ButtonRelease => {
+                self.key_handler.set_event_time_ms(ev.button.time as u32);
                 self.process_button(ev, false /* is_down */);
             }
 
+            // Only delivered without XInput2, which reports the motion with sub-pixel precision
+            xlib::MotionNotify => {
+                self.key_handler.set_event_time_ms(ev.motion.time as u32);
+                self.mouse_history.push(
+                    ev.motion.x as f32,
+                    ev.motion.y as f32,
+                    self.key_handler.get_event_time(),
+                );
+            }
+
             xlib::ConfigureNotify => {
                 // TODO : pass this onto the application
-                self.width = ev.configure.width as u32;
-                self.height = ev.configure.height as u32;
-                self.free_image();
-                self.ximage = Self::alloc_image(
-                    &self.d,
-                    self.width as usize,
-                    self.height as usize,
-                    &mut self.draw_buffer,
-                )
-                .expect("todo");
+                let width = ev.configure.width as u32;
+                let height = ev.configure.height as u32;
+
+                self.resize_image(width, height);
+                self.monitor_changed = true;
             }
+            // Grabbing the keyboard reports the focus moving to the grab window, which is this one
+            xlib::FocusOut if self.keyboard_grabbed && ev.focus_change.mode == xlib::NotifyGrab => {
+            }
+            // Grabs are global so the confinement and keyboard grab are only kept while focused
             xlib::FocusOut => {
                 self.active = false;
+                if self.cursor_confined {
+                    self.grab_pointer(false);
+                }
+                if self.keyboard_grabbed {
+                    self.grab_keyboard(false);
+                }
             }
             xlib::FocusIn => {
                 self.active = true;
+                if self.cursor_confined {
+                    self.grab_pointer(true);
+                }
+                if self.keyboard_grabbed {
+                    self.grab_keyboard(true);
+                }
+            }
+            xlib::VisibilityNotify => {
+                self.obscured = ev.visibility.state == xlib::VisibilityFullyObscured;
+            }
+            xlib::MapNotify => {
+                self.mapped = true;
+            }
+            xlib::UnmapNotify => {
+                self.mapped = false;
+            }
+            xlib::SelectionRequest => {
+                self.process_selection_request(&ev.selection_request);
+            }
+            // Another client took over the selection
+            xlib::SelectionClear if ev.selection_clear.selection == xlib::XA_PRIMARY => {
+                self.primary_selection = None;
             }
 
             _ => {}
@@ -1126,6 +3435,9 @@ impl Window {
     fn process_key(&mut self, mut ev: xlib::XEvent, is_down: bool) {
         // NOTE: need "mut" on ev due to dumbness in the X API
 
+        let keycode = unsafe { ev.key.keycode };
+        self.key_handler.set_scancode_state(keycode, is_down);
+
         // handle special keys...
 
         if self.d.keyb_ext {
@@ -1163,51 +3475,72 @@ impl Window {
         self.update_key_state(sym, is_down);
     }
 
-    fn emit_code_point_chars_to_callback(&mut self, event: &mut XKeyEvent) {
+    // Keys that are already down when the window opens count as held rather than pressed, they
+    // are passed through `process_key` so they're mapped the same way as key events
+    unsafe fn report_held_keys(&mut self) {
+        let mut keymap: [c_char; 32] = [0; 32];
+        (self.d.lib.XQueryKeymap)(self.d.display, keymap.as_mut_ptr());
+
+        self.key_handler.begin_held_keys();
+
+        for (i, &bits) in keymap.iter().enumerate() {
+            for bit in 0..8 {
+                if (bits as u8) & (1 << bit) == 0 {
+                    continue;
+                }
+
+                let mut ev: xlib::XEvent = mem::zeroed();
+                ev.key.type_ = xlib::KeyPress;
+                ev.key.display = self.d.display;
+                ev.key.window = self.handle;
+                ev.key.keycode = (i * 8 + bit) as c_uint;
+                self.process_key(ev, true);
+            }
+        }
+
+        self.key_handler.end_held_keys();
+    }
+
+    fn emit_code_point_chars(&mut self, event: &mut XKeyEvent) {
         const BUFFER_SIZE: usize = 32;
 
-        if let Some(callback) = &mut self.key_handler.key_callback {
-            let mut buff: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
-            let str = unsafe {
-                let mut keysym: KeySym = std::mem::zeroed();
-                let mut status: Status = 0;
-                let length_in_bytes = (self.d.lib.Xutf8LookupString)(
-                    self.xic,
-                    event as *mut XKeyEvent,
-                    buff.as_mut_ptr() as *mut c_char,
-                    (BUFFER_SIZE - 1) as c_int,
-                    (&mut keysym) as *mut KeySym,
-                    (&mut status) as *mut Status,
-                );
-                &buff[0..(length_in_bytes as usize + 1)]
-            };
+        let mut buff: [u8; BUFFER_SIZE] = [0; BUFFER_SIZE];
+        let str = unsafe {
+            let mut keysym: KeySym = std::mem::zeroed();
+            let mut status: Status = 0;
+            let length_in_bytes = (self.d.lib.Xutf8LookupString)(
+                self.xic,
+                event as *mut XKeyEvent,
+                buff.as_mut_ptr() as *mut c_char,
+                (BUFFER_SIZE - 1) as c_int,
+                (&mut keysym) as *mut KeySym,
+                (&mut status) as *mut Status,
+            );
+            &buff[0..(length_in_bytes as usize + 1)]
+        };
 
-            if let Ok(cstr) = CStr::from_bytes_with_nul(str) {
-                if let Ok(str) = cstr.to_str() {
-                    for c in str.chars() {
-                        callback.add_char(c as u32);
-                    }
+        if let Ok(cstr) = CStr::from_bytes_with_nul(str) {
+            if let Ok(str) = cstr.to_str() {
+                for c in str.chars() {
+                    self.key_handler.add_char(c as u32);
                 }
             }
         }
     }
 
     unsafe fn process_button(&mut self, ev: xlib::XEvent, is_down: bool) {
-        match ev.button.button {
-            xlib::Button1 => {
-                self.buttons[0] = if is_down { 1 } else { 0 };
-                return;
-            }
-            xlib::Button2 => {
-                self.buttons[1] = if is_down { 1 } else { 0 };
-                return;
-            }
-            xlib::Button3 => {
-                self.buttons[2] = if is_down { 1 } else { 0 };
-                return;
-            }
+        let button = match ev.button.button {
+            xlib::Button1 => Some((0, MouseButton::Left)),
+            xlib::Button2 => Some((1, MouseButton::Middle)),
+            xlib::Button3 => Some((2, MouseButton::Right)),
+            _ => None,
+        };
 
-            _ => {}
+        if let Some((index, button)) = button {
+            self.buttons[index] = if is_down { 1 } else { 0 };
+            self.button_modifiers
+                .set_button_state(button, is_down, self.key_handler.modifiers());
+            return;
         }
 
         // in X, the mouse wheel is usually mapped to Button4/5
@@ -1224,12 +3557,14 @@ impl Window {
             }
         };
 
-        self.scroll_x += scroll.0 as f32 * 0.1;
-        self.scroll_y += scroll.1 as f32 * 0.1;
+        let (x, y) = (scroll.0 as f32 * 0.1, scroll.1 as f32 * 0.1);
+        self.scroll_x += x;
+        self.scroll_y += y;
+        self.scroll_accumulator.add(x, y);
     }
 
     fn update_key_state(&mut self, sym: xlib::KeySym, is_down: bool) {
-        if sym > u32::max_value() as xlib::KeySym {
+        if sym > u32::MAX as xlib::KeySym {
             return;
         }
 
@@ -1355,16 +3690,106 @@ impl Window {
 
 impl Drop for Window {
     fn drop(&mut self) {
-        unsafe {
-            self.free_image();
+        unsafe { self.destroy_window() };
+    }
+}
 
-            // TODO  [ andrewj: right now DisplayInfo is not shared, so doing this is
-            //                  probably pointless ]
-            // XSaveContext(s_display, info->window, s_context, (XPointer)0);
+/// Returns the value of a valuator in an event scaled from its range to 0.0 - 1.0, or None if
+/// the event doesn't contain it.
+unsafe fn valuator_fraction(
+    state: &xinput2::XIValuatorState,
+    valuator: Option<Valuator>,
+) -> Option<f32> {
+    let (number, min, max) = valuator?;
 
-            (self.d.lib.XDestroyIC)(self.xic);
-            (self.d.lib.XCloseIM)(self.xim);
-            (self.d.lib.XDestroyWindow)(self.d.display, self.handle);
-        }
+    if state.mask.is_null() || number < 0 || number >= state.mask_len * 8 {
+        return None;
+    }
+
+    let mask = std::slice::from_raw_parts(state.mask, state.mask_len as usize);
+    if !xinput2::XIMaskIsSet(mask, number) {
+        return None;
+    }
+
+    // Values are only present for the valuators set in the mask
+    let index = (0..number)
+        .filter(|&i| xinput2::XIMaskIsSet(mask, i))
+        .count();
+    let value = *state.values.add(index);
+
+    if max > min {
+        Some(((value - min) / (max - min)).clamp(0.0, 1.0) as f32)
+    } else {
+        None
+    }
+}
+
+/// Wraps an array returned by Xlib, which may be null when it's empty.
+unsafe fn raw_slice<'a, T>(data: *const T, len: c_int) -> &'a [T] {
+    if data.is_null() || len <= 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, len as usize)
+    }
+}
+
+/// Moves the channels of `0xAARRGGBB` pixels to the given bit offsets, in alpha, red, green and
+/// blue order.
+fn swizzle_argb(buffer: &mut [u32], shifts: [u32; 4]) {
+    let [a, r, g, b] = shifts;
+
+    for pixel in buffer.iter_mut() {
+        let p = *pixel;
+        *pixel = ((p >> 24) << a)
+            | (((p >> 16) & 0xff) << r)
+            | (((p >> 8) & 0xff) << g)
+            | ((p & 0xff) << b);
+    }
+}
+
+/// Returns the data of the `_NET_WM_NAME` and `_NET_WM_ICON_NAME` properties for a title: its
+/// UTF-8 bytes without the terminating nul and their length, or None if it's too long for a
+/// property.
+fn utf8_title_property(title: &CStr) -> Option<(&[u8], c_int)> {
+    let data = title.to_bytes();
+    let len = c_int::try_from(data.len()).ok()?;
+    Some((data, len))
+}
+
+/// Converts text to Latin-1 for the STRING target, characters that don't exist in it are replaced
+/// with '?'.
+fn to_latin1(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| u8::try_from(u32::from(c)).unwrap_or(b'?'))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multibyte_title_is_stored_as_utf8() {
+        let title = "日本語 🎉";
+        let c_title = CString::new(title).unwrap();
+
+        let (data, len) = utf8_title_property(&c_title).unwrap();
+
+        assert_eq!(data, title.as_bytes());
+        assert_eq!(len as usize, title.len());
+        assert_eq!(std::str::from_utf8(data), Ok(title));
+    }
+
+    #[test]
+    fn empty_title_has_no_data() {
+        let c_title = CString::new("").unwrap();
+
+        assert_eq!(utf8_title_property(&c_title), Some((&[][..], 0)));
+    }
+
+    #[test]
+    fn string_selection_is_latin1() {
+        assert_eq!(to_latin1("café"), b"caf\xe9");
+        assert_eq!(to_latin1("日本語 🎉"), b"??? ?");
     }
 }
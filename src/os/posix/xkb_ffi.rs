@@ -43,6 +43,9 @@ pub enum xkb_keymap_format {
 
 pub type xkb_state_component = u32;
 
+/** The effective layout, taking the base, latched and locked layouts into account. */
+pub const XKB_STATE_LAYOUT_EFFECTIVE: xkb_state_component = 1 << 7;
+
 external_library!(XkbCommon, "xkbcommon",
 functions:
     fn xkb_keysym_to_utf32(xkb_keysym_t) -> u32,
@@ -54,9 +57,11 @@ functions:
                                   xkb_keymap_compile_flags
                                  ) -> *mut xkb_keymap,
     fn xkb_keymap_unref(*mut xkb_keymap) -> (),
+    fn xkb_keymap_layout_get_name(*mut xkb_keymap, xkb_layout_index_t) -> *const c_char,
     fn xkb_state_key_get_one_sym(*mut xkb_state, xkb_keycode_t) -> xkb_keysym_t,
     fn xkb_state_new(*mut xkb_keymap) -> *mut xkb_state,
     fn xkb_state_unref(*mut xkb_state) -> (),
+    fn xkb_state_serialize_layout(*mut xkb_state, xkb_state_component) -> xkb_layout_index_t,
     fn xkb_state_update_mask(*mut xkb_state,
                              xkb_mod_mask_t,
                              xkb_mod_mask_t,
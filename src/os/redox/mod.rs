@@ -7,20 +7,29 @@ use crate::error::Error;
 use crate::icon::Icon;
 use crate::key_handler::KeyHandler;
 use crate::mouse_handler;
+use crate::present::PresentCallbacks;
 use crate::InputCallback;
+use crate::Rect;
 use crate::Result;
-use crate::{CursorStyle, MouseButton, MouseMode};
-use crate::{Key, KeyRepeat};
-use crate::{MenuHandle, MenuItem, MenuItemHandle, UnixMenu, UnixMenuItem};
-use crate::{Scale, WindowOptions};
+use crate::WindowLevel;
+use crate::{BorderFill, CloseReason, CursorStyle, Monitor, MouseButton, MouseMode};
+use crate::{GammaRamp, Key, KeyMap, KeyRepeat, Modifiers, PixelFormat};
+use crate::{MenuHandle, MenuItem, MenuItemHandle, MenuSource, UnixMenu, UnixMenuItem};
+use crate::{PenState, Scale, TouchPoint, WindowOptions};
 
 use orbclient::Renderer;
 use std::cmp;
 use std::os::raw;
+use std::path::Path;
 
 pub struct Window {
     is_open: bool,
+    close_reason: Option<CloseReason>,
+    auto_close: bool,
+    close_requested: bool,
     is_active: bool,
+    transparency: bool,
+    pixel_format: PixelFormat,
     mouse_pos: Option<(i32, i32)>,
     mouse_scroll: Option<(i32, i32)>,
     /// The state of the left, middle and right mouse buttons
@@ -30,12 +39,39 @@ pub struct Window {
     window: orbclient::Window,
     window_scale: usize,
     key_handler: KeyHandler,
+    cursor_auto_hide: mouse_handler::CursorAutoHide,
+    // Cursor visibility from before the gameplay cursor mode was turned on
+    gameplay_cursor: Option<bool>,
+    mouse_history: mouse_handler::MouseHistory,
+    present_callbacks: PresentCallbacks,
+    cursor: CursorStyle,
     menu_counter: MenuHandle,
     menus: Vec<UnixMenu>,
+    context_menu: Option<(UnixMenu, i32, i32)>,
 }
 
 impl Window {
+    pub fn new_child(
+        _parent: *mut raw::c_void,
+        _name: &str,
+        _width: usize,
+        _height: usize,
+        _opts: WindowOptions,
+    ) -> Result<Window> {
+        Err(Error::Unsupported {
+            operation: "new_child",
+            platform: "RedoxOS",
+        })
+    }
+
     pub fn new(name: &str, width: usize, height: usize, opts: WindowOptions) -> Result<Window> {
+        if opts.gl {
+            return Err(Error::Unsupported {
+                operation: "WindowOptions::gl",
+                platform: "RedoxOS",
+            });
+        }
+
         let window_scale = match opts.scale {
             Scale::X1 => 1,
             Scale::X2 => 2,
@@ -81,14 +117,25 @@ impl Window {
                 mouse_scroll: None,
                 mouse_state: (false, false, false),
                 is_open: true,
+                close_reason: None,
+                auto_close: true,
+                close_requested: false,
                 is_active: true,
+                transparency: opts.transparency,
+                pixel_format: opts.pixel_format,
                 buffer_width: width,
                 buffer_height: height,
                 window,
                 window_scale,
                 key_handler: KeyHandler::new(),
+                cursor_auto_hide: mouse_handler::CursorAutoHide::new(),
+                gameplay_cursor: None,
+                mouse_history: mouse_handler::MouseHistory::new(),
+                present_callbacks: PresentCallbacks::default(),
+                cursor: CursorStyle::Arrow,
                 menu_counter: MenuHandle(0),
                 menus: Vec::new(),
+                context_menu: None,
             }),
             None => Err(Error::WindowCreate("Unable to open Window".to_owned())),
         }
@@ -98,8 +145,36 @@ impl Window {
         self.window.set_title(title)
     }
 
-    pub fn set_icon(&mut self, _icon: Icon) {
-        unimplemented!("Currenty not implemented on RedoxOS")
+    pub fn get_title(&self) -> String {
+        self.window.title()
+    }
+
+    pub fn set_document_edited(&mut self, _edited: bool) {
+        // Orbital has no document state
+    }
+
+    pub fn set_represented_file(&mut self, _path: &Path) {
+        // Orbital has no proxy icons
+    }
+
+    pub fn set_target_fps(&mut self, _fps: u32) {
+        // Updates aren't rate limited on RedoxOS yet
+    }
+
+    pub fn get_frame_time(&self) -> std::time::Duration {
+        // Updates aren't timed on RedoxOS yet
+        std::time::Duration::from_secs(0)
+    }
+
+    pub fn set_icon(&mut self, _icon: Icon) -> Result<()> {
+        Err(Error::Unsupported {
+            operation: "set_icon",
+            platform: "RedoxOS",
+        })
+    }
+
+    pub fn backend_name(&self) -> &'static str {
+        "orbital"
     }
 
     pub fn get_window_handle(&self) -> *mut raw::c_void {
@@ -107,8 +182,13 @@ impl Window {
     }
 
     pub fn update_with_buffer(&mut self, buffer: &[u32]) -> Result<()> {
+        if !self.is_open {
+            return Ok(());
+        }
+
         self.process_events();
         self.key_handler.update();
+        self.key_handler.update_just_pressed();
 
         let check_res = buffer_helper::check_buffer_size(
             self.buffer_width,
@@ -120,15 +200,63 @@ impl Window {
             return check_res;
         }
 
-        self.render_buffer(buffer);
+        let buffer = buffer_helper::to_argb(buffer, self.pixel_format);
+        self.render_buffer(&buffer);
         self.window.sync();
+        self.present_callbacks.frame_presented();
+        self.present_callbacks.frame_shown();
 
         Ok(())
     }
 
+    pub fn set_present_callback(&mut self, callback: Box<dyn FnOnce()>) {
+        self.present_callbacks.add(callback);
+    }
+
+    pub fn cancel_present_callbacks(&mut self) {
+        self.present_callbacks.clear_pending();
+    }
+
+    pub fn set_damage(&mut self, _rects: &[Rect]) {
+        // Orbital always redraws the whole window
+    }
+
+    pub fn gl_get_proc_address(&self, _name: &str) -> *const raw::c_void {
+        std::ptr::null()
+    }
+
+    pub fn gl_swap_buffers(&mut self) {}
+
+    pub fn update_with_buffer_vsync(
+        &mut self,
+        buffer: &[u32],
+        _buf_width: usize,
+        _buf_height: usize,
+        _buf_stride: usize,
+    ) -> Result<()> {
+        // Waiting for vsync isn't supported on RedoxOS
+        self.update_with_buffer(buffer)
+    }
+
+    pub fn poll_input(&mut self) {
+        // Polling input in between updates isn't supported on RedoxOS
+    }
+
     pub fn update(&mut self) {
+        if !self.is_open {
+            return;
+        }
+
         self.process_events();
         self.key_handler.update();
+        self.key_handler.update_just_pressed();
+
+        if let Some((x, y)) = self.mouse_pos {
+            if let Some(visible) = self.cursor_auto_hide.update(x as f32, y as f32) {
+                self.window.set_mouse_cursor(visible);
+            }
+        }
+
         self.window.sync();
     }
 
@@ -140,6 +268,123 @@ impl Window {
         (self.window.width() as usize, self.window.height() as usize)
     }
 
+    pub fn get_outer_position(&self) -> (isize, isize) {
+        // Orbital doesn't report the size of its decorations
+        (self.window.x() as isize, self.window.y() as isize)
+    }
+
+    pub fn get_outer_size(&self) -> (usize, usize) {
+        self.get_size()
+    }
+
+    pub fn get_unscaled_size(&self) -> (usize, usize) {
+        self.get_size()
+    }
+
+    pub fn get_buffer_scale(&self) -> (f32, f32) {
+        // The buffer is always drawn at the window scale
+        (self.window_scale as f32, self.window_scale as f32)
+    }
+
+    pub fn get_content_scale(&self) -> f32 {
+        // Orbital doesn't scale windows
+        1.0
+    }
+
+    #[inline]
+    pub fn get_native_pixel_format(&self) -> Option<PixelFormat> {
+        // Orbital windows are 0xAARRGGBB
+        Some(PixelFormat::Argb8888)
+    }
+
+    pub fn is_transparency_active(&self) -> bool {
+        // Orbital always composites transparent windows
+        self.transparency
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        false
+    }
+
+    pub fn set_maximized(&mut self, _maximized: bool) {
+        // Orbital doesn't let windows maximize themselves
+    }
+
+    pub fn get_monitors(&self) -> Vec<Monitor> {
+        // Orbital doesn't expose its displays
+        Vec::new()
+    }
+
+    pub fn get_keyboard_layout(&self) -> Option<String> {
+        // Orbital doesn't report the keyboard layout
+        None
+    }
+
+    pub fn set_gamma_ramp(&mut self, _ramp: &GammaRamp) -> Result<()> {
+        Err(Error::Unsupported {
+            operation: "set_gamma_ramp",
+            platform: "RedoxOS",
+        })
+    }
+
+    pub fn get_gamma_ramp(&self) -> Option<GammaRamp> {
+        None
+    }
+
+    pub fn display_count(&self) -> usize {
+        // Orbital doesn't expose its displays
+        0
+    }
+
+    pub fn current_monitor(&self) -> Option<Monitor> {
+        // Orbital doesn't expose its displays
+        None
+    }
+
+    pub fn moved_to_monitor(&self) -> bool {
+        false
+    }
+
+    pub fn set_size(&mut self, width: usize, height: usize) {
+        self.window.set_size(width as u32, height as u32);
+    }
+
+    pub fn set_border_fill(&mut self, _fill: BorderFill) {
+        // Border fills aren't supported
+    }
+
+    pub fn set_visible(&mut self, _visible: bool) {
+        // Orbital windows can't be hidden after they are created
+    }
+
+    pub fn set_always_on_bottom(&mut self, _enabled: bool) {
+        // Orbital doesn't support keeping windows below others
+    }
+
+    pub fn set_window_level(&mut self, _level: WindowLevel) {
+        // Orbital doesn't support window levels
+    }
+
+    pub fn set_skip_taskbar(&mut self, _skip: bool) {
+        // Orbital doesn't support hiding windows from the taskbar
+    }
+
+    pub fn set_caption_buttons(&mut self, _minimize: bool, _maximize: bool, _close: bool) {
+        // Orbital doesn't support hiding the buttons of the title bar
+    }
+
+    pub fn set_shadow(&mut self, _enabled: bool) {
+        // Orbital doesn't draw window shadows
+    }
+
+    pub fn beep(&self) {
+        // Orbital doesn't have a system sound
+    }
+
+    pub fn set_aspect_ratio(&mut self, _ratio: Option<(u32, u32)>) {
+        // Orbital doesn't support aspect ratio constraints
+    }
+
     pub fn get_scroll_wheel(&self) -> Option<(f32, f32)> {
         if let Some((scroll_x, scroll_y)) = self.mouse_scroll {
             Some((scroll_x as f32, scroll_y as f32))
@@ -148,6 +393,15 @@ impl Window {
         }
     }
 
+    pub fn get_scroll_accumulated(&self) -> (f32, f32) {
+        // scrolling isn't supported
+        (0.0, 0.0)
+    }
+
+    pub fn reset_scroll_accumulator(&mut self) {
+        // scrolling isn't supported
+    }
+
     pub fn get_mouse_down(&self, button: MouseButton) -> bool {
         match button {
             MouseButton::Left => self.mouse_state.0,
@@ -156,6 +410,15 @@ impl Window {
         }
     }
 
+    pub fn get_mouse_down_with_modifiers(&self, button: MouseButton) -> Option<Modifiers> {
+        // The modifiers aren't captured when the button goes down on RedoxOS
+        if self.get_mouse_down(button) {
+            Some(self.key_handler.modifiers())
+        } else {
+            None
+        }
+    }
+
     pub fn get_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
         if let Some((mouse_x, mouse_y)) = self.mouse_pos {
             mouse_handler::get_pos(
@@ -171,6 +434,16 @@ impl Window {
         }
     }
 
+    pub fn get_pen(&self) -> Option<PenState> {
+        // Pens aren't supported on Redox
+        None
+    }
+
+    pub fn get_touch_points(&self) -> Vec<TouchPoint> {
+        // Touch input isn't supported on Redox
+        Vec::new()
+    }
+
     pub fn get_unscaled_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
         if let Some((mouse_x, mouse_y)) = self.mouse_pos {
             mouse_handler::get_pos(
@@ -186,18 +459,89 @@ impl Window {
         }
     }
 
-    pub fn set_cursor_style(&mut self, _cursor: CursorStyle) {
-        // Orbital doesn't support cursor styles yet
+    pub fn get_mouse_history(&self) -> Vec<(f32, f32, std::time::Duration)> {
+        self.mouse_history.get(self.window_scale as f32)
+    }
+
+    pub fn get_mouse_pos_on_screen(&self) -> Option<(f32, f32)> {
+        self.mouse_pos
+            .map(|(x, y)| ((self.window.x() + x) as f32, (self.window.y() + y) as f32))
+    }
+
+    pub fn set_cursor_style(&mut self, cursor: CursorStyle) {
+        // Orbital doesn't support cursor styles yet, the style is only kept for the getter
+        self.cursor = cursor;
+    }
+
+    #[inline]
+    pub fn get_cursor_style(&self) -> CursorStyle {
+        self.cursor
+    }
+
+    #[inline]
+    pub fn get_cursor_hotspot(&self) -> (i32, i32) {
+        // Orbital doesn't tell where the point of its cursor is
+        (0, 0)
+    }
+
+    pub fn set_animated_cursor(
+        &mut self,
+        _frames: &[(&[u32], u32, u32)],
+        _frame_duration: std::time::Duration,
+        _hot_x: u32,
+        _hot_y: u32,
+    ) {
+        // Orbital only has its own cursors
     }
 
     pub fn set_cursor_visibility(&mut self, visibility: bool) {
+        self.cursor_auto_hide.set_visible(visibility);
         self.window.set_mouse_cursor(visibility);
     }
 
+    #[inline]
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_auto_hide.is_visible()
+    }
+
+    pub fn set_cursor_auto_hide(&mut self, timeout: Option<std::time::Duration>) {
+        if let Some(visible) = self.cursor_auto_hide.set_timeout(timeout) {
+            self.window.set_mouse_cursor(visible);
+        }
+    }
+
+    pub fn set_cursor_confined(&mut self, _confined: bool) {
+        // Orbital can't confine the cursor to a window
+    }
+
+    pub fn set_gameplay_cursor_mode(&mut self, enabled: bool) {
+        // Without confinement the cursor would leave the window, so only hide it
+        if enabled {
+            if self.gameplay_cursor.is_none() {
+                self.gameplay_cursor = Some(self.is_cursor_visible());
+                self.set_cursor_visibility(false);
+            }
+        } else if let Some(visible) = self.gameplay_cursor.take() {
+            self.set_cursor_visibility(visible);
+        }
+    }
+
+    pub fn get_mouse_delta(&self) -> (f32, f32) {
+        (0.0, 0.0)
+    }
+
+    pub fn set_keyboard_grab(&mut self, _grab: bool) {
+        // Orbital doesn't let windows grab the keyboard
+    }
+
     pub fn get_keys(&self) -> Vec<Key> {
         self.key_handler.get_keys()
     }
 
+    pub fn keys_down(&self) -> &[Key] {
+        self.key_handler.keys_down()
+    }
+
     pub fn get_keys_pressed(&self, repeat: KeyRepeat) -> Vec<Key> {
         self.key_handler.get_keys_pressed(repeat)
     }
@@ -206,10 +550,26 @@ impl Window {
         self.key_handler.get_keys_released()
     }
 
+    pub fn get_keys_just_pressed(&self) -> Vec<Key> {
+        self.key_handler.get_keys_just_pressed()
+    }
+
+    pub fn get_text_input(&self) -> String {
+        self.key_handler.get_text_input()
+    }
+
     pub fn is_key_down(&self, key: Key) -> bool {
         self.key_handler.is_key_down(key)
     }
 
+    pub fn get_input_timestamp(&self) -> Option<std::time::Duration> {
+        self.key_handler.get_event_time()
+    }
+
+    pub fn get_key_timestamp(&self, key: Key) -> Option<std::time::Duration> {
+        self.key_handler.get_key_time(key)
+    }
+
     pub fn set_key_repeat_delay(&mut self, delay: f32) {
         self.key_handler.set_key_repeat_delay(delay)
     }
@@ -218,10 +578,32 @@ impl Window {
         self.key_handler.set_key_repeat_rate(rate)
     }
 
+    #[inline]
+    pub fn set_key_map(&mut self, key_map: KeyMap) {
+        self.key_handler.set_key_map(key_map)
+    }
+
+    #[inline]
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.key_handler.is_action_down(action)
+    }
+
+    pub fn set_key_repeat_for(&mut self, keys: &[Key], delay: f32, rate: f32) {
+        self.key_handler.set_key_repeat_for(keys, delay, rate)
+    }
+
+    pub fn use_os_key_repeat(&mut self) {
+        // Orbital doesn't expose the key repeat settings, keep the current values
+    }
+
     pub fn is_key_pressed(&self, key: Key, repeat: KeyRepeat) -> bool {
         self.key_handler.is_key_pressed(key, repeat)
     }
 
+    pub fn is_key_repeat(&self, key: Key) -> bool {
+        self.key_handler.is_key_repeat(key)
+    }
+
     pub fn is_key_released(&self, key: Key) -> bool {
         self.key_handler.is_key_released(key)
     }
@@ -234,16 +616,58 @@ impl Window {
         self.is_open
     }
 
+    pub fn is_occluded(&self) -> bool {
+        // Orbital doesn't report if a window is covered
+        false
+    }
+
+    pub fn close(&mut self) {
+        // The Orbital window is only released when it's dropped
+        self.is_open = false;
+        self.close_reason
+            .get_or_insert(CloseReason::ProgrammaticClose);
+    }
+
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        self.close_reason
+    }
+
+    pub fn set_close_requested_handling(&mut self, auto: bool) {
+        self.auto_close = auto;
+    }
+
+    #[inline]
+    pub fn set_default_accelerators(&mut self, _enabled: bool) {
+        // minifb doesn't handle any keys itself on Redox
+    }
+
+    pub fn close_requested(&self) -> bool {
+        self.close_requested
+    }
+
     pub fn is_active(&mut self) -> bool {
         self.is_active
     }
 
+    #[inline]
+    pub fn get_primary_selection(&self) -> Option<String> {
+        // There is no primary selection on Redox
+        None
+    }
+
+    #[inline]
+    pub fn set_primary_selection(&mut self, _text: &str) {}
+
     fn process_events(&mut self) {
         self.mouse_scroll = None;
+        self.mouse_history.reset();
+        self.close_requested = false;
 
         for event in self.window.events() {
             match event.to_option() {
                 orbclient::EventOption::Key(key_event) => {
+                    self.key_handler
+                        .set_scancode_state(u32::from(key_event.scancode), key_event.pressed);
                     let key_opt = self.map_key_to_minifb(key_event.scancode);
                     if let Some(key) = key_opt {
                         self.key_handler.set_key_state(key, key_event.pressed);
@@ -251,12 +675,20 @@ impl Window {
                 }
                 orbclient::EventOption::Mouse(mouse_event) => {
                     self.mouse_pos = Some((mouse_event.x, mouse_event.y));
+                    // Orbital doesn't timestamp its events
+                    self.mouse_history
+                        .push(mouse_event.x as f32, mouse_event.y as f32, None);
                 }
                 orbclient::EventOption::Button(button_event) => {
                     self.mouse_state = (button_event.left, button_event.middle, button_event.right);
                 }
                 orbclient::EventOption::Quit(_) => {
-                    self.is_open = false;
+                    if self.auto_close {
+                        self.is_open = false;
+                        self.close_reason = Some(CloseReason::UserRequested);
+                    } else {
+                        self.close_requested = true;
+                    }
                 }
                 orbclient::EventOption::Focus(focus_event) => {
                     self.is_active = focus_event.focused;
@@ -407,9 +839,37 @@ impl Window {
         self.menus.retain(|ref menu| menu.handle != handle);
     }
 
+    pub fn update_menu(&mut self, handle: MenuHandle, menu: &Menu) {
+        if let Some(existing) = self.menus.iter_mut().find(|m| m.handle == handle) {
+            *existing = menu.internal.clone();
+            existing.handle = handle;
+        }
+    }
+
+    #[inline]
+    pub fn set_app_menu(&mut self, _menu: &Menu) {
+        // There is no application menu on Redox
+    }
+
+    pub fn show_context_menu(&mut self, menu: &Menu, x: i32, y: i32) {
+        self.context_menu = Some((menu.internal.clone(), x, y));
+    }
+
+    pub fn take_posix_context_menu(&mut self) -> Option<(UnixMenu, i32, i32)> {
+        self.context_menu.take()
+    }
+
     pub fn is_menu_pressed(&mut self) -> Option<usize> {
         None
     }
+
+    pub fn get_menu_height(&self) -> usize {
+        0
+    }
+
+    pub fn menu_activation_source(&self) -> Option<MenuSource> {
+        None
+    }
 }
 
 pub struct Menu {
@@ -438,6 +898,7 @@ impl Menu {
             enabled: true,
             key: Key::Unknown,
             modifier: 0,
+            icon: None,
         });
     }
 
@@ -457,6 +918,7 @@ impl Menu {
             enabled: item.enabled,
             key: item.key,
             modifier: item.modifier,
+            icon: item.icon.clone(),
         });
         item_handle
     }
@@ -466,4 +928,36 @@ impl Menu {
             .items
             .retain(|ref item| item.handle.0 != handle.0);
     }
+
+    pub fn clear(&mut self) {
+        self.internal.items.clear();
+    }
+}
+
+// orbital has no system tray
+pub struct TrayIcon;
+
+impl TrayIcon {
+    pub fn new(_icon: Icon, _tooltip: &str) -> Result<TrayIcon> {
+        Err(Error::Unsupported {
+            operation: "TrayIcon",
+            platform: "RedoxOS",
+        })
+    }
+
+    pub fn set_tooltip(&mut self, _tooltip: &str) {}
+
+    pub fn set_menu(&mut self, _menu: &Menu) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn update(&mut self) {}
+
+    pub fn is_menu_pressed(&mut self) -> Option<usize> {
+        None
+    }
+
+    pub fn is_clicked(&mut self) -> bool {
+        false
+    }
 }
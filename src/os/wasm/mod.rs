@@ -6,19 +6,24 @@ use web_sys::ImageData;
 use web_sys::{window, CanvasRenderingContext2d, HtmlCanvasElement};
 
 use crate::buffer_helper;
+use crate::error::Error;
 use crate::key_handler::KeyHandler;
 use crate::mouse_handler;
+use crate::present::PresentCallbacks;
 use crate::Icon;
 use crate::InputCallback;
+use crate::Rect;
 use crate::Result;
-use crate::{CursorStyle, MouseButton, MouseMode};
-use crate::{Key, KeyRepeat};
-use crate::{MenuHandle, MenuItem, MenuItemHandle, UnixMenu, UnixMenuItem};
-use crate::{Scale, WindowOptions};
+use crate::WindowLevel;
+use crate::{BorderFill, CloseReason, CursorStyle, Monitor, MouseButton, MouseMode};
+use crate::{GammaRamp, Key, KeyMap, KeyRepeat, Modifiers, PixelFormat};
+use crate::{MenuHandle, MenuItem, MenuItemHandle, MenuSource, UnixMenu, UnixMenuItem};
+use crate::{PenState, Scale, TouchPoint, WindowOptions};
 use core;
 use keycodes::event_to_key;
 use std::cell::{Cell, RefCell};
 use std::os::raw;
+use std::path::Path;
 use std::rc::Rc;
 
 #[inline(always)]
@@ -40,17 +45,46 @@ pub struct Window {
     height: u32,
     bg_color: u32,
     window_scale: usize,
+    pixel_format: PixelFormat,
     img_data: ImageData,
     canvas: HtmlCanvasElement,
     context: Rc<CanvasRenderingContext2d>,
     mouse_state: Rc<MouseState>,
+    mouse_history: mouse_handler::MouseHistory,
+    present_callbacks: PresentCallbacks,
     key_handler: Rc<RefCell<KeyHandler>>,
+    // Copy of the keys that are down, refreshed on update as the handler can't be borrowed out
+    keys_down: Vec<Key>,
     menu_counter: MenuHandle,
     menus: Vec<UnixMenu>,
+    is_open: bool,
+    // Neither is applied to the canvas yet, they're only kept for the getters
+    cursor: CursorStyle,
+    cursor_visible: bool,
 }
 
 impl Window {
+    pub fn new_child(
+        _parent: *mut raw::c_void,
+        _name: &str,
+        _width: usize,
+        _height: usize,
+        _opts: WindowOptions,
+    ) -> Result<Window> {
+        Err(Error::Unsupported {
+            operation: "new_child",
+            platform: "Web",
+        })
+    }
+
     pub fn new(name: &str, width: usize, height: usize, opts: WindowOptions) -> Result<Window> {
+        if opts.gl {
+            return Err(Error::Unsupported {
+                operation: "WindowOptions::gl",
+                platform: "Web",
+            });
+        }
+
         let window_scale = match opts.scale {
             Scale::X1 => 1,
             Scale::X2 => 2,
@@ -167,13 +201,20 @@ impl Window {
             height: height as u32,
             bg_color: 0,
             window_scale,
+            pixel_format: opts.pixel_format,
             img_data,
             canvas,
             context: context.clone(),
             key_handler,
+            keys_down: Vec::new(),
+            mouse_history: mouse_handler::MouseHistory::new(),
+            present_callbacks: PresentCallbacks::default(),
             mouse_state,
             menu_counter: MenuHandle(0),
             menus: Vec::new(),
+            is_open: true,
+            cursor: CursorStyle::Arrow,
+            cursor_visible: true,
         };
 
         window.set_title(name);
@@ -187,12 +228,41 @@ impl Window {
         document.set_title(title);
     }
 
+    pub fn get_title(&self) -> String {
+        let document = window().unwrap().document().unwrap();
+        document.title()
+    }
+
+    pub fn set_document_edited(&mut self, _edited: bool) {
+        // the page has no document state
+    }
+
+    pub fn set_represented_file(&mut self, _path: &Path) {
+        // the canvas isn't backed by a file
+    }
+
     #[inline]
     pub fn set_rate(&mut self, rate: Option<std::time::Duration>) {}
 
     #[inline]
     pub fn update_rate(&mut self) {}
 
+    #[inline]
+    pub fn set_target_fps(&mut self, _fps: u32) {
+        // The browser paces the frames
+    }
+
+    #[inline]
+    pub fn get_frame_time(&self) -> std::time::Duration {
+        // Frames aren't timed here since the browser paces them
+        std::time::Duration::from_secs(0)
+    }
+
+    #[inline]
+    pub fn backend_name(&self) -> &'static str {
+        "wasm"
+    }
+
     #[inline]
     pub fn get_window_handle(&self) -> *mut raw::c_void {
         0 as *mut raw::c_void
@@ -203,12 +273,70 @@ impl Window {
         // TODO?
     }
 
+    pub fn set_visible(&mut self, _visible: bool) {
+        // the canvas is part of the page
+    }
+
+    pub fn set_always_on_bottom(&mut self, _enabled: bool) {
+        // the canvas is part of the page
+    }
+
+    pub fn set_window_level(&mut self, _level: WindowLevel) {
+        // the canvas is part of the page
+    }
+
+    pub fn set_skip_taskbar(&mut self, _skip: bool) {
+        // the canvas is part of the page
+    }
+
+    pub fn set_caption_buttons(&mut self, _minimize: bool, _maximize: bool, _close: bool) {
+        // the canvas has no title bar
+    }
+
+    pub fn set_shadow(&mut self, _enabled: bool) {
+        // the canvas is part of the page
+    }
+
+    pub fn beep(&self) {
+        // browsers don't expose a system sound
+    }
+
     pub fn set_background_color(&mut self, bg_color: u32) {
         self.bg_color = bg_color;
     }
 
+    pub fn set_border_fill(&mut self, _fill: BorderFill) {
+        // only the background color is supported
+    }
+
     pub fn set_cursor_visibility(&mut self, visibility: bool) {
         //TODO?
+        self.cursor_visible = visibility;
+    }
+
+    #[inline]
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    pub fn set_cursor_auto_hide(&mut self, _timeout: Option<std::time::Duration>) {
+        // Cursor visibility isn't supported in the browser yet
+    }
+
+    pub fn set_cursor_confined(&mut self, _confined: bool) {
+        // Browsers can only lock the pointer, not confine it to an element
+    }
+
+    pub fn set_gameplay_cursor_mode(&mut self, _enabled: bool) {
+        // TODO: could be done with the pointer lock API
+    }
+
+    pub fn get_mouse_delta(&self) -> (f32, f32) {
+        (0.0, 0.0)
+    }
+
+    pub fn set_keyboard_grab(&mut self, _grab: bool) {
+        // Browsers keep their own shortcuts
     }
 
     pub fn update_with_buffer_stride(
@@ -219,6 +347,7 @@ impl Window {
         buf_stride: usize,
     ) -> Result<()> {
         buffer_helper::check_buffer_size(buf_width, buf_height, buf_width, buffer)?;
+        let buffer = buffer_helper::to_argb(buffer, self.pixel_format);
         // scaling not implemented. It's faster to just update the buffer
         //unsafe { self.scale_buffer(buffer, buf_width, buf_height, buf_stride) };
         self.update_with_buffer(&buffer).unwrap();
@@ -226,6 +355,36 @@ impl Window {
         Ok(())
     }
 
+    pub fn set_present_callback(&mut self, callback: Box<dyn FnOnce()>) {
+        self.present_callbacks.add(callback);
+    }
+
+    pub fn cancel_present_callbacks(&mut self) {
+        self.present_callbacks.clear_pending();
+    }
+
+    #[inline]
+    pub fn set_damage(&mut self, _rects: &[Rect]) {
+        // the whole canvas is always redrawn
+    }
+
+    pub fn gl_get_proc_address(&self, _name: &str) -> *const raw::c_void {
+        std::ptr::null()
+    }
+
+    pub fn gl_swap_buffers(&mut self) {}
+
+    pub fn update_with_buffer_vsync(
+        &mut self,
+        buffer: &[u32],
+        buf_width: usize,
+        buf_height: usize,
+        buf_stride: usize,
+    ) -> Result<()> {
+        // The browser only shows the canvas on its next animation frame anyway
+        self.update_with_buffer_stride(buffer, buf_width, buf_height, buf_stride)
+    }
+
     pub fn update_with_buffer(&mut self, buffer: &[u32]) -> Result<()> {
         buffer_helper::check_buffer_size(
             self.width as usize,
@@ -243,19 +402,47 @@ impl Window {
         .unwrap();
 
         self.update();
+        self.present_callbacks.frame_presented();
+        self.present_callbacks.frame_shown();
 
         Ok(())
     }
 
+    pub fn poll_input(&mut self) {
+        // The browser delivers input events as they happen, there's nothing to poll
+    }
+
     pub fn update(&mut self) {
-        self.key_handler.borrow_mut().update();
+        if !self.is_open {
+            return;
+        }
+
+        {
+            let mut key_handler = self.key_handler.borrow_mut();
+            key_handler.update();
+            key_handler.update_just_pressed();
+            self.keys_down.clear();
+            self.keys_down.extend_from_slice(key_handler.keys_down());
+        }
+
+        // The listeners only keep the latest position
+        self.mouse_history.reset();
+        if let Some((x, y)) = self.mouse_state.pos.get() {
+            self.mouse_history.push(x as f32, y as f32, None);
+        }
         self.context
             .put_image_data(&self.img_data, 0.0, 0.0)
             .unwrap();
     }
 
     #[inline]
-    pub fn set_icon(&mut self, icon: Icon) {}
+    pub fn set_icon(&mut self, _icon: Icon) -> Result<()> {
+        // the page's favicon belongs to the document, not the canvas
+        Err(Error::Unsupported {
+            operation: "set_icon",
+            platform: "Web",
+        })
+    }
 
     #[inline]
     pub fn set_position(&mut self, x: isize, y: isize) {}
@@ -266,11 +453,110 @@ impl Window {
         (x as isize, y as isize)
     }
 
+    pub fn get_outer_position(&self) -> (isize, isize) {
+        self.get_position()
+    }
+
+    pub fn get_outer_size(&self) -> (usize, usize) {
+        // the canvas has no decorations
+        self.get_unscaled_size()
+    }
+
     #[inline]
     pub fn get_size(&self) -> (usize, usize) {
         (self.width as usize, self.height as usize)
     }
 
+    #[inline]
+    pub fn get_unscaled_size(&self) -> (usize, usize) {
+        // The canvas is the buffer size times the window scale
+        (
+            self.width as usize * self.window_scale,
+            self.height as usize * self.window_scale,
+        )
+    }
+
+    #[inline]
+    pub fn get_buffer_scale(&self) -> (f32, f32) {
+        // The canvas is always the buffer size times the window scale
+        (self.window_scale as f32, self.window_scale as f32)
+    }
+
+    pub fn get_content_scale(&self) -> f32 {
+        web_sys::window().map_or(1.0, |window| window.device_pixel_ratio() as f32)
+    }
+
+    #[inline]
+    pub fn get_native_pixel_format(&self) -> Option<PixelFormat> {
+        // The canvas takes the pixels as RGBA bytes
+        None
+    }
+
+    pub fn is_transparency_active(&self) -> bool {
+        // The transparency option isn't supported on the web
+        false
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    pub fn set_maximized(&mut self, _maximized: bool) {
+        // The canvas is laid out by the page
+    }
+
+    #[inline]
+    pub fn get_monitors(&self) -> Vec<Monitor> {
+        // Browsers don't list the monitors
+        Vec::new()
+    }
+
+    #[inline]
+    pub fn get_keyboard_layout(&self) -> Option<String> {
+        // Browsers only expose the layout through an experimental API
+        None
+    }
+
+    #[inline]
+    pub fn set_gamma_ramp(&mut self, _ramp: &GammaRamp) -> Result<()> {
+        Err(Error::Unsupported {
+            operation: "set_gamma_ramp",
+            platform: "Web",
+        })
+    }
+
+    #[inline]
+    pub fn get_gamma_ramp(&self) -> Option<GammaRamp> {
+        None
+    }
+
+    #[inline]
+    pub fn display_count(&self) -> usize {
+        // Browsers don't list the monitors
+        0
+    }
+
+    #[inline]
+    pub fn current_monitor(&self) -> Option<Monitor> {
+        // Browsers don't say which monitor a page is shown on
+        None
+    }
+
+    #[inline]
+    pub fn moved_to_monitor(&self) -> bool {
+        false
+    }
+
+    pub fn set_size(&mut self, _width: usize, _height: usize) {
+        // The canvas size follows the buffer given to update_with_buffer
+    }
+
+    pub fn set_aspect_ratio(&mut self, _ratio: Option<(u32, u32)>) {
+        // The canvas can't be resized by the user
+    }
+
     pub fn get_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
         if let Some((mouse_x, mouse_y)) = self.mouse_state.pos.get() {
             mouse_handler::get_pos(
@@ -286,6 +572,16 @@ impl Window {
         }
     }
 
+    pub fn get_pen(&self) -> Option<PenState> {
+        // Pens aren't supported on the web yet
+        None
+    }
+
+    pub fn get_touch_points(&self) -> Vec<TouchPoint> {
+        // Touch input isn't supported on the web yet
+        Vec::new()
+    }
+
     pub fn get_unscaled_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
         if let Some((mouse_x, mouse_y)) = self.mouse_state.pos.get() {
             mouse_handler::get_pos(
@@ -301,6 +597,15 @@ impl Window {
         }
     }
 
+    pub fn get_mouse_history(&self) -> Vec<(f32, f32, std::time::Duration)> {
+        self.mouse_history.get(self.window_scale as f32)
+    }
+
+    pub fn get_mouse_pos_on_screen(&self) -> Option<(f32, f32)> {
+        // the page can't see the pointer outside of the browser
+        None
+    }
+
     pub fn get_mouse_down(&self, button: MouseButton) -> bool {
         match button {
             MouseButton::Left => self.mouse_state.left_button.get(),
@@ -309,17 +614,62 @@ impl Window {
         }
     }
 
+    pub fn get_mouse_down_with_modifiers(&self, button: MouseButton) -> Option<Modifiers> {
+        // The modifiers aren't captured when the button goes down on the web
+        if self.get_mouse_down(button) {
+            Some(self.key_handler.borrow().modifiers())
+        } else {
+            None
+        }
+    }
+
     pub fn get_scroll_wheel(&self) -> Option<(f32, f32)> {
         None
     }
 
+    pub fn get_scroll_accumulated(&self) -> (f32, f32) {
+        // scrolling isn't supported
+        (0.0, 0.0)
+    }
+
+    pub fn reset_scroll_accumulator(&mut self) {
+        // scrolling isn't supported
+    }
+
+    #[inline]
+    pub fn set_cursor_style(&mut self, cursor: CursorStyle) {
+        self.cursor = cursor;
+    }
+
+    #[inline]
+    pub fn get_cursor_style(&self) -> CursorStyle {
+        self.cursor
+    }
+
     #[inline]
-    pub fn set_cursor_style(&mut self, cursor: CursorStyle) {}
+    pub fn get_cursor_hotspot(&self) -> (i32, i32) {
+        // browsers don't expose the hotspots of the CSS cursors
+        (0, 0)
+    }
+
+    pub fn set_animated_cursor(
+        &mut self,
+        _frames: &[(&[u32], u32, u32)],
+        _frame_duration: std::time::Duration,
+        _hot_x: u32,
+        _hot_y: u32,
+    ) {
+        // custom cursors would need the frames encoded as CSS images
+    }
 
     pub fn get_keys(&self) -> Vec<Key> {
         self.key_handler.borrow().get_keys()
     }
 
+    pub fn keys_down(&self) -> &[Key] {
+        &self.keys_down
+    }
+
     pub fn get_keys_pressed(&self, repeat: KeyRepeat) -> Vec<Key> {
         self.key_handler.borrow().get_keys_pressed(repeat)
     }
@@ -328,6 +678,14 @@ impl Window {
         self.key_handler.borrow().is_key_down(key)
     }
 
+    pub fn get_input_timestamp(&self) -> Option<std::time::Duration> {
+        self.key_handler.borrow().get_event_time()
+    }
+
+    pub fn get_key_timestamp(&self, key: Key) -> Option<std::time::Duration> {
+        self.key_handler.borrow().get_key_time(key)
+    }
+
     pub fn set_key_repeat_delay(&mut self, delay: f32) {
         self.key_handler.borrow_mut().set_key_repeat_delay(delay)
     }
@@ -336,10 +694,33 @@ impl Window {
         self.key_handler.borrow_mut().set_key_repeat_rate(rate)
     }
 
+    pub fn set_key_map(&mut self, key_map: KeyMap) {
+        self.key_handler.borrow_mut().set_key_map(key_map)
+    }
+
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.key_handler.borrow().is_action_down(action)
+    }
+
+    pub fn set_key_repeat_for(&mut self, keys: &[Key], delay: f32, rate: f32) {
+        self.key_handler
+            .borrow_mut()
+            .set_key_repeat_for(keys, delay, rate)
+    }
+
+    #[inline]
+    pub fn use_os_key_repeat(&mut self) {
+        // Browsers don't expose the key repeat settings, keep the current values
+    }
+
     pub fn is_key_pressed(&self, key: Key, repeat: KeyRepeat) -> bool {
         self.key_handler.borrow().is_key_pressed(key, repeat)
     }
 
+    pub fn is_key_repeat(&self, key: Key) -> bool {
+        self.key_handler.borrow().is_key_repeat(key)
+    }
+
     pub fn is_key_released(&self, key: Key) -> bool {
         self.key_handler.borrow().is_key_released(key)
     }
@@ -350,17 +731,72 @@ impl Window {
 
     #[inline]
     pub fn is_open(&self) -> bool {
-        true
+        self.is_open
+    }
+
+    pub fn is_occluded(&self) -> bool {
+        window()
+            .and_then(|w| w.document())
+            .map_or(false, |d| d.hidden())
+    }
+
+    pub fn close(&mut self) {
+        self.canvas.remove();
+        self.is_open = false;
+    }
+
+    #[inline]
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        // A canvas can only be closed by the application
+        if self.is_open {
+            None
+        } else {
+            Some(CloseReason::ProgrammaticClose)
+        }
+    }
+
+    #[inline]
+    pub fn set_close_requested_handling(&mut self, _auto: bool) {
+        // A canvas can't be closed by the user
+    }
+
+    #[inline]
+    pub fn set_default_accelerators(&mut self, _enabled: bool) {
+        // Browser shortcuts can't be turned off
+    }
+
+    #[inline]
+    pub fn close_requested(&self) -> bool {
+        false
     }
 
     #[inline]
     pub fn get_keys_released(&self) -> Vec<Key> {
         self.key_handler.borrow().get_keys_released()
     }
+
+    #[inline]
+    pub fn get_keys_just_pressed(&self) -> Vec<Key> {
+        self.key_handler.borrow().get_keys_just_pressed()
+    }
+
+    #[inline]
+    pub fn get_text_input(&self) -> String {
+        self.key_handler.borrow().get_text_input()
+    }
     pub fn is_active(&mut self) -> bool {
         true
     }
 
+    #[inline]
+    pub fn get_primary_selection(&self) -> Option<String> {
+        // There is no primary selection in the browser
+        None
+    }
+
+    #[inline]
+    pub fn set_primary_selection(&mut self, _text: &str) {}
+
     fn next_menu_handle(&mut self) -> MenuHandle {
         let handle = self.menu_counter;
         self.menu_counter.0 += 1;
@@ -379,9 +815,33 @@ impl Window {
         self.menus.retain(|ref menu| menu.handle != handle);
     }
 
+    pub fn update_menu(&mut self, handle: MenuHandle, menu: &Menu) {
+        if let Some(existing) = self.menus.iter_mut().find(|m| m.handle == handle) {
+            *existing = menu.internal.clone();
+            existing.handle = handle;
+        }
+    }
+
+    #[inline]
+    pub fn set_app_menu(&mut self, _menu: &Menu) {
+        // Menus aren't shown in the browser
+    }
+
+    pub fn show_context_menu(&mut self, _menu: &Menu, _x: i32, _y: i32) {
+        // Menus aren't shown in the browser
+    }
+
     pub fn is_menu_pressed(&mut self) -> Option<usize> {
         None
     }
+
+    pub fn get_menu_height(&self) -> usize {
+        0
+    }
+
+    pub fn menu_activation_source(&self) -> Option<MenuSource> {
+        None
+    }
 }
 
 pub struct Menu {
@@ -418,11 +878,44 @@ impl Menu {
             enabled: item.enabled,
             key: item.key,
             modifier: item.modifier,
+            icon: item.icon.clone(),
         });
         item_handle
     }
 
     pub fn remove_item(&mut self, handle: &MenuItemHandle) {}
+
+    pub fn clear(&mut self) {
+        self.internal.items.clear();
+    }
+}
+
+// browsers have no system tray
+pub struct TrayIcon;
+
+impl TrayIcon {
+    pub fn new(_icon: Icon, _tooltip: &str) -> Result<TrayIcon> {
+        Err(Error::Unsupported {
+            operation: "TrayIcon",
+            platform: "Web",
+        })
+    }
+
+    pub fn set_tooltip(&mut self, _tooltip: &str) {}
+
+    pub fn set_menu(&mut self, _menu: &Menu) -> Result<()> {
+        Ok(())
+    }
+
+    pub fn update(&mut self) {}
+
+    pub fn is_menu_pressed(&mut self) -> Option<usize> {
+        None
+    }
+
+    pub fn is_clicked(&mut self) -> bool {
+        false
+    }
 }
 
 unsafe impl raw_window_handle::HasRawWindowHandle for Window {
@@ -432,3 +925,17 @@ unsafe impl raw_window_handle::HasRawWindowHandle for Window {
         raw_window_handle::RawWindowHandle::Web(handle)
     }
 }
+
+#[cfg(feature = "rwh_06")]
+impl Window {
+    pub fn raw_window_handle_06(&self) -> Option<raw_window_handle_06::RawWindowHandle> {
+        //TODO: assign a different ID to each window
+        let handle = raw_window_handle_06::WebWindowHandle::new(0);
+        Some(raw_window_handle_06::RawWindowHandle::Web(handle))
+    }
+
+    pub fn raw_display_handle_06(&self) -> Option<raw_window_handle_06::RawDisplayHandle> {
+        let handle = raw_window_handle_06::WebDisplayHandle::new();
+        Some(raw_window_handle_06::RawDisplayHandle::Web(handle))
+    }
+}
@@ -1,33 +1,54 @@
 #![cfg(target_os = "windows")]
 
 const INVALID_ACCEL: usize = 0xffffffff;
+// Not defined by winapi
+const MAPVK_VK_TO_VSC_EX: u32 = 4;
 
 use crate::error::Error;
 use crate::icon::Icon;
 use crate::key_handler::KeyHandler;
 use crate::rate::UpdateRate;
+use crate::touch_handler::TouchHandler;
+use crate::BorderFill;
+use crate::GammaRamp;
+use crate::MenuIcon;
+use crate::Modifiers;
+use crate::PixelFormat;
+use crate::Rect;
 use crate::Result;
-use crate::{CursorStyle, MenuHandle, MenuItem, MenuItemHandle};
+use crate::WindowLevel;
+use crate::{CloseReason, CursorStyle, MenuHandle, MenuItem, MenuItemHandle, MenuSource, Monitor};
 use crate::{
-    InputCallback, Key, KeyRepeat, MouseButton, MouseMode, Scale, ScaleMode, WindowOptions,
+    InputCallback, Key, KeyMap, KeyRepeat, MouseButton, MouseMode, PenState, Scale, ScaleMode,
+    TouchPhase, TouchPoint, WindowOptions,
 };
 use crate::{MENU_KEY_ALT, MENU_KEY_CTRL, MENU_KEY_SHIFT, MENU_KEY_WIN};
 
 use crate::buffer_helper;
 use crate::mouse_handler;
+use crate::present::PresentCallbacks;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::ffi::CString;
 use std::ffi::OsStr;
 use std::mem;
 use std::os::raw;
 use std::os::windows::ffi::OsStrExt;
+use std::path::Path;
 use std::ptr;
 
 use winapi::shared::basetsd;
 use winapi::shared::minwindef::{self, LPARAM, WPARAM};
 use winapi::shared::ntdef;
 use winapi::shared::windef;
+use winapi::shared::winerror;
+use winapi::um::dwmapi;
 use winapi::um::errhandlingapi;
 use winapi::um::fileapi::GetFullPathNameW;
 use winapi::um::libloaderapi;
+use winapi::um::shellapi;
+use winapi::um::shobjidl_core;
+use winapi::um::uxtheme;
 use winapi::um::wingdi;
 use winapi::um::winuser::{
     self, ICON_BIG, ICON_SMALL, IMAGE_ICON, LR_DEFAULTSIZE, LR_LOADFROMFILE, WM_SETICON,
@@ -41,6 +62,8 @@ struct BitmapInfo {
 }
 
 fn update_key_state(window: &mut Window, wparam: u32, state: bool) {
+    window.key_handler.set_scancode_state(wparam & 0x1ff, state);
+
     match wparam & 0x1ff {
         0x00B => window.key_handler.set_key_state(Key::Key0, state),
         0x002 => window.key_handler.set_key_state(Key::Key1, state),
@@ -145,10 +168,16 @@ fn update_key_state(window: &mut Window, wparam: u32, state: bool) {
     }
 }
 
+fn set_mouse_button(window: &mut Window, button: MouseButton, is_down: bool) {
+    window.mouse.state[button as usize] = is_down;
+    let modifiers = window.key_handler.modifiers();
+    window
+        .button_modifiers
+        .set_button_state(button, is_down, modifiers);
+}
+
 fn char_down(window: &mut Window, code_point: u32) {
-    if let Some(ref mut callback) = window.key_handler.key_callback {
-        callback.add_char(code_point);
-    }
+    window.key_handler.add_char(code_point);
 }
 
 #[cfg(target_arch = "x86_64")]
@@ -190,6 +219,54 @@ unsafe fn get_window_long(window: windef::HWND) -> basetsd::LONG_PTR {
     winuser::GetWindowLongPtrW(window, winuser::GWLP_USERDATA)
 }
 
+thread_local! {
+    // Low-level hooks are called on the thread that installed them, so the window that grabbed the
+    // keyboard is kept per thread
+    static KEYBOARD_GRAB_WINDOW: Cell<windef::HWND> = Cell::new(ptr::null_mut());
+}
+
+// Keys the system handles before they reach the window, Ctrl+Alt+Del can't be intercepted at all
+fn is_system_key(info: &winuser::KBDLLHOOKSTRUCT) -> bool {
+    let alt = (info.flags & winuser::LLKHF_ALTDOWN) != 0;
+    let ctrl = unsafe { winuser::GetKeyState(winuser::VK_CONTROL) } < 0;
+
+    match info.vkCode as raw::c_int {
+        winuser::VK_LWIN | winuser::VK_RWIN => true,
+        winuser::VK_TAB => alt,
+        winuser::VK_ESCAPE => alt || ctrl,
+        _ => false,
+    }
+}
+
+unsafe extern "system" fn keyboard_hook_proc(
+    code: raw::c_int,
+    wparam: minwindef::WPARAM,
+    lparam: minwindef::LPARAM,
+) -> minwindef::LRESULT {
+    let window = KEYBOARD_GRAB_WINDOW.with(|w| w.get());
+
+    if code == winuser::HC_ACTION
+        && !window.is_null()
+        && winuser::GetForegroundWindow() == window
+        && get_window_long(window) != 0
+    {
+        let info = &*(lparam as *const winuser::KBDLLHOOKSTRUCT);
+
+        if is_system_key(info) {
+            let wnd: &mut Window = mem::transmute(get_window_long(window));
+            let extended = (info.flags & winuser::LLKHF_EXTENDED) << 8;
+            let state = wparam as minwindef::UINT == winuser::WM_KEYDOWN
+                || wparam as minwindef::UINT == winuser::WM_SYSKEYDOWN;
+
+            wnd.key_handler.set_event_time_ms(info.time);
+            update_key_state(wnd, info.scanCode | extended, state);
+            return 1;
+        }
+    }
+
+    winuser::CallNextHookEx(ptr::null_mut(), code, wparam, lparam)
+}
+
 unsafe extern "system" fn wnd_proc(
     window: windef::HWND,
     msg: minwindef::UINT,
@@ -206,9 +283,17 @@ unsafe extern "system" fn wnd_proc(
 
     let mut wnd: &mut Window = mem::transmute(user_data);
 
+    // GetMessageTime returns the time of the message that is being handled
+    if (winuser::WM_KEYFIRST..=winuser::WM_KEYLAST).contains(&msg)
+        || (winuser::WM_MOUSEFIRST..=winuser::WM_MOUSELAST).contains(&msg)
+    {
+        wnd.key_handler
+            .set_event_time_ms(winuser::GetMessageTime() as u32);
+    }
+
     match msg {
         winuser::WM_SYSCOMMAND => {
-            if wparam == winuser::SC_KEYMENU {
+            if wparam == winuser::SC_KEYMENU && !wnd.default_accelerators {
                 return 0;
             }
         }
@@ -216,11 +301,12 @@ unsafe extern "system" fn wnd_proc(
         winuser::WM_MOUSEWHEEL => {
             let scroll = ((((wparam as u32) >> 16) & 0xffff) as i16) as f32 * 0.1;
             wnd.mouse.scroll = scroll;
+            wnd.scroll_accumulator.add(0.0, scroll);
         }
 
         winuser::WM_SETCURSOR => {
             if winapi::shared::minwindef::LOWORD(lparam as u32) == winuser::HTCLIENT as u16 {
-                winuser::SetCursor(wnd.cursors[wnd.cursor as usize]);
+                winuser::SetCursor(wnd.current_cursor());
                 return 1;
             }
         }
@@ -232,7 +318,11 @@ unsafe extern "system" fn wnd_proc(
 
         winuser::WM_SYSKEYDOWN => {
             update_key_state(wnd, (lparam as u32) >> 16, true);
-            return 0;
+
+            // DefWindowProc turns these into Alt+F4, Alt+Space, F10 and so on
+            if !wnd.default_accelerators {
+                return 0;
+            }
         }
 
         winuser::WM_CHAR => {
@@ -243,9 +333,9 @@ unsafe extern "system" fn wnd_proc(
             char_down(wnd, wparam as u32);
         }
 
-        winuser::WM_LBUTTONDOWN => wnd.mouse.state[0] = true,
+        winuser::WM_LBUTTONDOWN => set_mouse_button(wnd, MouseButton::Left, true),
 
-        winuser::WM_LBUTTONUP => wnd.mouse.state[0] = false,
+        winuser::WM_LBUTTONUP => set_mouse_button(wnd, MouseButton::Left, false),
 
         winuser::WM_MOUSEMOVE => {
             let button_checks = [
@@ -261,18 +351,107 @@ unsafe extern "system" fn wnd_proc(
                     wnd.mouse.state[i] = false;
                 }
             }
+
+            // Client coordinates, negative when the mouse is captured and left the window
+            let x = minwindef::LOWORD(lparam as u32) as i16;
+            let y = minwindef::HIWORD(lparam as u32) as i16;
+            wnd.mouse_history
+                .push(x as f32, y as f32, wnd.key_handler.get_event_time());
+        }
+
+        winuser::WM_TOUCH => {
+            let count = minwindef::LOWORD(wparam as u32) as usize;
+            let handle = lparam as winuser::HTOUCHINPUT;
+            let mut inputs: Vec<winuser::TOUCHINPUT> = vec![mem::zeroed(); count];
+
+            if winuser::GetTouchInputInfo(
+                handle,
+                count as u32,
+                inputs.as_mut_ptr(),
+                mem::size_of::<winuser::TOUCHINPUT>() as i32,
+            ) != 0
+            {
+                // Touch positions are given in hundredths of a pixel in screen coordinates
+                let mut origin = windef::POINT { x: 0, y: 0 };
+                winuser::ClientToScreen(window, &mut origin);
+
+                for input in inputs.iter() {
+                    let phase = if input.dwFlags & winuser::TOUCHEVENTF_DOWN != 0 {
+                        TouchPhase::Begin
+                    } else if input.dwFlags & winuser::TOUCHEVENTF_UP != 0 {
+                        TouchPhase::End
+                    } else {
+                        TouchPhase::Move
+                    };
+
+                    wnd.touch_handler.set_touch(
+                        input.dwID as u64,
+                        input.x as f32 / 100.0 - origin.x as f32,
+                        input.y as f32 / 100.0 - origin.y as f32,
+                        phase,
+                    );
+                }
+
+                winuser::CloseTouchInputHandle(handle);
+                return 0;
+            }
+        }
+
+        // These are passed on to DefWindowProc so the pen keeps working as a mouse
+        winuser::WM_POINTERUPDATE
+        | winuser::WM_POINTERDOWN
+        | winuser::WM_POINTERUP
+        | winuser::WM_POINTERLEAVE => {
+            let pointer_id = minwindef::LOWORD(wparam as u32) as u32;
+            let mut pointer_type = 0;
+            let mut info: winuser::POINTER_PEN_INFO = mem::zeroed();
+
+            if winuser::GetPointerType(pointer_id, &mut pointer_type) != 0
+                && pointer_type == winuser::PT_PEN
+                && winuser::GetPointerPenInfo(pointer_id, &mut info) != 0
+            {
+                let flags = info.pointerInfo.pointerFlags;
+                let mut point = info.pointerInfo.ptPixelLocation;
+                winuser::ScreenToClient(window, &mut point);
+
+                let pressure = if flags & winuser::POINTER_FLAG_INCONTACT == 0 {
+                    0.0
+                } else if info.penMask & winuser::PEN_MASK_PRESSURE != 0 {
+                    // Pressure is reported from 0 to 1024
+                    info.pressure as f32 / 1024.0
+                } else {
+                    1.0
+                };
+
+                wnd.pen = Some(PenState {
+                    x: point.x as f32,
+                    y: point.y as f32,
+                    pressure,
+                    tilt_x: info.tiltX as f32,
+                    tilt_y: info.tiltY as f32,
+                    in_range: msg != winuser::WM_POINTERLEAVE
+                        && flags & winuser::POINTER_FLAG_INRANGE != 0,
+                });
+            }
         }
 
-        winuser::WM_MBUTTONDOWN => wnd.mouse.state[1] = true,
+        winuser::WM_MBUTTONDOWN => set_mouse_button(wnd, MouseButton::Middle, true),
 
-        winuser::WM_MBUTTONUP => wnd.mouse.state[1] = false,
+        winuser::WM_MBUTTONUP => set_mouse_button(wnd, MouseButton::Middle, false),
 
-        winuser::WM_RBUTTONDOWN => wnd.mouse.state[2] = true,
+        winuser::WM_RBUTTONDOWN => set_mouse_button(wnd, MouseButton::Right, true),
 
-        winuser::WM_RBUTTONUP => wnd.mouse.state[2] = false,
+        winuser::WM_RBUTTONUP => set_mouse_button(wnd, MouseButton::Right, false),
 
         winuser::WM_CLOSE => {
+            if !wnd.auto_close {
+                // Let the application decide if the window should be closed
+                wnd.close_requested = true;
+                return 0;
+            }
+
             wnd.is_open = false;
+            wnd.close_reason = Some(CloseReason::UserRequested);
         }
 
         winuser::WM_KEYUP => {
@@ -282,12 +461,21 @@ unsafe extern "system" fn wnd_proc(
 
         winuser::WM_SYSKEYUP => {
             update_key_state(wnd, (lparam as u32) >> 16, false);
-            return 0;
+
+            if !wnd.default_accelerators {
+                return 0;
+            }
         }
 
         winuser::WM_COMMAND => {
             if lparam == 0 {
                 wnd.accel_key = (wparam & 0xffff) as usize;
+                // The high word is 1 when the command comes from an accelerator
+                wnd.accel_source = if (wparam >> 16) & 0xffff == 1 {
+                    MenuSource::Shortcut
+                } else {
+                    MenuSource::Click
+                };
             }
         }
 
@@ -303,6 +491,47 @@ unsafe extern "system" fn wnd_proc(
             let height = ((lparam as u32) >> 16) & 0xffff;
             wnd.width = width as i32;
             wnd.height = height as i32;
+
+            if wnd.cursor_confined && wnd.is_active() {
+                wnd.clip_cursor(true);
+            }
+        }
+
+        winuser::WM_SIZING => {
+            if let Some(ratio) = wnd.aspect_ratio {
+                let rect = &mut *(lparam as *mut windef::RECT);
+                wnd.constrain_to_aspect_ratio(rect, wparam as u32, ratio);
+                return minwindef::TRUE as minwindef::LRESULT;
+            }
+        }
+
+        winuser::WM_MOVE => {
+            if wnd.cursor_confined && wnd.is_active() {
+                wnd.clip_cursor(true);
+            }
+        }
+
+        // The clip rectangle is shared by the whole system so it's only kept while focused
+        winuser::WM_ACTIVATE => {
+            let active = (wparam & 0xffff) as minwindef::WORD != winuser::WA_INACTIVE;
+
+            if wnd.cursor_confined {
+                wnd.clip_cursor(active);
+            }
+
+            if wnd.keyboard_grabbed {
+                wnd.hook_keyboard(active);
+            }
+        }
+
+        winuser::WM_INPUTLANGCHANGE => {
+            wnd.key_handler.set_keyboard_layout(keyboard_layout_name());
+        }
+
+        // Sent to every top-level window when a display is added, removed or changes mode
+        winuser::WM_DISPLAYCHANGE => {
+            let count = winuser::GetSystemMetrics(winuser::SM_CMONITORS) as usize;
+            wnd.key_handler.set_display_count(count);
         }
 
         winuser::WM_PAINT => {
@@ -427,6 +656,10 @@ unsafe extern "system" fn wnd_proc(
                 _ => (),
             }
 
+            if wnd.border_fill.is_some() {
+                wnd.draw_border_fill(dc);
+            }
+
             wingdi::StretchDIBits(
                 dc,
                 x_offset,
@@ -493,18 +726,56 @@ pub struct Window {
     dc: Option<windef::HDC>,
     window: Option<windef::HWND>,
     clear_brush: windef::HBRUSH,
+    // Drawn instead of `clear_brush` when set, `border_buffer` holds it at the window size
+    border_fill: Option<BorderFill>,
+    border_buffer: Vec<u32>,
     is_open: bool,
+    // Hidden by `close`, the window is only destroyed when dropped
+    closed: bool,
+    close_reason: Option<CloseReason>,
+    auto_close: bool,
+    default_accelerators: bool,
+    close_requested: bool,
+    transparency: bool,
+    pixel_format: PixelFormat,
+    // Holds the buffer when it had to be converted, the pointer in `draw_params` is used until
+    // the next update
+    converted_buffer: Vec<u32>,
     scale_factor: i32,
     width: i32,
     height: i32,
-    menus: Vec<Menu>,
+    menus: Vec<(MenuHandle, Menu)>,
     key_handler: KeyHandler,
+    touch_handler: TouchHandler,
+    cursor_auto_hide: mouse_handler::CursorAutoHide,
+    mouse_delta: mouse_handler::MouseDelta,
+    scroll_accumulator: mouse_handler::ScrollAccumulator,
+    mouse_history: mouse_handler::MouseHistory,
+    present_callbacks: PresentCallbacks,
+    button_modifiers: mouse_handler::ButtonModifiers,
+    // Set once `poll_input` has started the input of the next update
+    input_polled: bool,
+    cursor_confined: bool,
+    keyboard_grabbed: bool,
+    keyboard_hook: windef::HHOOK,
+    aspect_ratio: Option<(u32, u32)>,
+    pen: Option<PenState>,
+    monitor: windef::HMONITOR,
+    moved_to_monitor: bool,
+    // Device names of the monitors and the ramps they had before `set_gamma_ramp` changed them
+    gamma_restore: Vec<([u16; 32], [[u16; 256]; 3])>,
     update_rate: UpdateRate,
     accel_table: windef::HACCEL,
     accel_key: usize,
+    accel_source: MenuSource,
+    menu_source: Option<MenuSource>,
     cursor: CursorStyle,
-    cursors: [windef::HCURSOR; 8],
+    cursors: [windef::HCURSOR; 14],
+    // Set with `set_animated_cursor`, shown instead of `cursor`
+    animated_cursor: Option<mouse_handler::AnimatedCursor<windef::HCURSOR>>,
     draw_params: DrawParameters,
+    // Only set for windows created with `WindowOptions::gl`
+    gl_context: windef::HGLRC,
 }
 
 unsafe impl raw_window_handle::HasRawWindowHandle for Window {
@@ -517,6 +788,24 @@ unsafe impl raw_window_handle::HasRawWindowHandle for Window {
     }
 }
 
+#[cfg(feature = "rwh_06")]
+impl Window {
+    pub fn raw_window_handle_06(&self) -> Option<raw_window_handle_06::RawWindowHandle> {
+        let hwnd = std::num::NonZeroIsize::new(self.window? as isize)?;
+        let mut handle = raw_window_handle_06::Win32WindowHandle::new(hwnd);
+        handle.hinstance =
+            std::num::NonZeroIsize::new(
+                unsafe { libloaderapi::GetModuleHandleA(ptr::null()) } as isize
+            );
+        Some(raw_window_handle_06::RawWindowHandle::Win32(handle))
+    }
+
+    pub fn raw_display_handle_06(&self) -> Option<raw_window_handle_06::RawDisplayHandle> {
+        let handle = raw_window_handle_06::WindowsDisplayHandle::new();
+        Some(raw_window_handle_06::RawDisplayHandle::Windows(handle))
+    }
+}
+
 impl Window {
     fn open_window(
         name: &str,
@@ -524,6 +813,7 @@ impl Window {
         height: usize,
         opts: WindowOptions,
         scale_factor: i32,
+        parent: windef::HWND,
     ) -> Option<windef::HWND> {
         unsafe {
             let class_name = to_wstring("minifb_window");
@@ -580,6 +870,14 @@ impl Window {
                 flags = winuser::WS_VISIBLE | winuser::WS_POPUP;
             }
 
+            // Child windows can't have a frame, they're placed in the top left corner instead
+            let (x, y) = if parent.is_null() {
+                (winuser::CW_USEDEFAULT, winuser::CW_USEDEFAULT)
+            } else {
+                flags = winuser::WS_CHILD | winuser::WS_CLIPSIBLINGS;
+                (0, 0)
+            };
+
             let new_width = width * scale_factor as usize;
             let new_height = height * scale_factor as usize;
 
@@ -600,11 +898,11 @@ impl Window {
                 class_name.as_ptr(),
                 window_name.as_ptr(),
                 flags,
-                winuser::CW_USEDEFAULT,
-                winuser::CW_USEDEFAULT,
+                x,
+                y,
                 rect.right,
                 rect.bottom,
-                ptr::null_mut(),
+                parent,
                 ptr::null_mut(),
                 ptr::null_mut(),
                 ptr::null_mut(),
@@ -617,37 +915,104 @@ impl Window {
                 return None;
             }
 
-            winuser::ShowWindow(handle, winuser::SW_NORMAL);
+            if opts.visible {
+                winuser::ShowWindow(handle, winuser::SW_NORMAL);
+            }
 
             Some(handle)
         }
     }
 
     pub fn new(name: &str, width: usize, height: usize, opts: WindowOptions) -> Result<Window> {
+        Self::create(name, width, height, opts, ptr::null_mut())
+    }
+
+    pub fn new_child(
+        parent: *mut raw::c_void,
+        name: &str,
+        width: usize,
+        height: usize,
+        opts: WindowOptions,
+    ) -> Result<Window> {
+        Self::create(name, width, height, opts, parent as windef::HWND)
+    }
+
+    fn create(
+        name: &str,
+        width: usize,
+        height: usize,
+        opts: WindowOptions,
+        parent: windef::HWND,
+    ) -> Result<Window> {
         unsafe {
             let scale_factor = Self::get_scale_factor(width, height, opts.scale);
 
-            let handle = Self::open_window(name, width, height, opts, scale_factor);
+            // Has to be set before the window is shown for the taskbar to pick it up
+            if let Some(app_id) = opts.app_id {
+                shobjidl_core::SetCurrentProcessExplicitAppUserModelID(to_wstring(app_id).as_ptr());
+            }
+
+            let handle = Self::open_window(name, width, height, opts, scale_factor, parent);
 
             if handle.is_none() {
                 return Err(Error::WindowCreate("Unable to create Window".to_owned()));
             }
 
-            let window = Window {
+            // Touches are still turned into mouse messages after WM_TOUCH, so this doesn't change
+            // how touch screens work for applications only using the mouse
+            winuser::RegisterTouchWindow(handle.unwrap(), 0);
+
+            let mut key_handler = KeyHandler::new();
+            key_handler.set_keyboard_layout(keyboard_layout_name());
+            key_handler
+                .set_display_count(winuser::GetSystemMetrics(winuser::SM_CMONITORS) as usize);
+
+            let mut window = Window {
                 mouse: MouseData::default(),
                 dc: Some(winuser::GetDC(handle.unwrap())),
                 window: Some(handle.unwrap()),
-                key_handler: KeyHandler::new(),
+                key_handler,
+                touch_handler: TouchHandler::new(),
+                cursor_auto_hide: mouse_handler::CursorAutoHide::new(),
+                mouse_delta: mouse_handler::MouseDelta::default(),
+                scroll_accumulator: mouse_handler::ScrollAccumulator::default(),
+                mouse_history: mouse_handler::MouseHistory::new(),
+                present_callbacks: PresentCallbacks::default(),
+                button_modifiers: mouse_handler::ButtonModifiers::default(),
+                input_polled: false,
+                cursor_confined: false,
+                keyboard_grabbed: false,
+                keyboard_hook: ptr::null_mut(),
+                aspect_ratio: None,
+                pen: None,
+                monitor: winuser::MonitorFromWindow(
+                    handle.unwrap(),
+                    winuser::MONITOR_DEFAULTTONEAREST,
+                ),
+                moved_to_monitor: false,
+                gamma_restore: Vec::new(),
                 update_rate: UpdateRate::new(),
                 is_open: true,
+                closed: false,
+                close_reason: None,
+                auto_close: true,
+                default_accelerators: false,
+                close_requested: false,
+                transparency: opts.transparency,
+                pixel_format: opts.pixel_format,
+                converted_buffer: Vec::new(),
                 scale_factor,
                 width: (width * scale_factor as usize) as i32,
                 height: (height * scale_factor as usize) as i32,
                 menus: Vec::new(),
                 accel_table: ptr::null_mut(),
                 accel_key: INVALID_ACCEL,
+                accel_source: MenuSource::Click,
+                menu_source: None,
                 cursor: CursorStyle::Arrow,
                 clear_brush: wingdi::CreateSolidBrush(0),
+                border_fill: None,
+                border_buffer: Vec::new(),
                 cursors: [
                     winuser::LoadCursorW(ptr::null_mut(), winuser::IDC_ARROW),
                     winuser::LoadCursorW(ptr::null_mut(), winuser::IDC_IBEAM),
@@ -657,21 +1022,121 @@ impl Window {
                     winuser::LoadCursorW(ptr::null_mut(), winuser::IDC_SIZEWE),
                     winuser::LoadCursorW(ptr::null_mut(), winuser::IDC_SIZENS),
                     winuser::LoadCursorW(ptr::null_mut(), winuser::IDC_SIZEALL),
+                    winuser::LoadCursorW(ptr::null_mut(), winuser::IDC_WAIT),
+                    winuser::LoadCursorW(ptr::null_mut(), winuser::IDC_APPSTARTING),
+                    winuser::LoadCursorW(ptr::null_mut(), winuser::IDC_HELP),
+                    winuser::LoadCursorW(ptr::null_mut(), winuser::IDC_NO),
+                    // Windows has no zoom cursors
+                    winuser::LoadCursorW(ptr::null_mut(), winuser::IDC_CROSS),
+                    winuser::LoadCursorW(ptr::null_mut(), winuser::IDC_CROSS),
                 ],
+                animated_cursor: None,
                 draw_params: DrawParameters {
                     scale_mode: opts.scale_mode,
                     ..DrawParameters::default()
                 },
+                gl_context: ptr::null_mut(),
             };
 
+            if opts.gl {
+                window.create_gl_context()?;
+            }
+
             if opts.topmost {
                 window.topmost(true)
             }
 
+            window.report_held_keys();
+
             Ok(window)
         }
     }
 
+    // Gives the window a double buffered pixel format and a GL context, which is made current
+    unsafe fn create_gl_context(&mut self) -> Result<()> {
+        let dc = self.dc.unwrap();
+
+        let mut pfd: wingdi::PIXELFORMATDESCRIPTOR = mem::zeroed();
+        pfd.nSize = mem::size_of::<wingdi::PIXELFORMATDESCRIPTOR>() as u16;
+        pfd.nVersion = 1;
+        pfd.dwFlags =
+            wingdi::PFD_DRAW_TO_WINDOW | wingdi::PFD_SUPPORT_OPENGL | wingdi::PFD_DOUBLEBUFFER;
+        pfd.iPixelType = wingdi::PFD_TYPE_RGBA;
+        pfd.cColorBits = 32;
+        pfd.cDepthBits = 24;
+        pfd.iLayerType = wingdi::PFD_MAIN_PLANE;
+
+        let format = wingdi::ChoosePixelFormat(dc, &pfd);
+        if format == 0 || wingdi::SetPixelFormat(dc, format, &pfd) == 0 {
+            return Err(Error::WindowCreate(
+                "Unable to set a GL pixel format".to_owned(),
+            ));
+        }
+
+        let context = wingdi::wglCreateContext(dc);
+        if context.is_null() {
+            return Err(Error::WindowCreate("wglCreateContext failed".to_owned()));
+        }
+
+        wingdi::wglMakeCurrent(dc, context);
+        self.gl_context = context;
+
+        Ok(())
+    }
+
+    pub fn gl_get_proc_address(&self, name: &str) -> *const raw::c_void {
+        let name = match CString::new(name) {
+            Ok(name) if !self.gl_context.is_null() => name,
+            _ => return ptr::null(),
+        };
+
+        unsafe {
+            let address = wingdi::wglGetProcAddress(name.as_ptr()) as isize;
+
+            // wglGetProcAddress doesn't know the GL 1.1 functions, which are exported by
+            // opengl32.dll. Some drivers return small values instead of null on failure.
+            if (-1..=3).contains(&address) {
+                let module = libloaderapi::GetModuleHandleA(b"opengl32.dll\0".as_ptr() as _);
+                return libloaderapi::GetProcAddress(module, name.as_ptr()) as *const raw::c_void;
+            }
+
+            address as *const raw::c_void
+        }
+    }
+
+    pub fn gl_swap_buffers(&mut self) {
+        if let (false, Some(dc)) = (self.gl_context.is_null(), self.dc) {
+            unsafe { wingdi::SwapBuffers(dc) };
+        }
+    }
+
+    // Keys that are already down when the window opens count as held rather than pressed. The
+    // keyboard state of the thread is only updated by messages, so the async state is used
+    unsafe fn report_held_keys(&mut self) {
+        self.key_handler.begin_held_keys();
+
+        for vk in 0..256 {
+            if winuser::GetAsyncKeyState(vk) >= 0 {
+                continue;
+            }
+
+            let scancode = winuser::MapVirtualKeyW(vk as u32, MAPVK_VK_TO_VSC_EX);
+            if scancode == 0 {
+                continue;
+            }
+
+            // Extended keys come back with an 0xe0 prefix, messages have the flag in bit 8
+            let extended = if scancode & 0xff00 == 0xe000 {
+                0x100
+            } else {
+                0
+            };
+            update_key_state(self, (scancode & 0xff) | extended, true);
+        }
+
+        self.key_handler.end_held_keys();
+    }
+
     #[inline]
     pub fn set_title(&mut self, title: &str) {
         unsafe {
@@ -680,55 +1145,97 @@ impl Window {
         }
     }
 
+    pub fn get_title(&self) -> String {
+        let window = match self.window {
+            Some(window) => window,
+            None => return String::new(),
+        };
+
+        unsafe {
+            let len = winuser::GetWindowTextLengthW(window);
+            let mut title = vec![0u16; len as usize + 1];
+            let len = winuser::GetWindowTextW(window, title.as_mut_ptr(), title.len() as i32);
+            String::from_utf16_lossy(&title[..len.max(0) as usize])
+        }
+    }
+
+    pub fn set_document_edited(&mut self, _edited: bool) {
+        // Windows has no document state, applications show it in the title
+    }
+
+    pub fn set_represented_file(&mut self, _path: &Path) {
+        // the title bar has no proxy icon
+    }
+
     #[inline]
-    pub fn set_icon(&mut self, icon: Icon) {
+    pub fn set_icon(&mut self, icon: Icon) -> Result<()> {
+        let s_pointer = match icon {
+            Icon::Path(s_pointer) => s_pointer,
+            Icon::Buffer(..) => {
+                return Err(Error::Unsupported {
+                    operation: "set_icon from a buffer",
+                    platform: "Windows",
+                })
+            }
+        };
+
         unsafe {
-            if let Icon::Path(s_pointer) = icon {
-                let mut buffer: Vec<u16> = Vec::new();
+            let mut buffer: Vec<u16> = Vec::new();
 
-                // call once to get the size of the buffer
-                let return_value =
-                    GetFullPathNameW(s_pointer, 0, buffer.as_mut_ptr(), std::ptr::null_mut());
+            // call once to get the size of the buffer
+            let return_value =
+                GetFullPathNameW(s_pointer, 0, buffer.as_mut_ptr(), std::ptr::null_mut());
 
-                // adjust size of the buffer
-                buffer.reserve(return_value as usize);
+            // adjust size of the buffer
+            buffer.reserve(return_value as usize);
 
-                let _ = GetFullPathNameW(
-                    s_pointer,
-                    return_value,
-                    buffer.as_mut_ptr(),
-                    std::ptr::null_mut(),
-                );
+            let _ = GetFullPathNameW(
+                s_pointer,
+                return_value,
+                buffer.as_mut_ptr(),
+                std::ptr::null_mut(),
+            );
 
-                let path = buffer.as_ptr();
+            let path = buffer.as_ptr();
 
-                // cx and cy are 0 so Windows uses the size of the resource
-                let icon = winapi::um::winuser::LoadImageW(
-                    std::ptr::null_mut(),
-                    path,
-                    IMAGE_ICON,
-                    0,
-                    0,
-                    LR_DEFAULTSIZE | LR_LOADFROMFILE,
+            // cx and cy are 0 so Windows uses the size of the resource
+            let icon = winapi::um::winuser::LoadImageW(
+                std::ptr::null_mut(),
+                path,
+                IMAGE_ICON,
+                0,
+                0,
+                LR_DEFAULTSIZE | LR_LOADFROMFILE,
+            );
+
+            if let Some(handle) = self.window {
+                winapi::um::winuser::SendMessageW(
+                    handle,
+                    WM_SETICON,
+                    ICON_SMALL as WPARAM,
+                    icon as LPARAM,
                 );
 
-                if let Some(handle) = self.window {
-                    winapi::um::winuser::SendMessageW(
-                        handle,
-                        WM_SETICON,
-                        ICON_SMALL as WPARAM,
-                        icon as LPARAM,
-                    );
+                // Both sizes use the same icon, so the previous one only has to be freed once
+                let previous = winapi::um::winuser::SendMessageW(
+                    handle,
+                    WM_SETICON,
+                    ICON_BIG as WPARAM,
+                    icon as LPARAM,
+                );
 
-                    winapi::um::winuser::SendMessageW(
-                        handle,
-                        WM_SETICON,
-                        ICON_BIG as WPARAM,
-                        icon as LPARAM,
-                    );
+                if previous != 0 && previous != icon as LPARAM {
+                    winuser::DestroyIcon(previous as windef::HICON);
                 }
             }
         }
+
+        Ok(())
+    }
+
+    #[inline]
+    pub fn backend_name(&self) -> &'static str {
+        "win32"
     }
 
     #[inline]
@@ -736,6 +1243,174 @@ impl Window {
         self.window.unwrap() as *mut raw::c_void
     }
 
+    pub fn get_buffer_scale(&self) -> (f32, f32) {
+        // Until a buffer is drawn assume it has the size the window was created with
+        let (buffer_width, buffer_height) = if self.draw_params.buffer_width == 0 {
+            (
+                (self.width / self.scale_factor) as usize,
+                (self.height / self.scale_factor) as usize,
+            )
+        } else {
+            (
+                self.draw_params.buffer_width as usize,
+                self.draw_params.buffer_height as usize,
+            )
+        };
+
+        buffer_helper::buffer_scale(
+            self.draw_params.scale_mode,
+            self.width as usize,
+            self.height as usize,
+            buffer_width,
+            buffer_height,
+        )
+    }
+
+    pub fn get_content_scale(&self) -> f32 {
+        // Processes that aren't DPI aware are always told 96 and scaled by the system instead
+        match self.dc {
+            Some(dc) => unsafe { wingdi::GetDeviceCaps(dc, wingdi::LOGPIXELSX) as f32 / 96.0 },
+            None => 1.0,
+        }
+    }
+
+    #[inline]
+    pub fn get_native_pixel_format(&self) -> Option<PixelFormat> {
+        // The DIB section is drawn as 0x00RRGGBB
+        Some(PixelFormat::Xrgb8888)
+    }
+
+    pub fn is_transparency_active(&self) -> bool {
+        if !self.transparency {
+            return false;
+        }
+
+        // Composition is always on since Windows 8 but can be turned off on Windows 7
+        let mut enabled: minwindef::BOOL = 0;
+        let result = unsafe { dwmapi::DwmIsCompositionEnabled(&mut enabled) };
+        winerror::SUCCEEDED(result) && enabled != 0
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        match self.window {
+            Some(window) => unsafe { winuser::IsZoomed(window) != 0 },
+            None => false,
+        }
+    }
+
+    pub fn set_maximized(&mut self, maximized: bool) {
+        // SW_RESTORE would also bring back a minimized window
+        if maximized == self.is_maximized() {
+            return;
+        }
+
+        if let Some(window) = self.window {
+            let command = if maximized {
+                winuser::SW_MAXIMIZE
+            } else {
+                winuser::SW_RESTORE
+            };
+
+            unsafe { winuser::ShowWindow(window, command) };
+        }
+    }
+
+    pub fn display_count(&self) -> usize {
+        unsafe { winuser::GetSystemMetrics(winuser::SM_CMONITORS) as usize }
+    }
+
+    pub fn get_monitors(&self) -> Vec<Monitor> {
+        unsafe extern "system" fn callback(
+            monitor: windef::HMONITOR,
+            _dc: windef::HDC,
+            _rect: windef::LPRECT,
+            data: LPARAM,
+        ) -> minwindef::BOOL {
+            let monitors = &mut *(data as *mut Vec<Monitor>);
+            monitors.extend(monitor_info(monitor));
+            minwindef::TRUE
+        }
+
+        let mut monitors: Vec<Monitor> = Vec::new();
+
+        unsafe {
+            winuser::EnumDisplayMonitors(
+                ptr::null_mut(),
+                ptr::null(),
+                Some(callback),
+                &mut monitors as *mut Vec<Monitor> as LPARAM,
+            );
+        }
+
+        monitors
+    }
+
+    #[inline]
+    pub fn get_keyboard_layout(&self) -> Option<String> {
+        keyboard_layout_name()
+    }
+
+    pub fn current_monitor(&self) -> Option<Monitor> {
+        unsafe {
+            let monitor =
+                winuser::MonitorFromWindow(self.window?, winuser::MONITOR_DEFAULTTONEAREST);
+            monitor_info(monitor)
+        }
+    }
+
+    pub fn set_gamma_ramp(&mut self, ramp: &GammaRamp) -> Result<()> {
+        let window = self
+            .window
+            .ok_or_else(|| Error::GammaRamp("The window was closed".to_owned()))?;
+        let mut table = [ramp.red, ramp.green, ramp.blue];
+        let restore = &mut self.gamma_restore;
+
+        let result = unsafe {
+            with_monitor_dc(window, |device, dc| {
+                // Keep the ramp the monitor had before the first change, it's restored on drop
+                if !restore.iter().any(|(d, _)| d == device) {
+                    let mut original = [[0u16; 256]; 3];
+                    if wingdi::GetDeviceGammaRamp(dc, original.as_mut_ptr() as _) != 0 {
+                        restore.push((*device, original));
+                    }
+                }
+
+                wingdi::SetDeviceGammaRamp(dc, table.as_mut_ptr() as _) != 0
+            })
+        };
+
+        match result {
+            Some(true) => Ok(()),
+            Some(false) => Err(Error::GammaRamp(
+                "SetDeviceGammaRamp refused the ramp".to_owned(),
+            )),
+            None => Err(Error::GammaRamp("Unable to open the monitor".to_owned())),
+        }
+    }
+
+    pub fn get_gamma_ramp(&self) -> Option<GammaRamp> {
+        unsafe {
+            with_monitor_dc(self.window?, |_, dc| {
+                let mut table = [[0u16; 256]; 3];
+                if wingdi::GetDeviceGammaRamp(dc, table.as_mut_ptr() as _) == 0 {
+                    return None;
+                }
+
+                Some(GammaRamp {
+                    red: table[0],
+                    green: table[1],
+                    blue: table[2],
+                })
+            })
+            .flatten()
+        }
+    }
+
+    #[inline]
+    pub fn moved_to_monitor(&self) -> bool {
+        self.moved_to_monitor
+    }
+
     #[inline]
     pub fn set_position(&mut self, x: isize, y: isize) {
         unsafe {
@@ -770,6 +1445,31 @@ impl Window {
         (x as isize, y as isize)
     }
 
+    pub fn get_outer_position(&self) -> (isize, isize) {
+        // GetWindowRect already includes the decorations
+        self.get_position()
+    }
+
+    pub fn get_outer_size(&self) -> (usize, usize) {
+        let mut rect = windef::RECT {
+            left: 0,
+            right: 0,
+            top: 0,
+            bottom: 0,
+        };
+
+        unsafe {
+            if winuser::GetWindowRect(self.window.unwrap(), &mut rect) == 0 {
+                return self.get_size();
+            }
+        }
+
+        (
+            (rect.right - rect.left) as usize,
+            (rect.bottom - rect.top) as usize,
+        )
+    }
+
     #[inline]
     pub fn topmost(&self, topmost: bool) {
         unsafe {
@@ -789,28 +1489,268 @@ impl Window {
         };
     }
 
-    #[inline]
-    pub fn get_size(&self) -> (usize, usize) {
-        (self.width as usize, self.height as usize)
-    }
+    pub fn set_visible(&mut self, visible: bool) {
+        if self.closed {
+            return;
+        }
 
-    pub fn get_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
-        let s = self.scale_factor as f32;
-        let w = self.width as f32;
-        let h = self.height as f32;
+        if let Some(window) = self.window {
+            let command = if visible {
+                winuser::SW_SHOW
+            } else {
+                winuser::SW_HIDE
+            };
 
-        // TODO: Needs to be fixed with resize support
-        mouse_handler::get_pos(mode, self.mouse.x, self.mouse.y, s, w, h)
+            unsafe { winuser::ShowWindow(window, command) };
+        }
     }
 
-    pub fn get_unscaled_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
-        let w = self.width as f32;
+    pub fn set_always_on_bottom(&mut self, enabled: bool) {
+        self.set_window_level(if enabled {
+            WindowLevel::Bottom
+        } else {
+            WindowLevel::Normal
+        });
+    }
+
+    pub fn set_window_level(&mut self, level: WindowLevel) {
+        let window = match self.window {
+            Some(window) => window,
+            None => return,
+        };
+
+        // Windows only knows about topmost and normal windows, the higher levels are all topmost
+        let insert_after = match level {
+            WindowLevel::Bottom => winuser::HWND_BOTTOM,
+            WindowLevel::Normal => winuser::HWND_NOTOPMOST,
+            WindowLevel::Floating | WindowLevel::ModalPanel | WindowLevel::ScreenSaver => {
+                winuser::HWND_TOPMOST
+            }
+        };
+
+        unsafe {
+            winuser::SetWindowPos(
+                window,
+                insert_after,
+                0,
+                0,
+                0,
+                0,
+                winuser::SWP_NOSIZE | winuser::SWP_NOMOVE | winuser::SWP_NOACTIVATE,
+            );
+        }
+    }
+
+    pub fn set_skip_taskbar(&mut self, skip: bool) {
+        let window = match self.window {
+            Some(window) => window,
+            None => return,
+        };
+
+        unsafe {
+            let ex_style =
+                winuser::GetWindowLongW(window, winuser::GWL_EXSTYLE) as minwindef::DWORD;
+            let ex_style = if skip {
+                ex_style | winuser::WS_EX_TOOLWINDOW
+            } else {
+                ex_style & !winuser::WS_EX_TOOLWINDOW
+            };
+
+            // The taskbar only picks the style up when the window is shown again
+            let visible = winuser::IsWindowVisible(window) != 0;
+            if visible {
+                winuser::ShowWindow(window, winuser::SW_HIDE);
+            }
+
+            winuser::SetWindowLongW(window, winuser::GWL_EXSTYLE, ex_style as ntdef::LONG);
+
+            if visible {
+                winuser::ShowWindow(window, winuser::SW_SHOWNA);
+            }
+        }
+    }
+
+    pub fn set_caption_buttons(&mut self, minimize: bool, maximize: bool, close: bool) {
+        let window = match self.window {
+            Some(window) => window,
+            None => return,
+        };
+
+        unsafe {
+            let mut style = winuser::GetWindowLongW(window, winuser::GWL_STYLE) as minwindef::DWORD;
+            for &(button, enabled) in &[
+                (winuser::WS_MINIMIZEBOX, minimize),
+                (winuser::WS_MAXIMIZEBOX, maximize),
+            ] {
+                if enabled {
+                    style |= button;
+                } else {
+                    style &= !button;
+                }
+            }
+            winuser::SetWindowLongW(window, winuser::GWL_STYLE, style as ntdef::LONG);
+
+            // The close button can't be removed on its own, graying out its system menu entry
+            // disables both the button and Alt+F4
+            let menu = winuser::GetSystemMenu(window, minwindef::FALSE);
+            if !menu.is_null() {
+                let state = if close {
+                    winuser::MF_ENABLED
+                } else {
+                    winuser::MF_GRAYED
+                };
+                winuser::EnableMenuItem(
+                    menu,
+                    winuser::SC_CLOSE as u32,
+                    winuser::MF_BYCOMMAND | state,
+                );
+            }
+
+            winuser::SetWindowPos(
+                window,
+                ptr::null_mut(),
+                0,
+                0,
+                0,
+                0,
+                winuser::SWP_NOSIZE
+                    | winuser::SWP_NOMOVE
+                    | winuser::SWP_NOZORDER
+                    | winuser::SWP_NOACTIVATE
+                    | winuser::SWP_FRAMECHANGED,
+            );
+        }
+    }
+
+    pub fn set_shadow(&mut self, enabled: bool) {
+        let window = match self.window {
+            Some(window) => window,
+            None => return,
+        };
+
+        unsafe {
+            let policy = if enabled {
+                dwmapi::DWMNCRP_ENABLED
+            } else {
+                dwmapi::DWMNCRP_DISABLED
+            };
+            dwmapi::DwmSetWindowAttribute(
+                window,
+                dwmapi::DWMWA_NCRENDERING_POLICY,
+                &policy as *const _ as *const _,
+                mem::size_of_val(&policy) as u32,
+            );
+
+            // DWM only draws the shadow around a frame, a window without a title bar gets one by
+            // extending the frame a pixel into the window
+            let style = winuser::GetWindowLongW(window, winuser::GWL_STYLE) as minwindef::DWORD;
+            if style & winuser::WS_CAPTION != winuser::WS_CAPTION {
+                let inset = if enabled { 1 } else { 0 };
+                let margins = uxtheme::MARGINS {
+                    cxLeftWidth: inset,
+                    cxRightWidth: inset,
+                    cyTopHeight: inset,
+                    cyBottomHeight: inset,
+                };
+                dwmapi::DwmExtendFrameIntoClientArea(window, &margins);
+            }
+        }
+    }
+
+    pub fn beep(&self) {
+        unsafe { winuser::MessageBeep(winuser::MB_OK) };
+    }
+
+    #[inline]
+    pub fn get_size(&self) -> (usize, usize) {
+        (self.width as usize, self.height as usize)
+    }
+
+    #[inline]
+    pub fn get_unscaled_size(&self) -> (usize, usize) {
+        // The window is already measured in screen pixels
+        (self.width as usize, self.height as usize)
+    }
+
+    pub fn set_size(&mut self, width: usize, height: usize) {
+        let window = match self.window {
+            Some(window) => window,
+            None => return,
+        };
+
+        unsafe {
+            let style = winuser::GetWindowLongW(window, winuser::GWL_STYLE) as minwindef::DWORD;
+            let ex_style =
+                winuser::GetWindowLongW(window, winuser::GWL_EXSTYLE) as minwindef::DWORD;
+            let has_menu = !winuser::GetMenu(window).is_null();
+
+            let mut rect = windef::RECT {
+                left: 0,
+                right: width as ntdef::LONG,
+                top: 0,
+                bottom: height as ntdef::LONG,
+            };
+
+            winuser::AdjustWindowRectEx(&mut rect, style, has_menu as minwindef::BOOL, ex_style);
+
+            // WM_SIZE is sent before SetWindowPos returns so width and height are updated there
+            winuser::SetWindowPos(
+                window,
+                ptr::null_mut(),
+                0,
+                0,
+                rect.right - rect.left,
+                rect.bottom - rect.top,
+                winuser::SWP_NOMOVE | winuser::SWP_NOZORDER | winuser::SWP_NOACTIVATE,
+            );
+        }
+    }
+
+    pub fn get_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
+        let s = self.scale_factor as f32;
+        let w = self.width as f32;
+        let h = self.height as f32;
+
+        // TODO: Needs to be fixed with resize support
+        mouse_handler::get_pos(mode, self.mouse.x, self.mouse.y, s, w, h)
+    }
+
+    pub fn get_mouse_history(&self) -> Vec<(f32, f32, std::time::Duration)> {
+        self.mouse_history.get(self.scale_factor as f32)
+    }
+
+    pub fn get_pen(&self) -> Option<PenState> {
+        let s = self.scale_factor as f32;
+        self.pen.map(|pen| PenState {
+            x: pen.x / s,
+            y: pen.y / s,
+            ..pen
+        })
+    }
+
+    pub fn get_touch_points(&self) -> Vec<TouchPoint> {
+        self.touch_handler
+            .get_touch_points(self.scale_factor as f32)
+    }
+
+    pub fn get_unscaled_mouse_pos(&self, mode: MouseMode) -> Option<(f32, f32)> {
+        let w = self.width as f32;
         let h = self.height as f32;
 
         // TODO: Needs to be fixed with resize support
         mouse_handler::get_pos(mode, self.mouse.x, self.mouse.y, 1.0, w, h)
     }
 
+    pub fn get_mouse_pos_on_screen(&self) -> Option<(f32, f32)> {
+        let mut point = windef::POINT { x: 0, y: 0 };
+
+        if unsafe { winuser::GetCursorPos(&mut point) } == 0 {
+            return None;
+        }
+
+        Some((point.x as f32, point.y as f32))
+    }
+
     pub fn get_mouse_down(&self, button: MouseButton) -> bool {
         match button {
             MouseButton::Left => self.mouse.state[0],
@@ -819,6 +1759,113 @@ impl Window {
         }
     }
 
+    pub fn get_mouse_down_with_modifiers(&self, button: MouseButton) -> Option<Modifiers> {
+        if !self.get_mouse_down(button) {
+            return None;
+        }
+
+        // Buttons that were already down when the window got focus have nothing captured
+        Some(
+            self.button_modifiers
+                .get(button)
+                .unwrap_or_else(|| self.key_handler.modifiers()),
+        )
+    }
+
+    #[inline]
+    pub fn set_aspect_ratio(&mut self, ratio: Option<(u32, u32)>) {
+        self.aspect_ratio = ratio;
+    }
+
+    /// Adjusts the window rectangle of a WM_SIZING message so the client area keeps the aspect
+    /// ratio. The edge that isn't being dragged follows the one that is.
+    unsafe fn constrain_to_aspect_ratio(
+        &self,
+        rect: &mut windef::RECT,
+        edge: u32,
+        (numerator, denominator): (u32, u32),
+    ) {
+        let window = match self.window {
+            Some(window) => window,
+            None => return,
+        };
+
+        // The size of the borders, title bar and menu around the client area
+        let mut window_rect: windef::RECT = mem::zeroed();
+        let mut client_rect: windef::RECT = mem::zeroed();
+        winuser::GetWindowRect(window, &mut window_rect);
+        winuser::GetClientRect(window, &mut client_rect);
+        let frame_width = (window_rect.right - window_rect.left) - client_rect.right;
+        let frame_height = (window_rect.bottom - window_rect.top) - client_rect.bottom;
+
+        let client_width = i64::from((rect.right - rect.left - frame_width).max(1));
+        let client_height = i64::from((rect.bottom - rect.top - frame_height).max(1));
+        let (numerator, denominator) = (i64::from(numerator), i64::from(denominator));
+
+        if edge == winuser::WMSZ_TOP as u32 || edge == winuser::WMSZ_BOTTOM as u32 {
+            let width = client_height * numerator / denominator;
+            rect.right = rect.left + width as i32 + frame_width;
+        } else {
+            let height = client_width * denominator / numerator;
+            let height = height as i32 + frame_height;
+
+            if edge == winuser::WMSZ_TOPLEFT as u32 || edge == winuser::WMSZ_TOPRIGHT as u32 {
+                rect.top = rect.bottom - height;
+            } else {
+                rect.bottom = rect.top + height;
+            }
+        }
+    }
+
+    /// Draws the border fill over the whole client area, the buffer is drawn on top of it
+    /// afterwards.
+    unsafe fn draw_border_fill(&mut self, dc: windef::HDC) {
+        let fill = match &self.border_fill {
+            Some(fill) => fill,
+            None => return,
+        };
+
+        let width = self.width.max(0) as usize;
+        let height = self.height.max(0) as usize;
+        let image = buffer_helper::image_rect(
+            self.draw_params.scale_mode,
+            width,
+            height,
+            self.draw_params.buffer_width as usize,
+            self.draw_params.buffer_height as usize,
+        );
+
+        self.border_buffer.resize(width * height, 0);
+        buffer_helper::fill_border(&mut self.border_buffer, width, height, image, fill);
+
+        let mut bitmap_info: BitmapInfo = mem::zeroed();
+        bitmap_info.bmi_header.biSize = mem::size_of::<wingdi::BITMAPINFOHEADER>() as u32;
+        bitmap_info.bmi_header.biPlanes = 1;
+        bitmap_info.bmi_header.biBitCount = 32;
+        bitmap_info.bmi_header.biCompression = wingdi::BI_BITFIELDS;
+        bitmap_info.bmi_header.biWidth = width as i32;
+        bitmap_info.bmi_header.biHeight = -(height as i32);
+        bitmap_info.bmi_colors[0].rgbRed = 0xff;
+        bitmap_info.bmi_colors[1].rgbGreen = 0xff;
+        bitmap_info.bmi_colors[2].rgbBlue = 0xff;
+
+        wingdi::StretchDIBits(
+            dc,
+            0,
+            0,
+            width as i32,
+            height as i32,
+            0,
+            0,
+            width as i32,
+            height as i32,
+            self.border_buffer.as_ptr() as *const winapi::ctypes::c_void,
+            mem::transmute(&bitmap_info),
+            wingdi::DIB_RGB_COLORS,
+            wingdi::SRCCOPY,
+        );
+    }
+
     pub fn get_scroll_wheel(&self) -> Option<(f32, f32)> {
         if self.mouse.scroll.abs() > 0.0 {
             Some((0.0, self.mouse.scroll))
@@ -827,9 +1874,111 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn get_scroll_accumulated(&self) -> (f32, f32) {
+        self.scroll_accumulator.get()
+    }
+
+    #[inline]
+    pub fn reset_scroll_accumulator(&mut self) {
+        self.scroll_accumulator.reset()
+    }
+
     #[inline]
     pub fn set_cursor_style(&mut self, cursor: CursorStyle) {
         self.cursor = cursor;
+        unsafe { self.free_animated_cursor() };
+    }
+
+    #[inline]
+    pub fn get_cursor_style(&self) -> CursorStyle {
+        self.cursor
+    }
+
+    pub fn get_cursor_hotspot(&self) -> (i32, i32) {
+        unsafe {
+            let mut icon_info: winuser::ICONINFO = mem::zeroed();
+            if winuser::GetIconInfo(self.current_cursor(), &mut icon_info) == 0 {
+                return (0, 0);
+            }
+
+            // GetIconInfo hands out copies of the bitmaps
+            if !icon_info.hbmMask.is_null() {
+                wingdi::DeleteObject(icon_info.hbmMask as *mut raw::c_void);
+            }
+            if !icon_info.hbmColor.is_null() {
+                wingdi::DeleteObject(icon_info.hbmColor as *mut raw::c_void);
+            }
+
+            (icon_info.xHotspot as i32, icon_info.yHotspot as i32)
+        }
+    }
+
+    pub fn set_animated_cursor(
+        &mut self,
+        frames: &[(&[u32], u32, u32)],
+        frame_duration: std::time::Duration,
+        hot_x: u32,
+        hot_y: u32,
+    ) {
+        unsafe {
+            let cursors: Vec<windef::HCURSOR> = frames
+                .iter()
+                .map(|&(buffer, width, height)| create_cursor(buffer, width, height, hot_x, hot_y))
+                .filter(|cursor| !cursor.is_null())
+                .collect();
+
+            if cursors.is_empty() {
+                return;
+            }
+
+            self.free_animated_cursor();
+            self.animated_cursor =
+                Some(mouse_handler::AnimatedCursor::new(cursors, frame_duration));
+
+            self.show_animated_frame();
+        }
+    }
+
+    // The cursor that WM_SETCURSOR sets
+    fn current_cursor(&self) -> windef::HCURSOR {
+        match self.animated_cursor {
+            Some(ref animated) => *animated.current(),
+            None => self.cursors[self.cursor as usize],
+        }
+    }
+
+    // Windows only asks for the cursor when the mouse moves, so new frames are set directly
+    // while the mouse is over the client area
+    unsafe fn show_animated_frame(&self) {
+        let window = match self.window {
+            Some(window) => window,
+            None => return,
+        };
+
+        let mut pos: windef::POINT = mem::zeroed();
+        winuser::GetCursorPos(&mut pos);
+        if winuser::WindowFromPoint(pos) != window {
+            return;
+        }
+
+        let mut rect: windef::RECT = mem::zeroed();
+        winuser::GetClientRect(window, &mut rect);
+        winuser::ScreenToClient(window, &mut pos);
+
+        if pos.x >= rect.left && pos.x < rect.right && pos.y >= rect.top && pos.y < rect.bottom {
+            winuser::SetCursor(self.current_cursor());
+        }
+    }
+
+    unsafe fn free_animated_cursor(&mut self) {
+        if let Some(animated) = self.animated_cursor.take() {
+            // Put the regular cursor back first, a cursor that is in use can't be destroyed
+            self.show_animated_frame();
+            for &cursor in animated.frames() {
+                winuser::DestroyCursor(cursor);
+            }
+        }
     }
 
     #[inline]
@@ -842,11 +1991,26 @@ impl Window {
         self.update_rate.update();
     }
 
+    #[inline]
+    pub fn set_target_fps(&mut self, fps: u32) {
+        self.update_rate.set_target_fps(fps);
+    }
+
+    #[inline]
+    pub fn get_frame_time(&self) -> std::time::Duration {
+        self.update_rate.frame_time()
+    }
+
     #[inline]
     pub fn get_keys(&self) -> Vec<Key> {
         self.key_handler.get_keys()
     }
 
+    #[inline]
+    pub fn keys_down(&self) -> &[Key] {
+        self.key_handler.keys_down()
+    }
+
     #[inline]
     pub fn get_keys_pressed(&self, repeat: KeyRepeat) -> Vec<Key> {
         self.key_handler.get_keys_pressed(repeat)
@@ -857,11 +2021,31 @@ impl Window {
         self.key_handler.get_keys_released()
     }
 
+    #[inline]
+    pub fn get_keys_just_pressed(&self) -> Vec<Key> {
+        self.key_handler.get_keys_just_pressed()
+    }
+
+    #[inline]
+    pub fn get_text_input(&self) -> String {
+        self.key_handler.get_text_input()
+    }
+
     #[inline]
     pub fn is_key_down(&self, key: Key) -> bool {
         self.key_handler.is_key_down(key)
     }
 
+    #[inline]
+    pub fn get_input_timestamp(&self) -> Option<std::time::Duration> {
+        self.key_handler.get_event_time()
+    }
+
+    #[inline]
+    pub fn get_key_timestamp(&self, key: Key) -> Option<std::time::Duration> {
+        self.key_handler.get_key_time(key)
+    }
+
     #[inline]
     pub fn set_input_callback(&mut self, callback: Box<dyn InputCallback>) {
         self.key_handler.set_input_callback(callback)
@@ -877,11 +2061,62 @@ impl Window {
         self.key_handler.set_key_repeat_rate(rate)
     }
 
+    #[inline]
+    pub fn set_key_map(&mut self, key_map: KeyMap) {
+        self.key_handler.set_key_map(key_map)
+    }
+
+    #[inline]
+    pub fn is_action_down(&self, action: &str) -> bool {
+        self.key_handler.is_action_down(action)
+    }
+
+    #[inline]
+    pub fn set_key_repeat_for(&mut self, keys: &[Key], delay: f32, rate: f32) {
+        self.key_handler.set_key_repeat_for(keys, delay, rate)
+    }
+
+    pub fn use_os_key_repeat(&mut self) {
+        let mut delay: minwindef::DWORD = 0;
+        let mut speed: minwindef::DWORD = 0;
+
+        unsafe {
+            if winuser::SystemParametersInfoW(
+                winuser::SPI_GETKEYBOARDDELAY,
+                0,
+                &mut delay as *mut _ as *mut raw::c_void,
+                0,
+            ) != 0
+            {
+                // 0 (about 250 ms) to 3 (about 1 sec)
+                self.key_handler
+                    .set_key_repeat_delay((delay.min(3) + 1) as f32 * 0.25);
+            }
+
+            if winuser::SystemParametersInfoW(
+                winuser::SPI_GETKEYBOARDSPEED,
+                0,
+                &mut speed as *mut _ as *mut raw::c_void,
+                0,
+            ) != 0
+            {
+                // 0 (about 2.5 repetitions per second) to 31 (about 30 repetitions per second)
+                let per_second = 2.5 + speed.min(31) as f32 * (27.5 / 31.0);
+                self.key_handler.set_key_repeat_rate(1.0 / per_second);
+            }
+        }
+    }
+
     #[inline]
     pub fn is_key_pressed(&self, key: Key, repeat: KeyRepeat) -> bool {
         self.key_handler.is_key_pressed(key, repeat)
     }
 
+    #[inline]
+    pub fn is_key_repeat(&self, key: Key) -> bool {
+        self.key_handler.is_key_repeat(key)
+    }
+
     #[inline]
     pub fn is_key_released(&self, key: Key) -> bool {
         self.key_handler.is_key_released(key)
@@ -892,7 +2127,24 @@ impl Window {
         self.is_open
     }
 
-    fn generic_update(&mut self, window: windef::HWND) {
+    #[inline]
+    pub fn set_close_requested_handling(&mut self, auto: bool) {
+        self.auto_close = auto;
+    }
+
+    #[inline]
+    pub fn set_default_accelerators(&mut self, enabled: bool) {
+        self.default_accelerators = enabled;
+    }
+
+    #[inline]
+    pub fn close_requested(&self) -> bool {
+        self.close_requested
+    }
+
+    // Clears the input state of the previous update before processing new messages, only once
+    // when `poll_input` is called in between updates
+    fn begin_input(&mut self, window: windef::HWND) {
         unsafe {
             let mut point: windef::POINT = mem::zeroed();
 
@@ -901,86 +2153,402 @@ impl Window {
 
             self.mouse.x = point.x as f32;
             self.mouse.y = point.y as f32;
-            self.mouse.scroll = 0.0;
+        }
+
+        if self.input_polled {
+            return;
+        }
+
+        self.mouse.scroll = 0.0;
+        self.mouse_delta.reset();
+        self.mouse_history.reset();
+        self.close_requested = false;
+
+        self.key_handler.update();
+        self.touch_handler.update();
+    }
+
+    // Measures the movement for the gameplay cursor mode and moves the cursor back to the center
+    fn update_mouse_delta(&mut self, window: windef::HWND) {
+        let center = ((self.width / 2) as f32, (self.height / 2) as f32);
+        let active = self.is_active();
+
+        if !self
+            .mouse_delta
+            .update(active, (self.mouse.x, self.mouse.y), center)
+        {
+            return;
+        }
+
+        unsafe {
+            let mut point = windef::POINT {
+                x: center.0 as i32,
+                y: center.1 as i32,
+            };
+            winuser::ClientToScreen(window, &mut point);
+            winuser::SetCursorPos(point.x, point.y);
+        }
+
+        self.mouse.x = center.0;
+        self.mouse.y = center.1;
+    }
+
+    fn generic_update(&mut self, window: windef::HWND) {
+        self.begin_input(window);
+        self.update_mouse_delta(window);
+
+        unsafe {
+            let monitor = winuser::MonitorFromWindow(window, winuser::MONITOR_DEFAULTTONEAREST);
+            self.moved_to_monitor = monitor != self.monitor;
+            self.monitor = monitor;
+
+            set_window_long(window, mem::transmute(self));
+        }
+
+        if let Some(visible) = self.cursor_auto_hide.update(self.mouse.x, self.mouse.y) {
+            self.show_cursor(visible);
+        }
+
+        if let Some(animated) = self.animated_cursor.as_mut() {
+            if animated.update().is_some() {
+                unsafe { self.show_animated_frame() };
+            }
+        }
+    }
+
+    fn message_loop(&self, _window: windef::HWND) {
+        unsafe {
+            let mut msg = mem::zeroed();
+
+            while winuser::PeekMessageW(&mut msg, std::ptr::null_mut(), 0, 0, winuser::PM_REMOVE)
+                != 0
+            {
+                let acc_condition = winuser::TranslateAcceleratorW(
+                    msg.hwnd,
+                    mem::transmute(self.accel_table),
+                    &mut msg,
+                ) == 0;
+
+                // Make this code a bit nicer
+                if self.accel_table.is_null() || acc_condition {
+                    winuser::TranslateMessage(&msg);
+                    winuser::DispatchMessageW(&msg);
+                }
+            }
+        }
+    }
+
+    #[allow(clippy::identity_op)]
+    pub fn set_background_color(&mut self, color: u32) {
+        unsafe {
+            wingdi::DeleteObject(self.clear_brush as *mut winapi::ctypes::c_void);
+            let r = (color >> 16) & 0xff;
+            let g = (color >> 8) & 0xff;
+            let b = (color >> 0) & 0xff;
+            self.clear_brush = wingdi::CreateSolidBrush((b << 16) | (g << 8) | r);
+        }
+
+        self.border_fill = None;
+    }
+
+    pub fn set_border_fill(&mut self, fill: BorderFill) {
+        self.border_fill = Some(fill);
+    }
+
+    pub fn set_cursor_visibility(&mut self, visibility: bool) {
+        self.cursor_auto_hide.set_visible(visibility);
+        self.show_cursor(visibility);
+    }
+
+    #[inline]
+    pub fn is_cursor_visible(&self) -> bool {
+        self.cursor_auto_hide.is_visible()
+    }
+
+    pub fn set_cursor_auto_hide(&mut self, timeout: Option<std::time::Duration>) {
+        if let Some(visible) = self.cursor_auto_hide.set_timeout(timeout) {
+            self.show_cursor(visible);
+        }
+    }
+
+    pub fn set_cursor_confined(&mut self, confined: bool) {
+        if confined {
+            if self.is_active() {
+                self.clip_cursor(true);
+            }
+        } else if self.cursor_confined {
+            self.clip_cursor(false);
+        }
+
+        self.cursor_confined = confined;
+    }
+
+    pub fn set_gameplay_cursor_mode(&mut self, enabled: bool) {
+        if enabled == self.mouse_delta.is_enabled() {
+            return;
+        }
+
+        if enabled {
+            self.mouse_delta
+                .save_cursor(self.is_cursor_visible(), self.cursor_confined);
+            self.set_cursor_visibility(false);
+            self.set_cursor_confined(true);
+        } else {
+            let (visible, confined) = self.mouse_delta.saved_cursor();
+            self.set_cursor_visibility(visible);
+            self.set_cursor_confined(confined);
+        }
+
+        self.mouse_delta.set_enabled(enabled);
+    }
+
+    #[inline]
+    pub fn get_mouse_delta(&self) -> (f32, f32) {
+        self.mouse_delta.get()
+    }
+
+    fn clip_cursor(&self, clip: bool) {
+        unsafe {
+            let window = match self.window {
+                Some(window) if clip => window,
+                _ => {
+                    winuser::ClipCursor(ptr::null());
+                    return;
+                }
+            };
+
+            let mut rect: windef::RECT = mem::zeroed();
+            winuser::GetClientRect(window, &mut rect);
+            winuser::MapWindowPoints(
+                window,
+                ptr::null_mut(),
+                &mut rect as *mut windef::RECT as *mut windef::POINT,
+                2,
+            );
+            winuser::ClipCursor(&rect);
+        }
+    }
+
+    pub fn set_keyboard_grab(&mut self, grab: bool) {
+        self.keyboard_grabbed = grab;
+        let active = self.is_active();
+        self.hook_keyboard(grab && active);
+    }
+
+    fn hook_keyboard(&mut self, hook: bool) {
+        unsafe {
+            if hook && self.keyboard_hook.is_null() {
+                self.keyboard_hook = winuser::SetWindowsHookExW(
+                    winuser::WH_KEYBOARD_LL,
+                    Some(keyboard_hook_proc),
+                    libloaderapi::GetModuleHandleW(ptr::null()),
+                    0,
+                );
+                KEYBOARD_GRAB_WINDOW.with(|w| w.set(self.window.unwrap_or(ptr::null_mut())));
+            } else if !hook && !self.keyboard_hook.is_null() {
+                winuser::UnhookWindowsHookEx(self.keyboard_hook);
+                self.keyboard_hook = ptr::null_mut();
+                KEYBOARD_GRAB_WINDOW.with(|w| w.set(ptr::null_mut()));
+            }
+        }
+    }
+
+    fn show_cursor(&mut self, visibility: bool) {
+        unsafe {
+            winuser::ShowCursor(visibility as i32);
+        }
+    }
+
+    pub fn update_with_buffer_stride(
+        &mut self,
+        buffer: &[u32],
+        buf_width: usize,
+        buf_height: usize,
+        buf_stride: usize,
+    ) -> Result<()> {
+        let window = match self.window {
+            Some(window) if !self.closed => window,
+            _ => return Ok(()),
+        };
+
+        Self::generic_update(self, window);
+
+        buffer_helper::check_buffer_size(buf_width, buf_height, buf_stride, buffer)?;
+
+        self.draw_params.buffer = match buffer_helper::to_argb(buffer, self.pixel_format) {
+            Cow::Borrowed(buffer) => buffer.as_ptr(),
+            Cow::Owned(converted) => {
+                self.converted_buffer = converted;
+                self.converted_buffer.as_ptr()
+            }
+        };
+        self.draw_params.buffer_width = buf_width as u32;
+        self.draw_params.buffer_height = buf_height as u32;
+        // stride currently not supported
+        //self.draw_params.buffer_stride = buf_stride as u32;
+
+        unsafe {
+            winuser::InvalidateRect(window, ptr::null_mut(), minwindef::TRUE);
+        }
+
+        Self::message_loop(self, window);
+        self.input_polled = false;
+        self.key_handler.update_just_pressed();
+
+        // The message loop painted the frame
+        self.present_callbacks.frame_presented();
+        self.present_callbacks.frame_shown();
+
+        Ok(())
+    }
+
+    pub fn set_present_callback(&mut self, callback: Box<dyn FnOnce()>) {
+        self.present_callbacks.add(callback);
+    }
+
+    pub fn cancel_present_callbacks(&mut self) {
+        self.present_callbacks.clear_pending();
+    }
+
+    pub fn set_damage(&mut self, _rects: &[Rect]) {
+        // the whole client area is always redrawn
+    }
+
+    pub fn update_with_buffer_vsync(
+        &mut self,
+        buffer: &[u32],
+        buf_width: usize,
+        buf_height: usize,
+        buf_stride: usize,
+    ) -> Result<()> {
+        self.update_with_buffer_stride(buffer, buf_width, buf_height, buf_stride)?;
+
+        // Blocks until the compositor presents the next frame, which happens on vblank. This
+        // fails when desktop composition is turned off, the rate limiter is used then.
+        if unsafe { dwmapi::DwmFlush() } == winerror::S_OK {
+            self.update_rate.update_unthrottled();
+        } else {
+            self.update_rate.update();
+        }
+
+        Ok(())
+    }
+
+    pub fn update(&mut self) {
+        let window = match self.window {
+            Some(window) if !self.closed => window,
+            _ => return,
+        };
+
+        Self::generic_update(self, window);
+        Self::message_loop(self, window);
+        self.input_polled = false;
+        self.key_handler.update_just_pressed();
+    }
+
+    pub fn poll_input(&mut self) {
+        let window = match self.window {
+            Some(window) if !self.closed => window,
+            _ => return,
+        };
 
-            self.key_handler.update();
+        self.begin_input(window);
+        self.update_mouse_delta(window);
+        self.input_polled = true;
 
+        unsafe {
             set_window_long(window, mem::transmute(self));
         }
+
+        Self::message_loop(self, window);
     }
 
-    fn message_loop(&self, _window: windef::HWND) {
-        unsafe {
-            let mut msg = mem::zeroed();
+    pub fn is_occluded(&self) -> bool {
+        let window = match self.window {
+            Some(window) => window,
+            None => return true,
+        };
 
-            while winuser::PeekMessageW(&mut msg, std::ptr::null_mut(), 0, 0, winuser::PM_REMOVE)
-                != 0
-            {
-                let acc_condition = winuser::TranslateAcceleratorW(
-                    msg.hwnd,
-                    mem::transmute(self.accel_table),
-                    &mut msg,
-                ) == 0;
+        unsafe {
+            if winuser::IsIconic(window) != 0 || winuser::IsWindowVisible(window) == 0 {
+                return true;
+            }
 
-                // Make this code a bit nicer
-                if self.accel_table.is_null() || acc_condition {
-                    winuser::TranslateMessage(&msg);
-                    winuser::DispatchMessageW(&msg);
+            // Without desktop composition the clip box of a fully covered window is empty
+            match self.dc {
+                Some(dc) => {
+                    let mut rect: windef::RECT = mem::zeroed();
+                    wingdi::GetClipBox(dc, &mut rect) == wingdi::NULLREGION
                 }
+                None => false,
             }
         }
     }
 
-    #[allow(clippy::identity_op)]
-    pub fn set_background_color(&mut self, color: u32) {
-        unsafe {
-            wingdi::DeleteObject(self.clear_brush as *mut winapi::ctypes::c_void);
-            let r = (color >> 16) & 0xff;
-            let g = (color >> 8) & 0xff;
-            let b = (color >> 0) & 0xff;
-            self.clear_brush = wingdi::CreateSolidBrush((b << 16) | (g << 8) | r);
+    pub fn close(&mut self) {
+        // The window is only hidden so the other functions can keep using it, it's destroyed
+        // when dropped
+        if !self.closed {
+            self.hook_keyboard(false);
+            self.clip_cursor(false);
+            if let Some(window) = self.window {
+                unsafe { winuser::ShowWindow(window, winuser::SW_HIDE) };
+            }
+            self.closed = true;
         }
+        self.is_open = false;
+        self.close_reason
+            .get_or_insert(CloseReason::ProgrammaticClose);
     }
 
-    pub fn set_cursor_visibility(&mut self, visibility: bool) {
-        unsafe {
-            winuser::ShowCursor(visibility as i32);
-        }
+    #[inline]
+    pub fn close_reason(&self) -> Option<CloseReason> {
+        self.close_reason
     }
 
-    pub fn update_with_buffer_stride(
-        &mut self,
-        buffer: &[u32],
-        buf_width: usize,
-        buf_height: usize,
-        buf_stride: usize,
-    ) -> Result<()> {
-        let window = self.window.unwrap();
+    /// Releases the native window. Safe to call more than once.
+    unsafe fn destroy_window(&mut self) {
+        self.hook_keyboard(false);
 
-        Self::generic_update(self, window);
+        for (device, mut original) in self.gamma_restore.drain(..) {
+            device_dc(&device, |dc| {
+                wingdi::SetDeviceGammaRamp(dc, original.as_mut_ptr() as _)
+            });
+        }
 
-        buffer_helper::check_buffer_size(buf_width, buf_height, buf_stride, buffer)?;
+        if !self.gl_context.is_null() {
+            wingdi::wglMakeCurrent(ptr::null_mut(), ptr::null_mut());
+            wingdi::wglDeleteContext(self.gl_context);
+            self.gl_context = ptr::null_mut();
+        }
 
-        self.draw_params.buffer = buffer.as_ptr();
-        self.draw_params.buffer_width = buf_width as u32;
-        self.draw_params.buffer_height = buf_height as u32;
-        // stride currently not supported
-        //self.draw_params.buffer_stride = buf_stride as u32;
+        if let Some(window) = self.window.take() {
+            if let Some(dc) = self.dc.take() {
+                winuser::ReleaseDC(window, dc);
+            }
 
-        unsafe {
-            winuser::InvalidateRect(window, ptr::null_mut(), minwindef::TRUE);
-        }
+            // Icons set with WM_SETICON aren't freed with the window
+            let icon = winuser::SendMessageW(window, winuser::WM_GETICON, ICON_BIG as WPARAM, 0);
 
-        Self::message_loop(self, window);
+            // This also destroys the menu bar and the menus in it
+            winuser::DestroyWindow(window);
 
-        Ok(())
-    }
+            if icon != 0 {
+                winuser::DestroyIcon(icon as windef::HICON);
+            }
+        }
 
-    pub fn update(&mut self) {
-        let window = self.window.unwrap();
+        if !self.clear_brush.is_null() {
+            wingdi::DeleteObject(self.clear_brush as *mut winapi::ctypes::c_void);
+            self.clear_brush = ptr::null_mut();
+        }
 
-        Self::generic_update(self, window);
-        Self::message_loop(self, window);
+        if !self.accel_table.is_null() {
+            winuser::DestroyAcceleratorTable(self.accel_table);
+            self.accel_table = ptr::null_mut();
+        }
+
+        self.free_animated_cursor();
     }
 
     #[inline]
@@ -994,6 +2562,15 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn get_primary_selection(&self) -> Option<String> {
+        // There is no primary selection on Windows
+        None
+    }
+
+    #[inline]
+    pub fn set_primary_selection(&mut self, _text: &str) {}
+
     unsafe fn get_scale_factor(width: usize, height: usize, scale: Scale) -> i32 {
         let factor: i32 = match scale {
             Scale::X1 => 1,
@@ -1049,7 +2626,7 @@ impl Window {
     unsafe fn set_accel_table(&mut self) {
         let mut temp_accel_table = Vec::<winuser::ACCEL>::new();
 
-        for menu in self.menus.iter() {
+        for (_, menu) in self.menus.iter() {
             for item in menu.accel_table.iter() {
                 temp_accel_table.push(*item);
             }
@@ -1083,25 +2660,26 @@ impl Window {
                 menu.name.as_ptr(),
             );
 
-            self.menus.push(menu.clone());
+            // TODO: Proper handle
+            let handle = MenuHandle(menu.menu_handle as u64);
+
+            self.menus.push((handle, menu.clone()));
             // TODO: Setup accel table
 
             //Self::add_menu_store(self, main_menu, menu_name, menu);
             self.set_accel_table();
 
             winuser::DrawMenuBar(window);
-        }
 
-        // TODO: Proper handle
-
-        MenuHandle(menu.menu_handle as u64)
+            handle
+        }
     }
 
     pub fn remove_menu(&mut self, handle: MenuHandle) {
         let window = self.window.unwrap();
         let main_menu = unsafe { winuser::GetMenu(window) };
         for i in 0..self.menus.len() {
-            if self.menus[i].menu_handle == handle.0 as windef::HMENU {
+            if self.menus[i].0 == handle {
                 unsafe {
                     let _t = winuser::RemoveMenu(
                         main_menu,
@@ -1110,21 +2688,108 @@ impl Window {
                     );
                     winuser::DrawMenuBar(self.window.unwrap());
                 }
-                self.menus.swap_remove(i);
+                // Keep the order in sync with the positions in the menu bar
+                self.menus.remove(i);
+                unsafe { self.set_accel_table() };
                 return;
             }
         }
     }
 
+    pub fn get_menu_height(&self) -> usize {
+        let window = match self.window {
+            Some(window) => window,
+            None => return 0,
+        };
+
+        unsafe {
+            if winuser::GetMenu(window).is_null() {
+                0
+            } else {
+                winuser::GetSystemMetrics(winuser::SM_CYMENU) as usize
+            }
+        }
+    }
+
+    pub fn update_menu(&mut self, handle: MenuHandle, menu: &Menu) {
+        let window = match self.window {
+            Some(window) => window,
+            None => return,
+        };
+
+        if let Some(i) = self.menus.iter().position(|(h, _)| *h == handle) {
+            unsafe {
+                let main_menu = winuser::GetMenu(window);
+                winuser::ModifyMenuW(
+                    main_menu,
+                    i as minwindef::UINT,
+                    winuser::MF_BYPOSITION | winuser::MF_POPUP,
+                    menu.menu_handle as basetsd::UINT_PTR,
+                    menu.name.as_ptr(),
+                );
+
+                self.menus[i].1 = menu.clone();
+                self.set_accel_table();
+
+                winuser::DrawMenuBar(window);
+            }
+        }
+    }
+
+    #[inline]
+    pub fn set_app_menu(&mut self, _menu: &Menu) {
+        // There is no application menu on Windows
+    }
+
+    pub fn show_context_menu(&mut self, menu: &Menu, x: i32, y: i32) {
+        let window = match self.window {
+            Some(window) => window,
+            None => return,
+        };
+
+        unsafe {
+            let mut point = windef::POINT {
+                x: x * self.scale_factor,
+                y: y * self.scale_factor,
+            };
+            winuser::ClientToScreen(window, &mut point);
+
+            // Without this the popup isn't dismissed when clicking outside of it
+            winuser::SetForegroundWindow(window);
+
+            let id = winuser::TrackPopupMenu(
+                menu.menu_handle,
+                winuser::TPM_LEFTALIGN | winuser::TPM_RIGHTBUTTON | winuser::TPM_RETURNCMD,
+                point.x,
+                point.y,
+                0,
+                window,
+                ptr::null(),
+            );
+
+            if id != 0 {
+                self.accel_key = id as usize;
+                self.accel_source = MenuSource::Click;
+            }
+        }
+    }
+
     pub fn is_menu_pressed(&mut self) -> Option<usize> {
         if self.accel_key == INVALID_ACCEL {
+            self.menu_source = None;
             None
         } else {
             let t = self.accel_key;
             self.accel_key = INVALID_ACCEL;
+            self.menu_source = Some(self.accel_source);
             Some(t)
         }
     }
+
+    #[inline]
+    pub fn menu_activation_source(&self) -> Option<MenuSource> {
+        self.menu_source
+    }
 }
 
 #[derive(Clone)]
@@ -1132,6 +2797,8 @@ pub struct Menu {
     menu_handle: windef::HMENU,
     name: Vec<u16>,
     accel_table: Vec<winuser::ACCEL>,
+    // Bitmaps of the item icons, menus don't take ownership of them
+    icons: Vec<windef::HBITMAP>,
 }
 
 impl Menu {
@@ -1141,10 +2808,47 @@ impl Menu {
                 menu_handle: winuser::CreatePopupMenu(),
                 name: to_wstring(name),
                 accel_table: Vec::new(),
+                icons: Vec::new(),
             })
         }
     }
 
+    /// Creates a 32-bit bitmap with premultiplied alpha, which is what menus expect for
+    /// transparent item bitmaps.
+    unsafe fn create_icon_bitmap(icon: &MenuIcon) -> windef::HBITMAP {
+        let mut bitmap_info: wingdi::BITMAPINFO = mem::zeroed();
+        bitmap_info.bmiHeader.biSize = mem::size_of::<wingdi::BITMAPINFOHEADER>() as u32;
+        bitmap_info.bmiHeader.biWidth = icon.width as i32;
+        // Negative height for a top-down bitmap
+        bitmap_info.bmiHeader.biHeight = -(icon.height as i32);
+        bitmap_info.bmiHeader.biPlanes = 1;
+        bitmap_info.bmiHeader.biBitCount = 32;
+        bitmap_info.bmiHeader.biCompression = wingdi::BI_RGB;
+
+        let mut bits = ptr::null_mut();
+        let bitmap = wingdi::CreateDIBSection(
+            ptr::null_mut(),
+            &bitmap_info,
+            wingdi::DIB_RGB_COLORS,
+            &mut bits,
+            ptr::null_mut(),
+            0,
+        );
+
+        if bitmap.is_null() || bits.is_null() {
+            return ptr::null_mut();
+        }
+
+        let pixels = std::slice::from_raw_parts_mut(bits as *mut u32, icon.data.len());
+        for (dst, &src) in pixels.iter_mut().zip(icon.data.iter()) {
+            let a = src >> 24;
+            let premultiply = |shift: u32| ((((src >> shift) & 0xff) * a / 255) << shift);
+            *dst = (a << 24) | premultiply(16) | premultiply(8) | premultiply(0);
+        }
+
+        bitmap
+    }
+
     fn map_key_to_vk_accel(key: Key) -> (raw::c_int, &'static str) {
         match key {
             Key::Key0 => (0x30, "0"),
@@ -1367,6 +3071,25 @@ impl Menu {
                     self.add_accel(vk_accel.0, menu_item);
                 }
             }
+
+            if let Some(ref icon) = menu_item.icon {
+                let bitmap = Self::create_icon_bitmap(icon);
+
+                if !bitmap.is_null() {
+                    let mut item_info: winuser::MENUITEMINFOW = mem::zeroed();
+                    item_info.cbSize = mem::size_of::<winuser::MENUITEMINFOW>() as u32;
+                    item_info.fMask = winuser::MIIM_BITMAP;
+                    item_info.hbmpItem = bitmap;
+
+                    winuser::SetMenuItemInfoW(
+                        self.menu_handle,
+                        menu_item.id as u32,
+                        minwindef::FALSE,
+                        &item_info,
+                    );
+                    self.icons.push(bitmap);
+                }
+            }
         }
 
         // TODO: This is not correct and needs to be fixed if remove_item is added. The
@@ -1379,18 +3102,384 @@ impl Menu {
     pub fn remove_item(&mut self, _item: &MenuItemHandle) {
         panic!("remove item hasn't been implemented");
     }
+
+    pub fn clear(&mut self) {
+        unsafe {
+            // RemoveMenu (unlike DeleteMenu) doesn't destroy sub menus as they are owned by
+            // other Menu instances
+            while winuser::GetMenuItemCount(self.menu_handle) > 0 {
+                winuser::RemoveMenu(self.menu_handle, 0, winuser::MF_BYPOSITION);
+            }
+        }
+
+        self.accel_table.clear();
+
+        // The items using the bitmaps are gone now. They aren't deleted when the menu is dropped
+        // as the menu itself may still be shown by a window.
+        for bitmap in self.icons.drain(..) {
+            unsafe { wingdi::DeleteObject(bitmap as *mut raw::c_void) };
+        }
+    }
 }
 
 impl Drop for Window {
     fn drop(&mut self) {
+        unsafe { self.destroy_window() };
+    }
+}
+
+// Message the shell sends to the tray window when the icon is clicked
+const WM_TRAY_ICON: u32 = winuser::WM_APP + 1;
+
+// Written by tray_wnd_proc and polled by TrayIcon
+struct TrayState {
+    menu: windef::HMENU,
+    menu_pressed: Option<usize>,
+    clicked: bool,
+}
+
+unsafe extern "system" fn tray_wnd_proc(
+    window: windef::HWND,
+    msg: minwindef::UINT,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> minwindef::LRESULT {
+    let state = get_window_long(window) as *mut TrayState;
+
+    if msg != WM_TRAY_ICON || state.is_null() {
+        return winuser::DefWindowProcW(window, msg, wparam, lparam);
+    }
+
+    let state = &mut *state;
+
+    match lparam as u32 {
+        winuser::WM_LBUTTONUP => state.clicked = true,
+        winuser::WM_RBUTTONUP if !state.menu.is_null() => {
+            let mut pos: windef::POINT = mem::zeroed();
+            winuser::GetCursorPos(&mut pos);
+
+            // Without this the menu doesn't close when clicking somewhere else
+            winuser::SetForegroundWindow(window);
+            let id = winuser::TrackPopupMenu(
+                state.menu,
+                winuser::TPM_RETURNCMD | winuser::TPM_RIGHTBUTTON,
+                pos.x,
+                pos.y,
+                0,
+                window,
+                ptr::null(),
+            );
+            winuser::PostMessageW(window, winuser::WM_NULL, 0, 0);
+
+            if id != 0 {
+                state.menu_pressed = Some(id as usize);
+            }
+        }
+        _ => {}
+    }
+
+    0
+}
+
+pub struct TrayIcon {
+    window: windef::HWND,
+    icon: windef::HICON,
+    state: Box<TrayState>,
+}
+
+impl TrayIcon {
+    pub fn new(icon: Icon, tooltip: &str) -> Result<TrayIcon> {
+        let path = match icon {
+            Icon::Path(path) => path,
+            Icon::Buffer(..) => {
+                return Err(Error::Unsupported {
+                    operation: "TrayIcon from a buffer",
+                    platform: "Windows",
+                })
+            }
+        };
+
+        unsafe {
+            let instance = libloaderapi::GetModuleHandleA(ptr::null());
+            let class_name = to_wstring("minifb_tray");
+            let class = winuser::WNDCLASSW {
+                style: 0,
+                lpfnWndProc: Some(tray_wnd_proc),
+                cbClsExtra: 0,
+                cbWndExtra: 0,
+                hInstance: instance,
+                hIcon: ptr::null_mut(),
+                hCursor: ptr::null_mut(),
+                hbrBackground: ptr::null_mut(),
+                lpszMenuName: ptr::null(),
+                lpszClassName: class_name.as_ptr(),
+            };
+
+            // ignore the "Class already exists" error for multiple tray icons
+            if winuser::RegisterClassW(&class) == 0 && errhandlingapi::GetLastError() != 1410 {
+                return Err(Error::WindowCreate(
+                    "Unable to register tray class".to_owned(),
+                ));
+            }
+
+            // A message-only window that receives the clicks on the icon
+            let window = winuser::CreateWindowExW(
+                0,
+                class_name.as_ptr(),
+                ptr::null(),
+                0,
+                0,
+                0,
+                0,
+                0,
+                winuser::HWND_MESSAGE,
+                ptr::null_mut(),
+                instance,
+                ptr::null_mut(),
+            );
+
+            if window.is_null() {
+                return Err(Error::WindowCreate(
+                    "Unable to create tray window".to_owned(),
+                ));
+            }
+
+            let icon = winuser::LoadImageW(
+                ptr::null_mut(),
+                path,
+                IMAGE_ICON,
+                winuser::GetSystemMetrics(winuser::SM_CXSMICON),
+                winuser::GetSystemMetrics(winuser::SM_CYSMICON),
+                LR_LOADFROMFILE,
+            ) as windef::HICON;
+
+            let mut state = Box::new(TrayState {
+                menu: ptr::null_mut(),
+                menu_pressed: None,
+                clicked: false,
+            });
+            set_window_long(window, &mut *state as *mut TrayState as _);
+
+            let mut data = Self::notify_data(window);
+            data.uFlags = shellapi::NIF_MESSAGE | shellapi::NIF_ICON;
+            data.uCallbackMessage = WM_TRAY_ICON;
+            data.hIcon = icon;
+
+            if shellapi::Shell_NotifyIconW(shellapi::NIM_ADD, &mut data) == 0 {
+                winuser::DestroyWindow(window);
+                winuser::DestroyIcon(icon);
+                return Err(Error::WindowCreate("Unable to add tray icon".to_owned()));
+            }
+
+            let mut tray_icon = TrayIcon {
+                window,
+                icon,
+                state,
+            };
+
+            tray_icon.set_tooltip(tooltip);
+
+            Ok(tray_icon)
+        }
+    }
+
+    unsafe fn notify_data(window: windef::HWND) -> shellapi::NOTIFYICONDATAW {
+        let mut data: shellapi::NOTIFYICONDATAW = mem::zeroed();
+        data.cbSize = mem::size_of::<shellapi::NOTIFYICONDATAW>() as minwindef::DWORD;
+        data.hWnd = window;
+        data.uID = 1;
+        data
+    }
+
+    pub fn set_tooltip(&mut self, tooltip: &str) {
+        unsafe {
+            let mut data = Self::notify_data(self.window);
+            data.uFlags = shellapi::NIF_TIP;
+
+            // The tooltip is truncated to fit, leaving room for the terminator
+            let tip = to_wstring(tooltip);
+            let len = (tip.len() - 1).min(data.szTip.len() - 1);
+            data.szTip[..len].copy_from_slice(&tip[..len]);
+
+            shellapi::Shell_NotifyIconW(shellapi::NIM_MODIFY, &mut data);
+        }
+    }
+
+    #[inline]
+    pub fn set_menu(&mut self, menu: &Menu) -> Result<()> {
+        self.state.menu = menu.menu_handle;
+        Ok(())
+    }
+
+    pub fn update(&mut self) {
         unsafe {
-            if self.dc.is_some() {
-                winuser::ReleaseDC(self.window.unwrap(), self.dc.unwrap());
+            let mut msg = mem::zeroed();
+
+            while winuser::PeekMessageW(&mut msg, self.window, 0, 0, winuser::PM_REMOVE) != 0 {
+                winuser::TranslateMessage(&msg);
+                winuser::DispatchMessageW(&msg);
             }
+        }
+    }
+
+    #[inline]
+    pub fn is_menu_pressed(&mut self) -> Option<usize> {
+        self.state.menu_pressed.take()
+    }
+
+    #[inline]
+    pub fn is_clicked(&mut self) -> bool {
+        mem::replace(&mut self.state.clicked, false)
+    }
+}
+
+impl Drop for TrayIcon {
+    fn drop(&mut self) {
+        unsafe {
+            let mut data = Self::notify_data(self.window);
+            shellapi::Shell_NotifyIconW(shellapi::NIM_DELETE, &mut data);
 
-            if self.window.is_some() {
-                winuser::DestroyWindow(self.window.unwrap());
+            winuser::DestroyWindow(self.window);
+            if !self.icon.is_null() {
+                winuser::DestroyIcon(self.icon);
             }
         }
     }
 }
+
+/// Creates a cursor from `0xAARRGGBB` pixels, returns null if it fails.
+unsafe fn create_cursor(
+    buffer: &[u32],
+    width: u32,
+    height: u32,
+    hot_x: u32,
+    hot_y: u32,
+) -> windef::HCURSOR {
+    let mut bitmap_info: wingdi::BITMAPINFO = mem::zeroed();
+    bitmap_info.bmiHeader.biSize = mem::size_of::<wingdi::BITMAPINFOHEADER>() as u32;
+    bitmap_info.bmiHeader.biWidth = width as i32;
+    // Negative height for a top-down bitmap
+    bitmap_info.bmiHeader.biHeight = -(height as i32);
+    bitmap_info.bmiHeader.biPlanes = 1;
+    bitmap_info.bmiHeader.biBitCount = 32;
+    bitmap_info.bmiHeader.biCompression = wingdi::BI_RGB;
+
+    let mut bits = ptr::null_mut();
+    let color = wingdi::CreateDIBSection(
+        ptr::null_mut(),
+        &bitmap_info,
+        wingdi::DIB_RGB_COLORS,
+        &mut bits,
+        ptr::null_mut(),
+        0,
+    );
+
+    if color.is_null() || bits.is_null() {
+        return ptr::null_mut();
+    }
+
+    let size = (width * height) as usize;
+    std::slice::from_raw_parts_mut(bits as *mut u32, size).copy_from_slice(&buffer[..size]);
+
+    // The mask is still required, but ignored as the color bitmap has alpha
+    let mask = wingdi::CreateBitmap(width as i32, height as i32, 1, 1, ptr::null());
+
+    let mut icon_info = winuser::ICONINFO {
+        fIcon: minwindef::FALSE,
+        xHotspot: hot_x.min(width - 1),
+        yHotspot: hot_y.min(height - 1),
+        hbmMask: mask,
+        hbmColor: color,
+    };
+    let cursor = winuser::CreateIconIndirect(&mut icon_info);
+
+    // The cursor has its own copies of the bitmaps
+    wingdi::DeleteObject(color as *mut raw::c_void);
+    wingdi::DeleteObject(mask as *mut raw::c_void);
+
+    cursor
+}
+
+// Returns the layout identifier of the calling thread, such as "00000409" for US English
+fn keyboard_layout_name() -> Option<String> {
+    let mut name = [0u16; winuser::KL_NAMELENGTH];
+
+    if unsafe { winuser::GetKeyboardLayoutNameW(name.as_mut_ptr()) } == 0 {
+        return None;
+    }
+
+    let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    Some(String::from_utf16_lossy(&name[..len]))
+}
+
+/// Calls `f` with the device name of the monitor the window is on and a DC for it, gamma ramps
+/// belong to display devices rather than windows.
+unsafe fn with_monitor_dc<T>(
+    window: windef::HWND,
+    f: impl FnOnce(&[u16; 32], windef::HDC) -> T,
+) -> Option<T> {
+    let monitor = winuser::MonitorFromWindow(window, winuser::MONITOR_DEFAULTTONEAREST);
+    let mut info: winuser::MONITORINFOEXW = mem::zeroed();
+    info.cbSize = mem::size_of::<winuser::MONITORINFOEXW>() as minwindef::DWORD;
+
+    if winuser::GetMonitorInfoW(monitor, &mut info as *mut _ as winuser::LPMONITORINFO) == 0 {
+        return None;
+    }
+
+    let device = info.szDevice;
+    device_dc(&device, |dc| f(&device, dc))
+}
+
+/// Calls `f` with a DC for the display device with the given name.
+unsafe fn device_dc<T>(device: &[u16; 32], f: impl FnOnce(windef::HDC) -> T) -> Option<T> {
+    let dc = wingdi::CreateDCW(device.as_ptr(), ptr::null(), ptr::null(), ptr::null());
+    if dc.is_null() {
+        return None;
+    }
+
+    let result = f(dc);
+    wingdi::DeleteDC(dc);
+    Some(result)
+}
+
+/// Returns the name, area and refresh rate of a monitor.
+unsafe fn monitor_info(monitor: windef::HMONITOR) -> Option<Monitor> {
+    let mut info: winuser::MONITORINFOEXW = mem::zeroed();
+    info.cbSize = mem::size_of::<winuser::MONITORINFOEXW>() as minwindef::DWORD;
+
+    if winuser::GetMonitorInfoW(monitor, &mut info as *mut _ as winuser::LPMONITORINFO) == 0 {
+        return None;
+    }
+
+    let mut mode: wingdi::DEVMODEW = mem::zeroed();
+    mode.dmSize = mem::size_of::<wingdi::DEVMODEW>() as minwindef::WORD;
+
+    // 0 and 1 mean the hardware's default refresh rate
+    let refresh_rate = if winuser::EnumDisplaySettingsW(
+        info.szDevice.as_ptr(),
+        winuser::ENUM_CURRENT_SETTINGS,
+        &mut mode,
+    ) != 0
+        && mode.dmDisplayFrequency > 1
+    {
+        Some(mode.dmDisplayFrequency as f32)
+    } else {
+        None
+    };
+
+    let len = info
+        .szDevice
+        .iter()
+        .position(|&c| c == 0)
+        .unwrap_or(info.szDevice.len());
+    let rect = info.rcMonitor;
+
+    Some(Monitor {
+        name: String::from_utf16_lossy(&info.szDevice[..len]),
+        x: rect.left as isize,
+        y: rect.top as isize,
+        width: (rect.right - rect.left) as usize,
+        height: (rect.bottom - rect.top) as usize,
+        refresh_rate,
+    })
+}
@@ -0,0 +1,45 @@
+//! Callbacks given to `Window::update_with_buffer_callback`, waiting for their frame to be shown.
+
+use std::collections::VecDeque;
+
+type Callback = Box<dyn FnOnce()>;
+
+#[derive(Default)]
+pub struct PresentCallbacks {
+    // Callbacks for the next frame that is presented
+    pending: Vec<Callback>,
+    // Callbacks of the frames that were presented but aren't on screen yet, oldest first
+    in_flight: VecDeque<Vec<Callback>>,
+}
+
+impl PresentCallbacks {
+    pub fn add(&mut self, callback: Callback) {
+        self.pending.push(callback);
+    }
+
+    /// Returns true if the next frame has callbacks waiting for it.
+    pub fn has_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    /// Drops the callbacks of a frame that won't be presented.
+    pub fn clear_pending(&mut self) {
+        self.pending.clear();
+    }
+
+    /// Marks the next frame as presented, its callbacks run when it's shown.
+    pub fn frame_presented(&mut self) {
+        if !self.pending.is_empty() {
+            self.in_flight.push_back(std::mem::take(&mut self.pending));
+        }
+    }
+
+    /// Runs the callbacks of the oldest frame that was presented.
+    pub fn frame_shown(&mut self) {
+        if let Some(callbacks) = self.in_flight.pop_front() {
+            for callback in callbacks {
+                callback();
+            }
+        }
+    }
+}
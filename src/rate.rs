@@ -5,8 +5,15 @@ use instant::{Duration, Instant};
 #[cfg(not(feature = "web"))]
 use std::time::{Duration, Instant};
 
+// How long before the deadline precise pacing stops sleeping and starts spinning, sleeping is
+// only accurate to a millisecond or two on most systems
+const SPIN_MARGIN: Duration = Duration::from_millis(2);
+
 pub struct UpdateRate {
     target_rate: Option<Duration>,
+    // Set by `set_target_fps`, spins for the end of the wait to hit the frame time closely
+    precise: bool,
+    frame_time: Duration,
     prev_time: Instant,
 }
 
@@ -15,13 +22,32 @@ impl UpdateRate {
         UpdateRate {
             // Default limit to 4 ms
             target_rate: Some(Duration::from_millis(4)),
+            precise: false,
+            frame_time: Duration::from_secs(0),
             prev_time: Instant::now(),
         }
     }
 
     #[inline]
     pub fn set_rate(&mut self, rate: Option<Duration>) {
-        self.target_rate = rate
+        self.target_rate = rate;
+        self.precise = false;
+    }
+
+    pub fn set_target_fps(&mut self, fps: u32) {
+        if fps == 0 {
+            self.target_rate = None;
+            self.precise = false;
+        } else {
+            self.target_rate = Some(Duration::from_nanos(1_000_000_000 / u64::from(fps)));
+            self.precise = true;
+        }
+    }
+
+    /// Time between the last two updates, including the time spent waiting.
+    #[inline]
+    pub fn frame_time(&self) -> Duration {
+        self.frame_time
     }
 
     pub fn update(&mut self) {
@@ -31,10 +57,31 @@ impl UpdateRate {
             if delta < target_rate {
                 let sleep_time = target_rate - delta;
                 //eprintln!("sleeping {} ms", sleep_time.as_secs_f64() * 1000.);
-                std::thread::sleep(sleep_time);
+                if self.precise {
+                    Self::precise_sleep(self.prev_time + target_rate, sleep_time);
+                } else {
+                    std::thread::sleep(sleep_time);
+                }
             }
+        }
+
+        self.update_unthrottled();
+    }
+
+    /// Same as `update` but doesn't wait, used when the backend already waited for vsync.
+    pub fn update_unthrottled(&mut self) {
+        let now = Instant::now();
+        self.frame_time = now - self.prev_time;
+        self.prev_time = now;
+    }
+
+    fn precise_sleep(deadline: Instant, sleep_time: Duration) {
+        if sleep_time > SPIN_MARGIN {
+            std::thread::sleep(sleep_time - SPIN_MARGIN);
+        }
 
-            self.prev_time = Instant::now();
+        while Instant::now() < deadline {
+            std::thread::yield_now();
         }
     }
 }
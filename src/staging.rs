@@ -0,0 +1,176 @@
+use crate::buffer_helper;
+use crate::error::Error;
+use crate::{Rect, Result};
+
+/// Frame that regions are copied into by `Window::upload_region` until `Window::present` shows it.
+/// It also keeps the last buffer given to `Window::update_with_buffer` for `Window::flush`, when
+/// one of the features that need it is on.
+pub struct StagingBuffer {
+    buffer: Vec<u32>,
+    width: usize,
+    height: usize,
+    // Size of the window when the staged frame was presented, None if it hasn't been yet
+    presented_at: Option<(usize, usize)>,
+    skip_identical: bool,
+    partial_update: bool,
+    retain_frame: bool,
+}
+
+impl StagingBuffer {
+    pub fn new() -> StagingBuffer {
+        StagingBuffer {
+            buffer: Vec::new(),
+            width: 0,
+            height: 0,
+            presented_at: None,
+            skip_identical: false,
+            partial_update: false,
+            retain_frame: false,
+        }
+    }
+
+    pub fn upload_region(
+        &mut self,
+        buffer: &[u32],
+        width: usize,
+        height: usize,
+        region: Rect,
+    ) -> Result<()> {
+        buffer_helper::check_buffer_size(width, height, width, buffer)?;
+
+        // Huge regions would overflow
+        let outside = |start: usize, len: usize, end: usize| match start.checked_add(len) {
+            Some(region_end) => region_end > end,
+            None => true,
+        };
+
+        if outside(region.x, region.width, width) || outside(region.y, region.height, height) {
+            return Err(Error::UpdateFailed(format!(
+                "Region {:?} is outside of the {} x {} buffer",
+                region, width, height
+            )));
+        }
+
+        // The parts outside of the region would be stale after a size change, so take the
+        // whole buffer instead
+        if width != self.width || height != self.height {
+            self.set_frame(buffer, width, height);
+            return Ok(());
+        }
+
+        self.presented_at = None;
+
+        for y in region.y..region.y + region.height {
+            let start = y * width + region.x;
+            let end = start + region.width;
+            self.buffer[start..end].copy_from_slice(&buffer[start..end]);
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the staged frame with a copy of `buffer`.
+    pub fn set_frame(&mut self, buffer: &[u32], width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.presented_at = None;
+        self.buffer.clear();
+        self.buffer.extend_from_slice(&buffer[..width * height]);
+    }
+
+    /// Keeps a copy of `buffer`, which was presented while the window had the given size, if
+    /// anything needs it later. Otherwise the staged frame is dropped so the copy is skipped on
+    /// the common path.
+    pub fn set_presented_frame(
+        &mut self,
+        buffer: &[u32],
+        width: usize,
+        height: usize,
+        window_size: (usize, usize),
+    ) {
+        if self.retain_frame || self.skip_identical || self.partial_update {
+            self.set_frame(buffer, width, height);
+            self.presented_at = Some(window_size);
+        } else {
+            self.width = 0;
+            self.height = 0;
+            self.presented_at = None;
+            self.buffer.clear();
+        }
+    }
+
+    /// Copies the whole rows of a `buffer` that is too small for its size into the staged frame
+    /// and returns how many there were. The rows after them are kept from the previous frame if
+    /// it had the same size, otherwise they're black.
+    pub fn set_partial_frame(&mut self, buffer: &[u32], width: usize, height: usize) -> usize {
+        if width != self.width || height != self.height {
+            self.width = width;
+            self.height = height;
+            self.buffer.clear();
+            self.buffer.resize(width * height, 0);
+        }
+
+        let rows = (buffer.len() / width.max(1)).min(height);
+        self.presented_at = None;
+        self.buffer[..rows * width].copy_from_slice(&buffer[..rows * width]);
+        rows
+    }
+
+    #[inline]
+    pub fn set_retain_frame(&mut self, enabled: bool) {
+        self.retain_frame = enabled;
+    }
+
+    #[inline]
+    pub fn set_partial_update(&mut self, allow: bool) {
+        self.partial_update = allow;
+    }
+
+    /// True when a buffer of the given size should be presented with `set_partial_frame`.
+    #[inline]
+    pub fn is_partial(&self, buffer: &[u32], width: usize, height: usize) -> bool {
+        self.partial_update && buffer.len() < width * height
+    }
+
+    #[inline]
+    pub fn set_skip_identical(&mut self, enabled: bool) {
+        self.skip_identical = enabled;
+    }
+
+    /// True when skipping identical frames is enabled and `buffer` matches the staged frame,
+    /// which was presented while the window had the given size.
+    pub fn is_presented(
+        &self,
+        buffer: &[u32],
+        width: usize,
+        height: usize,
+        window_size: (usize, usize),
+    ) -> bool {
+        self.skip_identical
+            && self.presented_at == Some(window_size)
+            && width == self.width
+            && height == self.height
+            && buffer[..width * height] == self.buffer[..]
+    }
+
+    /// Records that the staged frame was presented while the window had the given size.
+    #[inline]
+    pub fn mark_presented(&mut self, window_size: (usize, usize)) {
+        self.presented_at = Some(window_size);
+    }
+
+    /// True when regions were uploaded since the staged frame was last presented.
+    #[inline]
+    pub fn has_unpresented_frame(&self) -> bool {
+        !self.buffer.is_empty() && self.presented_at.is_none()
+    }
+
+    /// Returns the staged frame, or `None` if nothing has been uploaded yet.
+    pub fn frame(&self) -> Option<(&[u32], usize, usize)> {
+        if self.buffer.is_empty() {
+            None
+        } else {
+            Some((&self.buffer, self.width, self.height))
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::{TouchPhase, TouchPoint};
+
+pub struct TouchHandler {
+    points: Vec<TouchPoint>,
+    // Touches that ended in the same update they began in, reported as ended in the next one
+    ended: Vec<u64>,
+}
+
+impl TouchHandler {
+    pub fn new() -> TouchHandler {
+        TouchHandler {
+            points: Vec::new(),
+            ended: Vec::new(),
+        }
+    }
+
+    /// Called before new events are processed. Touches that ended are dropped and touches that
+    /// began are moved on to the `Move` phase, or to `End` if they ended already.
+    pub fn update(&mut self) {
+        self.points.retain(|p| p.phase != TouchPhase::End);
+
+        for point in self.points.iter_mut() {
+            point.phase = if self.ended.contains(&point.id) {
+                TouchPhase::End
+            } else {
+                TouchPhase::Move
+            };
+        }
+
+        self.ended.clear();
+    }
+
+    pub fn set_touch(&mut self, id: u64, x: f32, y: f32, phase: TouchPhase) {
+        match self.points.iter_mut().find(|p| p.id == id) {
+            Some(point) => {
+                point.x = x;
+                point.y = y;
+
+                // A touch that began since the last update is reported as such even if it moved
+                // or ended already, so quick taps aren't missed
+                if point.phase != TouchPhase::Begin {
+                    point.phase = phase;
+                } else if phase == TouchPhase::End && !self.ended.contains(&id) {
+                    self.ended.push(id);
+                }
+            }
+            None => self.points.push(TouchPoint { id, x, y, phase }),
+        }
+    }
+
+    pub fn get_touch_points(&self, scale: f32) -> Vec<TouchPoint> {
+        self.points
+            .iter()
+            .map(|p| TouchPoint {
+                x: p.x / scale,
+                y: p.y / scale,
+                ..*p
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn phases(handler: &TouchHandler) -> Vec<(u64, TouchPhase)> {
+        handler
+            .get_touch_points(1.0)
+            .iter()
+            .map(|p| (p.id, p.phase))
+            .collect()
+    }
+
+    #[test]
+    fn touches_go_through_all_phases() {
+        let mut handler = TouchHandler::new();
+        handler.set_touch(1, 10.0, 10.0, TouchPhase::Begin);
+        assert_eq!(phases(&handler), vec![(1, TouchPhase::Begin)]);
+
+        handler.update();
+        handler.set_touch(1, 20.0, 10.0, TouchPhase::Move);
+        assert_eq!(phases(&handler), vec![(1, TouchPhase::Move)]);
+
+        handler.update();
+        handler.set_touch(1, 20.0, 10.0, TouchPhase::End);
+        assert_eq!(phases(&handler), vec![(1, TouchPhase::End)]);
+
+        handler.update();
+        assert!(phases(&handler).is_empty());
+    }
+
+    #[test]
+    fn taps_within_one_update_begin_and_end() {
+        let mut handler = TouchHandler::new();
+        handler.set_touch(1, 10.0, 10.0, TouchPhase::Begin);
+        handler.set_touch(1, 12.0, 10.0, TouchPhase::Move);
+        handler.set_touch(1, 12.0, 10.0, TouchPhase::End);
+        assert_eq!(phases(&handler), vec![(1, TouchPhase::Begin)]);
+
+        handler.update();
+        assert_eq!(phases(&handler), vec![(1, TouchPhase::End)]);
+
+        handler.update();
+        assert!(phases(&handler).is_empty());
+    }
+}
@@ -4,8 +4,14 @@ const WINDOW_BORDERLESS: u32 = 1 << 1;
 const WINDOW_RESIZE: u32 = 1 << 2;
 #[allow(dead_code)]
 const WINDOW_TITLE: u32 = 1 << 3;
+#[allow(dead_code)]
+const WINDOW_COLOR_SPACE_SRGB: u32 = 1 << 4;
+#[allow(dead_code)]
+const WINDOW_COLOR_SPACE_LINEAR: u32 = 1 << 5;
+#[allow(dead_code)]
+const WINDOW_HIDDEN: u32 = 1 << 6;
 
-use crate::WindowOptions;
+use crate::{ColorSpace, WindowOptions};
 
 //
 // Construct a bitmask of flags (sent to backends) from WindowOpts
@@ -26,5 +32,15 @@ pub fn get_flags(opts: WindowOptions) -> u32 {
         flags |= WINDOW_RESIZE;
     }
 
+    if !opts.visible {
+        flags |= WINDOW_HIDDEN;
+    }
+
+    match opts.color_space {
+        ColorSpace::Srgb => flags |= WINDOW_COLOR_SPACE_SRGB,
+        ColorSpace::Linear => flags |= WINDOW_COLOR_SPACE_LINEAR,
+        ColorSpace::DisplayNative => (),
+    }
+
     flags
 }
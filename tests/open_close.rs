@@ -0,0 +1,63 @@
+// Runs without the test harness, as some platforms only allow windows on the main thread
+
+use minifb::{Window, WindowOptions};
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 180;
+const WINDOW_COUNT: usize = 1000;
+
+// Counts the open file descriptors of the process. Every window has its own connection to the
+// display server, so a leaked window or X resource would keep a descriptor open
+#[cfg(target_os = "linux")]
+fn open_fds() -> Option<usize> {
+    std::fs::read_dir("/proc/self/fd")
+        .ok()
+        .map(|entries| entries.count())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_fds() -> Option<usize> {
+    None
+}
+
+fn main() {
+    let buffer = vec![0x00ff_8000; WIDTH * HEIGHT];
+
+    // The first window loads the libraries, so start counting after it
+    match Window::new("Open Close Test", WIDTH, HEIGHT, WindowOptions::default()) {
+        Ok(window) => drop(window),
+        Err(e) => {
+            println!(
+                "Skipping the open/close test, no window can be opened: {}",
+                e
+            );
+            return;
+        }
+    }
+    let fds_before = open_fds();
+
+    for i in 0..WINDOW_COUNT {
+        let mut window = Window::new(
+            &format!("Open Close Test - {}/{}", i + 1, WINDOW_COUNT),
+            WIDTH,
+            HEIGHT,
+            WindowOptions::default(),
+        )
+        .expect("Unable to create window");
+
+        window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
+    }
+
+    let fds_after = open_fds();
+
+    match (fds_before, fds_after) {
+        (Some(before), Some(after)) => {
+            println!(
+                "Opened and closed {} windows, file descriptors went from {} to {}",
+                WINDOW_COUNT, before, after
+            );
+            assert!(after <= before, "windows leaked file descriptors");
+        }
+        _ => println!("Opened and closed {} windows", WINDOW_COUNT),
+    }
+}
@@ -0,0 +1,60 @@
+// Runs without the test harness, as some platforms only allow windows on the main thread
+
+use minifb::{Key, Menu, MouseMode, Window, WindowOptions};
+
+const WIDTH: usize = 320;
+const HEIGHT: usize = 180;
+
+fn main() {
+    let mut window = match Window::new("Close Test", WIDTH, HEIGHT, WindowOptions::default()) {
+        Ok(window) => window,
+        Err(e) => {
+            println!(
+                "Skipping the use after close test, no window can be opened: {}",
+                e
+            );
+            return;
+        }
+    };
+
+    let buffer = vec![0; WIDTH * HEIGHT];
+    window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
+
+    window.close();
+    assert!(!window.is_open());
+
+    // None of these may panic or talk to a native window that no longer exists
+    window.set_title("Closed");
+    let _ = window.get_title();
+    let _ = window.get_window_handle();
+    let _ = window.current_monitor();
+    let _ = window.is_maximized();
+    let state = window.save_state();
+    window.restore_state(&state);
+    window.set_position(10, 10);
+    let _ = window.get_position();
+    let _ = window.get_size();
+    let _ = window.get_outer_size();
+    window.set_size(WIDTH * 2, HEIGHT * 2);
+    window.topmost(true);
+    window.set_visible(true);
+    window.set_cursor_visibility(false);
+    window.set_cursor_confined(true);
+    window.set_gameplay_cursor_mode(true);
+    window.set_gameplay_cursor_mode(false);
+    window.set_keyboard_grab(true);
+    let _ = window.get_mouse_pos(MouseMode::Clamp);
+    let _ = window.is_key_down(Key::Escape);
+
+    if let Ok(menu) = Menu::new("Closed") {
+        window.add_menu(&menu);
+    }
+
+    window.update();
+    window.update_with_buffer(&buffer, WIDTH, HEIGHT).unwrap();
+    assert!(!window.is_open());
+
+    // Closing again does nothing
+    window.close();
+    assert!(!window.is_open());
+}